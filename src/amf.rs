@@ -0,0 +1,159 @@
+//! AMF (Additive Manufacturing File) writer: plain XML with one object whose
+//! parts are volumes over shared vertices, each pointing at a material per
+//! color, the layout multi-material printers expect
+
+use std::fmt::Write as _;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::color::Color;
+use crate::indexed::MeshPart;
+use crate::metadata::GenerationMetadata;
+use crate::output::{self, Units};
+use crate::threemf::escape;
+
+/// AMF colors are sRGB components from 0 to 1
+fn component(value: u8) -> f32 {
+    value as f32 / 255.0
+}
+
+/// The AMF document; material ids start at 1 because 0 means "no material"
+fn amf_xml(parts: &[MeshPart], metadata: Option<&GenerationMetadata>, units: Units) -> String {
+    let mut xml = String::new();
+    let _ = writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(xml, r#"<amf unit="{}" version="1.1">"#, units.name());
+    if let Some(metadata) = metadata {
+        for (name, value) in metadata.entries() {
+            let _ = writeln!(
+                xml,
+                r#"  <metadata type="{}">{}</metadata>"#,
+                name,
+                escape(&value)
+            );
+        }
+    }
+
+    let parts: Vec<&MeshPart> = parts
+        .iter()
+        .filter(|p| !p.mesh.indices.is_empty())
+        .collect();
+    let mut colors: Vec<Color> = Vec::new();
+    for part in &parts {
+        if !colors.contains(&part.color) {
+            colors.push(part.color);
+        }
+    }
+    for (i, color) in colors.iter().enumerate() {
+        let _ = writeln!(xml, r#"  <material id="{}">"#, i + 1);
+        let _ = writeln!(xml, r#"    <metadata type="name">{}</metadata>"#, color);
+        let _ = writeln!(
+            xml,
+            "    <color><r>{}</r><g>{}</g><b>{}</b><a>{}</a></color>",
+            component(color.r),
+            component(color.g),
+            component(color.b),
+            component(color.a)
+        );
+        let _ = writeln!(xml, "  </material>");
+    }
+
+    let _ = writeln!(xml, r#"  <object id="0">"#);
+    let name = metadata.map_or("wagyan".to_string(), |m| m.title.clone());
+    let _ = writeln!(
+        xml,
+        r#"    <metadata type="name">{}</metadata>"#,
+        escape(&name)
+    );
+    let _ = writeln!(xml, "    <mesh>\n      <vertices>");
+    let welded: Vec<_> = parts.iter().map(|p| p.mesh.shared_positions()).collect();
+    for (positions, _) in &welded {
+        for p in positions {
+            let _ = writeln!(
+                xml,
+                "        <vertex><coordinates><x>{}</x><y>{}</y><z>{}</z></coordinates></vertex>",
+                p[0], p[1], p[2]
+            );
+        }
+    }
+    let _ = writeln!(xml, "      </vertices>");
+    // Volumes index the one vertex list, so each part's indices are offset
+    let mut base = 0;
+    for (part, (positions, indices)) in parts.iter().zip(&welded) {
+        let material = colors.iter().position(|c| *c == part.color).unwrap_or(0) + 1;
+        let _ = writeln!(xml, r#"      <volume materialid="{}">"#, material);
+        let _ = writeln!(
+            xml,
+            r#"        <metadata type="name">{}</metadata>"#,
+            escape(&part.name)
+        );
+        for tri in indices.chunks(3) {
+            let _ = writeln!(
+                xml,
+                "        <triangle><v1>{}</v1><v2>{}</v2><v3>{}</v3></triangle>",
+                tri[0] as usize + base,
+                tri[1] as usize + base,
+                tri[2] as usize + base
+            );
+        }
+        let _ = writeln!(xml, "      </volume>");
+        base += positions.len();
+    }
+    let _ = writeln!(xml, "    </mesh>\n  </object>\n</amf>");
+    xml
+}
+
+pub fn write_amf(
+    path: &Path,
+    parts: &[MeshPart],
+    metadata: Option<&GenerationMetadata>,
+    units: Units,
+) -> Result<()> {
+    output::write_atomically(path, |file| {
+        write_amf_to_writer(BufWriter::new(file), parts, metadata, units)
+    })
+}
+
+pub fn write_amf_to_writer<W: Write>(
+    mut writer: W,
+    parts: &[MeshPart],
+    metadata: Option<&GenerationMetadata>,
+    units: Units,
+) -> Result<()> {
+    writer.write_all(amf_xml(parts, metadata, units).as_bytes())?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexed::IndexedMesh;
+    use crate::mesh::{rectangle_mesh, Orientation};
+
+    #[test]
+    fn parts_become_volumes_over_shared_vertices() {
+        let square = rectangle_mesh(0.0, 1.0, 0.0, 1.0);
+        let part = |name: &str, color: Color, z: f32| MeshPart {
+            name: name.into(),
+            mesh: IndexedMesh::extrude(&square, 1.0, &Orientation::Flat, z, None),
+            color,
+        };
+        let parts = [
+            part("plate", Color::LIGHT_GRAY, 0.0),
+            part("text", Color::rgb(0xff, 0, 0), 1.0),
+        ];
+        let xml = amf_xml(&parts, None, Units::Mm);
+
+        assert!(xml.contains(r#"<amf unit="millimeter" version="1.1">"#));
+        assert_eq!(xml.matches("<material id=").count(), 2);
+        assert!(xml.contains("<r>1</r><g>0</g><b>0</b><a>1</a>"));
+        // Two boxes of 8 corners, 12 triangles each
+        assert_eq!(xml.matches("<vertex>").count(), 16);
+        assert_eq!(xml.matches("<triangle>").count(), 24);
+        let text = xml.split(r#"<volume materialid="2">"#).nth(1).unwrap();
+        assert!(text.contains("<v1>8</v1>") || text.contains("<v2>8</v2>"));
+        assert!(xml.trim_end().ends_with("</amf>"));
+    }
+}
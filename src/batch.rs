@@ -0,0 +1,64 @@
+//! Label lists for batch runs: a plain text file with one label per line,
+//! or a CSV file whose first column holds the labels
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+/// First field of a CSV line; quoted fields may contain commas and `""`
+fn first_field(line: &str) -> String {
+    let line = line.trim();
+    let Some(quoted) = line.strip_prefix('"') else {
+        return line
+            .split(',')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+    };
+    let mut field = String::new();
+    let mut chars = quoted.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch == '"' {
+            if chars.peek() == Some(&'"') {
+                chars.next();
+            } else {
+                break;
+            }
+        }
+        field.push(ch);
+    }
+    field
+}
+
+/// Labels in file order; blank lines and lines starting with `#` are skipped
+pub fn parse_labels(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(first_field)
+        .filter(|label| !label.is_empty())
+        .collect()
+}
+
+pub fn read_labels(path: &Path) -> Result<Vec<String>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("cannot read label list {}", path.display()))?;
+    let labels = parse_labels(&contents);
+    anyhow::ensure!(!labels.is_empty(), "{} has no labels", path.display());
+    Ok(labels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_come_from_the_first_column() {
+        let csv = "# rack A\nsrv-01,10.0.0.1\n\n\"web, front\",10.0.0.2\n\"say \"\"hi\"\"\"\nplain line\n";
+        assert_eq!(
+            parse_labels(csv),
+            ["srv-01", "web, front", "say \"hi\"", "plain line"]
+        );
+    }
+}
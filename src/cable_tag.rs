@@ -0,0 +1,129 @@
+//! Cable tag preset: a small label with raised text and stadium-shaped
+//! slots at the ends for a zip tie
+
+use anyhow::Result;
+use lyon_path::math::{Box2D, Point};
+use ttf_parser::Face;
+
+use crate::cancel::CancelToken;
+use crate::nameplate::{centered_text, raised_parts, Outline};
+use crate::{Options, Part};
+
+/// Cable tag settings; the lettering itself comes from [`Options`]
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct CableTag {
+    /// Tag thickness in mm
+    pub thickness: f32,
+    /// Space around the text in mm
+    pub margin: f32,
+    pub corner_radius: f32,
+    /// Long side of each slot in mm: the zip tie width plus clearance
+    pub slot_width: f32,
+    /// Short side of each slot in mm: the zip tie thickness plus clearance
+    pub slot_height: f32,
+    /// Slots at both ends (2) or only the left one (1)
+    pub slots: u8,
+}
+
+impl Default for CableTag {
+    fn default() -> Self {
+        Self {
+            thickness: 1.6,
+            margin: 2.0,
+            corner_radius: 2.0,
+            slot_width: 5.0,
+            slot_height: 2.0,
+            slots: 2,
+        }
+    }
+}
+
+/// Build the tag (with its slots) and the lettering
+pub fn cable_tag_parts(
+    face: &Face<'_>,
+    options: &Options,
+    tag: &CableTag,
+    cancel: &CancelToken,
+) -> Result<Vec<Part>> {
+    anyhow::ensure!(
+        matches!(tag.slots, 1 | 2),
+        "a cable tag has 1 or 2 slots, not {}",
+        tag.slots
+    );
+    let (mut text, text_w, text_h) = centered_text(face, options, cancel)?;
+
+    // Slots run across the tag, each with a margin on both sides
+    let slot_space = tag.slot_height + tag.margin;
+    let half_h = (text_h + tag.margin).max(tag.slot_width * 0.5 + tag.margin);
+    let half_w = text_w + tag.margin + slot_space * f32::from(tag.slots) * 0.5;
+    // With one slot the text moves right, out of its way
+    let shift = if tag.slots == 1 {
+        slot_space * 0.5
+    } else {
+        0.0
+    };
+    crate::mesh::translate_mesh(&mut text, shift, 0.0);
+
+    let slot_x = half_w - tag.margin - tag.slot_height * 0.5;
+    let slot = |x: f32| {
+        Box2D::new(
+            Point::new(x - tag.slot_height * 0.5, -tag.slot_width * 0.5),
+            Point::new(x + tag.slot_height * 0.5, tag.slot_width * 0.5),
+        )
+    };
+    let outline = Outline {
+        half_w,
+        half_h,
+        radius: tag.corner_radius.min(half_w).min(half_h),
+        holes: Vec::new(),
+        hole_radius: 0.0,
+        slots: if tag.slots == 1 {
+            vec![slot(-slot_x)]
+        } else {
+            vec![slot(-slot_x), slot(slot_x)]
+        },
+    };
+    raised_parts(
+        text,
+        &outline,
+        options.snap_to_layers(tag.thickness),
+        0.0,
+        options,
+        options.tolerance(face),
+        cancel,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font;
+    use crate::mesh;
+
+    #[test]
+    fn slots_sit_beside_the_text() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let options = Options {
+            text: "srv-01".into(),
+            size: 8.0,
+            depth: 0.6,
+            ..Default::default()
+        };
+        let tag = CableTag::default();
+        let parts = cable_tag_parts(&face, &options, &tag, &CancelToken::new()).unwrap();
+        let (plate_min_x, plate_max_x, ..) = mesh::mesh_bounds(&parts[0].mesh).unwrap();
+        let (text_min_x, text_max_x, ..) = mesh::mesh_bounds(&parts[1].mesh).unwrap();
+        let side = tag.margin * 2.0 + tag.slot_height;
+        assert!((text_min_x - plate_min_x - side).abs() < 0.01);
+        assert!((plate_max_x - text_max_x - side).abs() < 0.01);
+
+        // The slots are cut out: two closed outlines inside the tag's
+        let loops = mesh::boundary_loops(&parts[0].mesh);
+        assert_eq!(loops.len(), 3);
+    }
+}
@@ -0,0 +1,242 @@
+//! Cake topper preset: lettering on a backing that joins the letters into
+//! one piece, with pointed sticks below for pushing into the cake
+
+use anyhow::{Context, Result};
+use lyon_path::math::Point;
+use ttf_parser::Face;
+
+use crate::cancel::CancelToken;
+use crate::mesh;
+use crate::outline;
+use crate::{build_path, layout, no_progress, Options, Part};
+
+/// Cake topper settings; the lettering itself comes from [`Options`]
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct CakeTopper {
+    /// How far the backing reaches past the letters in mm; wide enough
+    /// to bridge the gaps between them
+    pub outline: f32,
+    /// Backing thickness in mm
+    pub thickness: f32,
+    /// Number of sticks (1 or 2)
+    pub sticks: u8,
+    /// Stick length below the backing in mm, including the point
+    pub stick_length: f32,
+    pub stick_width: f32,
+}
+
+impl Default for CakeTopper {
+    fn default() -> Self {
+        Self {
+            outline: 2.0,
+            thickness: 3.0,
+            sticks: 2,
+            stick_length: 60.0,
+            stick_width: 5.0,
+        }
+    }
+}
+
+/// A stick running from `top` down to `bottom`, ending in a point
+pub(crate) fn stick(center_x: f32, width: f32, top: f32, bottom: f32) -> Vec<Point> {
+    let half = width * 0.5;
+    let shoulder = (bottom + width).min(top);
+    vec![
+        Point::new(center_x, bottom),
+        Point::new(center_x + half, shoulder),
+        Point::new(center_x + half, top),
+        Point::new(center_x - half, top),
+        Point::new(center_x - half, shoulder),
+    ]
+}
+
+/// Separate pieces in a set of outlines (counter-clockwise contours)
+fn pieces(outlines: &[Vec<Point>]) -> usize {
+    outlines.iter().filter(|o| outline::area(o) > 0.0).count()
+}
+
+/// Point of `outline`'s edges nearest to `p`
+fn nearest_on(outline: &[Point], p: Point) -> Point {
+    let mut best = outline[0];
+    for (i, &a) in outline.iter().enumerate() {
+        let b = outline[(i + 1) % outline.len()];
+        let edge = b - a;
+        let t = match edge.square_length() {
+            0.0 => 0.0,
+            length => ((p - a).dot(edge) / length).clamp(0.0, 1.0),
+        };
+        let q = a + edge * t;
+        if (q - p).square_length() < (best - p).square_length() {
+            best = q;
+        }
+    }
+    best
+}
+
+/// `backing` with every piece but the largest tied to another by a bar
+/// `width` wide across the shortest gap, until it is all one piece
+fn join_islands(mut backing: Vec<Vec<Point>>, width: f32, tolerance: f32) -> Vec<Vec<Point>> {
+    // Each round joins every island to something, so the count halves
+    while pieces(&backing) > 1 {
+        let solid: Vec<&Vec<Point>> = backing.iter().filter(|o| outline::area(o) > 0.0).collect();
+        let largest = (0..solid.len())
+            .max_by(|&a, &b| outline::area(solid[a]).total_cmp(&outline::area(solid[b])))
+            .unwrap_or(0);
+        let mut bars = Vec::new();
+        for (i, island) in solid.iter().enumerate().filter(|&(i, _)| i != largest) {
+            let mut gap: Option<(Point, Point)> = None;
+            for (_, other) in solid.iter().enumerate().filter(|&(j, _)| j != i) {
+                for &p in island.iter() {
+                    let q = nearest_on(other, p);
+                    if gap.is_none_or(|(a, b)| (q - p).square_length() < (b - a).square_length()) {
+                        gap = Some((p, q));
+                    }
+                }
+            }
+            bars.extend(gap.map(|(from, to)| vec![from, to]));
+        }
+        let joined = outline::union(&backing, &outline::stroke(&bars, width, tolerance));
+        if pieces(&joined) >= pieces(&backing) {
+            break;
+        }
+        backing = joined;
+    }
+    backing
+}
+
+/// Build the cake topper parts: the backing with its sticks, then the lettering
+pub fn cake_topper_parts(
+    face: &Face<'_>,
+    options: &Options,
+    topper: &CakeTopper,
+    cancel: &CancelToken,
+) -> Result<Vec<Part>> {
+    anyhow::ensure!(
+        matches!(topper.sticks, 1 | 2),
+        "a cake topper has 1 or 2 sticks, not {}",
+        topper.sticks
+    );
+    let tolerance = options.tolerance(face);
+    let path = build_path(face, options, &no_progress);
+    let mut text = mesh::tessellate_path_with_cancel(&path, tolerance, cancel)?;
+    let (min_x, max_x, min_y, max_y) =
+        mesh::mesh_bounds(&text).context("the text has no visible glyphs")?;
+    let (dx, dy) = (-(min_x + max_x) * 0.5, -(min_y + max_y) * 0.5);
+    mesh::translate_mesh(&mut text, dx, dy);
+    let (half_w, half_h) = ((max_x - min_x) * 0.5, (max_y - min_y) * 0.5);
+
+    let mut letters = outline::from_path(&path, tolerance);
+    outline::translate(&mut letters, dx, dy);
+    let mut backing = outline::offset(&letters, topper.outline, tolerance);
+
+    // Letters the outline does not reach are tied together along each baseline
+    let lines = layout(face, options).lines;
+    if pieces(&backing) > 1 {
+        let bars: Vec<Vec<Point>> = lines
+            .iter()
+            .map(|line| {
+                let y = line.baseline + dy;
+                outline::rectangle(
+                    (line.offset + dx).max(-half_w),
+                    (line.offset + line.width + dx).min(half_w),
+                    y - topper.outline,
+                    y + topper.outline,
+                )
+            })
+            .collect();
+        backing = outline::union(&backing, &bars);
+    }
+
+    // Sticks reach up to the first baseline so they also join the lines
+    let top = lines.first().map_or(0.0, |line| line.baseline + dy);
+    let bottom = -half_h - topper.outline - topper.stick_length;
+    let xs: &[f32] = if topper.sticks == 1 {
+        &[0.0]
+    } else {
+        &[-half_w * 0.5, half_w * 0.5]
+    };
+    let sticks: Vec<Vec<Point>> = xs
+        .iter()
+        .map(|&x| stick(x, topper.stick_width, top, bottom))
+        .collect();
+    backing = outline::union(&backing, &sticks);
+    // Dots, accents and anything else off the bars are tied to the nearest
+    // piece
+    backing = join_islands(backing, topper.outline * 2.0, tolerance);
+    anyhow::ensure!(
+        pieces(&backing) == 1,
+        "the backing does not join all the letters into one piece; increase the outline"
+    );
+
+    let depth = options.text_depth();
+    let thickness = options.snap_to_layers(topper.thickness);
+    Ok(vec![
+        Part {
+            name: "backing".to_string(),
+            mesh: outline::fill(&backing, tolerance, cancel)?,
+            depth: thickness,
+            z_offset: -(depth * 0.5 + thickness * 0.5),
+            letter: None,
+            chamfer: 0.0,
+            color: None,
+        },
+        Part {
+            name: "text".to_string(),
+            mesh: text,
+            depth,
+            z_offset: 0.0,
+            letter: None,
+            chamfer: 0.0,
+            color: None,
+        },
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font;
+
+    fn build(text: &str, topper: &CakeTopper) -> Vec<Part> {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let options = Options {
+            text: text.into(),
+            size: 20.0,
+            depth: 1.0,
+            ..Default::default()
+        };
+        cake_topper_parts(&face, &options, topper, &CancelToken::new()).unwrap()
+    }
+
+    #[test]
+    fn backing_is_one_piece_with_sticks_below_the_text() {
+        let topper = CakeTopper::default();
+        let parts = build("Happy Birthday", &topper);
+        let names: Vec<&str> = parts.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["backing", "text"]);
+
+        let (_, _, text_min_y, _) = mesh::mesh_bounds(&parts[1].mesh).unwrap();
+        let (_, _, min_y, _) = mesh::mesh_bounds(&parts[0].mesh).unwrap();
+        let reach = text_min_y - min_y;
+        assert!((reach - topper.outline - topper.stick_length).abs() < 0.01);
+    }
+
+    #[test]
+    fn dots_are_tied_to_the_letters_at_the_default_size() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        for text in ["Hi", "Mia", "jij"] {
+            let options = Options {
+                text: text.into(),
+                ..Default::default()
+            };
+            let parts =
+                cake_topper_parts(&face, &options, &CakeTopper::default(), &CancelToken::new());
+            assert!(parts.is_ok(), "{}: {:?}", text, parts.err());
+        }
+    }
+}
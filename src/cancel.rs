@@ -0,0 +1,102 @@
+//! Cooperative cancellation and time limits for long generations
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+
+/// Error returned when a generation is cancelled or runs past its deadline.
+/// Detect it with `err.downcast_ref::<Cancelled>()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cancelled {
+    /// [`CancelToken::cancel`] was called
+    Requested,
+    /// The token's deadline passed
+    TimedOut,
+}
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Cancelled::Requested => write!(f, "generation was cancelled"),
+            Cancelled::TimedOut => write!(f, "generation exceeded its time limit"),
+        }
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Shared cancellation flag with an optional deadline.
+/// Clones share the flag, so one clone can cancel work running on another thread.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    flag: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancelToken {
+    /// Token that only cancels when asked to
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Token that also cancels once `limit` has elapsed from now
+    pub fn with_timeout(limit: Duration) -> Self {
+        Self {
+            flag: Arc::default(),
+            deadline: Instant::now().checked_add(limit),
+        }
+    }
+
+    /// Request cancellation; running work stops at its next check
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    /// Why the work should stop, if it should
+    pub fn state(&self) -> Option<Cancelled> {
+        if self.flag.load(Ordering::Relaxed) {
+            Some(Cancelled::Requested)
+        } else if self.deadline.is_some_and(|d| Instant::now() >= d) {
+            Some(Cancelled::TimedOut)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state().is_some()
+    }
+
+    /// `Err(Cancelled)` once cancelled or timed out
+    pub fn check(&self) -> Result<()> {
+        match self.state() {
+            Some(reason) => Err(reason.into()),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clones_share_cancellation() {
+        let token = CancelToken::new();
+        let worker = token.clone();
+        assert!(worker.check().is_ok());
+
+        token.cancel();
+        let err = worker.check().unwrap_err();
+        assert_eq!(err.downcast_ref::<Cancelled>(), Some(&Cancelled::Requested));
+    }
+
+    #[test]
+    fn zero_timeout_expires_immediately() {
+        let token = CancelToken::with_timeout(Duration::ZERO);
+        assert_eq!(token.state(), Some(Cancelled::TimedOut));
+    }
+}
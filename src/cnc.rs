@@ -0,0 +1,337 @@
+//! CNC output: V-bit carving and end-mill pocketing of the lettering as
+//! G-code toolpaths, or a V-carve depth heightmap
+
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::Result;
+use lyon_path::math::Point;
+use ttf_parser::Face;
+
+use crate::{build_path, no_progress, outline, output, Options};
+
+/// Closed contours cut at one depth
+#[derive(Debug, Clone)]
+pub struct Toolpath {
+    /// Depth below the stock surface in mm (positive)
+    pub depth: f32,
+    pub contours: Vec<Vec<Point>>,
+}
+
+/// Machine settings for G-code output
+#[derive(Debug, Clone)]
+pub struct Machine {
+    /// Height above the stock for rapid moves in mm
+    pub safe_z: f32,
+    /// Cutting feed rate in mm/min
+    pub feed: f32,
+    /// Plunge feed rate in mm/min
+    pub plunge_feed: f32,
+}
+
+impl Default for Machine {
+    fn default() -> Self {
+        Self {
+            safe_z: 5.0,
+            feed: 800.0,
+            plunge_feed: 200.0,
+        }
+    }
+}
+
+/// Outlines of the lettering in mm, placed on the origin by `options.anchor`
+/// with `options.center`
+pub fn text_outlines(face: &Face<'_>, options: &Options) -> Vec<Vec<Point>> {
+    let path = build_path(face, options, &no_progress);
+    let mut outlines = outline::from_path(&path, options.tolerance(face));
+    let bounds = outlines.iter().flatten().fold(None, |acc, p| match acc {
+        None => Some((p.x, p.x, p.y, p.y)),
+        Some((x0, x1, y0, y1)) => Some((p.x.min(x0), p.x.max(x1), p.y.min(y0), p.y.max(y1))),
+    });
+    if let (true, Some(bounds)) = (options.center, bounds) {
+        let (dx, dy) = options.text_anchor().offset(bounds);
+        outline::translate(&mut outlines, dx, dy);
+    }
+    outlines
+}
+
+/// Depth at which a V-bit with the given included angle touches both walls
+/// of a groove `distance` mm from its center line
+pub fn vbit_depth(angle_degrees: f32, distance: f32) -> f32 {
+    distance / (angle_degrees.to_radians() * 0.5).tan()
+}
+
+/// V-carve toolpaths: successive inward offsets `stepover` apart, each cut
+/// as deep as the bit must go to reach the outline. The deepest passes
+/// follow the medial axis of each stroke.
+pub fn vcarve_passes(
+    outlines: &[Vec<Point>],
+    angle_degrees: f32,
+    stepover: f32,
+    tolerance: f32,
+) -> Vec<Toolpath> {
+    let mut passes = Vec::new();
+    let mut inset = stepover;
+    loop {
+        let contours = outline::offset(outlines, -inset, tolerance);
+        if contours.is_empty() {
+            break;
+        }
+        passes.push(Toolpath {
+            depth: vbit_depth(angle_degrees, inset),
+            contours,
+        });
+        inset += stepover;
+    }
+    passes
+}
+
+/// Pocketing toolpaths for an end mill of `tool_radius`: the first pass
+/// follows the outline one radius in, each further pass one radius further
+/// (half the tool width, so passes overlap). Stops after `passes` offsets,
+/// or when the pocket is cleared.
+pub fn pocket_passes(
+    outlines: &[Vec<Point>],
+    tool_radius: f32,
+    passes: Option<usize>,
+    depth: f32,
+    tolerance: f32,
+) -> Vec<Toolpath> {
+    (1..=passes.unwrap_or(usize::MAX))
+        .map(|pass| outline::offset(outlines, -(pass as f32) * tool_radius, tolerance))
+        .take_while(|contours| !contours.is_empty())
+        .map(|contours| Toolpath { depth, contours })
+        .collect()
+}
+
+/// Carving depth sampled on a grid, rows from the top (max y) down
+#[derive(Debug, Clone)]
+pub struct Heightmap {
+    pub width: usize,
+    pub height: usize,
+    /// Pixel size in mm
+    pub pixel: f32,
+    /// Model coordinates of the top-left pixel's corner
+    pub origin: Point,
+    /// Depth below the surface in mm, 0 outside the lettering
+    pub depths: Vec<f32>,
+}
+
+impl Heightmap {
+    pub fn max_depth(&self) -> f32 {
+        self.depths.iter().copied().fold(0.0, f32::max)
+    }
+}
+
+/// Depth of a V-carve at every pixel: the distance to the nearest outline
+/// edge, converted with [`vbit_depth`]
+pub fn vcarve_heightmap(outlines: &[Vec<Point>], angle_degrees: f32, pixel: f32) -> Heightmap {
+    let bounds = outlines.iter().flatten().fold(None, |acc, p| match acc {
+        None => Some((p.x, p.x, p.y, p.y)),
+        Some((x0, x1, y0, y1)) => Some((p.x.min(x0), p.x.max(x1), p.y.min(y0), p.y.max(y1))),
+    });
+    let (min_x, max_x, min_y, max_y) = bounds.unwrap_or_default();
+    // One pixel of untouched stock all round
+    let origin = Point::new(min_x - pixel, max_y + pixel);
+    let width = ((max_x - min_x) / pixel).ceil() as usize + 2;
+    let height = ((max_y - min_y) / pixel).ceil() as usize + 2;
+
+    // Squared distance (in pixels) to the nearest pixel outside the lettering
+    let mut field = vec![0.0; width * height];
+    for row in 0..height {
+        let y = origin.y - (row as f32 + 0.5) * pixel;
+        let mut crossings: Vec<f32> = outlines
+            .iter()
+            .flat_map(|points| {
+                (0..points.len()).filter_map(move |i| {
+                    let (a, b) = (points[i], points[(i + 1) % points.len()]);
+                    ((a.y <= y) != (b.y <= y)).then(|| a.x + (y - a.y) / (b.y - a.y) * (b.x - a.x))
+                })
+            })
+            .collect();
+        crossings.sort_by(f32::total_cmp);
+        for span in crossings.chunks_exact(2) {
+            let first = ((span[0] - origin.x) / pixel - 0.5).ceil().max(0.0) as usize;
+            let last = ((span[1] - origin.x) / pixel - 0.5).floor() as usize;
+            for column in first..=last.min(width - 1) {
+                field[row * width + column] = f32::INFINITY;
+            }
+        }
+    }
+    distance_transform(&mut field, width, height);
+
+    // The outline lies about half a pixel before the nearest outside center
+    let depths = field
+        .iter()
+        .map(|&squared| vbit_depth(angle_degrees, ((squared.sqrt() - 0.5) * pixel).max(0.0)))
+        .collect();
+    Heightmap {
+        width,
+        height,
+        pixel,
+        origin,
+        depths,
+    }
+}
+
+/// Exact squared Euclidean distance transform (Felzenszwalb & Huttenlocher):
+/// zeros stay, every other cell becomes the squared distance to the nearest zero
+fn distance_transform(field: &mut [f32], width: usize, height: usize) {
+    let mut line = vec![0.0; width.max(height)];
+    for column in 0..width {
+        for row in 0..height {
+            line[row] = field[row * width + column];
+        }
+        let distances = distance_transform_1d(&line[..height]);
+        for row in 0..height {
+            field[row * width + column] = distances[row];
+        }
+    }
+    for row in 0..height {
+        let distances = distance_transform_1d(&field[row * width..(row + 1) * width]);
+        field[row * width..(row + 1) * width].copy_from_slice(&distances);
+    }
+}
+
+fn distance_transform_1d(f: &[f32]) -> Vec<f32> {
+    let n = f.len();
+    let finite: Vec<usize> = (0..n).filter(|&q| f[q].is_finite()).collect();
+    if finite.is_empty() {
+        return vec![f32::INFINITY; n];
+    }
+    // Lower envelope of the parabolas rooted at the finite cells
+    let intersection = |p: usize, q: usize| {
+        ((f[q] + (q * q) as f32) - (f[p] + (p * p) as f32)) / (2.0 * (q as f32 - p as f32))
+    };
+    let mut roots = vec![finite[0]];
+    let mut bounds = vec![f32::NEG_INFINITY];
+    for &q in &finite[1..] {
+        let mut s = intersection(*roots.last().unwrap_or(&q), q);
+        while s <= *bounds.last().unwrap_or(&f32::NEG_INFINITY) {
+            roots.pop();
+            bounds.pop();
+            s = intersection(*roots.last().unwrap_or(&q), q);
+        }
+        roots.push(q);
+        bounds.push(s);
+    }
+    let mut k = 0;
+    (0..n)
+        .map(|q| {
+            while k + 1 < bounds.len() && bounds[k + 1] < q as f32 {
+                k += 1;
+            }
+            let d = q as f32 - roots[k] as f32;
+            d * d + f[roots[k]]
+        })
+        .collect()
+}
+
+pub fn write_heightmap_pgm(path: &Path, heightmap: &Heightmap) -> Result<()> {
+    output::write_atomically(path, |file| {
+        write_heightmap_pgm_to_writer(BufWriter::new(file), heightmap)
+    })
+}
+
+/// 8-bit binary PGM: white is the untouched surface, black the deepest cut
+pub fn write_heightmap_pgm_to_writer<W: Write>(mut writer: W, heightmap: &Heightmap) -> Result<()> {
+    let max_depth = heightmap.max_depth();
+    writeln!(writer, "P5")?;
+    writeln!(
+        writer,
+        "# pixel {} mm, black = {:.3} mm deep",
+        heightmap.pixel, max_depth
+    )?;
+    writeln!(writer, "{} {}\n255", heightmap.width, heightmap.height)?;
+    let scale = if max_depth > 0.0 {
+        255.0 / max_depth
+    } else {
+        0.0
+    };
+    let bytes: Vec<u8> = heightmap
+        .depths
+        .iter()
+        .map(|depth| 255 - (depth * scale).round() as u8)
+        .collect();
+    writer.write_all(&bytes)?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn write_gcode(path: &Path, toolpaths: &[Toolpath], machine: &Machine) -> Result<()> {
+    output::write_atomically(path, |file| {
+        write_gcode_to_writer(BufWriter::new(file), toolpaths, machine)
+    })
+}
+
+/// G-code in mm with absolute coordinates; Z 0 is the stock surface. Each
+/// contour is entered from safe height, cut once around, and left upward.
+pub fn write_gcode_to_writer<W: Write>(
+    mut writer: W,
+    toolpaths: &[Toolpath],
+    machine: &Machine,
+) -> Result<()> {
+    writeln!(writer, "(wagyan toolpaths)")?;
+    writeln!(writer, "G21 G90")?;
+    writeln!(writer, "G0 Z{:.3}", machine.safe_z)?;
+    for toolpath in toolpaths {
+        for contour in &toolpath.contours {
+            let Some(start) = contour.first() else {
+                continue;
+            };
+            writeln!(writer, "G0 X{:.3} Y{:.3}", start.x, start.y)?;
+            writeln!(
+                writer,
+                "G1 Z{:.3} F{}",
+                -toolpath.depth, machine.plunge_feed
+            )?;
+            for (i, p) in contour.iter().skip(1).chain([start]).enumerate() {
+                if i == 0 {
+                    writeln!(writer, "G1 X{:.3} Y{:.3} F{}", p.x, p.y, machine.feed)?;
+                } else {
+                    writeln!(writer, "G1 X{:.3} Y{:.3}", p.x, p.y)?;
+                }
+            }
+            writeln!(writer, "G0 Z{:.3}", machine.safe_z)?;
+        }
+    }
+    writeln!(writer, "M2")?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vcarve_reaches_the_middle_of_a_stroke() {
+        // A 2 mm wide bar: a 90° bit meets both walls 1 mm deep
+        let bar = vec![outline::rectangle(0.0, 10.0, 0.0, 2.0)];
+        let passes = vcarve_passes(&bar, 90.0, 0.25, 0.01);
+        assert_eq!(passes.len(), 3);
+        assert!((passes[2].depth - 0.75).abs() < 1e-4);
+
+        let heightmap = vcarve_heightmap(&bar, 90.0, 0.1);
+        assert_eq!((heightmap.width, heightmap.height), (102, 22));
+        assert!((heightmap.max_depth() - 0.95).abs() < 0.06);
+        // Outside the bar nothing is cut
+        assert_eq!(heightmap.depths[0], 0.0);
+
+        let mut out = Vec::new();
+        write_gcode_to_writer(&mut out, &passes, &Machine::default()).unwrap();
+        let gcode = String::from_utf8(out).unwrap();
+        assert_eq!(gcode.matches("G1 Z-0.750").count(), 1);
+        assert!(gcode.ends_with("M2\n"));
+    }
+
+    #[test]
+    fn pocket_passes_step_in_by_the_tool_radius() {
+        let square = vec![outline::rectangle(0.0, 4.0, 0.0, 4.0)];
+        let passes = pocket_passes(&square, 0.5, None, 1.0, 0.01);
+        // Insets of 0.5, 1.0 and 1.5 mm; at 2.0 mm nothing is left
+        assert_eq!(passes.len(), 3);
+        assert!((outline::area(&passes[0].contours[0]) - 9.0).abs() < 1e-3);
+        assert_eq!(pocket_passes(&square, 0.5, Some(1), 1.0, 0.01).len(), 1);
+    }
+}
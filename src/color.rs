@@ -0,0 +1,90 @@
+//! Colors for exports that carry materials
+
+use std::fmt;
+use std::str::FromStr;
+
+/// sRGB color with alpha, parsed from `#rrggbb` / `#rrggbbaa`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const LIGHT_GRAY: Color = Color::rgb(0xcc, 0xcc, 0xcc);
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b, a: 0xff }
+    }
+
+    /// Linear RGBA factors (glTF `baseColorFactor` is linear)
+    pub fn to_linear(self) -> [f32; 4] {
+        fn channel(c: u8) -> f32 {
+            let c = c as f32 / 255.0;
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        }
+        [
+            channel(self.r),
+            channel(self.g),
+            channel(self.b),
+            self.a as f32 / 255.0,
+        ]
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)?;
+        if self.a != 0xff {
+            write!(f, "{:02x}", self.a)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.trim().trim_start_matches('#');
+        let byte = |i: usize| {
+            hex.get(i..i + 2)
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+                .ok_or_else(|| format!("invalid color '{}' (expected #rrggbb or #rrggbbaa)", s))
+        };
+        match hex.len() {
+            6 => Ok(Color::rgb(byte(0)?, byte(2)?, byte(4)?)),
+            8 => Ok(Color {
+                r: byte(0)?,
+                g: byte(2)?,
+                b: byte(4)?,
+                a: byte(6)?,
+            }),
+            _ => Err(format!(
+                "invalid color '{}' (expected #rrggbb or #rrggbbaa)",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hex_colors() {
+        assert_eq!("#ff8000".parse(), Ok(Color::rgb(0xff, 0x80, 0x00)));
+        assert_eq!("00ff0080".parse::<Color>().map(|c| c.a), Ok(0x80));
+        assert!("#12345".parse::<Color>().is_err());
+        assert!("#gg0000".parse::<Color>().is_err());
+        assert_eq!(Color::rgb(1, 2, 3).to_string(), "#010203");
+    }
+}
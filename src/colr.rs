@@ -0,0 +1,375 @@
+//! COLR/CPAL color glyphs (emoji) split into one flat part per palette color
+
+use anyhow::Result;
+use lyon_path::math::Point;
+use lyon_path::Path;
+use ttf_parser::colr::{ClipBox, CompositeMode, Paint, Painter};
+use ttf_parser::{Face, GlyphId, OutlineBuilder, RgbaColor, Transform};
+
+use crate::cancel::CancelToken;
+use crate::color::Color;
+use crate::layout::{find_glyph, glyph_face, glyph_outline, LyonOutlineBuilder, PlacedGlyph};
+use crate::mesh::Mesh2D;
+use crate::outline;
+
+/// One painted layer of a color glyph
+#[derive(Debug, Clone, Copy)]
+pub struct ColorLayer {
+    /// Glyph whose outline is filled
+    pub gid: GlyphId,
+    pub color: Color,
+    /// Placement of the outline within the color glyph, in font units
+    pub transform: Transform,
+}
+
+/// Records the outline and transform of every paint; gradients take the
+/// color of their first stop, as a print has one filament per area
+struct LayerCollector<'a, 'f> {
+    face: &'f Face<'a>,
+    palette: u16,
+    transforms: Vec<Transform>,
+    outline: Option<(GlyphId, Transform)>,
+    layers: Vec<ColorLayer>,
+}
+
+impl LayerCollector<'_, '_> {
+    fn transform(&self) -> Transform {
+        self.transforms.last().copied().unwrap_or_default()
+    }
+
+    fn push(&mut self, transform: Transform) {
+        self.transforms
+            .push(Transform::combine(self.transform(), transform));
+    }
+}
+
+impl<'a> Painter<'a> for LayerCollector<'a, '_> {
+    fn outline_glyph(&mut self, glyph_id: GlyphId) {
+        self.outline = Some((glyph_id, self.transform()));
+    }
+
+    fn paint(&mut self, paint: Paint<'a>) {
+        let coords = self.face.variation_coordinates();
+        let color = match paint {
+            Paint::Solid(color) => Some(color),
+            Paint::LinearGradient(gradient) => {
+                gradient.stops(self.palette, coords).next().map(|s| s.color)
+            }
+            Paint::RadialGradient(gradient) => {
+                gradient.stops(self.palette, coords).next().map(|s| s.color)
+            }
+            Paint::SweepGradient(gradient) => {
+                gradient.stops(self.palette, coords).next().map(|s| s.color)
+            }
+        };
+        if let (Some((gid, transform)), Some(color)) = (self.outline, color) {
+            // Fully transparent layers paint nothing
+            if color.alpha > 0 {
+                self.layers.push(ColorLayer {
+                    gid,
+                    color: Color {
+                        r: color.red,
+                        g: color.green,
+                        b: color.blue,
+                        a: 0xff,
+                    },
+                    transform,
+                });
+            }
+        }
+    }
+
+    fn push_clip(&mut self) {}
+
+    fn push_clip_box(&mut self, _clipbox: ClipBox) {}
+
+    fn pop_clip(&mut self) {}
+
+    fn push_layer(&mut self, _mode: CompositeMode) {}
+
+    fn pop_layer(&mut self) {}
+
+    fn push_translate(&mut self, tx: f32, ty: f32) {
+        self.push(Transform::new_translate(tx, ty));
+    }
+
+    fn push_scale(&mut self, sx: f32, sy: f32) {
+        self.push(Transform::new(sx, 0.0, 0.0, sy, 0.0, 0.0));
+    }
+
+    fn push_rotate(&mut self, angle: f32) {
+        // COLR angles are in multiples of 180°
+        let (sin, cos) = (angle * std::f32::consts::PI).sin_cos();
+        self.push(Transform::new(cos, sin, -sin, cos, 0.0, 0.0));
+    }
+
+    fn push_skew(&mut self, skew_x: f32, skew_y: f32) {
+        let (x, y) = (skew_x * std::f32::consts::PI, skew_y * std::f32::consts::PI);
+        self.push(Transform::new(1.0, y.tan(), -x.tan(), 1.0, 0.0, 0.0));
+    }
+
+    fn push_transform(&mut self, transform: Transform) {
+        self.push(transform);
+    }
+
+    fn pop_transform(&mut self) {
+        self.transforms.pop();
+    }
+}
+
+/// Layers of a COLR glyph from the bottom up, using `palette` of the CPAL
+/// table (foreground-colored layers come out black); `None` for glyphs
+/// without color layers
+pub fn color_layers(face: &Face<'_>, gid: GlyphId, palette: u16) -> Option<Vec<ColorLayer>> {
+    if !face.is_color_glyph(gid) {
+        return None;
+    }
+    let mut collector = LayerCollector {
+        face,
+        palette,
+        transforms: Vec::new(),
+        outline: None,
+        layers: Vec::new(),
+    };
+    let black = RgbaColor::new(0, 0, 0, 0xff);
+    face.paint_color_glyph(gid, palette, black, &mut collector)?;
+    Some(collector.layers).filter(|layers| !layers.is_empty())
+}
+
+/// Applies a COLR transform to outline points before passing them on
+struct Transformed<'b, B: OutlineBuilder> {
+    inner: &'b mut B,
+    transform: Transform,
+}
+
+impl<B: OutlineBuilder> Transformed<'_, B> {
+    fn map(&self, x: f32, y: f32) -> (f32, f32) {
+        let t = &self.transform;
+        (t.a * x + t.c * y + t.e, t.b * x + t.d * y + t.f)
+    }
+}
+
+impl<B: OutlineBuilder> OutlineBuilder for Transformed<'_, B> {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.map(x, y);
+        self.inner.move_to(x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.map(x, y);
+        self.inner.line_to(x, y);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (x1, y1) = self.map(x1, y1);
+        let (x, y) = self.map(x, y);
+        self.inner.quad_to(x1, y1, x, y);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (x1, y1) = self.map(x1, y1);
+        let (x2, y2) = self.map(x2, y2);
+        let (x, y) = self.map(x, y);
+        self.inner.curve_to(x1, y1, x2, y2, x, y);
+    }
+
+    fn close(&mut self) {
+        self.inner.close();
+    }
+}
+
+/// Whether any character of `text` is drawn from color layers
+pub fn has_color_glyphs(face: &Face<'_>, fallbacks: &[Face<'_>], text: &str) -> bool {
+    text.chars().any(|ch| {
+        find_glyph(face, fallbacks, ch)
+            .is_some_and(|(font, gid)| glyph_face(face, fallbacks, font).0.is_color_glyph(gid))
+    })
+}
+
+/// Meshes of the laid-out text split by color: the plain glyphs first with
+/// no color, then one mesh per layer color in order of first use. Every
+/// layer loses the area the layers painted over it cover, so the parts sit
+/// side by side like the colors of the emoji and never overlap.
+pub fn color_meshes(
+    face: &Face<'_>,
+    fallbacks: &[Face<'_>],
+    glyphs: &[PlacedGlyph],
+    scale: f32,
+    shear: f32,
+    tolerance: f32,
+    cancel: &CancelToken,
+) -> Result<Vec<(Option<Color>, Mesh2D)>> {
+    let mut plain = Path::builder();
+    let mut layers: Vec<(Color, Vec<Vec<Point>>)> = Vec::new();
+    for glyph in glyphs {
+        let (glyph_face, units) = glyph.face(face, fallbacks);
+        let glyph_scale = scale * units;
+        let Some(color_layers) = color_layers(glyph_face, glyph.gid, 0) else {
+            let mut placed = LyonOutlineBuilder {
+                builder: &mut plain,
+                offset_x: glyph.x,
+                offset_y: glyph.y,
+                scale: glyph_scale,
+                shear,
+                angle: glyph.angle,
+            };
+            glyph_outline(glyph_face, glyph.gid, &mut placed);
+            continue;
+        };
+        for layer in color_layers {
+            let mut builder = Path::builder();
+            let mut placed = LyonOutlineBuilder {
+                builder: &mut builder,
+                offset_x: glyph.x,
+                offset_y: glyph.y,
+                scale: glyph_scale,
+                shear,
+                angle: glyph.angle,
+            };
+            let mut transformed = Transformed {
+                inner: &mut placed,
+                transform: layer.transform,
+            };
+            glyph_face.outline_glyph(layer.gid, &mut transformed);
+            layers.push((layer.color, outline::from_path(&builder.build(), tolerance)));
+        }
+    }
+    cancel.check()?;
+
+    // Top layer first, each cut by everything above it
+    let mut covered: Vec<Vec<Point>> = Vec::new();
+    let mut visible: Vec<(Color, Vec<Vec<Point>>)> = Vec::new();
+    for (color, outlines) in layers.into_iter().rev() {
+        let shown = outline::difference(&outlines, &covered);
+        covered = outline::union(&covered, &outlines);
+        visible.push((color, shown));
+    }
+    visible.reverse();
+
+    let mut by_color: Vec<(Color, Vec<Vec<Point>>)> = Vec::new();
+    for (color, shown) in visible {
+        match by_color.iter_mut().find(|(c, _)| *c == color) {
+            Some((_, outlines)) => *outlines = outline::union(outlines, &shown),
+            None => by_color.push((color, shown)),
+        }
+    }
+
+    let mut meshes = Vec::new();
+    let plain = outline::from_path(&plain.build(), tolerance);
+    if !plain.is_empty() {
+        meshes.push((None, outline::fill(&plain, tolerance, cancel)?));
+    }
+    for (color, outlines) in by_color {
+        if !outlines.is_empty() {
+            meshes.push((Some(color), outline::fill(&outlines, tolerance, cancel)?));
+        }
+    }
+    Ok(meshes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font;
+    use crate::layout::{layout_glyphs, Shaping};
+    use crate::woff::{with_tables, Table};
+
+    /// The embedded font with a COLRv0 'A' made of a red 'O' under a blue 'I'
+    fn color_font() -> Vec<u8> {
+        let base = font::EMBEDDED_FONT;
+        let face = font::parse_face(base, 0).unwrap();
+        let gid = |ch| face.glyph_index(ch).unwrap().0;
+        let be16 = |v: u16| v.to_be_bytes();
+
+        let mut colr = Vec::new();
+        for word in [0, 1] {
+            colr.extend(be16(word));
+        }
+        colr.extend(14u32.to_be_bytes());
+        colr.extend(20u32.to_be_bytes());
+        colr.extend(be16(2));
+        for word in [gid('A'), 0, 2, gid('O'), 0, gid('I'), 1] {
+            colr.extend(be16(word));
+        }
+        let mut cpal = Vec::new();
+        for word in [0, 2, 1, 2] {
+            cpal.extend(be16(word));
+        }
+        cpal.extend(14u32.to_be_bytes());
+        cpal.extend(be16(0));
+        cpal.extend([0, 0, 0xff, 0xff, 0xff, 0, 0, 0xff]);
+
+        with_tables(
+            base,
+            vec![
+                Table {
+                    tag: *b"COLR",
+                    data: colr,
+                },
+                Table {
+                    tag: *b"CPAL",
+                    data: cpal,
+                },
+            ],
+        )
+    }
+
+    #[test]
+    fn layers_come_bottom_up_with_palette_colors() {
+        let bytes = color_font();
+        let face = font::parse_face(&bytes, 0).unwrap();
+        let layers = color_layers(&face, face.glyph_index('A').unwrap(), 0).unwrap();
+        assert_eq!(layers.len(), 2);
+        assert_eq!(layers[0].gid, face.glyph_index('O').unwrap());
+        assert_eq!(layers[0].color, Color::rgb(0xff, 0, 0));
+        assert_eq!(layers[1].color, Color::rgb(0, 0, 0xff));
+        assert!(color_layers(&face, face.glyph_index('B').unwrap(), 0).is_none());
+    }
+
+    #[test]
+    fn color_glyphs_split_into_parts_per_color() {
+        let bytes = color_font();
+        let face = font::parse_face(&bytes, 0).unwrap();
+        let layout = layout_glyphs(&face, &[], "AB", 0.1, 0.0, 0.0, &Shaping::default());
+        assert!(has_color_glyphs(&face, &[], "AB") && !has_color_glyphs(&face, &[], "B"));
+
+        let cancel = CancelToken::new();
+        let meshes = color_meshes(&face, &[], &layout.glyphs, 0.1, 0.0, 0.01, &cancel).unwrap();
+        let colors: Vec<_> = meshes.iter().map(|(color, _)| *color).collect();
+        assert_eq!(
+            colors,
+            [
+                None,
+                Some(Color::rgb(0xff, 0, 0)),
+                Some(Color::rgb(0, 0, 0xff))
+            ]
+        );
+
+        // The red 'O' loses the part of its ring under the blue 'I'
+        let area = |mesh: &Mesh2D| -> f32 {
+            outline::from_mesh(mesh)
+                .iter()
+                .map(|o| outline::area(o))
+                .sum()
+        };
+        let mut builder = Path::builder();
+        let o = face.glyph_index('O').unwrap();
+        face.outline_glyph(
+            o,
+            &mut LyonOutlineBuilder {
+                builder: &mut builder,
+                offset_x: 0.0,
+                offset_y: 0.0,
+                scale: 0.1,
+                shear: 0.0,
+                angle: 0.0,
+            },
+        );
+        let whole: f32 = outline::from_path(&builder.build(), 0.01)
+            .iter()
+            .map(|o| outline::area(o))
+            .sum();
+        let red = area(&meshes[1].1);
+        assert!(red < whole * 0.99 && red > whole * 0.5, "{} {}", red, whole);
+    }
+}
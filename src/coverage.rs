@@ -0,0 +1,151 @@
+//! Glyph coverage of the text: which characters the fonts draw and which
+//! would be skipped as missing, for `wagyan coverage` and `--strict`
+
+use std::io::Write;
+
+use anyhow::Result;
+use serde_json::json;
+use ttf_parser::Face;
+
+use crate::layout::find_glyph;
+use crate::{font, Options};
+
+/// Distinct characters of the text (line breaks aside) by the font that
+/// draws them, in order of first appearance
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Coverage {
+    /// Characters `--font` has
+    pub primary: Vec<char>,
+    /// Characters taken from a fallback font, with its number (1 for the
+    /// first `--fallback-font`)
+    pub fallback: Vec<(char, usize)>,
+    /// Characters no font has
+    pub missing: Vec<char>,
+}
+
+/// Sort the characters of `text` by the font that draws them
+pub fn coverage(face: &Face<'_>, fallbacks: &[Face<'_>], text: &str) -> Coverage {
+    let mut seen = Vec::new();
+    let mut coverage = Coverage::default();
+    for ch in text.chars().filter(|&ch| ch != '\n') {
+        if seen.contains(&ch) {
+            continue;
+        }
+        seen.push(ch);
+        match find_glyph(face, fallbacks, ch) {
+            Some((0, _)) => coverage.primary.push(ch),
+            Some((font, _)) => coverage.fallback.push((ch, font)),
+            None => coverage.missing.push(ch),
+        }
+    }
+    coverage
+}
+
+/// With `options.strict`, fail when the fonts lack any character of the
+/// text instead of skipping it
+pub fn ensure_covered(face: &Face<'_>, options: &Options) -> Result<()> {
+    if !options.strict {
+        return Ok(());
+    }
+    let missing = coverage(
+        face,
+        &font::fallback_faces(&options.fallback_data()),
+        &options.laid_out_text(),
+    )
+    .missing;
+    anyhow::ensure!(
+        missing.is_empty(),
+        "--strict: the font has no glyph for {}",
+        describe(&missing).join(", ")
+    );
+    Ok(())
+}
+
+/// `'あ' (U+3042)` for each character
+fn describe(chars: &[char]) -> Vec<String> {
+    chars
+        .iter()
+        .map(|&ch| format!("'{}' (U+{:04X})", ch, ch as u32))
+        .collect()
+}
+
+impl Coverage {
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let codepoint = |ch: char| format!("U+{:04X}", ch as u32);
+        let fallback: Vec<_> = self
+            .fallback
+            .iter()
+            .map(|&(ch, font)| {
+                json!({
+                    "char": ch.to_string(),
+                    "codepoint": codepoint(ch),
+                    "fallback": font,
+                })
+            })
+            .collect();
+        let missing: Vec<_> = self
+            .missing
+            .iter()
+            .map(|&ch| json!({ "char": ch.to_string(), "codepoint": codepoint(ch) }))
+            .collect();
+        json!({
+            "characters": self.primary.len() + self.fallback.len() + self.missing.len(),
+            "primary": self.primary.len(),
+            "fallback": fallback,
+            "missing": missing,
+            "complete": self.is_complete(),
+        })
+    }
+
+    /// Human-readable report
+    pub fn write_text<W: Write>(&self, mut out: W) -> Result<()> {
+        let total = self.primary.len() + self.fallback.len() + self.missing.len();
+        writeln!(
+            out,
+            "{} distinct characters: {} from the font, {} from fallback fonts, {} missing",
+            total,
+            self.primary.len(),
+            self.fallback.len(),
+            self.missing.len()
+        )?;
+        for &(ch, font) in &self.fallback {
+            writeln!(out, "  fallback {}: {}", font, describe(&[ch]).remove(0))?;
+        }
+        if !self.missing.is_empty() {
+            writeln!(out, "missing: {}", describe(&self.missing).join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font;
+
+    #[test]
+    fn characters_are_sorted_by_the_font_that_draws_them() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let coverage = coverage(&face, &[], "aあa\n\u{13000}");
+        assert_eq!(coverage.primary, ['a', 'あ']);
+        assert_eq!(coverage.missing, ['\u{13000}']);
+        assert!(!coverage.is_complete());
+        assert_eq!(coverage.to_json()["missing"][0]["codepoint"], "U+13000");
+
+        let mut options = Options {
+            text: "a\u{13000}".into(),
+            ..Options::default()
+        };
+        assert!(ensure_covered(&face, &options).is_ok());
+        options.strict = true;
+        let err = ensure_covered(&face, &options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "--strict: the font has no glyph for '\u{13000}' (U+13000)"
+        );
+    }
+}
@@ -0,0 +1,385 @@
+//! Bending laid-out text around a circle (`--arc-radius`, `--arc-angle`),
+//! for the legends of coins and medals, or along any SVG path
+//! (`--path-file`), for wavy banners.
+//!
+//! Glyphs keep their shape: the middle of each glyph's advance moves onto
+//! the curve, as far along it as it was along the line, and the glyph
+//! turns to follow the tangent there, so only the baseline bends. Lines
+//! after the first follow parallel curves, the distance between them
+//! unchanged.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use lyon_path::iterator::PathIterator;
+use lyon_path::math::Point;
+use lyon_path::Event;
+use ttf_parser::Face;
+use usvg::tiny_skia_path::PathSegment;
+use usvg::{Group, Node};
+
+use crate::layout::{hor_advance, Layout};
+use crate::DEFAULT_TOLERANCE;
+
+/// Millimetres in a CSS pixel, the unit SVG coordinates are in
+const MM_PER_PX: f32 = 25.4 / 96.0;
+
+/// Which half of the circle the text reads along
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum ArcSide {
+    /// Over the top, clockwise, letters standing out from the centre
+    #[default]
+    Top,
+    /// Under the bottom, counter-clockwise, letters pointing at the
+    /// centre, so they still read left to right
+    Bottom,
+}
+
+/// A circle for text to follow
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Arc {
+    /// Radius of the first line's baseline in mm; `None` takes the one
+    /// that makes the widest line span `angle`
+    pub radius: Option<f32>,
+    /// Angle the widest line spans in radians; with `radius` too the
+    /// glyphs spread out (or close up) to fill it. `None` keeps their
+    /// spacing
+    pub angle: Option<f32>,
+    pub side: ArcSide,
+}
+
+/// A curve for text to follow, flattened to points in mm with y up
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TextPath {
+    pub points: Vec<Point>,
+}
+
+impl TextPath {
+    /// The first path of the SVG file at `path`
+    pub fn load(path: &Path) -> Result<Self> {
+        let data =
+            std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        Self::from_svg(&data)
+            .with_context(|| format!("no path to lay text along in {}", path.display()))
+    }
+
+    /// The first subpath of the first path in SVG document `data`, with
+    /// shapes such as circles counting as paths. Elements with neither fill
+    /// nor stroke are not drawn, so they are not found either.
+    pub fn from_svg(data: &[u8]) -> Result<Self> {
+        let tree = usvg::Tree::from_data(data, &usvg::Options::default())?;
+        let path = first_path(tree.root()).context("the document has no path")?;
+        let data = path
+            .data()
+            .clone()
+            .transform(path.abs_transform())
+            .context("the path has no size")?;
+        let mm = |p: usvg::tiny_skia_path::Point| Point::new(p.x * MM_PER_PX, -p.y * MM_PER_PX);
+        let mut builder = lyon_path::Path::builder();
+        let mut open = false;
+        for segment in data.segments() {
+            match segment {
+                PathSegment::MoveTo(_) if open => break,
+                PathSegment::MoveTo(p) => {
+                    builder.begin(mm(p));
+                    open = true;
+                }
+                PathSegment::LineTo(p) => {
+                    builder.line_to(mm(p));
+                }
+                PathSegment::QuadTo(p1, p) => {
+                    builder.quadratic_bezier_to(mm(p1), mm(p));
+                }
+                PathSegment::CubicTo(p1, p2, p) => {
+                    builder.cubic_bezier_to(mm(p1), mm(p2), mm(p));
+                }
+                PathSegment::Close => {
+                    builder.close();
+                    open = false;
+                    break;
+                }
+            }
+        }
+        if open {
+            builder.end(false);
+        }
+        let mut points: Vec<Point> = Vec::new();
+        for event in builder.build().iter().flattened(DEFAULT_TOLERANCE) {
+            let to = match event {
+                Event::Begin { at } => at,
+                Event::Line { to, .. } => to,
+                Event::End {
+                    first, close: true, ..
+                } => first,
+                _ => continue,
+            };
+            if points.last() != Some(&to) {
+                points.push(to);
+            }
+        }
+        anyhow::ensure!(points.len() > 1, "the path has no length");
+        Ok(Self { points })
+    }
+
+    /// Point `distance` mm along the path and the direction it heads there
+    /// (radians counter-clockwise from +x); beyond either end the path
+    /// carries on straight
+    fn at(&self, distance: f32) -> (Point, f32) {
+        let mut start = 0.0;
+        let segments = self.points.windows(2);
+        let last = segments.len() - 1;
+        for (i, pair) in segments.enumerate() {
+            let (a, b) = (pair[0], pair[1]);
+            let length = (b - a).length();
+            if distance < start + length || i == last {
+                let t = if i == 0 {
+                    distance
+                } else {
+                    distance.max(start)
+                } - start;
+                let direction = (b - a) / length;
+                return (a + direction * t, direction.y.atan2(direction.x));
+            }
+            start += length;
+        }
+        unreachable!("a text path has at least two points")
+    }
+}
+
+/// First path drawn in `group` or its descendants
+fn first_path(group: &Group) -> Option<&usvg::Path> {
+    group.children().iter().find_map(|node| match node {
+        Node::Group(group) => first_path(group),
+        Node::Path(path) => Some(path.as_ref()),
+        _ => None,
+    })
+}
+
+/// Move and turn every glyph of horizontal `layout` onto a curve: `at`
+/// takes how far along the curve the middle of a glyph goes and how far
+/// above the first baseline it was, and gives the point for it and the
+/// angle to turn the glyph by. `left` is the position along the lines
+/// that maps to the start of the curve.
+fn place_along(
+    face: &Face<'_>,
+    fallbacks: &[Face<'_>],
+    layout: &mut Layout,
+    scale: f32,
+    left: f32,
+    at: impl Fn(f32, f32) -> (Point, f32),
+) {
+    let Some(baseline) = layout.lines.first().map(|line| line.baseline) else {
+        return;
+    };
+    for glyph in &mut layout.glyphs {
+        let (glyph_face, units) = glyph.face(face, fallbacks);
+        let half = hor_advance(glyph_face, glyph.gid).unwrap_or(0) as f32 * scale * units / 2.0;
+        let (middle, angle) = at(glyph.x + half - left, glyph.y - baseline);
+        let (sin, cos) = angle.sin_cos();
+        glyph.x = middle.x - half * cos;
+        glyph.y = middle.y - half * sin;
+        glyph.angle = angle;
+    }
+}
+
+/// Left end and width of the widest extent of `layout`'s lines
+fn extent(layout: &Layout) -> (f32, f32) {
+    let left = layout.lines.iter().map(|line| line.offset);
+    let right = layout.lines.iter().map(|line| line.offset + line.width);
+    let left = left.fold(f32::INFINITY, f32::min);
+    (left, right.fold(f32::NEG_INFINITY, f32::max) - left)
+}
+
+/// Lay horizontal `layout` along `path`, the left end of its lines at the
+/// start of the path and the first baseline on it
+pub fn follow(
+    face: &Face<'_>,
+    fallbacks: &[Face<'_>],
+    layout: &mut Layout,
+    scale: f32,
+    path: &TextPath,
+) {
+    if layout.lines.is_empty() {
+        return;
+    }
+    let (left, _) = extent(layout);
+    place_along(face, fallbacks, layout, scale, left, |along, rise| {
+        let (point, angle) = path.at(along);
+        let (sin, cos) = angle.sin_cos();
+        (
+            Point::new(point.x - rise * sin, point.y + rise * cos),
+            angle,
+        )
+    });
+}
+
+/// Bend horizontal `layout` around `arc`: the middle of the text stays
+/// where it is, on the top (or bottom) of a circle that touches the first
+/// baseline there
+pub fn bend(face: &Face<'_>, fallbacks: &[Face<'_>], layout: &mut Layout, scale: f32, arc: Arc) {
+    let Some(first) = layout.lines.first() else {
+        return;
+    };
+    let baseline = first.baseline;
+    let (left, width) = extent(layout);
+    let middle = left + width / 2.0;
+    let (radius, spread) = match (arc.radius, arc.angle) {
+        (Some(radius), Some(angle)) if width > 0.0 => (radius, angle * radius / width),
+        (Some(radius), _) => (radius, 1.0),
+        (None, Some(angle)) if width > 0.0 => (width / angle, 1.0),
+        _ => return,
+    };
+    // +1 turns the text clockwise over the top, -1 the other way under it
+    let side = match arc.side {
+        ArcSide::Top => 1.0,
+        ArcSide::Bottom => -1.0,
+    };
+    let centre = (middle, baseline - side * radius);
+    place_along(face, fallbacks, layout, scale, middle, |along, rise| {
+        let reach = radius + side * rise;
+        let angle = -side * along * spread / radius;
+        let (sin, cos) = angle.sin_cos();
+        let point = Point::new(centre.0 - side * reach * sin, centre.1 + side * reach * cos);
+        (point, angle)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{font, layout, Options};
+
+    #[test]
+    fn glyphs_sit_on_the_circle_facing_its_tangent() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let options = Options {
+            text: "OOOOO".to_string(),
+            center: false,
+            ..Options::default()
+        };
+        let scale = options.scale(&face);
+        for side in [ArcSide::Top, ArcSide::Bottom] {
+            let mut bent = layout(&face, &options);
+            let straight = bent.glyphs.clone();
+            let arc = Arc {
+                radius: Some(50.0),
+                angle: None,
+                side,
+            };
+            bend(&face, &[], &mut bent, scale, arc);
+            let width = bent.lines[0].width;
+            let sign = if side == ArcSide::Top { 1.0 } else { -1.0 };
+            let centre = (width / 2.0, bent.lines[0].baseline - sign * 50.0);
+            let half = hor_advance(&face, straight[0].gid).unwrap() as f32 * scale / 2.0;
+            for (glyph, before) in bent.glyphs.iter().zip(&straight) {
+                // The middle of the advance is on the circle ...
+                let mid = glyph.place(lyon_path::math::Point::new(half, 0.0));
+                let distance = ((mid.x - centre.0).powi(2) + (mid.y - centre.1).powi(2)).sqrt();
+                assert!((distance - 50.0).abs() < 1e-3, "{}", distance);
+                // ... as far round it as it was along the line
+                let along = before.x + half - width / 2.0;
+                assert!((glyph.angle + sign * along / 50.0).abs() < 1e-5);
+            }
+            // Both halves read left to right
+            assert!(bent.glyphs[0].x < bent.glyphs[4].x);
+        }
+    }
+
+    #[test]
+    fn svg_paths_are_read_in_mm_with_y_up() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="100mm" height="50mm" viewBox="0 0 100 50">
+            <g transform="translate(10 40)"><path d="M0 0 L30 0 L30 -20 M50 0 L60 0" stroke="black" fill="none"/></g>
+        </svg>"#;
+        let path = TextPath::from_svg(svg.as_bytes()).unwrap();
+        let points: Vec<(f32, f32)> = path
+            .points
+            .iter()
+            .map(|p| ((p.x * 1e3).round() / 1e3, (p.y * 1e3).round() / 1e3))
+            .collect();
+        assert_eq!(points, [(10.0, -40.0), (40.0, -40.0), (40.0, -20.0)]);
+        assert!(TextPath::from_svg(br#"<svg xmlns="http://www.w3.org/2000/svg"/>"#).is_err());
+    }
+
+    #[test]
+    fn glyphs_follow_the_path_by_arc_length() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let options = Options {
+            text: "IIIIIIII".to_string(),
+            center: false,
+            ..Options::default()
+        };
+        let scale = options.scale(&face);
+        // Along +x, then straight up
+        let path = TextPath {
+            points: vec![
+                Point::new(0.0, 0.0),
+                Point::new(100.0, 0.0),
+                Point::new(100.0, 100.0),
+            ],
+        };
+        let mut laid = layout(&face, &options);
+        let straight = laid.glyphs.clone();
+        follow(&face, &[], &mut laid, scale, &path);
+        let half = hor_advance(&face, straight[0].gid).unwrap() as f32 * scale / 2.0;
+        for (glyph, before) in laid.glyphs.iter().zip(&straight) {
+            let along = before.x + half;
+            let mid = glyph.place(Point::new(half, 0.0));
+            let expected = if along < 100.0 {
+                (along, 0.0, 0.0)
+            } else {
+                (100.0, along - 100.0, std::f32::consts::FRAC_PI_2)
+            };
+            assert!(
+                (mid.x - expected.0).abs() < 1e-3,
+                "{:?} {:?}",
+                mid,
+                expected
+            );
+            assert!(
+                (mid.y - expected.1).abs() < 1e-3,
+                "{:?} {:?}",
+                mid,
+                expected
+            );
+            assert!((glyph.angle - expected.2).abs() < 1e-5);
+        }
+        assert!(
+            laid.glyphs.last().unwrap().angle > 1.0,
+            "the text turns the corner"
+        );
+    }
+
+    #[test]
+    fn an_angle_alone_sets_the_radius() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let options = Options {
+            text: "WAGYAN".to_string(),
+            ..Options::default()
+        };
+        let scale = options.scale(&face);
+        let mut bent = layout(&face, &options);
+        let width = bent.lines[0].width;
+        let arc = Arc {
+            radius: None,
+            angle: Some(std::f32::consts::PI),
+            side: ArcSide::Top,
+        };
+        bend(&face, &[], &mut bent, scale, arc);
+        let mut spread = layout(&face, &options);
+        let arc = Arc {
+            radius: Some(width / std::f32::consts::PI),
+            angle: None,
+            side: ArcSide::Top,
+        };
+        bend(&face, &[], &mut spread, scale, arc);
+        for (a, b) in bent.glyphs.iter().zip(&spread.glyphs) {
+            assert!((a.x - b.x).abs() < 1e-3 && (a.angle - b.angle).abs() < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Door sign preset: a rounded plate with a chamfered top edge, centered
+//! raised text and either two screw holes or a flat back for adhesive pads
+
+use anyhow::Result;
+use lyon_path::math::Point;
+use ttf_parser::Face;
+
+use crate::cancel::CancelToken;
+use crate::nameplate::{centered_text, chamfer_plate, raised_parts, Outline};
+use crate::{Options, Part};
+
+/// How the sign is fixed to the door
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Mount {
+    /// Two screw holes in the top corners
+    Holes,
+    /// No holes: a flat back for double-sided tape
+    Adhesive,
+}
+
+/// Door sign settings; the lettering itself comes from [`Options`]
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct DoorSign {
+    /// Plate thickness in mm, chamfer included
+    pub thickness: f32,
+    /// Space between the text and the plate edge in mm
+    pub margin: f32,
+    pub corner_radius: f32,
+    /// Size of the 45° chamfer along the top edge in mm (0 disables)
+    pub chamfer: f32,
+    pub mount: Mount,
+    pub hole_diameter: f32,
+}
+
+impl Default for DoorSign {
+    fn default() -> Self {
+        Self {
+            thickness: 4.0,
+            margin: 10.0,
+            corner_radius: 5.0,
+            chamfer: 1.0,
+            mount: Mount::Holes,
+            hole_diameter: 4.0,
+        }
+    }
+}
+
+/// Build the plate (straight base, then the chamfered top layer) and the lettering
+pub fn doorsign_parts(
+    face: &Face<'_>,
+    options: &Options,
+    sign: &DoorSign,
+    cancel: &CancelToken,
+) -> Result<Vec<Part>> {
+    let thickness = options.snap_to_layers(sign.thickness);
+    let chamfer = options.snap_to_layers(sign.chamfer);
+    anyhow::ensure!(
+        chamfer < thickness,
+        "a {} mm chamfer needs a plate thicker than that (plate is {} mm)",
+        chamfer,
+        thickness
+    );
+    let (text, text_w, text_h) = centered_text(face, options, cancel)?;
+
+    let (half_w, half_h) = (text_w + sign.margin, text_h + sign.margin);
+    let corner = sign.margin * 0.5;
+    let outline = Outline {
+        half_w,
+        half_h,
+        radius: sign.corner_radius.min(half_w).min(half_h),
+        holes: match sign.mount {
+            Mount::Holes => vec![
+                Point::new(-half_w + corner, half_h - corner),
+                Point::new(half_w - corner, half_h - corner),
+            ],
+            Mount::Adhesive => Vec::new(),
+        },
+        hole_radius: sign.hole_diameter * 0.5,
+        slots: Vec::new(),
+    };
+    let tolerance = options.tolerance(face);
+    let mut parts = raised_parts(
+        text,
+        &outline,
+        thickness - chamfer,
+        0.0,
+        options,
+        tolerance,
+        cancel,
+    )?;
+    chamfer_plate(&mut parts, chamfer, options.text_depth());
+    Ok(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font;
+
+    #[test]
+    fn chamfered_plate_stacks_under_the_text() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let options = Options {
+            text: "会議室A".into(),
+            size: 20.0,
+            depth: 1.0,
+            ..Default::default()
+        };
+        let sign = DoorSign::default();
+        let parts = doorsign_parts(&face, &options, &sign, &CancelToken::new()).unwrap();
+        let names: Vec<&str> = parts.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["plate", "chamfer", "text"]);
+
+        // Base bottom at -thickness below the text, chamfer directly under the text
+        let bottom = |p: &Part| p.z_offset - p.depth * 0.5;
+        let top = |p: &Part| p.z_offset + p.depth * 0.5;
+        assert!((bottom(&parts[0]) - (-0.5 - sign.thickness)).abs() < 1e-5);
+        assert!((top(&parts[0]) - bottom(&parts[1])).abs() < 1e-5);
+        assert!((top(&parts[1]) - bottom(&parts[2])).abs() < 1e-5);
+        assert_eq!(parts[1].chamfer, sign.chamfer);
+    }
+}
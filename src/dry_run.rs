@@ -0,0 +1,219 @@
+//! `--dry-run`: layout-only report (bounding box, lines, triangle estimate)
+
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use lyon_path::math::Point;
+use ttf_parser::{Face, OutlineBuilder};
+
+use crate::layout::{glyph_bounds, glyph_outline, Layout};
+use crate::mesh::Anchor;
+
+/// Settings that affect the reported geometry
+pub struct DryRunParams<'a> {
+    pub scale: f32,
+    /// Slant of the glyphs (see [`crate::Options::shear`])
+    pub shear: f32,
+    pub tolerance: f32,
+    pub depth: f32,
+    pub plate: f32,
+    pub plate_margin: f32,
+    pub center: bool,
+    /// Point of the text `center` puts on the origin
+    pub anchor: Anchor,
+    pub outputs: &'a [PathBuf],
+    /// Fallback faces the layout took glyphs from
+    pub fallbacks: &'a [Face<'a>],
+}
+
+/// Counts flattened outline points using Wang's formula for curve subdivision
+struct SegmentCounter {
+    /// Tolerance expressed in font units
+    tolerance: f32,
+    last: (f32, f32),
+    points: usize,
+}
+
+impl SegmentCounter {
+    fn add_curve(&mut self, deviation: f32, end: (f32, f32)) {
+        let n = (deviation / self.tolerance).sqrt().ceil().max(1.0);
+        self.points += n as usize;
+        self.last = end;
+    }
+}
+
+fn norm(x: f32, y: f32) -> f32 {
+    (x * x + y * y).sqrt()
+}
+
+impl OutlineBuilder for SegmentCounter {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.last = (x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.points += 1;
+        self.last = (x, y);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (x0, y0) = self.last;
+        let d = norm(x0 - 2.0 * x1 + x, y0 - 2.0 * y1 + y);
+        self.add_curve(d / 8.0, (x, y));
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (x0, y0) = self.last;
+        let d = norm(x0 - 2.0 * x1 + x2, y0 - 2.0 * y1 + y2)
+            .max(norm(x1 - 2.0 * x2 + x, y1 - 2.0 * y2 + y));
+        self.add_curve(d * 3.0 / 4.0, (x, y));
+    }
+
+    fn close(&mut self) {}
+}
+
+/// Estimate the extruded triangle count: each outline point contributes
+/// roughly one triangle per cap and two side-wall triangles.
+fn estimate_triangles(face: &Face<'_>, layout: &Layout, params: &DryRunParams) -> usize {
+    let mut points = 0;
+    for glyph in &layout.glyphs {
+        let (glyph_face, units) = glyph.face(face, params.fallbacks);
+        let mut counter = SegmentCounter {
+            tolerance: (params.tolerance / (params.scale * units)).max(f32::EPSILON),
+            last: (0.0, 0.0),
+            points: 0,
+        };
+        glyph_outline(glyph_face, glyph.gid, &mut counter);
+        points += counter.points;
+    }
+    let plate = if params.plate > 0.0 { 12 } else { 0 };
+    points * 4 + plate
+}
+
+/// Layout bounding box (min_x, max_x, min_y, max_y) from glyph boxes
+pub(crate) fn layout_bounds(
+    face: &Face<'_>,
+    fallbacks: &[Face<'_>],
+    layout: &Layout,
+    scale: f32,
+    shear: f32,
+) -> Option<(f32, f32, f32, f32)> {
+    let mut bounds: Option<(f32, f32, f32, f32)> = None;
+    for glyph in &layout.glyphs {
+        let (glyph_face, units) = glyph.face(face, fallbacks);
+        let Some(rect) = glyph_bounds(glyph_face, glyph.gid) else {
+            continue;
+        };
+        let scale = scale * units;
+        // Slanting moves the top of the box sideways against the bottom,
+        // and turning the glyph moves every corner
+        for (x, y) in [
+            (rect.x_min, rect.y_min),
+            (rect.x_max, rect.y_min),
+            (rect.x_min, rect.y_max),
+            (rect.x_max, rect.y_max),
+        ] {
+            let (x, y) = (x as f32 + y as f32 * shear, y as f32);
+            let p = glyph.place(Point::new(x * scale, y * scale));
+            bounds = Some(match bounds {
+                Some((a, b, c, d)) => (a.min(p.x), b.max(p.x), c.min(p.y), d.max(p.y)),
+                None => (p.x, p.x, p.y, p.y),
+            });
+        }
+    }
+    bounds
+}
+
+/// Write a human-readable dry-run report
+pub fn report<W: Write>(
+    mut out: W,
+    face: &Face<'_>,
+    layout: &Layout,
+    params: &DryRunParams,
+) -> Result<()> {
+    writeln!(out, "dry run (nothing written)")?;
+    for path in params.outputs {
+        writeln!(out, "  output: {}", path.display())?;
+    }
+
+    writeln!(out, "  lines: {}", layout.lines.len())?;
+    for (i, line) in layout.lines.iter().enumerate() {
+        writeln!(
+            out,
+            "    {}: {:?} width {:.3} baseline {:.3}",
+            i + 1,
+            line.text,
+            line.width,
+            line.baseline
+        )?;
+    }
+
+    match layout_bounds(face, params.fallbacks, layout, params.scale, params.shear) {
+        Some((mut min_x, mut max_x, mut min_y, mut max_y)) => {
+            if params.center {
+                let (dx, dy) = params.anchor.offset((min_x, max_x, min_y, max_y));
+                min_x += dx;
+                max_x += dx;
+                min_y += dy;
+                max_y += dy;
+            }
+            let (mut z0, z1) = (-params.depth * 0.5, params.depth * 0.5);
+            if params.plate > 0.0 {
+                min_x -= params.plate_margin;
+                max_x += params.plate_margin;
+                min_y -= params.plate_margin;
+                max_y += params.plate_margin;
+                z0 -= params.plate;
+            }
+            writeln!(
+                out,
+                "  bounding box: x {:.3}..{:.3}, y {:.3}..{:.3}, z {:.3}..{:.3}",
+                min_x, max_x, min_y, max_y, z0, z1
+            )?;
+            writeln!(
+                out,
+                "  size: {:.3} x {:.3} x {:.3}",
+                max_x - min_x,
+                max_y - min_y,
+                z1 - z0
+            )?;
+        }
+        None => writeln!(out, "  bounding box: empty (no outlines)")?,
+    }
+
+    writeln!(
+        out,
+        "  estimated triangles: ~{}",
+        estimate_triangles(face, layout, params)
+    )?;
+
+    if layout.missing.is_empty() {
+        writeln!(out, "  missing glyphs: none")?;
+    } else {
+        let missing: Vec<String> = layout.missing.iter().map(|c| format!("'{}'", c)).collect();
+        writeln!(out, "  missing glyphs: {}", missing.join(", "))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count_quad(tolerance: f32) -> usize {
+        let mut counter = SegmentCounter {
+            tolerance,
+            last: (0.0, 0.0),
+            points: 0,
+        };
+        counter.quad_to(50.0, 100.0, 100.0, 0.0);
+        counter.points
+    }
+
+    #[test]
+    fn segment_counter_subdivides_curves_by_tolerance() {
+        assert!(count_quad(10.0) >= 1);
+        assert!(count_quad(0.1) > count_quad(10.0));
+    }
+}
@@ -0,0 +1,324 @@
+//! DXF (R12) writer for laser cutters: polylines on named layers
+
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::Result;
+use lyon_path::math::Point;
+
+use crate::output::{self, Units};
+use crate::{outline, Part, DEFAULT_TOLERANCE};
+
+/// Layer with the outlines the laser cuts through
+pub const CUT_LAYER: &str = "CUT";
+/// Layer with the outlines the laser engraves
+pub const ENGRAVE_LAYER: &str = "ENGRAVE";
+
+/// Layer with milling toolpaths (see `--toolpath-offset`)
+pub const TOOLPATH_LAYER: &str = "TOOLPATH";
+
+/// Named set of closed outlines and open paths
+#[derive(Debug, Clone)]
+pub struct DxfLayer {
+    pub name: String,
+    /// AutoCAD color index (1 red, 5 blue, ...); laser software often maps colors to settings
+    pub color: u8,
+    pub outlines: Vec<Vec<Point>>,
+    /// Open polylines, e.g. the centerlines of single-stroke lettering
+    pub paths: Vec<Vec<Point>>,
+}
+
+impl DxfLayer {
+    /// Multiply every coordinate by `factor`, e.g. to write in other units than mm
+    pub fn scale(&mut self, factor: f32) {
+        for line in self.outlines.iter_mut().chain(&mut self.paths) {
+            for p in line {
+                *p *= factor;
+            }
+        }
+    }
+}
+
+/// Split the parts for a laser: the first part and anything at its level
+/// (the plate, or the text when there is none) is cut, everything above it
+/// is engraved. The cut outlines grow by half the `kerf` so parts come out
+/// at their nominal size.
+pub fn laser_layers(parts: &[Part], kerf: f32) -> Vec<DxfLayer> {
+    let (cut, engrave) = split_cut_engrave(parts);
+    vec![
+        DxfLayer {
+            name: CUT_LAYER.to_string(),
+            color: 1,
+            outlines: compensate_kerf(&cut, kerf),
+            paths: Vec::new(),
+        },
+        DxfLayer {
+            name: ENGRAVE_LAYER.to_string(),
+            color: 5,
+            outlines: engrave,
+            paths: Vec::new(),
+        },
+    ]
+}
+
+/// [`laser_layers`] with every letter part on a layer of its own,
+/// `LETTER-1`, `LETTER-2`, ... in text order, so CAM software can give each
+/// letter its own toolpath. Letters keep the role they have there: red and
+/// kerf-compensated when cut, blue when engraved.
+pub fn letter_layers(parts: &[Part], kerf: f32) -> Vec<DxfLayer> {
+    let bottom = |part: &Part| part.z_offset - part.depth * 0.5;
+    let shapes: Vec<Part> = parts
+        .iter()
+        .filter(|part| part.letter.is_none())
+        .cloned()
+        .collect();
+    let mut layers = if shapes.is_empty() {
+        Vec::new()
+    } else {
+        laser_layers(&shapes, kerf)
+    };
+    for (i, part) in parts
+        .iter()
+        .filter(|part| part.letter.is_some())
+        .enumerate()
+    {
+        let cut = parts
+            .first()
+            .is_some_and(|base| (bottom(part) - bottom(base)).abs() < 1e-4);
+        let outlines = outline::from_mesh(&part.mesh);
+        layers.push(DxfLayer {
+            name: format!("LETTER-{}", i + 1),
+            color: if cut { 1 } else { 5 },
+            outlines: if cut {
+                compensate_kerf(&outlines, kerf)
+            } else {
+                outlines
+            },
+            paths: Vec::new(),
+        });
+    }
+    layers
+}
+
+/// Outlines to cut and to engrave (see [`laser_layers`])
+fn split_cut_engrave(parts: &[Part]) -> (Vec<Vec<Point>>, Vec<Vec<Point>>) {
+    let bottom = |part: &Part| part.z_offset - part.depth * 0.5;
+    let (mut cut, mut engrave) = (Vec::new(), Vec::new());
+    if let Some(base) = parts.first() {
+        for part in parts {
+            if (bottom(part) - bottom(base)).abs() < 1e-4 {
+                cut.extend(outline::from_mesh(&part.mesh));
+            } else if part.mesh.vertices != base.mesh.vertices {
+                // A copy of the plate (a chamfer layer) adds nothing to engrave
+                engrave.extend(outline::from_mesh(&part.mesh));
+            }
+        }
+    }
+    (cut, engrave)
+}
+
+/// Grow cut outlines by half the kerf: outer contours move outward and
+/// holes inward, so the beam's centerline runs outside the part
+pub fn compensate_kerf(outlines: &[Vec<Point>], kerf: f32) -> Vec<Vec<Point>> {
+    if kerf <= 0.0 {
+        return outlines.to_vec();
+    }
+    outline::offset(
+        &outline::union(outlines, &[]),
+        kerf * 0.5,
+        DEFAULT_TOLERANCE,
+    )
+}
+
+/// Warnings for cut features the kerf compensation destroys: holes
+/// narrower than the kerf close up, parts closer than it fuse together
+pub fn kerf_warnings(parts: &[Part], kerf: f32) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if kerf <= 0.0 {
+        return warnings;
+    }
+    let (cut, _) = split_cut_engrave(parts);
+    let counts = |outlines: &[Vec<Point>]| {
+        let holes = outlines.iter().filter(|c| outline::area(c) < 0.0).count();
+        (outlines.len() - holes, holes)
+    };
+    let (parts_before, holes_before) = counts(&outline::union(&cut, &[]));
+    let (parts_after, holes_after) = counts(&compensate_kerf(&cut, kerf));
+    if holes_after < holes_before {
+        warnings.push(format!(
+            "{} hole(s) in the cut outline are narrower than the {} mm kerf and close up; increase the size or lower --kerf",
+            holes_before - holes_after,
+            kerf
+        ));
+    }
+    if parts_after < parts_before {
+        warnings.push(format!(
+            "{} cut part(s) are less than the {} mm kerf apart and fuse together; add --spacing or lower --kerf",
+            parts_before - parts_after,
+            kerf
+        ));
+    }
+    warnings
+}
+
+pub fn write_dxf(path: &Path, layers: &[DxfLayer], units: Units) -> Result<()> {
+    output::write_atomically(path, |file| {
+        write_dxf_to_writer(BufWriter::new(file), layers, units)
+    })
+}
+
+/// `$INSUNITS` code of a drawing unit
+fn insunits(units: Units) -> u16 {
+    match units {
+        Units::Inch => 1,
+        Units::Mm => 4,
+        Units::Cm => 5,
+    }
+}
+
+/// Group code / value pairs, one per line each
+fn pair<W: Write>(writer: &mut W, code: u16, value: impl std::fmt::Display) -> Result<()> {
+    writeln!(writer, "{:>3}\n{}", code, value)?;
+    Ok(())
+}
+
+pub fn write_dxf_to_writer<W: Write>(
+    mut writer: W,
+    layers: &[DxfLayer],
+    units: Units,
+) -> Result<()> {
+    let w = &mut writer;
+    pair(w, 0, "SECTION")?;
+    pair(w, 2, "HEADER")?;
+    pair(w, 9, "$ACADVER")?;
+    pair(w, 1, "AC1009")?;
+    pair(w, 9, "$INSUNITS")?;
+    pair(w, 70, insunits(units))?;
+    pair(w, 0, "ENDSEC")?;
+
+    pair(w, 0, "SECTION")?;
+    pair(w, 2, "TABLES")?;
+    pair(w, 0, "TABLE")?;
+    pair(w, 2, "LAYER")?;
+    pair(w, 70, layers.len())?;
+    for layer in layers {
+        pair(w, 0, "LAYER")?;
+        pair(w, 2, &layer.name)?;
+        pair(w, 70, 0)?;
+        pair(w, 62, layer.color)?;
+        pair(w, 6, "CONTINUOUS")?;
+    }
+    pair(w, 0, "ENDTAB")?;
+    pair(w, 0, "ENDSEC")?;
+
+    pair(w, 0, "SECTION")?;
+    pair(w, 2, "ENTITIES")?;
+    for layer in layers {
+        let closed = layer.outlines.iter().map(|points| (points, true));
+        let open = layer.paths.iter().map(|points| (points, false));
+        for (points, closed) in closed.chain(open) {
+            pair(w, 0, "POLYLINE")?;
+            pair(w, 8, &layer.name)?;
+            pair(w, 66, 1)?;
+            pair(w, 10, 0.0)?;
+            pair(w, 20, 0.0)?;
+            pair(w, 30, 0.0)?;
+            pair(w, 70, u8::from(closed))?;
+            for p in points {
+                pair(w, 0, "VERTEX")?;
+                pair(w, 8, &layer.name)?;
+                pair(w, 10, p.x)?;
+                pair(w, 20, p.y)?;
+                pair(w, 30, 0.0)?;
+            }
+            pair(w, 0, "SEQEND")?;
+            pair(w, 8, &layer.name)?;
+        }
+    }
+    pair(w, 0, "ENDSEC")?;
+    pair(w, 0, "EOF")?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh;
+    use crate::{model_parts, Options};
+
+    #[test]
+    fn plate_is_cut_with_kerf_and_text_engraved() {
+        let options = Options {
+            plate: 1.0,
+            ..Default::default()
+        };
+        let parts = model_parts(mesh::rectangle_mesh(0.0, 10.0, 0.0, 4.0), &options);
+        let layers = laser_layers(&parts, 0.2);
+        assert_eq!(layers[0].name, CUT_LAYER);
+        assert_eq!(layers[0].outlines.len(), 1);
+        assert_eq!(layers[1].outlines.len(), 1);
+
+        // 14 x 8 plate grown by 0.1 on every side (rounded corners)
+        let cut = outline::area(&layers[0].outlines[0]);
+        let expected = 14.2 * 8.2 - 0.01 * (4.0 - std::f32::consts::PI);
+        assert!((cut - expected).abs() < 0.01, "{}", cut);
+
+        let mut out = Vec::new();
+        write_dxf_to_writer(&mut out, &layers, Units::Mm).unwrap();
+        let dxf = String::from_utf8(out).unwrap();
+        assert!(dxf.starts_with("  0\nSECTION\n"));
+        assert!(dxf.contains("$INSUNITS\n 70\n4\n"));
+        assert!(dxf.ends_with("  0\nEOF\n"));
+        assert_eq!(dxf.matches("\nPOLYLINE\n").count(), 2);
+        assert_eq!(dxf.matches("\nVERTEX\n  8\nENGRAVE\n").count(), 4);
+    }
+
+    #[test]
+    fn kerf_shrinks_holes_and_warns_when_they_close() {
+        // A 10 mm square with a 0.5 mm square hole
+        let mut hole = outline::rectangle(4.75, 5.25, 4.75, 5.25);
+        hole.reverse();
+        let outlines = vec![outline::rectangle(0.0, 10.0, 0.0, 10.0), hole];
+        let parts = vec![Part {
+            name: "text".to_string(),
+            mesh: outline::fill(&outlines, 0.01, &crate::cancel::CancelToken::new()).unwrap(),
+            depth: 1.0,
+            z_offset: 0.0,
+            letter: None,
+            chamfer: 0.0,
+            color: None,
+        }];
+        let cut = laser_layers(&parts, 0.2).remove(0).outlines;
+        let hole = cut.iter().find(|c| outline::area(c) < 0.0).unwrap();
+        assert!((outline::area(hole) + 0.3 * 0.3).abs() < 1e-3);
+        assert!(kerf_warnings(&parts, 0.2).is_empty());
+
+        let warnings = kerf_warnings(&parts, 0.6);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("1 hole(s)"));
+    }
+
+    #[test]
+    fn letters_get_layers_of_their_own() {
+        let letters = vec![
+            ('I', mesh::rectangle_mesh(0.0, 4.0, 0.0, 4.0)),
+            ('I', mesh::rectangle_mesh(6.0, 10.0, 0.0, 4.0)),
+        ];
+        let plain = crate::letter_parts(letters.clone(), &Options::default());
+        let layers = letter_layers(&plain, 0.0);
+        let names: Vec<&str> = layers.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(names, ["LETTER-1", "LETTER-2"]);
+        assert!(layers.iter().all(|l| l.color == 1 && l.outlines.len() == 1));
+
+        let options = Options {
+            plate: 1.0,
+            ..Default::default()
+        };
+        let layers = letter_layers(&crate::letter_parts(letters, &options), 0.0);
+        assert_eq!(layers.len(), 4);
+        assert_eq!(layers[0].name, CUT_LAYER);
+        assert!(layers[1].outlines.is_empty());
+        assert_eq!((layers[3].name.as_str(), layers[3].color), ("LETTER-2", 5));
+    }
+}
@@ -0,0 +1,674 @@
+//! Font loading and face selection
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+use fontdb::{Database, Family, Query, Source};
+use ttf_parser::{Face, Permissions, Tag};
+
+use crate::i18n::Message;
+
+/// Bundled Noto Sans JP Regular (SIL OFL 1.1), built in by the
+/// `embedded-font` feature. Unit tests always have it.
+#[cfg(any(test, feature = "embedded-font"))]
+pub const EMBEDDED_FONT: &[u8] = include_bytes!("../assets/fonts/NotoSansJP-Regular.otf");
+
+/// Font drawn with when no font file is given
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Builtin {
+    /// Noto Sans JP Regular (the `embedded-font` feature)
+    #[default]
+    Sans,
+    /// DejaVu Serif, Latin, Greek and Cyrillic only (the `builtin-serif` feature)
+    Serif,
+    /// DejaVu Sans Mono, Latin, Greek and Cyrillic only (the `builtin-mono` feature)
+    Mono,
+}
+
+impl Builtin {
+    /// The font file, or an error naming the feature that builds it in
+    pub fn bytes(self) -> Result<&'static [u8]> {
+        #[cfg(feature = "embedded-font")]
+        const SANS: Option<&[u8]> = Some(EMBEDDED_FONT);
+        #[cfg(not(feature = "embedded-font"))]
+        const SANS: Option<&[u8]> = None;
+        #[cfg(feature = "builtin-serif")]
+        const SERIF: Option<&[u8]> = Some(include_bytes!("../assets/fonts/DejaVuSerif.ttf"));
+        #[cfg(not(feature = "builtin-serif"))]
+        const SERIF: Option<&[u8]> = None;
+        #[cfg(feature = "builtin-mono")]
+        const MONO: Option<&[u8]> = Some(include_bytes!("../assets/fonts/DejaVuSansMono.ttf"));
+        #[cfg(not(feature = "builtin-mono"))]
+        const MONO: Option<&[u8]> = None;
+
+        let (bytes, name, feature) = match self {
+            Builtin::Sans => (SANS, "sans", "embedded-font"),
+            Builtin::Serif => (SERIF, "serif", "builtin-serif"),
+            Builtin::Mono => (MONO, "mono", "builtin-mono"),
+        };
+        bytes.with_context(|| Message::BuiltinFontMissing(name, feature).to_string())
+    }
+}
+
+/// Read a font file, falling back to the `builtin` font
+pub fn load_font_bytes(path: Option<&Path>, builtin: Builtin) -> Result<Cow<'static, [u8]>> {
+    match path {
+        Some(path) => Ok(Cow::Owned(read_font_file(path)?)),
+        None => Ok(Cow::Borrowed(builtin.bytes()?)),
+    }
+}
+
+/// Font file contents, WOFF and WOFF2 unpacked to the sfnt inside
+fn read_font_file(path: &Path) -> Result<Vec<u8>> {
+    let bytes = fs::read(path).with_context(|| Message::ReadFontFailed(path).to_string())?;
+    let unpacked =
+        crate::woff::decode(&bytes).with_context(|| Message::ReadFontFailed(path).to_string())?;
+    Ok(unpacked.unwrap_or(bytes))
+}
+
+/// Contents of a font file, shared by every face parsed from it
+pub type FontData = Arc<[u8]>;
+
+/// Fallback font files read so far, with the size and modification time
+/// they had then
+type FallbackCache = HashMap<PathBuf, (u64, Option<SystemTime>, FontData)>;
+
+fn fallback_cache() -> MutexGuard<'static, FallbackCache> {
+    static LOADED: OnceLock<Mutex<FallbackCache>> = OnceLock::new();
+    LOADED
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Contents of the fallback font files, checked to parse. Each file is read
+/// once and shared for as long as it is unchanged on disk, since layout
+/// looks the faces up again for every model of a batch; an edited file is
+/// read again.
+pub fn fallback_fonts(paths: &[PathBuf]) -> Result<Vec<FontData>> {
+    let mut loaded = fallback_cache();
+    paths
+        .iter()
+        .map(|path| {
+            let stamp = fs::metadata(path)
+                .map(|meta| (meta.len(), meta.modified().ok()))
+                .ok();
+            if let Some((len, modified, data)) = loaded.get(path) {
+                if stamp == Some((*len, *modified)) {
+                    return Ok(data.clone());
+                }
+            }
+            let data: FontData = read_font_file(path)?.into();
+            parse_face(&data, 0).with_context(|| Message::ParseFontFailed(path).to_string())?;
+            if let Some((len, modified)) = stamp {
+                loaded.insert(path.clone(), (len, modified, data.clone()));
+            }
+            Ok(data)
+        })
+        .collect()
+}
+
+/// Forget the fallback font files [`fallback_fonts`] has read, freeing them
+/// once no face uses them any more
+pub fn clear_fallback_cache() {
+    fallback_cache().clear();
+}
+
+/// First face of each of the `fonts` (the first of collections), the
+/// fallbacks layout draws characters from
+pub fn fallback_faces(fonts: &[FontData]) -> Vec<Face<'_>> {
+    fonts
+        .iter()
+        .filter_map(|data| parse_face(data, 0).ok())
+        .collect()
+}
+
+/// Parse one face, validating the index against the collection size
+pub fn parse_face(font_bytes: &[u8], face_index: u32) -> Result<Face<'_>> {
+    let face_count = ttf_parser::fonts_in_collection(font_bytes).unwrap_or(1);
+    anyhow::ensure!(face_count > 0, "font file appears to have no faces");
+    anyhow::ensure!(
+        face_index < face_count,
+        "{}",
+        Message::FaceIndexOutOfRange(face_index, face_count)
+    );
+
+    Face::parse(font_bytes, face_index)
+        .with_context(|| format!("failed to parse font (face index {})", face_index))
+}
+
+/// `name` lowercased without whitespace, for matching names the way people
+/// type them
+fn name_key(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Names a face answers to: its full name, family plus subfamily (both the
+/// typographic and the legacy pair) and PostScript name, the full name first
+fn face_names(face: &Face<'_>) -> Vec<String> {
+    use ttf_parser::name_id;
+    let name = |id| crate::metadata::name_string(face, id);
+    let mut names: Vec<String> = [name(name_id::FULL_NAME)].into_iter().flatten().collect();
+    for (family, subfamily) in [
+        (name_id::TYPOGRAPHIC_FAMILY, name_id::TYPOGRAPHIC_SUBFAMILY),
+        (name_id::FAMILY, name_id::SUBFAMILY),
+    ] {
+        if let (Some(family), Some(subfamily)) = (name(family), name(subfamily)) {
+            names.push(format!("{} {}", family, subfamily));
+        }
+    }
+    names.extend(name(name_id::POST_SCRIPT_NAME));
+    names
+}
+
+/// Index of the face of a collection (.ttc) named `name`, ignoring case and
+/// spaces; see `face_names` for the names that match. Fails with a list of
+/// the faces when none does.
+pub fn find_face_index(font_bytes: &[u8], name: &str) -> Result<u32> {
+    let face_count = ttf_parser::fonts_in_collection(font_bytes).unwrap_or(1);
+    let faces: Vec<Vec<String>> = (0..face_count)
+        .map(|index| {
+            Face::parse(font_bytes, index)
+                .map(|face| face_names(&face))
+                .unwrap_or_default()
+        })
+        .collect();
+    match_face_name(&faces, name)
+}
+
+fn match_face_name(faces: &[Vec<String>], name: &str) -> Result<u32> {
+    faces
+        .iter()
+        .position(|names| names.iter().any(|n| name_key(n) == name_key(name)))
+        .map(|index| index as u32)
+        .with_context(|| {
+            let available: Vec<String> = faces
+                .iter()
+                .enumerate()
+                .map(|(index, names)| {
+                    let name = names.first().map_or("(unnamed)", String::as_str);
+                    format!("{}: {}", index, name)
+                })
+                .collect();
+            format!(
+                "the font has no face named \"{}\" (available: {})",
+                name,
+                available.join(", ")
+            )
+        })
+}
+
+/// A variable font axis setting, `wght=700` on the command line
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Variation {
+    /// Axis tag such as `wght`, `wdth` or `opsz`
+    pub axis: String,
+    pub value: f32,
+}
+
+impl FromStr for Variation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (axis, value) = s
+            .split_once('=')
+            .with_context(|| format!("expected AXIS=VALUE (e.g. wght=700), got: {}", s))?;
+        let axis = axis.trim();
+        anyhow::ensure!(
+            (1..=4).contains(&axis.len()) && axis.bytes().all(|b| b.is_ascii_graphic()),
+            "axis tags are 1 to 4 ASCII characters, got: {}",
+            axis
+        );
+        let value = value
+            .trim()
+            .parse()
+            .with_context(|| format!("invalid value for axis {}: {}", axis, value))?;
+        Ok(Variation {
+            axis: axis.to_string(),
+            value,
+        })
+    }
+}
+
+impl fmt::Display for Variation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.axis, self.value)
+    }
+}
+
+/// A named instance from a variable font's `fvar` table, such as
+/// "SemiBold" at `wght=600`
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamedInstance {
+    pub name: String,
+    /// Coordinates on every axis of the font
+    pub variations: Vec<Variation>,
+}
+
+/// The named instances of a variable font; empty for static fonts
+pub fn named_instances(face: &Face<'_>) -> Vec<NamedInstance> {
+    face.raw_face()
+        .table(Tag::from_bytes(b"fvar"))
+        .and_then(|fvar| parse_instances(face, fvar))
+        .unwrap_or_default()
+}
+
+/// Instance records of an `fvar` table, named from `face`'s name table
+fn parse_instances(face: &Face<'_>, fvar: &[u8]) -> Option<Vec<NamedInstance>> {
+    let u16_at = |at: usize| Some(u16::from_be_bytes(fvar.get(at..at + 2)?.try_into().ok()?));
+    let fixed_at = |at: usize| {
+        Some(i32::from_be_bytes(fvar.get(at..at + 4)?.try_into().ok()?) as f32 / 65536.0)
+    };
+    let axes_offset = u16_at(4)? as usize;
+    let (axis_count, axis_size) = (u16_at(8)? as usize, u16_at(10)? as usize);
+    let (instance_count, instance_size) = (u16_at(12)? as usize, u16_at(14)? as usize);
+    let tags = (0..axis_count)
+        .map(|i| {
+            let at = axes_offset + i * axis_size;
+            let tag = String::from_utf8_lossy(fvar.get(at..at + 4)?);
+            Some(tag.trim_end().to_string())
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let instances_offset = axes_offset + axis_count * axis_size;
+    (0..instance_count)
+        .map(|i| {
+            let at = instances_offset + i * instance_size;
+            let name = crate::metadata::name_string(face, u16_at(at)?)
+                .unwrap_or_else(|| format!("instance {}", i + 1));
+            let variations = tags
+                .iter()
+                .enumerate()
+                .map(|(axis, tag)| {
+                    Some(Variation {
+                        axis: tag.clone(),
+                        value: fixed_at(at + 4 + axis * 4)?,
+                    })
+                })
+                .collect::<Option<_>>()?;
+            Some(NamedInstance { name, variations })
+        })
+        .collect()
+}
+
+/// Axis settings of the instance called `name`, ignoring case and spaces
+/// ("semibold" finds "Semi Bold")
+pub fn instance_variations(face: &Face<'_>, name: &str) -> Result<Vec<Variation>> {
+    Ok(find_instance(&named_instances(face), name)?
+        .variations
+        .clone())
+}
+
+fn find_instance<'a>(instances: &'a [NamedInstance], name: &str) -> Result<&'a NamedInstance> {
+    anyhow::ensure!(
+        !instances.is_empty(),
+        "--instance needs a variable font, and this font has no named instances"
+    );
+    instances
+        .iter()
+        .find(|instance| name_key(&instance.name) == name_key(name))
+        .with_context(|| {
+            let names: Vec<_> = instances.iter().map(|i| i.name.as_str()).collect();
+            format!(
+                "the font has no instance named \"{}\" (available: {})",
+                name,
+                names.join(", ")
+            )
+        })
+}
+
+/// Set the variation coordinates of a variable font; axes the font lacks
+/// and values outside an axis's range are errors naming the available ones.
+/// A later setting of the same axis wins.
+pub fn apply_variations(face: &mut Face<'_>, variations: &[Variation]) -> Result<()> {
+    if variations.is_empty() {
+        return Ok(());
+    }
+    let axes: Vec<_> = face.variation_axes().into_iter().collect();
+    anyhow::ensure!(
+        !axes.is_empty(),
+        "--variation needs a variable font, and this font has no variation axes"
+    );
+    let available = || {
+        axes.iter()
+            .map(|axis| format!("{} {}..{}", axis.tag, axis.min_value, axis.max_value))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    for variation in variations {
+        let mut tag = [b' '; 4];
+        tag[..variation.axis.len()].copy_from_slice(variation.axis.as_bytes());
+        let tag = Tag::from_bytes(&tag);
+        let axis = axes.iter().find(|axis| axis.tag == tag).with_context(|| {
+            format!(
+                "the font has no {} axis (available: {})",
+                variation.axis,
+                available()
+            )
+        })?;
+        anyhow::ensure!(
+            (axis.min_value..=axis.max_value).contains(&variation.value),
+            "{}={} is outside the axis range {}..{}",
+            variation.axis,
+            variation.value,
+            axis.min_value,
+            axis.max_value
+        );
+        face.set_variation(tag, variation.value)
+            .with_context(|| format!("failed to set the {} axis", variation.axis))?;
+    }
+    Ok(())
+}
+
+/// The font's OS/2 `fsType` embedding permission when it restricts reuse:
+/// `Restricted` (no embedding without the vendor's permission) or
+/// `PreviewAndPrint` (read-only embedding). `None` for installable and
+/// editable fonts, or fonts without an OS/2 table.
+pub fn embedding_restriction(face: &Face<'_>) -> Option<Permissions> {
+    face.permissions()
+        .filter(|p| matches!(p, Permissions::Restricted | Permissions::PreviewAndPrint))
+}
+
+/// Cap height in font units: the OS/2 value, else the top of 'H'
+pub fn cap_height(face: &Face<'_>) -> Option<f32> {
+    face.capital_height()
+        .filter(|h| *h > 0)
+        .or_else(|| {
+            let gid = face.glyph_index('H')?;
+            face.glyph_bounding_box(gid).map(|rect| rect.y_max)
+        })
+        .filter(|h| *h > 0)
+        .map(f32::from)
+}
+
+/// Weight and slant of an installed font picked with `--family`
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FontStyle {
+    #[default]
+    Regular,
+    Bold,
+    Italic,
+    BoldItalic,
+}
+
+impl FontStyle {
+    fn query(self) -> (fontdb::Weight, fontdb::Style) {
+        let weight = match self {
+            FontStyle::Bold | FontStyle::BoldItalic => fontdb::Weight::BOLD,
+            FontStyle::Regular | FontStyle::Italic => fontdb::Weight::NORMAL,
+        };
+        let style = match self {
+            FontStyle::Italic | FontStyle::BoldItalic => fontdb::Style::Italic,
+            FontStyle::Regular | FontStyle::Bold => fontdb::Style::Normal,
+        };
+        (weight, style)
+    }
+}
+
+/// File and face index of the installed font of `family` (any case) closest
+/// to `style`
+pub fn find_system_font(family: &str, style: FontStyle) -> Result<(PathBuf, u32)> {
+    let mut db = Database::new();
+    db.load_system_fonts();
+    find_font(&db, family, style)
+}
+
+fn find_font(db: &Database, family: &str, style: FontStyle) -> Result<(PathBuf, u32)> {
+    let mut families: Vec<&str> = db
+        .faces()
+        .flat_map(|face| face.families.iter().map(|(name, _)| name.as_str()))
+        .collect();
+    families.sort_unstable();
+    families.dedup();
+    let Some(name) = families
+        .iter()
+        .find(|name| name.eq_ignore_ascii_case(family.trim()))
+    else {
+        let close = close_matches(family, &families);
+        anyhow::bail!("{}", Message::UnknownFamily(family, &close));
+    };
+    let (weight, style) = style.query();
+    let query = Query {
+        families: &[Family::Name(name)],
+        weight,
+        style,
+        ..Query::default()
+    };
+    let face = db
+        .query(&query)
+        .and_then(|id| db.face(id))
+        .with_context(|| format!("no usable face in font family \"{}\"", name))?;
+    match &face.source {
+        Source::File(path) | Source::SharedFile(path, _) => Ok((path.clone(), face.index)),
+        Source::Binary(_) => anyhow::bail!("font family \"{}\" is not in a font file", name),
+    }
+}
+
+/// Up to five installed families that contain `family` or are a few typos
+/// away from it, ignoring case and spaces
+fn close_matches<'a>(family: &str, families: &[&'a str]) -> Vec<&'a str> {
+    let key = |name: &str| -> Vec<char> {
+        name.chars()
+            .filter(|c| !c.is_whitespace())
+            .flat_map(char::to_lowercase)
+            .collect()
+    };
+    let wanted = key(family);
+    let mut scored: Vec<(usize, &str)> = families
+        .iter()
+        .filter_map(|&name| {
+            let candidate = key(name);
+            let contains =
+                |a: &[char], b: &[char]| !b.is_empty() && a.windows(b.len()).any(|w| w == b);
+            let distance = edit_distance(&wanted, &candidate);
+            let close = contains(&candidate, &wanted)
+                || contains(&wanted, &candidate)
+                || distance <= (wanted.len() / 4).max(2);
+            close.then_some((distance, name))
+        })
+        .collect();
+    scored.sort();
+    scored.into_iter().take(5).map(|(_, name)| name).collect()
+}
+
+/// Levenshtein distance
+fn edit_distance(a: &[char], b: &[char]) -> usize {
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The embedded font with its OS/2 fsType replaced
+    fn with_fs_type(fs_type: u16) -> Vec<u8> {
+        let mut bytes = EMBEDDED_FONT.to_vec();
+        let read_u16 = |b: &[u8], at: usize| u16::from_be_bytes([b[at], b[at + 1]]) as usize;
+        let read_u32 = |b: &[u8], at: usize| {
+            u32::from_be_bytes([b[at], b[at + 1], b[at + 2], b[at + 3]]) as usize
+        };
+        let os2 = (0..read_u16(&bytes, 4))
+            .map(|i| 12 + i * 16)
+            .find(|&record| &bytes[record..record + 4] == b"OS/2")
+            .map(|record| read_u32(&bytes, record + 8))
+            .unwrap();
+        bytes[os2 + 8..os2 + 10].copy_from_slice(&fs_type.to_be_bytes());
+        bytes
+    }
+
+    #[test]
+    fn faces_are_found_by_name() {
+        let faces = vec![
+            vec![
+                "Yu Gothic UI Regular".to_string(),
+                "YuGothicUI-Regular".to_string(),
+            ],
+            vec!["Yu Gothic UI Semibold".to_string()],
+        ];
+        assert_eq!(match_face_name(&faces, "yu gothic ui semibold").unwrap(), 1);
+        assert_eq!(match_face_name(&faces, "YuGothicUI-Regular").unwrap(), 0);
+        let err = match_face_name(&faces, "Yu Gothic UI Bold").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the font has no face named \"Yu Gothic UI Bold\" \
+             (available: 0: Yu Gothic UI Regular, 1: Yu Gothic UI Semibold)"
+        );
+
+        let face = parse_face(EMBEDDED_FONT, 0).unwrap();
+        let full_name = face_names(&face).remove(0);
+        assert_eq!(find_face_index(EMBEDDED_FONT, &full_name).unwrap(), 0);
+    }
+
+    #[test]
+    fn restricted_fonts_are_reported() {
+        let face = parse_face(EMBEDDED_FONT, 0).unwrap();
+        assert_eq!(embedding_restriction(&face), None);
+
+        let restricted = with_fs_type(0x0002);
+        let face = parse_face(&restricted, 0).unwrap();
+        assert_eq!(embedding_restriction(&face), Some(Permissions::Restricted));
+
+        let print = with_fs_type(0x0004);
+        let face = parse_face(&print, 0).unwrap();
+        assert_eq!(
+            embedding_restriction(&face),
+            Some(Permissions::PreviewAndPrint)
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "builtin-serif", feature = "builtin-mono"))]
+    fn builtin_fonts_are_distinct_faces() {
+        let name = |builtin: Builtin| {
+            let face = parse_face(builtin.bytes().unwrap(), 0).unwrap();
+            crate::metadata::font_name(&face).unwrap()
+        };
+        assert_eq!(name(Builtin::Serif), "DejaVu Serif");
+        assert_eq!(name(Builtin::Mono), "DejaVu Sans Mono");
+        let mono = parse_face(Builtin::Mono.bytes().unwrap(), 0).unwrap();
+        let advance = |ch| mono.glyph_hor_advance(mono.glyph_index(ch).unwrap());
+        assert_eq!(advance('i'), advance('W'));
+    }
+
+    #[test]
+    fn variations_parse_and_need_variable_fonts() {
+        let variation: Variation = "wght=700".parse().unwrap();
+        assert_eq!(
+            variation,
+            Variation {
+                axis: "wght".into(),
+                value: 700.0
+            }
+        );
+        assert!("wght".parse::<Variation>().is_err());
+        assert!("wght=bold".parse::<Variation>().is_err());
+        assert!("weight=700".parse::<Variation>().is_err());
+
+        let mut face = parse_face(EMBEDDED_FONT, 0).unwrap();
+        apply_variations(&mut face, &[]).unwrap();
+        let error = apply_variations(&mut face, &[variation]).unwrap_err();
+        assert!(error.to_string().contains("needs a variable font"));
+    }
+
+    #[test]
+    fn named_instances_come_from_fvar() {
+        // One wght axis (100..900) and instances named by name IDs 2
+        // ("Regular") and 300 (missing from the name table)
+        let mut fvar = Vec::new();
+        for value in [1u16, 0, 16, 2, 1, 20, 2, 8] {
+            fvar.extend(value.to_be_bytes());
+        }
+        fvar.extend(b"wght");
+        for value in [100i32, 400, 900] {
+            fvar.extend((value << 16).to_be_bytes());
+        }
+        fvar.extend([0, 0, 1, 0]);
+        for (name_id, weight) in [(2u16, 400i32), (300, 700)] {
+            fvar.extend(name_id.to_be_bytes());
+            fvar.extend([0, 0]);
+            fvar.extend((weight << 16).to_be_bytes());
+        }
+
+        let face = parse_face(EMBEDDED_FONT, 0).unwrap();
+        assert!(named_instances(&face).is_empty());
+        let instances = parse_instances(&face, &fvar).unwrap();
+        let names: Vec<_> = instances.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, ["Regular", "instance 2"]);
+        let found = find_instance(&instances, "INSTANCE2").unwrap();
+        assert_eq!(found.variations[0].to_string(), "wght=700");
+        let error = find_instance(&instances, "Black").unwrap_err().to_string();
+        assert!(
+            error.contains("available: Regular, instance 2"),
+            "{}",
+            error
+        );
+        assert!(find_instance(&[], "Regular").is_err());
+    }
+
+    #[test]
+    fn edited_fallback_fonts_are_read_again() {
+        let dir = std::env::temp_dir().join(format!("wagyan-fallback-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("fallback.otf");
+        fs::write(&path, EMBEDDED_FONT).unwrap();
+        let fonts = fallback_fonts(std::slice::from_ref(&path)).unwrap();
+        assert_eq!(fallback_faces(&fonts).len(), 1);
+
+        fs::write(&path, b"not a font").unwrap();
+        assert!(fallback_fonts(std::slice::from_ref(&path)).is_err());
+        // Faces already handed out keep their data
+        assert_eq!(fallback_faces(&fonts).len(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn family_names_resolve_to_installed_files() {
+        let dir = std::env::temp_dir().join(format!("wagyan-family-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("NotoSansJP-Regular.otf");
+        fs::write(&path, EMBEDDED_FONT).unwrap();
+        let mut db = Database::new();
+        db.load_font_file(&path).unwrap();
+
+        let (found, index) = find_font(&db, "noto sans japanese", FontStyle::Bold).unwrap();
+        assert_eq!((found, index), (path, 0));
+        let error = find_font(&db, "Noto Sans Japan", FontStyle::Regular)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("close matches: Noto Sans Japanese"),
+            "{}",
+            error
+        );
+        let error = find_font(&db, "Helvetica", FontStyle::Regular)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("--font"), "{}", error);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
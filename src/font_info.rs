@@ -0,0 +1,216 @@
+//! `wagyan font-info`: what a font file offers, for working out why text
+//! comes out looking wrong (no kerning table, a missing axis, the wrong
+//! face of a collection, ...)
+
+use std::io::Write;
+
+use anyhow::Result;
+use serde_json::json;
+use ttf_parser::{name_id, Face};
+
+use crate::metadata::name_string;
+
+/// A variation axis from `fvar`, in the font's own units
+#[derive(Debug, Clone, PartialEq)]
+pub struct AxisInfo {
+    pub tag: String,
+    pub name: Option<String>,
+    pub min: f32,
+    pub default: f32,
+    pub max: f32,
+}
+
+/// Everything `font-info` reports; metrics are in font units
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontInfo {
+    pub family: Option<String>,
+    pub subfamily: Option<String>,
+    pub full_name: Option<String>,
+    pub version: Option<String>,
+    pub units_per_em: u16,
+    pub ascender: i16,
+    /// Below the baseline, so usually negative
+    pub descender: i16,
+    pub line_gap: i16,
+    pub glyph_count: u16,
+    /// Table tags in file order, e.g. "cmap", "GPOS", "CFF "
+    pub tables: Vec<String>,
+    /// GSUB and GPOS feature tags, sorted, for `--features`
+    pub features: Vec<String>,
+    pub axes: Vec<AxisInfo>,
+    pub instances: usize,
+}
+
+fn tag_string(tag: ttf_parser::Tag) -> String {
+    String::from_utf8_lossy(&tag.to_bytes()).into_owned()
+}
+
+/// Collect the metadata of `face`
+pub fn font_info(face: &Face<'_>) -> FontInfo {
+    let tables = face
+        .raw_face()
+        .table_records
+        .into_iter()
+        .map(|record| tag_string(record.tag))
+        .collect();
+    let mut features: Vec<String> = [face.tables().gsub, face.tables().gpos]
+        .into_iter()
+        .flatten()
+        .flat_map(|table| table.features)
+        .map(|feature| tag_string(feature.tag))
+        .collect();
+    features.sort();
+    features.dedup();
+    let axes = face
+        .variation_axes()
+        .into_iter()
+        .map(|axis| AxisInfo {
+            tag: tag_string(axis.tag),
+            name: name_string(face, axis.name_id),
+            min: axis.min_value,
+            default: axis.def_value,
+            max: axis.max_value,
+        })
+        .collect();
+    FontInfo {
+        family: [name_id::TYPOGRAPHIC_FAMILY, name_id::FAMILY]
+            .iter()
+            .find_map(|&id| name_string(face, id)),
+        subfamily: [name_id::TYPOGRAPHIC_SUBFAMILY, name_id::SUBFAMILY]
+            .iter()
+            .find_map(|&id| name_string(face, id)),
+        full_name: name_string(face, name_id::FULL_NAME),
+        version: name_string(face, name_id::VERSION),
+        units_per_em: face.units_per_em(),
+        ascender: face.ascender(),
+        descender: face.descender(),
+        line_gap: face.line_gap(),
+        glyph_count: face.number_of_glyphs(),
+        tables,
+        features,
+        axes,
+        instances: crate::font::named_instances(face).len(),
+    }
+}
+
+impl FontInfo {
+    fn has_table(&self, tag: &str) -> bool {
+        self.tables.iter().any(|t| t == tag)
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let axes: Vec<_> = self
+            .axes
+            .iter()
+            .map(|axis| {
+                json!({
+                    "tag": axis.tag,
+                    "name": axis.name,
+                    "min": axis.min,
+                    "default": axis.default,
+                    "max": axis.max,
+                })
+            })
+            .collect();
+        json!({
+            "family": self.family,
+            "subfamily": self.subfamily,
+            "full_name": self.full_name,
+            "version": self.version,
+            "units_per_em": self.units_per_em,
+            "ascender": self.ascender,
+            "descender": self.descender,
+            "line_gap": self.line_gap,
+            "glyph_count": self.glyph_count,
+            "tables": self.tables,
+            "kern": self.has_table("kern"),
+            "gpos": self.has_table("GPOS"),
+            "gsub": self.has_table("GSUB"),
+            "features": self.features,
+            "axes": axes,
+            "instances": self.instances,
+        })
+    }
+
+    /// Human-readable report
+    pub fn write_text<W: Write>(&self, mut out: W) -> Result<()> {
+        let unknown = || "(unknown)".to_string();
+        writeln!(
+            out,
+            "family: {} / {}",
+            self.family.clone().unwrap_or_else(unknown),
+            self.subfamily.clone().unwrap_or_else(unknown)
+        )?;
+        if let Some(name) = &self.full_name {
+            writeln!(out, "full name: {}", name)?;
+        }
+        if let Some(version) = &self.version {
+            writeln!(out, "version: {}", version)?;
+        }
+        writeln!(
+            out,
+            "units per em {}, ascender {}, descender {}, line gap {}",
+            self.units_per_em, self.ascender, self.descender, self.line_gap
+        )?;
+        writeln!(out, "glyphs: {}", self.glyph_count)?;
+        let yes_no = |tag| if self.has_table(tag) { "yes" } else { "no" };
+        writeln!(
+            out,
+            "kern: {}, GPOS: {}, GSUB: {}",
+            yes_no("kern"),
+            yes_no("GPOS"),
+            yes_no("GSUB")
+        )?;
+        writeln!(out, "tables: {}", self.tables.join(", "))?;
+        if !self.features.is_empty() {
+            writeln!(out, "features: {}", self.features.join(","))?;
+        }
+        if self.axes.is_empty() {
+            writeln!(out, "variation axes: none")?;
+        } else {
+            writeln!(out, "variation axes:")?;
+            for axis in &self.axes {
+                write!(
+                    out,
+                    "  {} {}..{} (default {})",
+                    axis.tag, axis.min, axis.max, axis.default
+                )?;
+                match &axis.name {
+                    Some(name) => writeln!(out, " {}", name)?,
+                    None => writeln!(out)?,
+                }
+            }
+            writeln!(out, "named instances: {}", self.instances)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font;
+
+    #[test]
+    fn reports_the_embedded_font() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let info = font_info(&face);
+        assert_eq!(info.family.as_deref(), Some("Noto Sans Japanese"));
+        assert_eq!(info.units_per_em, 1000);
+        assert!(info.ascender > 0 && info.descender < 0);
+        assert_eq!(info.glyph_count, face.number_of_glyphs());
+        assert!(info.has_table("GPOS") && info.has_table("CFF "));
+        assert!(info.features.iter().any(|tag| tag == "vert"));
+        assert!(info.axes.is_empty());
+
+        let json = info.to_json();
+        assert_eq!(json["gpos"], true);
+        assert_eq!(json["units_per_em"], 1000);
+
+        let mut text = Vec::new();
+        info.write_text(&mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.starts_with("family: Noto Sans Japanese / Regular"));
+        assert!(text.contains("variation axes: none"));
+    }
+}
@@ -0,0 +1,126 @@
+//! Garden marker preset: a plant label with raised text and a pointed
+//! stake below it, printed lying flat
+
+use anyhow::Result;
+use lyon_path::builder::BorderRadii;
+use lyon_path::math::{Box2D, Point};
+use lyon_path::{Path, Polygon, Winding};
+use ttf_parser::Face;
+
+use crate::cake_topper::stick;
+use crate::cancel::CancelToken;
+use crate::mesh;
+use crate::nameplate::centered_text;
+use crate::{Options, Part};
+
+/// Garden marker settings; the lettering itself comes from [`Options`]
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct GardenMarker {
+    /// Marker thickness in mm
+    pub thickness: f32,
+    /// Space between the text and the tag edge in mm
+    pub margin: f32,
+    pub corner_radius: f32,
+    /// Stake length below the tag in mm, including the point
+    pub stake_length: f32,
+    /// Stake width in mm (never wider than the tag)
+    pub stake_width: f32,
+}
+
+impl Default for GardenMarker {
+    fn default() -> Self {
+        Self {
+            thickness: 2.5,
+            margin: 3.0,
+            corner_radius: 3.0,
+            stake_length: 100.0,
+            stake_width: 10.0,
+        }
+    }
+}
+
+/// Build the marker (tag and stake in one piece) and the lettering
+pub fn garden_marker_parts(
+    face: &Face<'_>,
+    options: &Options,
+    marker: &GardenMarker,
+    cancel: &CancelToken,
+) -> Result<Vec<Part>> {
+    let (text, text_w, text_h) = centered_text(face, options, cancel)?;
+    let (half_w, half_h) = (text_w + marker.margin, text_h + marker.margin);
+    let radius = marker.corner_radius.min(half_w).min(half_h);
+
+    // The stake starts inside the tag, clear of its rounded corners
+    let stake = stick(
+        0.0,
+        marker.stake_width.min(2.0 * (half_w - radius)),
+        -half_h + radius,
+        -half_h - marker.stake_length,
+    );
+    let mut builder = Path::builder();
+    builder.add_rounded_rectangle(
+        &Box2D::new(Point::new(-half_w, -half_h), Point::new(half_w, half_h)),
+        &BorderRadii::new(radius),
+        Winding::Positive,
+    );
+    builder.add_polygon(Polygon {
+        points: &stake,
+        closed: true,
+    });
+    let plate =
+        mesh::tessellate_path_with_cancel(&builder.build(), options.tolerance(face), cancel)?;
+
+    let depth = options.text_depth();
+    let thickness = options.snap_to_layers(marker.thickness);
+    Ok(vec![
+        Part {
+            name: "marker".to_string(),
+            mesh: plate,
+            depth: thickness,
+            z_offset: -(depth * 0.5 + thickness * 0.5),
+            letter: None,
+            chamfer: 0.0,
+            color: None,
+        },
+        Part {
+            name: "text".to_string(),
+            mesh: text,
+            depth,
+            z_offset: 0.0,
+            letter: None,
+            chamfer: 0.0,
+            color: None,
+        },
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font;
+
+    #[test]
+    fn stake_points_down_from_the_tag() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let options = Options {
+            text: "ミニトマト".into(),
+            size: 12.0,
+            depth: 0.8,
+            ..Default::default()
+        };
+        let marker = GardenMarker::default();
+        let parts = garden_marker_parts(&face, &options, &marker, &CancelToken::new()).unwrap();
+        let (_, _, text_min_y, _) = mesh::mesh_bounds(&parts[1].mesh).unwrap();
+        let (_, _, min_y, _) = mesh::mesh_bounds(&parts[0].mesh).unwrap();
+        let reach = text_min_y - min_y;
+        assert!((reach - marker.margin - marker.stake_length).abs() < 0.01);
+
+        // Tag and stake fuse into one outline
+        assert_eq!(mesh::boundary_loops(&parts[0].mesh).len(), 1);
+    }
+}
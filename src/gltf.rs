@@ -0,0 +1,300 @@
+//! glTF 2.0 writers, binary (GLB) and JSON (.gltf with the buffer embedded
+//! as a data URI), with one PBR material per part color; each part becomes a node
+
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use crate::color::Color;
+use crate::indexed::{to_y_up, MeshPart};
+use crate::output;
+
+const GLB_MAGIC: &[u8; 4] = b"glTF";
+const GLB_VERSION: u32 = 2;
+const CHUNK_JSON: u32 = 0x4E4F_534A;
+const CHUNK_BIN: u32 = 0x004E_4942;
+
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+
+/// Accumulates the BIN chunk and the JSON arrays that describe it
+#[derive(Default)]
+struct Builder {
+    bin: Vec<u8>,
+    buffer_views: Vec<Value>,
+    accessors: Vec<Value>,
+}
+
+impl Builder {
+    fn push_view(&mut self, bytes: &[u8], target: u32) -> usize {
+        let offset = self.bin.len();
+        self.bin.extend_from_slice(bytes);
+        while !self.bin.len().is_multiple_of(4) {
+            self.bin.push(0);
+        }
+        self.buffer_views.push(json!({
+            "buffer": 0,
+            "byteOffset": offset,
+            "byteLength": bytes.len(),
+            "target": target,
+        }));
+        self.buffer_views.len() - 1
+    }
+
+    fn push_vec3(&mut self, values: &[[f32; 3]], with_bounds: bool) -> usize {
+        let bytes: Vec<u8> = values
+            .iter()
+            .flat_map(|v| to_y_up(*v))
+            .flat_map(f32::to_le_bytes)
+            .collect();
+        let view = self.push_view(&bytes, ARRAY_BUFFER);
+        let mut accessor = json!({
+            "bufferView": view,
+            "componentType": FLOAT,
+            "count": values.len(),
+            "type": "VEC3",
+        });
+        if with_bounds {
+            let mut lo = [f32::INFINITY; 3];
+            let mut hi = [f32::NEG_INFINITY; 3];
+            for v in values.iter().map(|v| to_y_up(*v)) {
+                for k in 0..3 {
+                    lo[k] = lo[k].min(v[k]);
+                    hi[k] = hi[k].max(v[k]);
+                }
+            }
+            accessor["min"] = json!(lo);
+            accessor["max"] = json!(hi);
+        }
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    /// Texture coordinates, flipped to glTF's top-left origin
+    fn push_uvs(&mut self, uvs: &[[f32; 2]]) -> usize {
+        let bytes: Vec<u8> = uvs
+            .iter()
+            .flat_map(|[u, v]| [*u, 1.0 - v])
+            .flat_map(f32::to_le_bytes)
+            .collect();
+        let view = self.push_view(&bytes, ARRAY_BUFFER);
+        self.accessors.push(json!({
+            "bufferView": view,
+            "componentType": FLOAT,
+            "count": uvs.len(),
+            "type": "VEC2",
+        }));
+        self.accessors.len() - 1
+    }
+
+    fn push_indices(&mut self, indices: &[u32]) -> usize {
+        let bytes: Vec<u8> = indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+        let view = self.push_view(&bytes, ELEMENT_ARRAY_BUFFER);
+        self.accessors.push(json!({
+            "bufferView": view,
+            "componentType": UNSIGNED_INT,
+            "count": indices.len(),
+            "type": "SCALAR",
+        }));
+        self.accessors.len() - 1
+    }
+}
+
+/// Build the glTF JSON document and its binary buffer
+fn document(parts: &[MeshPart]) -> (Value, Vec<u8>) {
+    let mut builder = Builder::default();
+    let mut colors: Vec<Color> = Vec::new();
+    let mut meshes = Vec::new();
+    let mut nodes = Vec::new();
+
+    for part in parts.iter().filter(|p| !p.mesh.indices.is_empty()) {
+        let material = match colors.iter().position(|c| *c == part.color) {
+            Some(i) => i,
+            None => {
+                colors.push(part.color);
+                colors.len() - 1
+            }
+        };
+        let position = builder.push_vec3(&part.mesh.positions, true);
+        let normal = builder.push_vec3(&part.mesh.normals, false);
+        let uv = builder.push_uvs(&part.mesh.uvs);
+        let indices = builder.push_indices(&part.mesh.indices);
+        meshes.push(json!({
+            "name": part.name,
+            "primitives": [{
+                "attributes": { "POSITION": position, "NORMAL": normal, "TEXCOORD_0": uv },
+                "indices": indices,
+                "material": material,
+            }],
+        }));
+        nodes.push(json!({ "name": part.name, "mesh": meshes.len() - 1 }));
+    }
+
+    let materials: Vec<Value> = colors
+        .iter()
+        .map(|color| {
+            let mut material = json!({
+                "name": color.to_string(),
+                "pbrMetallicRoughness": {
+                    "baseColorFactor": color.to_linear(),
+                    "metallicFactor": 0.0,
+                    "roughnessFactor": 0.8,
+                },
+            });
+            if color.a < 0xff {
+                material["alphaMode"] = json!("BLEND");
+            }
+            material
+        })
+        .collect();
+
+    let doc = json!({
+        "asset": {
+            "version": "2.0",
+            "generator": concat!("wagyan ", env!("CARGO_PKG_VERSION")),
+        },
+        "scene": 0,
+        "scenes": [{ "nodes": (0..nodes.len()).collect::<Vec<_>>() }],
+        "nodes": nodes,
+        "meshes": meshes,
+        "materials": materials,
+        "accessors": builder.accessors,
+        "bufferViews": builder.buffer_views,
+        "buffers": [{ "byteLength": builder.bin.len() }],
+    });
+    (doc, builder.bin)
+}
+
+pub fn write_glb(path: &Path, parts: &[MeshPart]) -> Result<()> {
+    output::write_atomically(path, |file| {
+        write_glb_to_writer(BufWriter::new(file), parts)
+    })
+}
+
+pub fn write_glb_to_writer<W: Write>(mut writer: W, parts: &[MeshPart]) -> Result<()> {
+    let (doc, bin) = document(parts);
+    let mut json = serde_json::to_vec(&doc)?;
+    while !json.len().is_multiple_of(4) {
+        json.push(b' ');
+    }
+
+    let total = 12 + 8 + json.len() + 8 + bin.len();
+    let total = u32::try_from(total)
+        .map_err(|_| anyhow::anyhow!("model is too large for GLB ({} bytes)", total))?;
+
+    writer.write_all(GLB_MAGIC)?;
+    writer.write_all(&GLB_VERSION.to_le_bytes())?;
+    writer.write_all(&total.to_le_bytes())?;
+    writer.write_all(&(json.len() as u32).to_le_bytes())?;
+    writer.write_all(&CHUNK_JSON.to_le_bytes())?;
+    writer.write_all(&json)?;
+    writer.write_all(&(bin.len() as u32).to_le_bytes())?;
+    writer.write_all(&CHUNK_BIN.to_le_bytes())?;
+    writer.write_all(&bin)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Self-contained .gltf: the JSON document with the buffer as a base64 data
+/// URI, for loaders and viewers that take a single text file
+pub fn write_gltf(path: &Path, parts: &[MeshPart]) -> Result<()> {
+    output::write_atomically(path, |file| {
+        write_gltf_to_writer(BufWriter::new(file), parts)
+    })
+}
+
+pub fn write_gltf_to_writer<W: Write>(mut writer: W, parts: &[MeshPart]) -> Result<()> {
+    let (mut doc, bin) = document(parts);
+    doc["buffers"][0]["uri"] = json!(format!(
+        "data:application/octet-stream;base64,{}",
+        base64(&bin)
+    ));
+    serde_json::to_writer(&mut writer, &doc)?;
+    writer.write_all(b"\n")?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Standard base64 with padding
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexed::IndexedMesh;
+    use crate::mesh::{rectangle_mesh, Orientation};
+
+    #[test]
+    fn glb_has_valid_header_and_chunks() {
+        let square = rectangle_mesh(0.0, 1.0, 0.0, 1.0);
+        let parts = [MeshPart {
+            name: "text".into(),
+            mesh: IndexedMesh::extrude(&square, 1.0, &Orientation::Flat, 0.0, None),
+            color: Color::LIGHT_GRAY,
+        }];
+        let mut out = Vec::new();
+        write_glb_to_writer(&mut out, &parts).unwrap();
+
+        assert_eq!(&out[0..4], b"glTF");
+        let word = |at: usize| u32::from_le_bytes(out[at..at + 4].try_into().unwrap()) as usize;
+        assert_eq!(word(8), out.len());
+        let json_len = word(12);
+        assert_eq!(word(16), CHUNK_JSON as usize);
+        let doc: Value = serde_json::from_slice(&out[20..20 + json_len]).unwrap();
+        assert_eq!(doc["materials"].as_array().unwrap().len(), 1);
+        assert_eq!(doc["accessors"][2]["type"], "VEC2");
+        assert_eq!(doc["accessors"][3]["count"], 36);
+
+        let bin_at = 20 + json_len;
+        assert_eq!(word(bin_at + 4), CHUNK_BIN as usize);
+        assert_eq!(
+            word(bin_at),
+            doc["buffers"][0]["byteLength"].as_u64().unwrap() as usize
+        );
+    }
+
+    #[test]
+    fn gltf_embeds_the_buffer_as_base64() {
+        assert_eq!(base64(b"wagyan"), "d2FneWFu");
+        assert_eq!(base64(b"ab"), "YWI=");
+        assert_eq!(base64(b"a"), "YQ==");
+
+        let square = rectangle_mesh(0.0, 1.0, 0.0, 1.0);
+        let parts = [MeshPart {
+            name: "text".into(),
+            mesh: IndexedMesh::extrude(&square, 1.0, &Orientation::Flat, 0.0, None),
+            color: Color::LIGHT_GRAY,
+        }];
+        let mut out = Vec::new();
+        write_gltf_to_writer(&mut out, &parts).unwrap();
+        let doc: Value = serde_json::from_slice(&out).unwrap();
+        let uri = doc["buffers"][0]["uri"].as_str().unwrap();
+        let data = uri
+            .strip_prefix("data:application/octet-stream;base64,")
+            .unwrap();
+        let length = doc["buffers"][0]["byteLength"].as_u64().unwrap() as usize;
+        assert_eq!(data.len(), length.div_ceil(3) * 4);
+        assert_eq!(doc["nodes"][0]["name"], "text");
+    }
+}
@@ -0,0 +1,181 @@
+//! Per-glyph tessellation cache, used when many models share the same characters
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use lyon_path::math::Box2D;
+use lyon_path::Path;
+use ttf_parser::{Face, GlyphId};
+
+use crate::cancel::CancelToken;
+use crate::layout::{glyph_face, glyph_outline, LyonOutlineBuilder, PlacedGlyph};
+use crate::mesh::{tessellate_path_with_cancel, Mesh2D};
+use crate::outline;
+
+/// Tessellated glyph meshes at the origin, keyed by face (see
+/// [`PlacedGlyph::font`]), glyph id and size
+pub struct GlyphMeshCache {
+    scale: f32,
+    tolerance: f32,
+    /// Outward offset of every outline in mm, as `Options::weight`
+    weight: f32,
+    /// Slant of every outline (see [`LyonOutlineBuilder::shear`])
+    shear: f32,
+    meshes: HashMap<(usize, GlyphId, u32), Mesh2D>,
+}
+
+impl GlyphMeshCache {
+    pub fn new(scale: f32, tolerance: f32) -> Self {
+        Self {
+            scale,
+            tolerance,
+            weight: 0.0,
+            shear: 0.0,
+            meshes: HashMap::new(),
+        }
+    }
+
+    /// Embolden the glyphs by `weight` mm (see [`crate::outline::embolden`])
+    pub fn with_weight(mut self, weight: f32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Slant the glyphs by `shear` (see [`Options::shear`](crate::Options::shear))
+    pub fn with_shear(mut self, shear: f32) -> Self {
+        self.shear = shear;
+        self
+    }
+
+    /// Layout scale the cached meshes were built at
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Tessellated outline of one glyph of face `font` at the origin, drawn
+    /// at `size` (see [`PlacedGlyph::size`]); empty for blank glyphs
+    pub fn glyph_mesh(
+        &mut self,
+        face: &Face<'_>,
+        fallbacks: &[Face<'_>],
+        font: usize,
+        gid: GlyphId,
+        size: f32,
+        cancel: &CancelToken,
+    ) -> Result<&Mesh2D> {
+        let key = (font, gid, size.to_bits());
+        if !self.meshes.contains_key(&key) {
+            let (glyph_face, units) = glyph_face(face, fallbacks, font);
+            let mut builder = Path::builder();
+            let mut adapter = LyonOutlineBuilder {
+                builder: &mut builder,
+                offset_x: 0.0,
+                offset_y: 0.0,
+                scale: self.scale * units * size,
+                shear: self.shear,
+                angle: 0.0,
+            };
+            glyph_outline(glyph_face, gid, &mut adapter);
+            let mut path = builder.build();
+            if self.weight != 0.0 {
+                path = outline::embolden(&path, self.weight, self.tolerance);
+            }
+            let mesh = tessellate_path_with_cancel(&path, self.tolerance, cancel)?;
+            self.meshes.insert(key, mesh);
+        }
+        Ok(&self.meshes[&key])
+    }
+
+    /// One mesh per glyph with an outline, moved (and turned) to its
+    /// laid-out position, with the character it draws
+    pub fn glyph_meshes(
+        &mut self,
+        face: &Face<'_>,
+        fallbacks: &[Face<'_>],
+        glyphs: &[PlacedGlyph],
+        cancel: &CancelToken,
+    ) -> Result<Vec<(char, Mesh2D)>> {
+        let mut out = Vec::new();
+        for glyph in glyphs {
+            let mesh =
+                self.glyph_mesh(face, fallbacks, glyph.font, glyph.gid, glyph.size, cancel)?;
+            if mesh.indices.is_empty() {
+                continue;
+            }
+            let mut mesh = mesh.clone();
+            for p in &mut mesh.vertices {
+                *p = glyph.place(*p);
+            }
+            out.push((glyph.ch, mesh));
+        }
+        Ok(out)
+    }
+
+    /// Merge cached glyph meshes moved to their laid-out positions, or
+    /// `None` when the bounding boxes of two glyphs overlap: the meshes are
+    /// joined without the union [`crate::text_parts`] performs, so touching
+    /// strokes would give non-manifold walls
+    pub fn assemble(
+        &mut self,
+        face: &Face<'_>,
+        fallbacks: &[Face<'_>],
+        glyphs: &[PlacedGlyph],
+        cancel: &CancelToken,
+    ) -> Result<Option<Mesh2D>> {
+        let mut out = Mesh2D {
+            vertices: Vec::new(),
+            indices: Vec::new(),
+        };
+        let mut boxes: Vec<Box2D> = Vec::new();
+
+        for glyph in glyphs {
+            let mesh =
+                self.glyph_mesh(face, fallbacks, glyph.font, glyph.gid, glyph.size, cancel)?;
+            if mesh.indices.is_empty() {
+                continue;
+            }
+            let placed = Box2D::from_points(mesh.vertices.iter().map(|&p| glyph.place(p)));
+            if boxes.iter().any(|other| other.intersects(&placed)) {
+                return Ok(None);
+            }
+            boxes.push(placed);
+            let base = out.vertices.len();
+            anyhow::ensure!(
+                base + mesh.vertices.len() <= u16::MAX as usize + 1,
+                "text is too long for a single mesh (more than {} vertices)",
+                u16::MAX as usize + 1
+            );
+            out.vertices
+                .extend(mesh.vertices.iter().map(|&p| glyph.place(p)));
+            out.indices
+                .extend(mesh.indices.iter().map(|&i| i + base as u16));
+        }
+
+        Ok(Some(out))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{font, Options};
+
+    #[test]
+    fn overlapping_glyphs_are_not_assembled() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let options = Options {
+            text: "AB".into(),
+            ..Default::default()
+        };
+        let mut glyphs = crate::layout(&face, &options).glyphs;
+        let mut cache = GlyphMeshCache::new(options.scale(&face), options.tolerance(&face));
+        let cancel = CancelToken::new();
+        let assembled = cache.assemble(&face, &[], &glyphs, &cancel).unwrap();
+        assert!(assembled.is_some());
+
+        // B drawn over A
+        glyphs[1].x = glyphs[0].x + 1.0;
+        let assembled = cache.assemble(&face, &[], &glyphs, &cancel).unwrap();
+        assert!(assembled.is_none());
+    }
+}
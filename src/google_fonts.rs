@@ -0,0 +1,166 @@
+//! Fonts fetched by family name from Google Fonts (`--google-font`).
+//!
+//! The CSS API answers a client it does not recognise as a browser with one
+//! `@font-face` per style whose `src` is a plain TrueType file, so asking
+//! for the stylesheet and then the file it names gives the whole font. Both
+//! requests go through the `curl` command, and the font is kept in a cache
+//! directory so later renders work offline.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{Context, Result};
+
+use crate::font::FontStyle;
+
+const CSS_API: &str = "https://fonts.googleapis.com/css2";
+
+/// Directory fetched fonts are kept in: `wagyan/google-fonts` under
+/// `$XDG_CACHE_HOME`, `~/.cache` or `%LOCALAPPDATA%`
+pub fn cache_dir() -> Result<PathBuf> {
+    let var = |name| std::env::var_os(name).filter(|value| !value.is_empty());
+    let base = var("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| var("HOME").map(|home| Path::new(&home).join(".cache")))
+        .or_else(|| var("LOCALAPPDATA").map(PathBuf::from))
+        .context("no cache directory for --google-font (set XDG_CACHE_HOME or HOME)")?;
+    Ok(base.join("wagyan").join("google-fonts"))
+}
+
+/// Cached file of `family` in `style`, e.g. `ZenMaruGothic-bold.ttf`
+fn cache_file(dir: &Path, family: &str, style: FontStyle) -> PathBuf {
+    let name: String = family.chars().filter(|c| c.is_alphanumeric()).collect();
+    dir.join(format!("{}-{}.ttf", name, style_name(style)))
+}
+
+fn style_name(style: FontStyle) -> &'static str {
+    match style {
+        FontStyle::Regular => "regular",
+        FontStyle::Bold => "bold",
+        FontStyle::Italic => "italic",
+        FontStyle::BoldItalic => "bold-italic",
+    }
+}
+
+/// `text` percent-encoded for a URL query, leaving only unreserved
+/// characters as they are
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Stylesheet URL for one style of `family`
+fn css_url(family: &str, style: FontStyle) -> String {
+    let name = family
+        .split_whitespace()
+        .map(percent_encode)
+        .collect::<Vec<_>>()
+        .join("+");
+    let axes = match style {
+        FontStyle::Regular => "",
+        FontStyle::Bold => ":wght@700",
+        FontStyle::Italic => ":ital@1",
+        FontStyle::BoldItalic => ":ital,wght@1,700",
+    };
+    format!("{}?family={}{}", CSS_API, name, axes)
+}
+
+/// URL of the first font file a stylesheet points at
+fn font_url(css: &str) -> Option<&str> {
+    let start = css.find("src: url(")? + "src: url(".len();
+    let end = start + css[start..].find(')')?;
+    Some(css[start..end].trim_matches(|c| c == '\'' || c == '"'))
+}
+
+/// Body of a GET request, or `None` when the server answers with an error
+/// status (Google Fonts says 400 to families it does not have). The font
+/// URL comes from the server's stylesheet, so curl only speaks HTTPS,
+/// redirects included, and never reads it as an option.
+fn download(url: &str) -> Result<Option<Vec<u8>>> {
+    let output = Command::new("curl")
+        .args(["--silent", "--show-error", "--location", "--fail"])
+        .args(["--proto", "=https", "--proto-redir", "=https", "--", url])
+        .output()
+        .context("--google-font downloads with curl, which could not be run")?;
+    if output.status.success() {
+        return Ok(Some(output.stdout));
+    }
+    // curl exits with 22 when --fail sees an HTTP error status
+    if output.status.code() == Some(22) {
+        return Ok(None);
+    }
+    anyhow::bail!(
+        "failed to download {}: {}",
+        url,
+        String::from_utf8_lossy(&output.stderr).trim()
+    )
+}
+
+/// Path of `family` in `style` from Google Fonts, downloading it into the
+/// [`cache_dir`] the first time
+pub fn fetch(family: &str, style: FontStyle) -> Result<PathBuf> {
+    let path = cache_file(&cache_dir()?, family, style);
+    if path.exists() {
+        return Ok(path);
+    }
+    let css = download(&css_url(family, style))?.with_context(|| {
+        format!(
+            "Google Fonts has no family named \"{}\" in {} (names are as on fonts.google.com, e.g. \"Zen Maru Gothic\")",
+            family,
+            style_name(style)
+        )
+    })?;
+    let css = String::from_utf8_lossy(&css);
+    let url = font_url(&css).context("Google Fonts answered without a font file")?;
+    let bytes = download(url)?.with_context(|| format!("failed to download {}", url))?;
+    crate::font::parse_face(&bytes, 0)
+        .with_context(|| format!("Google Fonts sent an unreadable font for \"{}\"", family))?;
+    crate::output::write_atomically(&path, |file| Ok(file.write_all(&bytes)?))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stylesheet_urls_name_the_style_axes() {
+        assert_eq!(
+            css_url(" Zen Maru  Gothic ", FontStyle::Regular),
+            "https://fonts.googleapis.com/css2?family=Zen+Maru+Gothic"
+        );
+        assert_eq!(
+            css_url("Noto Serif JP", FontStyle::BoldItalic),
+            "https://fonts.googleapis.com/css2?family=Noto+Serif+JP:ital,wght@1,700"
+        );
+        assert_eq!(
+            css_url("M PLUS 1p&subset=x#", FontStyle::Regular),
+            "https://fonts.googleapis.com/css2?family=M+PLUS+1p%26subset%3Dx%23"
+        );
+        assert_eq!(
+            css_url("源ノ角", FontStyle::Regular),
+            "https://fonts.googleapis.com/css2?family=%E6%BA%90%E3%83%8E%E8%A7%92"
+        );
+        assert_eq!(
+            cache_file(Path::new("cache"), "Zen Maru Gothic", FontStyle::Bold),
+            Path::new("cache").join("ZenMaruGothic-bold.ttf")
+        );
+    }
+
+    #[test]
+    fn font_url_is_the_first_src() {
+        let css = "@font-face {\n  font-family: 'Zen Maru Gothic';\n  src: url(https://fonts.gstatic.com/s/zenmarugothic/v1/a.ttf) format('truetype');\n}\n";
+        assert_eq!(
+            font_url(css),
+            Some("https://fonts.gstatic.com/s/zenmarugothic/v1/a.ttf")
+        );
+        assert_eq!(font_url("/* no fonts */"), None);
+    }
+}
@@ -0,0 +1,371 @@
+//! OpenType features: the GSUB substitutions of `--features` (ligatures,
+//! small caps, stylistic sets, contextual alternates) applied to a run of
+//! glyphs before it is positioned
+
+use ttf_parser::gsub::SingleSubstitution;
+use ttf_parser::gsub::SubstitutionSubtable;
+use ttf_parser::opentype_layout::{
+    ChainedContextLookup, ContextLookup, LayoutTable, SequenceLookupRecord,
+};
+use ttf_parser::{Face, GlyphId, Tag};
+
+/// A glyph of a run with the character it was looked up for; a ligature
+/// keeps the first character of its components
+pub type RunGlyph = (GlyphId, char);
+
+/// Contextual lookups may call further lookups; fonts that nest deeper than
+/// this (or loop) stop being substituted there
+const MAX_NESTING: usize = 8;
+
+/// Parse a feature tag such as `liga` or `ss01`
+pub fn parse_tag(s: &str) -> Result<Tag, String> {
+    if (1..=4).contains(&s.len()) && s.bytes().all(|b| b.is_ascii_graphic()) {
+        let mut tag = [b' '; 4];
+        tag[..s.len()].copy_from_slice(s.as_bytes());
+        Ok(Tag::from_bytes(&tag))
+    } else {
+        Err(format!(
+            "feature tags are 1 to 4 ASCII characters, got: {}",
+            s
+        ))
+    }
+}
+
+/// Whether the font's GSUB table has `feature` in any script
+pub fn has_feature(face: &Face<'_>, feature: Tag) -> bool {
+    face.tables()
+        .gsub
+        .is_some_and(|gsub| gsub.features.into_iter().any(|f| f.tag == feature))
+}
+
+/// Lookups of the `features` in the order they apply, from a GSUB or GPOS
+/// table. Features are taken from every script, so Latin and Japanese in one
+/// line both get theirs.
+pub(crate) fn feature_lookups(gsub: &LayoutTable<'_>, features: &[Tag]) -> Vec<u16> {
+    let mut lookups: Vec<u16> = gsub
+        .features
+        .into_iter()
+        .filter(|feature| features.contains(&feature.tag))
+        .flat_map(|feature| feature.lookup_indices)
+        .collect();
+    lookups.sort_unstable();
+    lookups.dedup();
+    lookups
+}
+
+/// Apply the GSUB lookups of `features` to `run`. Lookup flags that skip
+/// marks are not honored, which only matters for mark-heavy scripts.
+pub fn substitute(face: &Face<'_>, features: &[Tag], run: &mut Vec<RunGlyph>) {
+    if features.is_empty() {
+        return;
+    }
+    let Some(gsub) = face.tables().gsub else {
+        return;
+    };
+    for index in feature_lookups(&gsub, features) {
+        let reverse = gsub.lookups.get(index).is_some_and(|lookup| {
+            lookup
+                .subtables
+                .get::<SubstitutionSubtable>(0)
+                .is_some_and(|subtable| subtable.is_reverse())
+        });
+        if reverse {
+            for at in (0..run.len()).rev() {
+                apply(&gsub, index, run, at, 0);
+            }
+        } else {
+            let mut at = 0;
+            while at < run.len() {
+                at = apply(&gsub, index, run, at, 0).unwrap_or(at + 1);
+            }
+        }
+    }
+}
+
+/// Apply lookup `index` at `run[at]`: the position after what it replaced,
+/// `None` when none of its subtables matched
+fn apply(
+    gsub: &LayoutTable<'_>,
+    index: u16,
+    run: &mut Vec<RunGlyph>,
+    at: usize,
+    depth: usize,
+) -> Option<usize> {
+    let lookup = gsub.lookups.get(index)?;
+    let glyph = run.get(at)?.0;
+    for subtable in lookup.subtables.into_iter::<SubstitutionSubtable>() {
+        let Some(covered) = subtable.coverage().get(glyph) else {
+            continue;
+        };
+        let next = match subtable {
+            SubstitutionSubtable::Single(SingleSubstitution::Format1 { delta, .. }) => {
+                run[at].0 = GlyphId(glyph.0.wrapping_add(delta as u16));
+                Some(at + 1)
+            }
+            SubstitutionSubtable::Single(SingleSubstitution::Format2 { substitutes, .. }) => {
+                run[at].0 = substitutes.get(covered)?;
+                Some(at + 1)
+            }
+            SubstitutionSubtable::Multiple(table) => {
+                let ch = run[at].1;
+                let glyphs: Vec<_> = table
+                    .sequences
+                    .get(covered)?
+                    .substitutes
+                    .into_iter()
+                    .map(|gid| (gid, ch))
+                    .collect();
+                let len = glyphs.len();
+                run.splice(at..at + 1, glyphs);
+                Some(at + len)
+            }
+            // The first alternate; feature values that pick others are not supported
+            SubstitutionSubtable::Alternate(table) => {
+                run[at].0 = table.alternate_sets.get(covered)?.alternates.get(0)?;
+                Some(at + 1)
+            }
+            SubstitutionSubtable::Ligature(table) => table
+                .ligature_sets
+                .get(covered)?
+                .into_iter()
+                .find_map(|ligature| {
+                    let len = ligature.components.len() as usize;
+                    let rest = run.get(at + 1..at + 1 + len)?;
+                    let matched = rest
+                        .iter()
+                        .zip(ligature.components)
+                        .all(|(glyph, component)| glyph.0 == component);
+                    matched.then(|| {
+                        run[at].0 = ligature.glyph;
+                        run.drain(at + 1..at + 1 + len);
+                        at + 1
+                    })
+                }),
+            SubstitutionSubtable::Context(table) => context(gsub, &table, run, at, covered, depth),
+            SubstitutionSubtable::ChainContext(table) => {
+                chain_context(gsub, &table, run, at, covered, depth)
+            }
+            SubstitutionSubtable::ReverseChainSingle(table) => {
+                let back = table
+                    .backtrack_coverages
+                    .into_iter()
+                    .enumerate()
+                    .all(|(k, c)| at.checked_sub(k + 1).is_some_and(|i| c.contains(run[i].0)));
+                let ahead = table
+                    .lookahead_coverages
+                    .into_iter()
+                    .enumerate()
+                    .all(|(k, c)| run.get(at + 1 + k).is_some_and(|g| c.contains(g.0)));
+                if back && ahead {
+                    run[at].0 = table.substitutes.get(covered)?;
+                    Some(at + 1)
+                } else {
+                    None
+                }
+            }
+        };
+        if next.is_some() {
+            return next;
+        }
+    }
+    None
+}
+
+/// Which part of a context a glyph is tested against
+#[derive(Clone, Copy)]
+enum Part {
+    Backtrack,
+    Input,
+    Lookahead,
+}
+
+/// Whether the glyphs around `run[at]` match a context: `test(part, k,
+/// glyph)` checks the k-th glyph back from `at`, the k-th input glyph after
+/// `run[at]` and the k-th glyph after the input
+fn matches(
+    run: &[RunGlyph],
+    at: usize,
+    counts: [usize; 3],
+    test: impl Fn(Part, usize, GlyphId) -> bool,
+) -> bool {
+    let [backtrack, input, lookahead] = counts;
+    (0..backtrack).all(|k| {
+        at.checked_sub(k + 1)
+            .is_some_and(|i| test(Part::Backtrack, k, run[i].0))
+    }) && (0..input).all(|k| {
+        run.get(at + 1 + k)
+            .is_some_and(|g| test(Part::Input, k, g.0))
+    }) && (0..lookahead).all(|k| {
+        run.get(at + 1 + input + k)
+            .is_some_and(|g| test(Part::Lookahead, k, g.0))
+    })
+}
+
+/// Run the nested lookups of a matched context over its `len` input glyphs
+/// starting at `at`; the position after the (possibly resized) input
+fn apply_records(
+    gsub: &LayoutTable<'_>,
+    records: impl IntoIterator<Item = SequenceLookupRecord>,
+    run: &mut Vec<RunGlyph>,
+    at: usize,
+    len: usize,
+    depth: usize,
+) -> usize {
+    let mut end = at + len;
+    if depth < MAX_NESTING {
+        for record in records {
+            let position = at + record.sequence_index as usize;
+            if position >= end {
+                continue;
+            }
+            let before = run.len();
+            apply(gsub, record.lookup_list_index, run, position, depth + 1);
+            end = (end + run.len()).saturating_sub(before);
+        }
+    }
+    end
+}
+
+fn context(
+    gsub: &LayoutTable<'_>,
+    table: &ContextLookup<'_>,
+    run: &mut Vec<RunGlyph>,
+    at: usize,
+    covered: u16,
+    depth: usize,
+) -> Option<usize> {
+    let (rule_set, class) = match table {
+        ContextLookup::Format1 { sets, .. } => (sets.get(covered)?, None),
+        ContextLookup::Format2 { classes, sets, .. } => {
+            (sets.get(classes.get(run[at].0))?, Some(classes))
+        }
+        ContextLookup::Format3 {
+            coverages, lookups, ..
+        } => {
+            let input = coverages.len() as usize;
+            let found = matches(run, at, [0, input, 0], |_, k, glyph| {
+                coverages.get(k as u16).is_some_and(|c| c.contains(glyph))
+            });
+            return found.then(|| apply_records(gsub, *lookups, run, at, input + 1, depth));
+        }
+    };
+    let rule = rule_set.into_iter().find(|rule| {
+        matches(run, at, [0, rule.input.len() as usize, 0], |_, k, glyph| {
+            let value = class.map_or(glyph.0, |classes| classes.get(glyph));
+            rule.input.get(k as u16) == Some(value)
+        })
+    })?;
+    let len = rule.input.len() as usize + 1;
+    Some(apply_records(gsub, rule.lookups, run, at, len, depth))
+}
+
+fn chain_context(
+    gsub: &LayoutTable<'_>,
+    table: &ChainedContextLookup<'_>,
+    run: &mut Vec<RunGlyph>,
+    at: usize,
+    covered: u16,
+    depth: usize,
+) -> Option<usize> {
+    let (rule_set, classes) = match table {
+        ChainedContextLookup::Format1 { sets, .. } => (sets.get(covered)?, None),
+        ChainedContextLookup::Format2 {
+            backtrack_classes,
+            input_classes,
+            lookahead_classes,
+            sets,
+            ..
+        } => (
+            sets.get(input_classes.get(run[at].0))?,
+            Some([backtrack_classes, input_classes, lookahead_classes]),
+        ),
+        ChainedContextLookup::Format3 {
+            backtrack_coverages,
+            input_coverages,
+            lookahead_coverages,
+            lookups,
+            ..
+        } => {
+            let counts = [
+                backtrack_coverages.len() as usize,
+                input_coverages.len() as usize,
+                lookahead_coverages.len() as usize,
+            ];
+            let found = matches(run, at, counts, |part, k, glyph| {
+                let coverages = match part {
+                    Part::Backtrack => backtrack_coverages,
+                    Part::Input => input_coverages,
+                    Part::Lookahead => lookahead_coverages,
+                };
+                coverages.get(k as u16).is_some_and(|c| c.contains(glyph))
+            });
+            let len = counts[1] + 1;
+            return found.then(|| apply_records(gsub, *lookups, run, at, len, depth));
+        }
+    };
+    let rule = rule_set.into_iter().find(|rule| {
+        let counts = [
+            rule.backtrack.len() as usize,
+            rule.input.len() as usize,
+            rule.lookahead.len() as usize,
+        ];
+        matches(run, at, counts, |part, k, glyph| {
+            let (values, class) = match part {
+                Part::Backtrack => (rule.backtrack, classes.map(|c| c[0])),
+                Part::Input => (rule.input, classes.map(|c| c[1])),
+                Part::Lookahead => (rule.lookahead, classes.map(|c| c[2])),
+            };
+            let value = class.map_or(glyph.0, |classes| classes.get(glyph));
+            values.get(k as u16) == Some(value)
+        })
+    })?;
+    let len = rule.input.len() as usize + 1;
+    Some(apply_records(gsub, rule.lookups, run, at, len, depth))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font;
+
+    fn run(face: &Face<'_>, text: &str) -> Vec<RunGlyph> {
+        text.chars()
+            .map(|ch| (face.glyph_index(ch).unwrap(), ch))
+            .collect()
+    }
+
+    #[test]
+    fn features_substitute_glyphs() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let plain = run(&face, "ffi fl");
+
+        let mut unchanged = plain.clone();
+        substitute(&face, &[], &mut unchanged);
+        substitute(&face, &[parse_tag("zzzz").unwrap()], &mut unchanged);
+        assert_eq!(unchanged, plain);
+
+        // Ligatures merge their components, keeping the first character
+        let mut ligated = plain.clone();
+        substitute(&face, &[parse_tag("liga").unwrap()], &mut ligated);
+        let chars: String = ligated.iter().map(|g| g.1).collect();
+        assert_eq!(chars, "f f");
+        assert_eq!(ligated[1], plain[3]);
+
+        // Full-width forms replace each glyph one for one
+        let mut wide = plain.clone();
+        substitute(&face, &[parse_tag("fwid").unwrap()], &mut wide);
+        assert_eq!(wide.len(), plain.len());
+        assert!(wide
+            .iter()
+            .zip(&plain)
+            .all(|(w, p)| w.0 != p.0 && w.1 == p.1));
+
+        // Discretionary ligatures squeeze a katakana unit into one square
+        let mut unit = run(&face, "キロメートル");
+        substitute(&face, &[parse_tag("dlig").unwrap()], &mut unit);
+        assert_eq!(unit.len(), 1);
+
+        assert!(parse_tag("ss01").is_ok());
+        assert!(parse_tag("stylistic").is_err());
+    }
+}
@@ -0,0 +1,396 @@
+//! Single-stroke (Hershey) fonts: glyphs are open polylines instead of
+//! filled outlines, for engraving, plotting and cutting along a centerline.
+//!
+//! Reads the `.jhf` format: one glyph per record, a 5-column glyph number,
+//! a 3-column vertex count, then two characters per vertex (coordinate =
+//! character − 'R', y pointing down, `" R"` lifting the pen). The first
+//! vertex holds the left and right bearings. Records may wrap over several
+//! lines. Glyphs map to ASCII in file order, starting at the space.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use lyon_path::math::Point;
+
+use crate::cancel::CancelToken;
+use crate::layout::Shaping;
+use crate::mesh::{Anchor, Mesh2D};
+use crate::wrap::{self, TextLine};
+use crate::{i18n, layout, outline, resolve_tolerance, Align, LineHeight, Options, SizeTarget};
+
+/// Font units per em: the usual line spacing of the Hershey fonts
+pub const EM_UNITS: f32 = 32.0;
+/// Capital height of the Roman Hershey fonts in font units
+const CAP_HEIGHT: f32 = 21.0;
+/// Baseline in glyph coordinates (y down)
+const BASELINE: f32 = 9.0;
+
+#[derive(Debug, Clone)]
+struct Glyph {
+    left: f32,
+    right: f32,
+    /// Strokes in glyph coordinates, y down
+    strokes: Vec<Vec<(f32, f32)>>,
+}
+
+/// Parsed single-stroke font
+#[derive(Debug, Clone)]
+pub struct HersheyFont {
+    glyphs: Vec<Glyph>,
+}
+
+fn coordinate(ch: char) -> f32 {
+    (ch as i32 - 'R' as i32) as f32
+}
+
+impl HersheyFont {
+    pub fn parse(source: &str) -> Result<Self> {
+        let chars: Vec<char> = source
+            .chars()
+            .filter(|c| !matches!(c, '\n' | '\r'))
+            .collect();
+        let mut glyphs = Vec::new();
+        let mut rest = chars.as_slice();
+        while rest.iter().any(|c| !c.is_whitespace()) {
+            let record = glyphs.len() + 1;
+            anyhow::ensure!(rest.len() >= 8, "glyph {}: truncated header", record);
+            let count: String = rest[5..8].iter().collect();
+            let count: usize = count
+                .trim()
+                .parse()
+                .with_context(|| format!("glyph {}: invalid vertex count '{}'", record, count))?;
+            anyhow::ensure!(count > 0, "glyph {} has no bearings", record);
+            let end = 8 + count * 2;
+            anyhow::ensure!(rest.len() >= end, "glyph {}: truncated vertices", record);
+
+            let mut pairs = rest[8..end].chunks(2).map(|p| (p[0], p[1]));
+            let (left, right) = pairs.next().unwrap_or(('R', 'R'));
+            let mut strokes = vec![Vec::new()];
+            for (x, y) in pairs {
+                if (x, y) == (' ', 'R') {
+                    strokes.push(Vec::new());
+                } else if let Some(stroke) = strokes.last_mut() {
+                    stroke.push((coordinate(x), coordinate(y)));
+                }
+            }
+            strokes.retain(|stroke| !stroke.is_empty());
+            glyphs.push(Glyph {
+                left: coordinate(left),
+                right: coordinate(right),
+                strokes,
+            });
+            rest = &rest[end..];
+        }
+        anyhow::ensure!(!glyphs.is_empty(), "no glyphs in the stroke font");
+        Ok(Self { glyphs })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("cannot read stroke font {}", path.display()))?;
+        Self::parse(&source).with_context(|| format!("invalid stroke font {}", path.display()))
+    }
+
+    fn glyph(&self, ch: char) -> Option<&Glyph> {
+        let index = (ch as usize).checked_sub(' ' as usize)?;
+        self.glyphs.get(index)
+    }
+
+    /// Lay out the text as polylines in mm: lines one em apart (times
+    /// `options.line_height`), the first baseline at y = 0, each placed as
+    /// `options.align` says; anchored at [`Anchor::BaselineLeft`], the first
+    /// line starts at x = 0. Characters the font lacks are skipped with a
+    /// warning.
+    pub fn polylines(&self, options: &Options) -> Vec<Vec<Point>> {
+        let scale = self.scale(options);
+        let style = Style::new(options, scale);
+        let text = options.laid_out_text();
+        let lines = match options.max_width {
+            Some(width) => wrap::wrap_lines(&text, width, options.break_anywhere, |line| {
+                self.line_width(line, scale, &style)
+            }),
+            None => text_lines(&text),
+        };
+        let mut polylines = self.layout(
+            &lines,
+            scale,
+            &style,
+            options.line_height,
+            options.align,
+            options.max_width,
+        );
+        if options.center && options.text_anchor() == Anchor::BaselineLeft {
+            let widths: Vec<f32> = lines
+                .iter()
+                .map(|line| self.line_width(&line.text, scale, &style))
+                .collect();
+            let width = options
+                .max_width
+                .unwrap_or(widths.iter().copied().fold(0.0, f32::max));
+            let first = widths.first().copied().unwrap_or(0.0);
+            let start = (width - first).max(0.0) * options.align.factor();
+            outline::translate(&mut polylines, -start, 0.0);
+        }
+        polylines
+    }
+
+    /// Millimeters per font unit
+    fn scale(&self, options: &Options) -> f32 {
+        // An absolute line height has no meaning before the scale is known
+        let line_height = match options.line_height {
+            LineHeight::Mm(_) => LineHeight::default(),
+            factor => factor,
+        };
+        let unit = self.layout(
+            &text_lines(&options.laid_out_text()),
+            1.0,
+            &Style::new(options, 1.0),
+            line_height,
+            Align::Left,
+            None,
+        );
+        match options.size_target {
+            None => options.size / EM_UNITS,
+            Some(SizeTarget::CapHeight(mm)) => mm / CAP_HEIGHT,
+            Some(SizeTarget::Height(mm)) => {
+                let ys = unit.iter().flatten().map(|p| p.y);
+                let (lo, hi) = ys.fold((f32::MAX, f32::MIN), |(lo, hi), y| (lo.min(y), hi.max(y)));
+                if hi > lo {
+                    mm / (hi - lo)
+                } else {
+                    options.size / EM_UNITS
+                }
+            }
+        }
+    }
+
+    /// The lettering drawn with a round tool `width` mm wide, and the
+    /// centerlines it follows; with `options.center` both are placed on the
+    /// origin by `options.anchor`
+    pub fn stroke_text(
+        &self,
+        options: &Options,
+        width: f32,
+        cancel: &CancelToken,
+    ) -> Result<(Mesh2D, Vec<Vec<Point>>)> {
+        let mut lines = self.polylines(options);
+        let bounds = lines.iter().flatten().fold(None, |acc, p| match acc {
+            None => Some((p.x, p.x, p.y, p.y)),
+            Some((x0, x1, y0, y1)) => Some((p.x.min(x0), p.x.max(x1), p.y.min(y0), p.y.max(y1))),
+        });
+        let bounds = bounds.context("the text has no visible glyphs")?;
+        if options.center {
+            let (dx, dy) = options.text_anchor().offset(bounds);
+            outline::translate(&mut lines, dx, dy);
+        }
+        let tolerance = resolve_tolerance(self.scale(options) * EM_UNITS, options.tolerance);
+        let mesh = outline::fill(
+            &outline::stroke(&lines, width, tolerance),
+            tolerance,
+            cancel,
+        )?;
+        Ok((mesh, lines))
+    }
+
+    fn layout(
+        &self,
+        text: &[TextLine],
+        scale: f32,
+        style: &Style,
+        line_height: LineHeight,
+        align: Align,
+        width: Option<f32>,
+    ) -> Vec<Vec<Point>> {
+        let mut lines = Vec::new();
+        let widths: Vec<f32> = text
+            .iter()
+            .map(|line| self.line_width(&line.text, scale, style))
+            .collect();
+        let width = width.unwrap_or(widths.iter().copied().fold(0.0, f32::max));
+        let any_wrapped = text.iter().any(|line| line.wrapped);
+        for (index, line) in text.iter().enumerate() {
+            let baseline = -(index as f32) * line_height.resolve(EM_UNITS * scale);
+            let shortfall = (width - widths[index]).max(0.0);
+            let drawn: Vec<char> = line
+                .text
+                .chars()
+                .filter(|&ch| self.glyph(ch).is_some())
+                .collect();
+            // As in layout::align_lines: once wrapped, paragraph ends stay natural
+            let stretch = match any_wrapped {
+                true => line.wrapped,
+                false => index + 1 < text.len(),
+            };
+            let shifts = if align == Align::Justify && stretch && drawn.len() > 1 {
+                layout::justify_shifts(&drawn, shortfall)
+            } else {
+                vec![shortfall * align.factor(); drawn.len()]
+            };
+            let mut shifts = shifts.into_iter();
+            let mut pen_x = 0.0;
+            for ch in line.text.chars() {
+                if ch == '\t' {
+                    pen_x = style.tab_stop(pen_x);
+                    continue;
+                }
+                let Some(glyph) = self.glyph(ch) else {
+                    eprintln!("⚠️ {}", i18n::Message::MissingGlyph(ch));
+                    continue;
+                };
+                let (start, advance) = style.shaping.cell((glyph.right - glyph.left) * scale);
+                let x = pen_x + start + shifts.next().unwrap_or(0.0);
+                for stroke in &glyph.strokes {
+                    lines.push(
+                        stroke
+                            .iter()
+                            .map(|&(gx, gy)| {
+                                let height = BASELINE - gy;
+                                Point::new(
+                                    x + (gx - glyph.left + height * style.shear) * scale,
+                                    baseline + height * scale,
+                                )
+                            })
+                            .collect(),
+                    );
+                }
+                pen_x += advance + style.gap(ch);
+            }
+        }
+        lines
+    }
+
+    /// Advance of `line` without the gap after its last glyph
+    fn line_width(&self, line: &str, scale: f32, style: &Style) -> f32 {
+        let (mut pen_x, mut width) = (0.0, 0.0);
+        for ch in line.chars() {
+            if ch == '\t' {
+                pen_x = style.tab_stop(pen_x);
+                continue;
+            }
+            let Some(glyph) = self.glyph(ch) else {
+                continue;
+            };
+            let advance = style.shaping.cell((glyph.right - glyph.left) * scale).1;
+            width = pen_x + advance;
+            pen_x += advance + style.gap(ch);
+        }
+        width
+    }
+}
+
+/// How the glyphs are spaced in mm at a scale, and slanted
+struct Style {
+    /// Em-based tracking and word spacing, the `monospace` cell and tab stops
+    shaping: Shaping,
+    spacing: f32,
+    em: f32,
+    /// See [`Options::shear`]
+    shear: f32,
+}
+
+impl Style {
+    fn new(options: &Options, scale: f32) -> Self {
+        Style {
+            shaping: Shaping {
+                tracking: options.tracking,
+                word_spacing: options.word_spacing,
+                monospace: options.monospace,
+                tab_width: options.tab_width,
+                ..Shaping::default()
+            },
+            spacing: options.spacing,
+            em: EM_UNITS * scale,
+            shear: options.shear(),
+        }
+    }
+
+    /// Space after `ch`: `spacing` plus tracking and word spacing
+    fn gap(&self, ch: char) -> f32 {
+        self.spacing + self.shaping.extra_space(ch, self.em)
+    }
+
+    fn tab_stop(&self, pen_x: f32) -> f32 {
+        self.shaping.tab_stop(pen_x, self.em)
+    }
+}
+
+/// `text` split at its `\n`s, unwrapped
+fn text_lines(text: &str) -> Vec<TextLine> {
+    text.split('\n')
+        .map(|line| TextLine {
+            text: line.to_string(),
+            wrapped: false,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Space and '!' from the Roman Simplex font; the '!' record wraps
+    const FONT: &str = "12345  1JZ\n  714  9MWRFRT RRYQ\nZR[SZRY\n";
+
+    #[test]
+    fn glyphs_become_polylines_on_the_baseline() {
+        let font = HersheyFont::parse(FONT).unwrap();
+        let options = Options {
+            text: "! !".into(),
+            size_target: Some(SizeTarget::CapHeight(21.0)),
+            ..Default::default()
+        };
+        let lines = font.polylines(&options);
+        // Stem and dot for each '!'
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], [Point::new(5.0, 21.0), Point::new(5.0, 7.0)]);
+        assert_eq!(lines[1].len(), 5);
+        assert_eq!(lines[1][2], Point::new(5.0, 0.0));
+        // '!' is 10 units wide, the space 16
+        assert_eq!(lines[2][0].x, 31.0);
+
+        assert!(HersheyFont::parse("12345  3JZ").is_err());
+
+        // A 1 mm tool widens the centered strokes by half a millimeter all round
+        let (mesh, lines) = font
+            .stroke_text(&options, 1.0, &CancelToken::new())
+            .unwrap();
+        let (min_x, max_x, min_y, max_y) = crate::mesh::mesh_bounds(&mesh).unwrap();
+        assert!((max_y - lines[0][0].y - 0.5).abs() < 0.01);
+        assert!((min_x + max_x).abs() < 0.01 && (min_y + max_y).abs() < 0.01);
+    }
+
+    #[test]
+    fn short_lines_align_right() {
+        let font = HersheyFont::parse(FONT).unwrap();
+        let options = Options {
+            text: "! !\n!".into(),
+            size_target: Some(SizeTarget::CapHeight(21.0)),
+            align: Align::Right,
+            ..Default::default()
+        };
+        let lines = font.polylines(&options);
+        // The second line's '!' sits under the first line's last one
+        assert_eq!(lines[4][0].x, lines[2][0].x);
+
+        // Justified, the first line's '!'s reach both ends of 50 mm
+        let options = Options {
+            align: Align::Justify,
+            max_width: Some(50.0),
+            ..options
+        };
+        let lines = font.polylines(&options);
+        assert_eq!(lines[0][0].x, 5.0);
+        assert_eq!(lines[2][0].x, 45.0);
+        assert_eq!(lines[4][0].x, 5.0);
+
+        // Wrapped at 40 mm, "! ! !" leaves one '!' for a second line
+        let options = Options {
+            text: "! ! !".into(),
+            align: Align::Left,
+            max_width: Some(40.0),
+            ..options
+        };
+        let lines = font.polylines(&options);
+        assert_eq!(lines[4][0], Point::new(5.0, -11.0));
+    }
+}
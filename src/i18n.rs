@@ -0,0 +1,770 @@
+//! Localized messages for people: status lines, warnings, errors and `--help`.
+//! Model files, dry-run reports and JSON keep the same text in every language
+//! so scripts can rely on them.
+
+use std::fmt;
+use std::path::Path;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Language of the human-facing messages
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Lang {
+    #[default]
+    En,
+    Ja,
+}
+
+static CURRENT: AtomicU8 = AtomicU8::new(0);
+
+/// Select the language for the rest of the process
+pub fn set_lang(lang: Lang) {
+    CURRENT.store(lang as u8, Ordering::Relaxed);
+}
+
+/// The language selected with [`set_lang`] (English until then)
+pub fn lang() -> Lang {
+    match CURRENT.load(Ordering::Relaxed) {
+        1 => Lang::Ja,
+        _ => Lang::En,
+    }
+}
+
+impl Lang {
+    /// Language of a POSIX locale name such as `ja_JP.UTF-8`; `None` for
+    /// locales without a translation
+    pub fn from_locale(locale: &str) -> Option<Lang> {
+        let code = locale.split(['_', '.', '@', '-']).next()?;
+        match code.to_ascii_lowercase().as_str() {
+            "ja" => Some(Lang::Ja),
+            "en" | "c" | "posix" => Some(Lang::En),
+            _ => None,
+        }
+    }
+
+    /// Language from the environment: `WAGYAN_LANG`, then the usual
+    /// `LC_ALL`, `LC_MESSAGES` and `LANG`, the first one set deciding
+    pub fn from_env() -> Lang {
+        ["WAGYAN_LANG", "LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Lang::from_locale(&value))
+            .unwrap_or_default()
+    }
+
+    /// Language for a command line: a `--lang` argument wins over the
+    /// environment. Runs before clap so `--help` comes out localized too.
+    pub fn detect<I: IntoIterator<Item = String>>(args: I) -> Lang {
+        Lang::take_from_args(args).0
+    }
+
+    /// [`Lang::detect`] that also takes the `--lang` arguments it
+    /// understood out of the command line. Clap would take one ahead of a
+    /// subcommand for an argument of the main command, which conflicts with
+    /// the subcommand; values it does not know are left for clap to report.
+    pub fn take_from_args<I: IntoIterator<Item = String>>(args: I) -> (Lang, Vec<String>) {
+        let mut args = args.into_iter();
+        let mut rest = Vec::new();
+        let mut requested = None;
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                rest.push(arg);
+                break;
+            }
+            let (value, separate) = match arg.strip_prefix("--lang") {
+                Some("") => (args.next(), true),
+                Some(value) => (value.strip_prefix('=').map(str::to_string), false),
+                None => (None, false),
+            };
+            match value.as_deref().map(Lang::from_locale) {
+                Some(Some(lang)) => requested = Some(lang),
+                Some(None) if separate => rest.extend([arg, value.unwrap_or_default()]),
+                _ => rest.push(arg),
+            }
+        }
+        rest.extend(args);
+        (requested.unwrap_or_else(Lang::from_env), rest)
+    }
+}
+
+/// A message shown to the user; `Display` renders it in the current [`lang`]
+#[derive(Debug, Clone, Copy)]
+pub enum Message<'a> {
+    /// The font has no glyph for this character
+    MissingGlyph(char),
+    /// The font has no glyph for this character, drawn with --missing-glyph
+    ReplacedGlyph(char),
+    Wrote(&'a Path),
+    /// Context for any error that ends the run
+    ConversionFailed,
+    KerfOnlyDxf,
+    SlicerOnly3mf,
+    /// The named font forbids embedding (OS/2 fsType)
+    RestrictedFont(&'a str),
+    /// The named font only allows preview & print embedding
+    PreviewAndPrintFont(&'a str),
+    /// Text in a joining or reordering script without the shaping feature
+    UnshapedScript,
+    /// The output path has no extension a format is known by
+    UnknownExtension(&'a Path),
+    /// The output exists and neither --force nor --backup was given
+    RefusedOverwrite(&'a Path),
+    /// The same output path given more than once
+    RepeatedOutput(&'a Path),
+    CreateDirectoryFailed(&'a Path),
+    /// Moving the old output (first path) to its backup (second) failed
+    BackupFailed(&'a Path, &'a Path),
+    ReadFontFailed(&'a Path),
+    ParseFontFailed(&'a Path),
+    /// The face index (first) of a font with this many faces (second)
+    FaceIndexOutOfRange(u32, u32),
+    /// The named builtin font, left out of a build without the feature
+    BuiltinFontMissing(&'a str, &'a str),
+    /// No installed family has the name; close matches if any
+    UnknownFamily(&'a str, &'a [&'a str]),
+    /// Writing a file in the named format failed
+    WriteFailed(&'a str, &'a Path),
+    /// Writing the named format to standard output failed
+    WriteStdoutFailed(&'a str),
+    /// --strict with this many characters missing from the font
+    StrictMissing(usize),
+    /// The toolpaths came out empty
+    NothingToCut,
+    /// A negative --weight of this many mm left nothing of the letters
+    ThinnedAway(f32),
+}
+
+impl Message<'_> {
+    pub fn render(&self, lang: Lang) -> String {
+        match (self, lang) {
+            (Message::MissingGlyph(ch), Lang::En) => format!("Skip missing glyph: '{}'", ch),
+            (Message::MissingGlyph(ch), Lang::Ja) => {
+                format!("フォントにない文字を飛ばしました: '{}'", ch)
+            }
+            (Message::ReplacedGlyph(ch), Lang::En) => {
+                format!("Replace missing glyph: '{}'", ch)
+            }
+            (Message::ReplacedGlyph(ch), Lang::Ja) => {
+                format!("フォントにない文字を置き換えました: '{}'", ch)
+            }
+            (Message::Wrote(path), Lang::En) => format!("wrote: {}", path.display()),
+            (Message::Wrote(path), Lang::Ja) => format!("書き出しました: {}", path.display()),
+            (Message::ConversionFailed, Lang::En) => "conversion failed".to_string(),
+            (Message::ConversionFailed, Lang::Ja) => "変換に失敗しました".to_string(),
+            (Message::KerfOnlyDxf, Lang::En) => "--kerf only applies to .dxf output".to_string(),
+            (Message::KerfOnlyDxf, Lang::Ja) => "--kerf は .dxf 出力にだけ効きます".to_string(),
+            (Message::SlicerOnly3mf, Lang::En) => "--slicer only applies to .3mf output".to_string(),
+            (Message::SlicerOnly3mf, Lang::Ja) => "--slicer は .3mf 出力にだけ効きます".to_string(),
+            (Message::RestrictedFont(name), Lang::En) => format!(
+                "{} is marked restricted-license (OS/2 fsType): its vendor allows no embedding or reuse without permission; check the license, then pass --ack-license",
+                name
+            ),
+            (Message::RestrictedFont(name), Lang::Ja) => format!(
+                "{} は埋め込み禁止のライセンス (OS/2 fsType) です。許諾なしの埋め込みや再利用は認められていません。ライセンスを確認したうえで --ack-license を指定してください",
+                name
+            ),
+            (Message::PreviewAndPrintFont(name), Lang::En) => format!(
+                "{} only allows preview & print embedding (OS/2 fsType); check its license before selling what you make with it",
+                name
+            ),
+            (Message::PreviewAndPrintFont(name), Lang::Ja) => format!(
+                "{} はプレビューと印刷のための埋め込みしか認めていません (OS/2 fsType)。作ったものを販売する前にライセンスを確認してください",
+                name
+            ),
+            (Message::UnshapedScript, Lang::En) => "Arabic, Hebrew, Indic and Thai text needs wagyan built with the `shaping` feature; its letters will not join or position correctly".to_string(),
+            (Message::UnshapedScript, Lang::Ja) => "アラビア文字・ヘブライ文字・インド系文字・タイ文字はビルド機能 shaping がないと正しく連結・配置されません".to_string(),
+            (Message::UnknownExtension(path), Lang::En) => format!(
+                "unknown output extension: {} (use .stl, .3mf, .amf, .glb, .gltf, .obj, .ply, .dxf, .svg or .step, optionally followed by .gz or .zst, or pass --format)",
+                path.display()
+            ),
+            (Message::UnknownExtension(path), Lang::Ja) => format!(
+                "出力の拡張子がわかりません: {} (.stl、.3mf、.amf、.glb、.gltf、.obj、.ply、.dxf、.svg、.step のいずれか (後ろに .gz か .zst も可) にするか、--format を指定してください)",
+                path.display()
+            ),
+            (Message::RefusedOverwrite(path), Lang::En) => format!(
+                "refusing to overwrite existing file: {} (use --force to overwrite or --backup to keep a copy)",
+                path.display()
+            ),
+            (Message::RefusedOverwrite(path), Lang::Ja) => format!(
+                "既存のファイルは上書きしません: {} (上書きするなら --force、控えを残すなら --backup)",
+                path.display()
+            ),
+            (Message::RepeatedOutput(path), Lang::En) => {
+                format!("output given more than once: {}", path.display())
+            }
+            (Message::RepeatedOutput(path), Lang::Ja) => {
+                format!("同じ出力が複数回指定されています: {}", path.display())
+            }
+            (Message::CreateDirectoryFailed(path), Lang::En) => {
+                format!("failed to create directory: {}", path.display())
+            }
+            (Message::CreateDirectoryFailed(path), Lang::Ja) => {
+                format!("ディレクトリを作れませんでした: {}", path.display())
+            }
+            (Message::BackupFailed(path, backup), Lang::En) => format!(
+                "failed to back up {} to {}",
+                path.display(),
+                backup.display()
+            ),
+            (Message::BackupFailed(path, backup), Lang::Ja) => format!(
+                "{} を {} に退避できませんでした",
+                path.display(),
+                backup.display()
+            ),
+            (Message::ReadFontFailed(path), Lang::En) => {
+                format!("failed to read font file: {}", path.display())
+            }
+            (Message::ReadFontFailed(path), Lang::Ja) => {
+                format!("フォントファイルを読めませんでした: {}", path.display())
+            }
+            (Message::ParseFontFailed(path), Lang::En) => {
+                format!("failed to parse font file: {}", path.display())
+            }
+            (Message::ParseFontFailed(path), Lang::Ja) => {
+                format!("フォントファイルを解釈できませんでした: {}", path.display())
+            }
+            (Message::FaceIndexOutOfRange(index, count), Lang::En) => format!(
+                "face index {} is out of range (available 0..={}; font has {} face{})",
+                index,
+                count.saturating_sub(1),
+                count,
+                if *count == 1 { "" } else { "s" },
+            ),
+            (Message::FaceIndexOutOfRange(index, count), Lang::Ja) => format!(
+                "フェイス番号 {} は範囲外です (フォントのフェイスは {} 個、0..={})",
+                index,
+                count,
+                count.saturating_sub(1),
+            ),
+            (Message::BuiltinFontMissing(name, feature), Lang::En) => format!(
+                "the builtin {} font needs wagyan built with the `{}` feature; pass a font file with --font instead",
+                name, feature
+            ),
+            (Message::BuiltinFontMissing(name, feature), Lang::Ja) => format!(
+                "内蔵フォント {} を使うには wagyan をビルド機能 `{}` つきでビルドする必要があります。代わりに --font でフォントファイルを指定してください",
+                name, feature
+            ),
+            (Message::UnknownFamily(family, []), Lang::En) => format!(
+                "no installed font family named \"{}\" (pass a font file with --font instead)",
+                family
+            ),
+            (Message::UnknownFamily(family, close), Lang::En) => format!(
+                "no installed font family named \"{}\"; close matches: {}",
+                family,
+                close.join(", ")
+            ),
+            (Message::UnknownFamily(family, []), Lang::Ja) => format!(
+                "\"{}\" という名前のフォントファミリーはインストールされていません (代わりに --font でフォントファイルを指定してください)",
+                family
+            ),
+            (Message::UnknownFamily(family, close), Lang::Ja) => format!(
+                "\"{}\" という名前のフォントファミリーはインストールされていません。近い名前: {}",
+                family,
+                close.join(", ")
+            ),
+            (Message::WriteFailed(format, path), Lang::En) => {
+                format!("failed to write {}: {}", format, path.display())
+            }
+            (Message::WriteFailed(format, path), Lang::Ja) => {
+                format!("{} を書き出せませんでした: {}", format, path.display())
+            }
+            (Message::WriteStdoutFailed(format), Lang::En) => {
+                format!("failed to write {} to stdout", format)
+            }
+            (Message::WriteStdoutFailed(format), Lang::Ja) => {
+                format!("{} を標準出力に書き出せませんでした", format)
+            }
+            (Message::StrictMissing(count), Lang::En) => format!(
+                "--strict: {} character{} missing",
+                count,
+                if *count == 1 { " is" } else { "s are" }
+            ),
+            (Message::StrictMissing(count), Lang::Ja) => {
+                format!("--strict: フォントにない文字が {} 個あります", count)
+            }
+            (Message::NothingToCut, Lang::En) => {
+                "nothing to cut: the letters are narrower than the tool (or --stepover)".to_string()
+            }
+            (Message::ThinnedAway(weight), Lang::En) => format!(
+                "--weight {} thins the letters away: nothing of them is left",
+                weight
+            ),
+            (Message::ThinnedAway(weight), Lang::Ja) => {
+                format!("--weight {} では文字が細りきって何も残りません", weight)
+            }
+            (Message::NothingToCut, Lang::Ja) => {
+                "削るところがありません: 文字が工具 (または --stepover) より細すぎます".to_string()
+            }
+        }
+    }
+}
+
+impl fmt::Display for Message<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render(lang()))
+    }
+}
+
+/// Japanese counterpart of [`crate::UNITS_NOTE`]
+pub const JA_UNITS_NOTE: &str =
+    "単位: 長さはすべてミリメートルです (STL の 1 単位 = 1 mm、スライサーの想定どおり)。\
+--size はフォントの em サイズなので、字形はたいていそれより小さくなります。\
+実寸を合わせたいときは --cap-height-mm か --height-mm を使ってください。";
+
+/// Japanese `--help` text for the arguments of the main command, the font
+/// and output flags the subcommands share included, by clap argument id
+const JA_ARG_HELP: &[(&str, &str)] = &[
+    ("text", "描画するテキスト"),
+    (
+        "glyph_id",
+        "テキストの代わりにフォントのこのグリフ ID を 1 行に並べる (文字に対応しないグリフも可)。繰り返すかカンマ区切りで複数指定",
+    ),
+    (
+        "codepoint",
+        "テキストの代わりにこのコードポイント (U+3042、0x3042、3042) を描く。エスケープやプレースホルダーは処理しない。繰り返すかカンマ区切りで複数指定",
+    ),
+    (
+        "markup",
+        "テキスト中の {size=MM}、{depth=MM}、{font=FILE} タグ ({/size} などで閉じる) で一部だけ文字サイズ・押し出し量・フォントを変える。^{..} と _{..} は上付き・下付き。{{ と }} は波括弧そのもの",
+    ),
+    (
+        "runs",
+        "テキストを JSON のラン一覧 ({\"text\": \"東京\", \"font\": \"jp.otf\", \"size\": 48, \"depth\": 4, \"tracking\": 0.1} など) から読み、順に並べる (\"text\" だけが必須)",
+    ),
+    ("font", "フォントファイル (.ttf/.otf/.woff/.woff2)。省略時は --builtin の内蔵フォント"),
+    (
+        "builtin",
+        "--font なしで使う内蔵フォント: sans (Noto Sans JP Regular)、serif (DejaVu Serif)、mono (DejaVu Sans Mono)。serif と mono に和文はない",
+    ),
+    ("face_index", "フォントコレクション (.ttc) 内のフェイス番号 (0 始まり)"),
+    (
+        "face_name",
+        "フォントコレクション (.ttc) のフェイスを名前で指定 (例: \"Yu Gothic UI Semibold\"、大文字小文字・空白は無視)",
+    ),
+    ("fallback_font", "--font にない文字 (和文に混じる英字や記号など) に使うフォントファイル。複数指定すると順に探す"),
+    ("variation", "可変フォントの軸の値 (wght=700 など)。繰り返すかカンマ区切りで複数指定 (wght=700,wdth=85)"),
+    ("instance", "可変フォントの名前付きインスタンス (\"SemiBold\" など、--list-instances で一覧)。--variation の指定が優先される"),
+    ("family", "フォントファイルの代わりに使うインストール済みフォントのファミリー名 (例: \"Noto Serif JP\")"),
+    (
+        "google_font",
+        "フォントファイルの代わりに Google Fonts からダウンロード (キャッシュ) して使うファミリー名 (例: \"Zen Maru Gothic\"、ビルド機能 google-fonts が必要)",
+    ),
+    ("font_style", "--family または --google-font のスタイル。インストールされている中で最も近いものを使う"),
+    ("ack_license", "埋め込み制限 (OS/2 fsType) のあるフォントを、ライセンス確認済みとして使う"),
+    ("tolerance", "テッセレーションの許容誤差 (小さいほど細かい)。既定値はサイズに比例"),
+    ("spacing", "文字間に足す間隔 (mm)"),
+    ("tracking", "文字間に足す間隔を em 単位で指定 (例: 0.05em)。--size に比例する。負の値で詰める"),
+    ("word_spacing", "空白のあとに足す間隔を em 単位で指定 (例: 0.25em)"),
+    ("weight", "擬似ボールド: アウトラインを外側にこの幅 (mm) だけ太らせる (例: +0.5mm)。負の値で細くする"),
+    ("slant", "擬似イタリック: 全グリフをこの角度 (度) だけ右に傾ける (例: 12)。負の値で左に傾ける"),
+    ("normalize", "レイアウト前の Unicode 正規化: nfc は分解された文字を合成する (か + U+3099 が が になる)。nfkc は全角・半角形も通常の形にする。既定は none"),
+    ("text_transform", "レイアウト前に大文字・小文字を変換する: upper (大文字)、lower (小文字)、title (単語の頭を大文字)、smallcaps (スモールキャップス。フォントに smcp が無ければ縮小した大文字)"),
+    ("monospace", "全グリフの送り幅を固定する (mm)。グリフは幅の中央に置かれ、カーニングはしない"),
+    ("tab_width", "テキスト中のタブが進むタブ位置の間隔 (mm)。既定は 4 em"),
+    ("kerning", "カーニングがあれば適用する (--no-kerning で無効)"),
+    ("no_kerning", "カーニングを無効にする"),
+    ("features", "適用する OpenType 機能 (カンマ区切り)。liga で合字、smcp でスモールキャップ、ss01〜ss20 でスタイルセットなど"),
+    ("shaper", "シェーピングエンジン: builtin または harfbuzz (複雑な文字体系やフォント既定の機能向け。ビルド機能 shaping が必要)"),
+    ("direction", "各行の基本方向: auto は最初のヘブライ文字・アラビア文字・ラテン文字に従う。rtl で右から左"),
+    ("writing_mode", "vertical で縦書き: 各行を上から下への列にし、列は右から左へ並べる (表札向け)"),
+    (
+        "align",
+        "短い行を最も長い行 (または --max-width) に対してどこに置くか: left (左揃え)、center (中央揃え)、right (右揃え、縦書きでは上・中央・下)、justify (最終行以外の語間・字間を広げる両端揃え)",
+    ),
+    (
+        "max_width",
+        "この幅 (mm) を超える行を空白・ハイフン・和文の文字間で禁則処理をして折り返す (、。はぶら下げ)。--align はこの幅で揃え、justify はこの幅まで広げる",
+    ),
+    ("line_height", "行送り (ベースライン間の距離): フォント本来の行の高さの倍率 (0.9、120%) か長さ (14mm)。縦書きでは列の間隔"),
+    ("break_anywhere", "--max-width の折り返しで禁則に反しない任意の文字間での改行を許し、長い単語や空白のない文も行いっぱいに詰める"),
+    (
+        "missing_glyph",
+        "フォントにない文字の扱い: skip (飛ばす)、notdef、box (豆腐の箱)、または代わりの 1 文字",
+    ),
+    ("strict", "フォントにない文字を警告して飛ばす代わりに、一覧つきのエラーで失敗する"),
+    ("no_escape", "\"\\n\" を改行に、\"\\t\" をタブに変換しない"),
+    ("size", "フォントの em サイズ (mm)。字形はこれより小さくなります"),
+    ("cap_height_mm", "大文字の高さをこの値 (mm) に合わせる"),
+    ("height_mm", "全行を含む文字全体の高さをこの値 (mm) に合わせる"),
+    ("depth", "押し出しの深さ (mm)"),
+    ("plate", "背面プレートの厚さ (mm、0 で無効)"),
+    ("plate_margin", "プレートを文字の周りに広げる幅 (mm)"),
+    ("orient", "配置する面 (flat: XY 床面、front: 正面を向いた XZ 面)"),
+    ("no_center", "原点への自動センタリングをしない"),
+    ("anchor", "原点に置くテキストの基準点: バウンディングボックスの中心、1 行目のベースラインの始点、左上の角、下辺の中央"),
+    ("arc_radius", "半径 (mm) の円周に沿ってテキストを曲げ、各文字を接線の向きに回転する (コインやメダル向け)"),
+    ("arc_angle", "最も長い行がこの角度 (度、360 まで) にわたるよう円周に沿って曲げる。--arc-radius と併用すると文字の間隔を広げて埋める"),
+    ("arc_side", "曲げたテキストを読む半円: top (時計回り、文字は外向き) または bottom (文字は中心向き)"),
+    ("path_file", "この SVG ファイルの最初のパスに沿ってテキストを配置し、各文字をパスの向きに回転する (パスの始点から)"),
+    ("warp", "レイアウト後にテキスト全体を変形する: arch (アーチ)、wave (波)、flag (旗)、perspective (遠近)。文字は結合されるのでパーツに分けられない"),
+    ("warp_amount", "--warp の強さ (テキストの高さに対する割合、-1 から 1。負の値で逆向き)"),
+    ("jitter", "各グリフの位置・回転・サイズをランダムにずらして手作り風にする: 最大この em 数・ラジアン・サイズ比まで (0 から 1。0.03 で控えめ)"),
+    ("seed", "--jitter の乱数シード。同じシードなら常に同じ揺らぎになる"),
+    ("mode", "テキストの周りに作るモデル"),
+    ("loop_diameter", "ペンダントの輪の穴の直径 (mm)"),
+    ("loop_width", "ペンダントの輪の幅 (mm)"),
+    ("no_connector", "ペンダントの輪と文字の間をつながない"),
+    ("diameter", "トークン (円盤) の直径 (mm)"),
+    ("thickness", "トークンの厚さ (mm、--engrave では全体の厚さ)"),
+    ("engrave", "文字を浮き出させる代わりにトークンへ --depth の深さで彫り込む"),
+    ("back", "トークンの裏面にも文字を鏡像で入れる"),
+    (
+        "stroke_font",
+        "--font の代わりに使う一筆書きの Hershey フォント (.jhf)。線は --stroke-width の幅に太らせ、.dxf/.svg 出力では中心線を彫刻する",
+    ),
+    ("stroke_width", "--stroke-font の線を引く工具 (またはノズル) の幅 (mm)"),
+    (
+        "vcarve",
+        "この刃先角 (度) の V ビットで文字を V 彫りする。--output は .nc/.gcode (ツールパス) か .pgm (深さのハイトマップ)",
+    ),
+    (
+        "toolpath_offset",
+        "この半径 (mm) のエンドミルで文字を彫る: アウトラインを内側へ順にオフセットしたパス (PASSES で先頭の何周かだけに限定可) を --depth の深さで削る。--output は .dxf か .nc/.gcode",
+    ),
+    ("stepover", "V 彫りのパスの間隔 (mm)。ハイトマップの画素の大きさも兼ねる"),
+    ("list_instances", "可変フォント (--font) の名前付きインスタンスと軸の値を表示して終了"),
+    ("dry_run", "レイアウトだけ行い、外形・行数・三角形数の見積もりを表示 (何も書き出さない)"),
+    ("preview_term", "テッセレーション結果をブロック文字で表示 (何も書き出さない)"),
+    (
+        "emit_placements",
+        "各グリフの文字・ID・位置を、グリフごとに 1 つの共有メッシュとともにこの JSON ファイルにも書き出す (ゲームエンジン向け)",
+    ),
+    (
+        "counter",
+        "{counter} の値。既定は出力パスで使われていれば未使用の最初の番号、なければ 1",
+    ),
+    (
+        "sequence",
+        "START..END (両端を含む) の番号ごとに 1 つずつモデルを作る。番号はテンプレート変数 n で使える",
+    ),
+    ("output", "出力ファイル (既定は標準出力)。拡張子で形式が決まります (.stl/.glb/.obj/.ply/.3mf/.amf/.dxf/.svg)。{date}、{counter[:0N]}、{text} が使えます。繰り返すと同じモデルを複数の形式で書き出します"),
+    ("force", "既存の出力ファイルを上書きする"),
+    (
+        "backup",
+        "新しい出力を書き終えたら既存のファイルを <名前>.bak (使用済みなら <名前>.1.bak、…) に退避する",
+    ),
+    ("quiet", "状況メッセージを出さない (標準出力には STL データしか出ません)"),
+    ("timeout", "生成がこの秒数を超えたら中断する"),
+    ("printer", "プリンタのプリセット: --layer-height の既定値を決め、造形範囲の超過や細すぎる線を警告"),
+    ("layer_height", "高さをこの積層ピッチ (mm) の整数倍に丸める"),
+    ("base_color", "GLB/3MF/AMF 出力の基本色 (#rrggbb または #rrggbbaa)"),
+    ("text_color", "GLB/3MF/AMF 出力の文字の色 (既定は --base-color)"),
+    ("plate_color", "GLB/3MF/AMF 出力のプレートなど文字以外の色 (既定は --base-color)"),
+    ("letter_colors", "文字ごとに順に割り当てる色 (カンマ区切り)。GLB/3MF/AMF 出力用"),
+    ("glyph_groups", "OBJ/GLB/3MF/AMF 出力で文字ごとに名前付きのオブジェクト (A_0、B_1 …) に分ける"),
+    ("smooth_normals", "GLB/OBJ 出力で曲面の側壁をスムーズシェーディングする"),
+    ("crease_angle", "--smooth-normals と同じく側壁を滑らかにし、この角度 (度) より鋭い角はフラットなまま残す"),
+    ("no_normals", "OBJ 出力に法線を書かず、溶接した頂点からインポーター側で陰影を付けさせる"),
+    ("sidecar", "生成メタデータ (フォント、バージョン、再現用ハッシュ) を <出力>.json にも書き出す"),
+    ("format", "出力形式 (ファイルにも標準出力にも有効)。既定は --output の拡張子から判定し、なければ ASCII STL"),
+    ("slicer", "3MF 出力に PrusaSlicer/Bambu Studio 用のプロジェクト情報を入れる: ベッド中央への配置、パーツ名と色ごとのフィラメント、プレートから浮き出た文字の最初の層でのフィラメント交換"),
+    ("units", "書き出す座標の単位。3MF/AMF/DXF/SVG は単位を宣言し、OBJ はコメントに書き、STL/PLY/glTF は換算のみ (AMF に cm はありません)"),
+    ("name", "モデル名: ASCII STL の solid 名 (既定は出力ファイル名)、3MF/AMF のタイトル、OBJ のオブジェクト名"),
+    ("compress", "出力ストリームを圧縮する (既定は --output が .gz なら gzip、.zst なら zstd)"),
+    ("kerf", "レーザーのカーフ幅 (mm)。DXF の CUT レイヤーをその半分だけ太らせる"),
+    ("letter_layers", "DXF で文字ごとに別レイヤー (LETTER-1、LETTER-2 …) に分ける"),
+    ("lang", "メッセージの言語 (既定は LANG などのロケールから判定)"),
+];
+
+/// Japanese `--help` text for the arguments of each subcommand of its own,
+/// by subcommand name and clap argument id
+const JA_SUBCOMMAND_ARG_HELP: &[(&str, &[(&str, &str)])] = &[
+    (
+        "nameplate",
+        &[
+            ("text", "プレートに入れる名前"),
+            ("style", "プレートの外形"),
+            ("height_mm", "全行を含む文字の高さ (mm)"),
+            ("cap_height_mm", "大文字の高さ (mm、--height-mm より優先)"),
+            ("depth", "文字を浮き出させる (または彫る) 深さ (mm)"),
+            ("thickness", "プレートの厚さ (mm)"),
+            ("margin", "文字とプレートの縁の間隔 (mm)"),
+            ("corner_radius", "--style rounded の角の半径 (mm)"),
+            ("rim", "浮き出した縁の幅 (mm、0 で無効)"),
+            ("engrave", "文字を浮き出させる代わりにプレートへ彫り込む"),
+            ("no_holes", "2 つの取り付け穴を開けない"),
+            ("hole_diameter", "取り付け穴の直径 (mm)"),
+            ("orient", "配置する面 (flat: ベッドに寝かせてそのまま印刷できる向き)"),
+        ],
+    ),
+    (
+        "cake-topper",
+        &[
+            ("text", "トッパーの文字 (つながった筆記体のフォントが映える)"),
+            ("height_mm", "全行を含む文字の高さ (mm)"),
+            ("cap_height_mm", "大文字の高さ (mm、--height-mm より優先)"),
+            ("depth", "文字が背板から浮き出す高さ (mm)"),
+            ("thickness", "背板の厚さ (mm)"),
+            ("outline", "背板が文字からはみ出す幅 (mm)"),
+            ("sticks", "スティックの本数"),
+            ("stick_length", "背板から下のスティックの長さ (mm、先端を含む)"),
+            ("stick_width", "スティックの幅 (mm)"),
+            ("orient", "配置する面 (flat: ベッドに寝かせてそのまま印刷できる向き)"),
+        ],
+    ),
+    (
+        "license-plate",
+        &[
+            ("text", "プレートの文字"),
+            ("height_mm", "全行を含む文字の高さ (mm、収まらなければ縮める)"),
+            ("cap_height_mm", "大文字の高さ (mm、--height-mm より優先)"),
+            ("depth", "文字と枠を浮き出させる高さ (mm)"),
+            ("thickness", "プレートの厚さ (mm)"),
+            ("width", "プレートの幅 (mm、既定は 12 インチ)"),
+            ("height", "プレートの高さ (mm、既定は 6 インチ)"),
+            ("corner_radius", "角の半径 (mm)"),
+            ("frame", "浮き出した枠の幅 (mm、0 で無効)"),
+            ("bolts", "ボルト穴: なし (none)、上の列だけ (top)、四隅すべて (all)"),
+            ("bolt_spacing_x", "ボルト穴の中心間の横の距離 (mm、既定は 7 インチ)"),
+            ("bolt_spacing_y", "ボルト穴の中心間の縦の距離 (mm、既定は 4.75 インチ)"),
+            ("bolt_diameter", "ボルト穴の直径 (mm)"),
+            ("orient", "配置する面 (flat: ベッドに寝かせてそのまま印刷できる向き)"),
+        ],
+    ),
+    (
+        "doorsign",
+        &[
+            ("text", "部屋の名前などサインの文字"),
+            ("height_mm", "全行を含む文字の高さ (mm)"),
+            ("cap_height_mm", "大文字の高さ (mm、--height-mm より優先)"),
+            ("depth", "文字を浮き出させる高さ (mm)"),
+            ("thickness", "プレートの厚さ (mm)"),
+            ("margin", "文字とプレートの縁の間隔 (mm)"),
+            ("corner_radius", "角の半径 (mm)"),
+            ("chamfer", "上面の縁の 45° 面取りの大きさ (mm、0 で無効)"),
+            ("mount", "上の両角のネジ穴か、両面テープ用の平らな背面か"),
+            ("hole_diameter", "ネジ穴の直径 (mm)"),
+            ("orient", "配置する面 (flat: ベッドに寝かせてそのまま印刷できる向き)"),
+        ],
+    ),
+    (
+        "cable-tag",
+        &[
+            ("text", "ラベルの文字"),
+            (
+                "batch",
+                "このファイルの 1 行 (CSV なら最初の列) ごとにタグを作る。--output には {n} か {text} が必要",
+            ),
+            ("height_mm", "全行を含む文字の高さ (mm)"),
+            ("cap_height_mm", "大文字の高さ (mm、--height-mm より優先)"),
+            ("depth", "文字を浮き出させる高さ (mm)"),
+            ("thickness", "タグの厚さ (mm)"),
+            ("margin", "文字とスロットの周りの余白 (mm)"),
+            ("corner_radius", "角の半径 (mm)"),
+            ("slot_width", "スロットの長さ (mm): 結束バンドの幅に余裕を足したもの"),
+            ("slot_height", "スロットの高さ (mm): 結束バンドの厚さに余裕を足したもの"),
+            ("slots", "スロットの数 (2 なら両端に 1 つずつ)"),
+            ("orient", "配置する面 (flat: ベッドに寝かせてそのまま印刷できる向き)"),
+        ],
+    ),
+    (
+        "plaque",
+        &[
+            ("lines", "題字、続いて本文 1 行ごとに 1 つの引数"),
+            ("title_height", "題字の大文字の高さ (mm)"),
+            ("body_height", "本文の行の大文字の高さ (mm)"),
+            (
+                "line_heights",
+                "本文の最初の何行かの大文字の高さ (mm、カンマ区切り。残りは --body-height)",
+            ),
+            ("line_gap", "各行の下の間隔 (その行の大文字の高さに対する割合)"),
+            ("depth", "文字を浮き出させる高さ (mm)"),
+            ("thickness", "ベベルを含むプレートの厚さ (mm)"),
+            ("margin", "文字とプレートの縁の間隔 (mm)"),
+            ("corner_radius", "角の半径 (mm)"),
+            ("bevel", "上面の縁の 45° ベベルの大きさ (mm、0 で無効)"),
+            ("no_holes", "四隅のネジ穴を開けない"),
+            ("hole_diameter", "ネジ穴の直径 (mm)"),
+            ("orient", "配置する面 (flat: ベッドに寝かせてそのまま印刷できる向き)"),
+        ],
+    ),
+    (
+        "garden-marker",
+        &[
+            ("text", "植物の名前"),
+            (
+                "batch",
+                "このファイルの 1 行 (CSV なら最初の列) ごとにラベルを作る。--output には {n} か {text} が必要",
+            ),
+            ("height_mm", "全行を含む文字の高さ (mm)"),
+            ("cap_height_mm", "大文字の高さ (mm、--height-mm より優先)"),
+            ("depth", "文字を浮き出させる高さ (mm)"),
+            ("thickness", "ラベルの厚さ (mm)"),
+            ("margin", "文字の周りの余白 (mm)"),
+            ("corner_radius", "札の角の半径 (mm)"),
+            ("stake_length", "札から下の杭の長さ (mm、先端を含む)"),
+            ("stake_width", "杭の幅 (mm)"),
+            ("orient", "配置する面 (flat: ベッドに寝かせてそのまま印刷できる向き)"),
+        ],
+    ),
+    (
+        "model-label",
+        &[
+            ("text", "部屋や建物の名前"),
+            (
+                "batch",
+                "このファイルの 1 行 (CSV なら最初の列) ごとにラベルを作る。--output には {n} か {text} が必要",
+            ),
+            ("cap_height_mm", "大文字の高さ (mm)"),
+            ("depth", "文字を浮き出させる高さ (mm)"),
+            ("thickness", "背板の厚さ (mm)"),
+            ("margin", "文字の周りの背板の幅 (mm)"),
+            ("min_stroke", "文字の線の最小の太さ (mm)。これより細い線はこの太さまで太らせる"),
+            ("orient", "配置する面 (flat: ベッドに寝かせてそのまま印刷できる向き)"),
+        ],
+    ),
+    (
+        "tui",
+        &[
+            ("text", "最初に表示するテキスト"),
+            ("columns", "プレビューの幅 (文字数)"),
+        ],
+    ),
+    (
+        "measure",
+        &[
+            ("text", "測るテキスト"),
+            ("size", "フォントの em サイズ (mm)"),
+            ("cap_height_mm", "大文字の高さがちょうどこの値 (mm) になるよう拡大縮小する (--size より優先)"),
+            ("height_mm", "テキスト全体の高さがちょうどこの値 (mm) になるよう拡大縮小する (--size より優先)"),
+            ("json", "通常のレポートの代わりに JSON を出力する"),
+        ],
+    ),
+    (
+        "coverage",
+        &[
+            ("text", "調べるテキスト"),
+            ("json", "通常のレポートの代わりに JSON を出力する"),
+        ],
+    ),
+    (
+        "font-info",
+        &[("json", "通常のレポートの代わりに JSON を出力する")],
+    ),
+];
+
+/// Japanese `about` text for the subcommands
+const JA_ABOUT: &[(&str, &str)] = &[
+    (
+        "nameplate",
+        "ネームプレート: 角丸プレート、縁、取り付け穴つき",
+    ),
+    (
+        "cake-topper",
+        "ケーキトッパー: 背板でつないだ文字と、ケーキに刺すスティック",
+    ),
+    (
+        "license-plate",
+        "ナンバープレート風の看板: 枠とボルト穴つき、文字は中央",
+    ),
+    (
+        "doorsign",
+        "ドアサイン: 面取りした角丸プレート、中央の文字、ネジ穴または平らな背面",
+    ),
+    (
+        "cable-tag",
+        "ケーブルタグ: 両端に結束バンド用スロットのある小さな札",
+    ),
+    (
+        "plaque",
+        "記念盾: ベベル付きプレート、大きな題字、本文行、四隅のネジ穴",
+    ),
+    (
+        "garden-marker",
+        "植物ラベル: とがった杭の上の札。1 枚でも --batch の一覧でも",
+    ),
+    (
+        "model-label",
+        "模型用ラベル: レジン造形向けに線を太らせた小さな文字と薄い背板",
+    ),
+    (
+        "measure",
+        "行幅・外形・アセント/ディセント・文字送りを表示 (形状は作らない)",
+    ),
+    (
+        "coverage",
+        "フォントとフォールバックフォントにない文字を一覧表示",
+    ),
+    (
+        "font-info",
+        "フォント名・メトリクス・テーブル・OpenType 機能・可変軸を表示",
+    ),
+    (
+        "tui",
+        "サイズ・深さ・プレートを対話的に調整してプレビューし、コマンドを表示",
+    ),
+];
+
+/// Japanese help for an argument id, if translated: one of the main
+/// command (or one every command shares) without `command`, else one of
+/// that subcommand of its own
+pub fn ja_arg_help(command: Option<&str>, id: &str) -> Option<&'static str> {
+    let table = match command {
+        None => JA_ARG_HELP,
+        Some(command) => JA_SUBCOMMAND_ARG_HELP
+            .iter()
+            .find(|(name, _)| *name == command)
+            .map(|(_, table)| *table)?,
+    };
+    table
+        .iter()
+        .find(|(key, _)| *key == id)
+        .map(|(_, help)| *help)
+}
+
+/// Japanese description of a subcommand, if translated
+pub fn ja_about(command: &str) -> Option<&'static str> {
+    JA_ABOUT
+        .iter()
+        .find(|(key, _)| *key == command)
+        .map(|(_, about)| *about)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lang_comes_from_the_flag_then_the_locale() {
+        assert_eq!(Lang::from_locale("ja_JP.UTF-8"), Some(Lang::Ja));
+        assert_eq!(Lang::from_locale("C.UTF-8"), Some(Lang::En));
+        assert_eq!(Lang::from_locale("fr_FR"), None);
+
+        let args = |line: &str| line.split(' ').map(str::to_string).collect::<Vec<_>>();
+        assert_eq!(Lang::detect(args("wagyan --lang ja Hi")), Lang::Ja);
+        assert_eq!(
+            Lang::detect(args("wagyan --lang=ja --lang=en Hi")),
+            Lang::En
+        );
+        // After "--" it is text, not a flag
+        assert_eq!(
+            Lang::detect(args("wagyan --lang ja -- --lang=en")),
+            Lang::Ja
+        );
+        assert_eq!(
+            Lang::take_from_args(args(
+                "wagyan --lang ja nameplate --lang=ja_JP 田中 -- --lang en"
+            )),
+            (Lang::Ja, args("wagyan nameplate 田中 -- --lang en"))
+        );
+        // Left for clap to reject
+        assert_eq!(
+            Lang::take_from_args(args("wagyan --lang fr Hi")).1,
+            args("wagyan --lang fr Hi")
+        );
+
+        assert_eq!(
+            Message::MissingGlyph('☃').render(Lang::En),
+            "Skip missing glyph: '☃'"
+        );
+        assert!(Message::Wrote(Path::new("a.stl"))
+            .render(Lang::Ja)
+            .ends_with("a.stl"));
+        assert!(ja_arg_help(None, "output").is_some() && ja_about("tui").is_some());
+        assert_eq!(
+            ja_arg_help(Some("nameplate"), "style"),
+            Some("プレートの外形")
+        );
+        assert_eq!(ja_arg_help(Some("nameplate"), "output"), None);
+    }
+}
@@ -0,0 +1,359 @@
+//! Indexed 3D meshes with per-vertex normals, for formats that share vertices
+
+use std::collections::HashMap;
+
+use lyon_path::math::Point;
+
+use crate::color::Color;
+use crate::mesh::{boundary_edges, inset_boundary, map_point, Mesh2D, Orientation};
+
+/// Default crease angle for smooth side walls: sharper corners stay faceted
+pub const DEFAULT_CREASE_DEGREES: f32 = 40.0;
+
+/// Texture coordinates repeat every this many mm, on caps and walls alike
+pub const UV_TILE_MM: f32 = 100.0;
+
+/// Y-up coordinates (glTF, OBJ) of a point in the Z-up model
+pub(crate) fn to_y_up(v: [f32; 3]) -> [f32; 3] {
+    [v[0], v[2], -v[1]]
+}
+
+/// Bit patterns of a vertex's position, normal and texture coordinates
+type VertexKey = ([u32; 3], [u32; 3], [u32; 2]);
+
+/// Triangle mesh with shared vertices and per-vertex normals (Z-up)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IndexedMesh {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    /// Texture coordinates: planar on the caps, an unrolled strip along
+    /// each side wall (see [`UV_TILE_MM`])
+    pub uvs: Vec<[f32; 2]>,
+    pub indices: Vec<u32>,
+}
+
+/// A named, colored mesh; one object/node in formats that keep parts apart
+pub struct MeshPart {
+    pub name: String,
+    pub mesh: IndexedMesh,
+    pub color: Color,
+}
+
+impl IndexedMesh {
+    fn push_vertex(&mut self, position: [f32; 3], normal: [f32; 3], uv: [f32; 2]) -> u32 {
+        self.positions.push(position);
+        self.normals.push(normal);
+        self.uvs.push(uv);
+        (self.positions.len() - 1) as u32
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// Axis-aligned bounds as (min, max), `None` when empty
+    pub fn bounds(&self) -> Option<([f32; 3], [f32; 3])> {
+        let first = *self.positions.first()?;
+        Some(self.positions.iter().fold((first, first), |(lo, hi), p| {
+            (
+                [lo[0].min(p[0]), lo[1].min(p[1]), lo[2].min(p[2])],
+                [hi[0].max(p[0]), hi[1].max(p[1]), hi[2].max(p[2])],
+            )
+        }))
+    }
+
+    /// Merge vertices with bit-identical position, normal and texture coordinates
+    pub fn weld(&mut self) {
+        let mut seen: HashMap<VertexKey, u32> = HashMap::new();
+        let mut positions = Vec::new();
+        let mut normals = Vec::new();
+        let mut uvs = Vec::new();
+        let mut remap = Vec::with_capacity(self.positions.len());
+
+        for ((p, n), uv) in self.positions.iter().zip(&self.normals).zip(&self.uvs) {
+            let key = (
+                p.map(f32::to_bits),
+                n.map(f32::to_bits),
+                uv.map(f32::to_bits),
+            );
+            let index = *seen.entry(key).or_insert_with(|| {
+                positions.push(*p);
+                normals.push(*n);
+                uvs.push(*uv);
+                (positions.len() - 1) as u32
+            });
+            remap.push(index);
+        }
+
+        for i in &mut self.indices {
+            *i = remap[*i as usize];
+        }
+        self.positions = positions;
+        self.normals = normals;
+        self.uvs = uvs;
+    }
+
+    /// Build an extruded mesh: flat caps, side walls either faceted (`None`)
+    /// or smoothed across edges meeting at less than `crease_degrees`.
+    pub fn extrude(
+        mesh: &Mesh2D,
+        depth: f32,
+        orient: &Orientation,
+        z_offset: f32,
+        crease_degrees: Option<f32>,
+    ) -> Self {
+        Self::extrude_chamfered(mesh, depth, orient, z_offset, crease_degrees, 0.0)
+    }
+
+    /// [`IndexedMesh::extrude`] with the top outline `chamfer` inside the
+    /// bottom one, so the side walls slope inward
+    pub fn extrude_chamfered(
+        mesh: &Mesh2D,
+        depth: f32,
+        orient: &Orientation,
+        z_offset: f32,
+        crease_degrees: Option<f32>,
+        chamfer: f32,
+    ) -> Self {
+        let mut out = IndexedMesh::default();
+        let z0 = -depth * 0.5 + z_offset;
+        let z1 = depth * 0.5 + z_offset;
+        let up = map_normal([0.0, 0.0, 1.0], orient);
+        let down = map_normal([0.0, 0.0, -1.0], orient);
+
+        // Caps share the 2D vertices
+        let top = if chamfer > 0.0 {
+            inset_boundary(mesh, chamfer)
+        } else {
+            mesh.vertices.clone()
+        };
+        let planar = |p: &Point| [p.x / UV_TILE_MM, p.y / UV_TILE_MM];
+        let top_base = out.positions.len() as u32;
+        for p in &top {
+            out.push_vertex(map_point(*p, z1, orient), up, planar(p));
+        }
+        let bottom_base = out.positions.len() as u32;
+        for p in &mesh.vertices {
+            out.push_vertex(map_point(*p, z0, orient), down, planar(p));
+        }
+        for tri in mesh.indices.chunks(3) {
+            let (a, b, c) = (tri[0] as u32, tri[1] as u32, tri[2] as u32);
+            out.indices
+                .extend([top_base + a, top_base + b, top_base + c]);
+            out.indices
+                .extend([bottom_base + c, bottom_base + b, bottom_base + a]);
+        }
+
+        // Side walls: one quad per boundary edge, normals optionally averaged
+        let edges = boundary_edges(&mesh.indices);
+        let edge_normals: Vec<[f32; 2]> = edges
+            .iter()
+            .map(|&(i0, i1)| edge_normal(mesh.vertices[i0 as usize], mesh.vertices[i1 as usize]))
+            .collect();
+
+        // Neighbor lookup: the edge ending at / starting from each vertex
+        let mut incoming: HashMap<u16, Vec<usize>> = HashMap::new();
+        let mut outgoing: HashMap<u16, Vec<usize>> = HashMap::new();
+        for (e, &(i0, i1)) in edges.iter().enumerate() {
+            outgoing.entry(i0).or_default().push(e);
+            incoming.entry(i1).or_default().push(e);
+        }
+        // Walls unroll along each boundary loop: u is the distance travelled
+        // from where the loop starts, v the height above the bottom
+        let edge_length = |e: usize| {
+            let (i0, i1) = edges[e];
+            (mesh.vertices[i1 as usize] - mesh.vertices[i0 as usize]).length()
+        };
+        let mut u_start = vec![None; edges.len()];
+        for first in 0..edges.len() {
+            let (mut edge, mut u) = (first, 0.0);
+            while u_start[edge].is_none() {
+                u_start[edge] = Some(u);
+                u += edge_length(edge);
+                let next = outgoing
+                    .get(&edges[edge].1)
+                    .and_then(|next| next.iter().copied().find(|&e| u_start[e].is_none()));
+                match next {
+                    Some(next) => edge = next,
+                    None => break,
+                }
+            }
+        }
+
+        let crease_cos = crease_degrees.map(|deg| deg.to_radians().cos());
+        let vertex_normal = |e: usize, neighbor: Option<&Vec<usize>>| -> [f32; 2] {
+            let own = edge_normals[e];
+            let (Some(cos), Some([other])) = (crease_cos, neighbor.map(Vec::as_slice)) else {
+                return own;
+            };
+            let other = edge_normals[*other];
+            if own[0] * other[0] + own[1] * other[1] >= cos {
+                normalize2([own[0] + other[0], own[1] + other[1]])
+            } else {
+                own
+            }
+        };
+
+        for (e, &(i0, i1)) in edges.iter().enumerate() {
+            let p0 = mesh.vertices[i0 as usize];
+            let p1 = mesh.vertices[i1 as usize];
+            // Start vertex blends with the edge coming in; end vertex with the edge going out
+            let n0 = vertex_normal(e, incoming.get(&i0));
+            let n1 = vertex_normal(e, outgoing.get(&i1));
+            // A chamfered wall leans back, so its normal tilts up
+            let tilt = |n: [f32; 2]| {
+                if chamfer <= 0.0 {
+                    return map_normal([n[0], n[1], 0.0], orient);
+                }
+                let len = (depth * depth + chamfer * chamfer).sqrt();
+                map_normal(
+                    [n[0] * depth / len, n[1] * depth / len, chamfer / len],
+                    orient,
+                )
+            };
+            let (n0, n1) = (tilt(n0), tilt(n1));
+
+            let t0 = top[i0 as usize];
+            let t1 = top[i1 as usize];
+            let u0 = u_start[e].unwrap_or_default() / UV_TILE_MM;
+            let u1 = u0 + edge_length(e) / UV_TILE_MM;
+            let v1 = depth / UV_TILE_MM;
+            let top0 = out.push_vertex(map_point(t0, z1, orient), n0, [u0, v1]);
+            let top1 = out.push_vertex(map_point(t1, z1, orient), n1, [u1, v1]);
+            let bot0 = out.push_vertex(map_point(p0, z0, orient), n0, [u0, 0.0]);
+            let bot1 = out.push_vertex(map_point(p1, z0, orient), n1, [u1, 0.0]);
+            out.indices.extend([bot1, top1, top0, bot0, bot1, top0]);
+        }
+
+        out.weld();
+        out
+    }
+
+    /// Vertices merged by position alone (normals dropped), as needed for
+    /// watertight formats like 3MF
+    pub fn shared_positions(&self) -> (Vec<[f32; 3]>, Vec<u32>) {
+        let mut seen: HashMap<[u32; 3], u32> = HashMap::new();
+        let mut positions = Vec::new();
+        let remap: Vec<u32> = self
+            .positions
+            .iter()
+            .map(|p| {
+                *seen.entry(p.map(f32::to_bits)).or_insert_with(|| {
+                    positions.push(*p);
+                    (positions.len() - 1) as u32
+                })
+            })
+            .collect();
+        let indices = self.indices.iter().map(|&i| remap[i as usize]).collect();
+        (positions, indices)
+    }
+
+    /// Append another mesh
+    pub fn append(&mut self, other: &IndexedMesh) {
+        let base = self.positions.len() as u32;
+        self.positions.extend_from_slice(&other.positions);
+        self.normals.extend_from_slice(&other.normals);
+        self.uvs.extend_from_slice(&other.uvs);
+        self.indices.extend(other.indices.iter().map(|i| i + base));
+    }
+
+    /// Multiply the positions by `factor`; normals and texture coordinates
+    /// stay, so textures keep their size in mm
+    pub fn scale(&mut self, factor: f32) {
+        for p in &mut self.positions {
+            *p = p.map(|v| v * factor);
+        }
+    }
+}
+
+/// Outward normal of a boundary edge of counter-clockwise triangles
+fn edge_normal(p0: Point, p1: Point) -> [f32; 2] {
+    normalize2([p1.y - p0.y, -(p1.x - p0.x)])
+}
+
+fn normalize2(v: [f32; 2]) -> [f32; 2] {
+    let len = (v[0] * v[0] + v[1] * v[1]).sqrt();
+    if len == 0.0 {
+        [0.0, 0.0]
+    } else {
+        [v[0] / len, v[1] / len]
+    }
+}
+
+/// Rotate a direction like `map_point` rotates positions
+fn map_normal(n: [f32; 3], orient: &Orientation) -> [f32; 3] {
+    map_point(Point::new(n[0], n[1]), n[2], orient)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::{calc_normal, rectangle_mesh};
+
+    #[test]
+    fn side_normals_match_faceted_extrusion() {
+        let square = rectangle_mesh(0.0, 1.0, 0.0, 1.0);
+        let mesh = IndexedMesh::extrude(&square, 1.0, &Orientation::Flat, 0.0, None);
+        for tri in mesh.indices.chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|k| mesh.positions[tri[k] as usize]);
+            let face = calc_normal(a, b, c);
+            let vertex = mesh.normals[tri[0] as usize];
+            let dot: f32 = (0..3).map(|k| face[k] * vertex[k]).sum();
+            assert!(dot > 0.99, "normal {:?} vs face {:?}", vertex, face);
+        }
+    }
+
+    #[test]
+    fn square_corners_stay_sharp_when_smoothing() {
+        let square = rectangle_mesh(0.0, 1.0, 0.0, 1.0);
+        let smooth = IndexedMesh::extrude(
+            &square,
+            1.0,
+            &Orientation::Flat,
+            0.0,
+            Some(DEFAULT_CREASE_DEGREES),
+        );
+        assert_eq!(smooth.triangle_count(), 12);
+        for n in &smooth.normals {
+            assert!(n.iter().all(|c| [-1.0, 0.0, 1.0].contains(c)), "{:?}", n);
+        }
+    }
+
+    #[test]
+    fn walls_unroll_along_the_outline() {
+        let square = rectangle_mesh(0.0, 10.0, 0.0, 10.0);
+        let mesh = IndexedMesh::extrude(&square, 5.0, &Orientation::Flat, 0.0, None);
+        assert_eq!(mesh.uvs.len(), mesh.positions.len());
+        // The four walls cover u from 0 to 40 mm, v up to the depth
+        let walls = || {
+            mesh.normals
+                .iter()
+                .zip(&mesh.uvs)
+                .filter(|(n, _)| n[2] == 0.0)
+        };
+        let max_u = walls().map(|(_, uv)| uv[0]).fold(0.0, f32::max);
+        let max_v = walls().map(|(_, uv)| uv[1]).fold(0.0, f32::max);
+        assert!((max_u * UV_TILE_MM - 40.0).abs() < 1e-4);
+        assert!((max_v * UV_TILE_MM - 5.0).abs() < 1e-4);
+        // Caps are projected straight down
+        let top = mesh.normals.iter().position(|n| n[2] == 1.0).unwrap();
+        let [x, y, _] = mesh.positions[top];
+        assert_eq!(mesh.uvs[top], [x / UV_TILE_MM, y / UV_TILE_MM]);
+    }
+
+    #[test]
+    fn shallow_corners_are_smoothed() {
+        // With a crease angle this wide every corner of the sliver is blended
+        let mesh = Mesh2D {
+            vertices: vec![
+                Point::new(0.0, 0.0),
+                Point::new(2.0, 0.0),
+                Point::new(1.0, 0.1),
+            ],
+            indices: vec![0, 1, 2],
+        };
+        let smooth = IndexedMesh::extrude(&mesh, 1.0, &Orientation::Flat, 0.0, Some(179.0));
+        let side: Vec<_> = smooth.normals.iter().filter(|n| n[2] == 0.0).collect();
+        assert!(side.iter().any(|n| n[0].abs() > 1e-3 && n[1].abs() > 1e-3));
+    }
+}
@@ -0,0 +1,77 @@
+//! Hand-made wobble (`--jitter`): every glyph nudged, turned and resized
+//! by a small random amount, the same each time for the same seed.
+//!
+//! The numbers come from SplitMix64, which is tiny and good enough to
+//! scatter letters; glyphs draw from it in layout order, so a seed gives
+//! the same result on every platform and in every release.
+
+use crate::layout::Layout;
+
+/// Turn in radians a glyph gets at most per unit of `amount`
+const MAX_TURN: f32 = 1.0;
+
+/// SplitMix64 (Steele, Lea and Flood), the generator Java's
+/// `SplittableRandom` uses
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in -1..1
+    fn signed(&mut self) -> f32 {
+        // The top 24 bits fill an f32 mantissa exactly
+        (self.next() >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+    }
+}
+
+/// Move each glyph of `layout` up to `amount` em (`em` mm) either way,
+/// turn it up to `amount` radians and scale it by up to `amount` of its
+/// size, drawing from a generator started at `seed`
+pub fn apply(layout: &mut Layout, amount: f32, seed: u64, em: f32) {
+    if amount == 0.0 {
+        return;
+    }
+    let mut random = SplitMix64(seed);
+    for glyph in &mut layout.glyphs {
+        glyph.x += random.signed() * amount * em;
+        glyph.y += random.signed() * amount * em;
+        glyph.angle += random.signed() * amount * MAX_TURN;
+        glyph.size *= 1.0 + random.signed() * amount;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font;
+
+    #[test]
+    fn the_same_seed_wobbles_the_same_way() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let options = crate::Options {
+            text: "wobble".to_string(),
+            ..crate::Options::default()
+        };
+        let wobbled = |seed| {
+            let mut layout = crate::layout(&face, &options);
+            apply(&mut layout, 0.05, seed, 72.0);
+            layout.glyphs
+        };
+        let plain = crate::layout(&face, &options).glyphs;
+        let (a, b, c) = (wobbled(42), wobbled(42), wobbled(7));
+        for ((a, b), plain) in a.iter().zip(&b).zip(&plain) {
+            assert_eq!((a.x, a.y, a.angle, a.size), (b.x, b.y, b.angle, b.size));
+            assert!((a.x - plain.x).abs() <= 0.05 * 72.0);
+            assert!((a.y - plain.y).abs() <= 0.05 * 72.0);
+            assert!(a.angle.abs() <= 0.05 && (a.size - 1.0).abs() <= 0.05);
+        }
+        assert!(a.iter().zip(&c).any(|(a, c)| a.x != c.x));
+        assert!(a.iter().zip(&plain).all(|(a, plain)| a.x != plain.x));
+    }
+}
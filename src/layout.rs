@@ -0,0 +1,1928 @@
+//! Text layout: glyph placement and outline collection
+
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+use lyon_path::math::Point;
+use lyon_path::path::Builder as PathBuilder;
+use ttf_parser::gpos::{PairAdjustment, PositioningSubtable};
+use ttf_parser::{Face, GlyphId, OutlineBuilder, Tag};
+use unicode_bidi::{Level, ParagraphBidiInfo};
+
+use crate::gsub;
+use crate::i18n;
+use crate::progress::{ProgressFn, Stage};
+use crate::svg_glyph;
+
+/// Kerning between two glyphs in font units: the pair adjustments of the
+/// GPOS `kern` feature when the font has one, else the legacy `kern` table
+pub fn kerning_value(face: &Face<'_>, left: GlyphId, right: GlyphId) -> Option<i16> {
+    let kern = Tag::from_bytes(b"kern");
+    if let Some(gpos) = face.tables().gpos {
+        let lookups = gsub::feature_lookups(&gpos, &[kern]);
+        if !lookups.is_empty() {
+            return gpos_kerning(&gpos, &lookups, left, right);
+        }
+    }
+    let kern = face.tables().kern.as_ref()?;
+    for subtable in kern.subtables.into_iter() {
+        if !subtable.horizontal || subtable.has_cross_stream || subtable.has_state_machine {
+            continue;
+        }
+        if let Some(value) = subtable.glyphs_kerning(left, right) {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// Whether `ch` is a combining mark, drawn over or under the character
+/// before it rather than after it
+pub fn is_mark(ch: char) -> bool {
+    matches!(
+        ch as u32,
+        0x0300..=0x036F // Combining Diacritical Marks
+            | 0x1AB0..=0x1AFF // Combining Diacritical Marks Extended
+            | 0x1DC0..=0x1DFF // Combining Diacritical Marks Supplement
+            | 0x20D0..=0x20FF // Combining Diacritical Marks for Symbols
+            | 0x3099..=0x309A // Combining kana voiced sound marks
+            | 0xFE20..=0xFE2F // Combining Half Marks
+    )
+}
+
+/// Offset in font units from the origin of `base` to that of `mark` that
+/// meets their anchors in the GPOS `mark` lookups, or the `mkmk` ones when
+/// `base` is itself a mark
+fn anchor_offset(
+    face: &Face<'_>,
+    base: GlyphId,
+    mark: GlyphId,
+    to_mark: bool,
+) -> Option<(f32, f32)> {
+    let gpos = face.tables().gpos?;
+    let feature = Tag::from_bytes(if to_mark { b"mkmk" } else { b"mark" });
+    for index in gsub::feature_lookups(&gpos, &[feature]) {
+        let Some(lookup) = gpos.lookups.get(index) else {
+            continue;
+        };
+        for subtable in lookup.subtables.into_iter::<PositioningSubtable>() {
+            let (mark_coverage, marks, base_coverage, anchors) = match subtable {
+                PositioningSubtable::MarkToBase(table) if !to_mark => (
+                    table.mark_coverage,
+                    table.marks,
+                    table.base_coverage,
+                    table.anchors,
+                ),
+                PositioningSubtable::MarkToMark(table) if to_mark => (
+                    table.mark1_coverage,
+                    table.marks,
+                    table.mark2_coverage,
+                    table.mark2_matrix,
+                ),
+                _ => continue,
+            };
+            let (Some(mark_index), Some(base_index)) =
+                (mark_coverage.get(mark), base_coverage.get(base))
+            else {
+                continue;
+            };
+            let (class, mark_anchor) = marks.get(mark_index)?;
+            let base_anchor = anchors.get(base_index, class)?;
+            return Some((
+                base_anchor.x as f32 - mark_anchor.x as f32,
+                base_anchor.y as f32 - mark_anchor.y as f32,
+            ));
+        }
+    }
+    None
+}
+
+/// Origin of the combining `mark` in font units from that of `base`, with
+/// `above` the last mark put on the same base and its origin. The font's
+/// anchors decide where they exist; otherwise a mark with an advance of its
+/// own is centred over the base, and one without starts where the base
+/// ends, as fonts draw such marks back over the glyph before them.
+fn mark_origin(
+    face: &Face<'_>,
+    base: GlyphId,
+    above: Option<(GlyphId, (f32, f32))>,
+    mark: GlyphId,
+) -> (f32, f32) {
+    let stacked = above.and_then(|(prev, (x, y))| {
+        let (dx, dy) = anchor_offset(face, prev, mark, true)?;
+        Some((x + dx, y + dy))
+    });
+    if let Some(origin) = stacked.or_else(|| anchor_offset(face, base, mark, false)) {
+        return origin;
+    }
+    let base_advance = hor_advance(face, base).unwrap_or(0) as f32;
+    match hor_advance(face, mark).unwrap_or(0) as f32 {
+        0.0 => (base_advance, 0.0),
+        advance => ((base_advance - advance) / 2.0, 0.0),
+    }
+}
+
+/// `mark` placed on `glyphs[base]` by [`mark_origin`], `scale` layout units
+/// per font unit of `face`; `above` is updated to it
+fn attach_mark(
+    face: &Face<'_>,
+    glyphs: &[PlacedGlyph],
+    base: usize,
+    above: &mut Option<(GlyphId, (f32, f32))>,
+    (gid, ch): gsub::RunGlyph,
+    scale: f32,
+) -> PlacedGlyph {
+    let base = glyphs[base];
+    let origin = mark_origin(face, base.gid, *above, gid);
+    *above = Some((gid, origin));
+    let unit = scale * base.size;
+    PlacedGlyph {
+        gid,
+        ch,
+        x: base.x + origin.0 * unit,
+        y: base.y + origin.1 * unit,
+        ..base
+    }
+}
+
+/// Sum of the first pair adjustment of each of `lookups` that covers
+/// `left` followed by `right`
+fn gpos_kerning(
+    gpos: &ttf_parser::opentype_layout::LayoutTable<'_>,
+    lookups: &[u16],
+    left: GlyphId,
+    right: GlyphId,
+) -> Option<i16> {
+    let mut total: Option<i16> = None;
+    for &index in lookups {
+        let Some(lookup) = gpos.lookups.get(index) else {
+            continue;
+        };
+        let adjustment = lookup
+            .subtables
+            .into_iter::<PositioningSubtable>()
+            .find_map(|subtable| {
+                let PositioningSubtable::Pair(pair) = subtable else {
+                    return None;
+                };
+                let covered = pair.coverage().get(left)?;
+                let (first, _) = match pair {
+                    PairAdjustment::Format1 { sets, .. } => sets.get(covered)?.get(right)?,
+                    PairAdjustment::Format2 {
+                        classes, matrix, ..
+                    } => matrix.get((classes.0.get(left), classes.1.get(right)))?,
+                };
+                Some(first.x_advance)
+            });
+        if let Some(value) = adjustment {
+            total = Some(total.unwrap_or(0).saturating_add(value));
+        }
+    }
+    total
+}
+
+/// A glyph positioned by the layout (pen position in output units)
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlacedGlyph {
+    #[cfg_attr(feature = "serde", serde(with = "glyph_id_serde"))]
+    pub gid: GlyphId,
+    /// Face the glyph comes from: 0 for the primary font, then the
+    /// fallbacks in order
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub font: usize,
+    /// Character the glyph was laid out for
+    pub ch: char,
+    pub x: f32,
+    pub y: f32,
+    /// Size relative to the rest of the text: below 1 for the capitals
+    /// that stand in for small capitals the font lacks
+    #[cfg_attr(feature = "serde", serde(default = "full_size"))]
+    pub size: f32,
+    /// Counter-clockwise turn about `x`, `y` in radians, for text bent
+    /// along a curve (see [`crate::curve`])
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub angle: f32,
+    /// Extrusion depth in mm of the run the glyph belongs to, when it
+    /// differs from the rest of the text (see [`crate::runs`])
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub depth: Option<f32>,
+}
+
+#[cfg(feature = "serde")]
+fn full_size() -> f32 {
+    1.0
+}
+
+impl PlacedGlyph {
+    /// The face this glyph comes from and the factor that brings its font
+    /// units to the layout's, `size` included (see [`glyph_face`])
+    pub fn face<'f, 'a>(
+        &self,
+        face: &'f Face<'a>,
+        fallbacks: &'f [Face<'a>],
+    ) -> (&'f Face<'a>, f32) {
+        let (glyph_face, units) = glyph_face(face, fallbacks, self.font);
+        (glyph_face, units * self.size)
+    }
+
+    /// Where `p`, relative to the glyph's origin, lands in the layout once
+    /// the glyph is turned by `angle` and moved to `x`, `y`
+    pub fn place(&self, p: Point) -> Point {
+        let (sin, cos) = self.angle.sin_cos();
+        Point::new(
+            p.x * cos - p.y * sin + self.x,
+            p.x * sin + p.y * cos + self.y,
+        )
+    }
+}
+
+/// One laid-out line of text
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LayoutLine {
+    pub text: String,
+    pub width: f32,
+    pub baseline: f32,
+    /// Glyphs the line placed, which ligatures can make fewer than its
+    /// characters
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub glyph_count: usize,
+    /// How far [`align_lines`] slid the line along its direction
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub offset: f32,
+    /// Broken by wrapping at `max_width` rather than at a `\n` or the end
+    /// of the text
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub wrapped: bool,
+}
+
+/// Result of laying out text: glyph positions plus per-line metrics
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Layout {
+    pub glyphs: Vec<PlacedGlyph>,
+    pub lines: Vec<LayoutLine>,
+    pub missing: Vec<char>,
+}
+
+/// `GlyphId` is serialized as its raw `u16`
+#[cfg(feature = "serde")]
+mod glyph_id_serde {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use ttf_parser::GlyphId;
+
+    pub fn serialize<S: Serializer>(gid: &GlyphId, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u16(gid.0)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<GlyphId, D::Error> {
+        u16::deserialize(deserializer).map(GlyphId)
+    }
+}
+
+/// First face with a glyph for `ch`: `face`, then the `fallbacks` in order.
+/// Returns the face's index (0 for `face`) and the glyph.
+pub fn find_glyph(face: &Face<'_>, fallbacks: &[Face<'_>], ch: char) -> Option<(usize, GlyphId)> {
+    std::iter::once(face)
+        .chain(fallbacks)
+        .enumerate()
+        .find_map(|(font, face)| Some((font, face.glyph_index(ch)?)))
+}
+
+/// The face a placed glyph comes from, with the factor that brings its font
+/// units to those of `face`, whose em the layout is scaled to
+pub fn glyph_face<'f, 'a>(
+    face: &'f Face<'a>,
+    fallbacks: &'f [Face<'a>],
+    font: usize,
+) -> (&'f Face<'a>, f32) {
+    match font.checked_sub(1).and_then(|i| fallbacks.get(i)) {
+        Some(fallback) => (
+            fallback,
+            face.units_per_em() as f32 / fallback.units_per_em() as f32,
+        ),
+        None => (face, 1.0),
+    }
+}
+/// Engine that picks and positions the glyphs of a line
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Shaper {
+    /// One glyph per character, with GSUB features and pair kerning
+    #[default]
+    Builtin,
+    /// HarfBuzz-compatible shaping via rustybuzz (needs the `shaping` feature)
+    Harfbuzz,
+}
+
+impl Shaper {
+    /// Fails when the shaper is not compiled into this build
+    pub fn check(self) -> anyhow::Result<()> {
+        if self == Shaper::Harfbuzz && !cfg!(feature = "shaping") {
+            anyhow::bail!("--shaper harfbuzz needs wagyan built with the `shaping` feature");
+        }
+        Ok(())
+    }
+}
+
+/// Stand-in glyph id for the tofu box of [`MissingGlyph::Box`]. Glyph ids
+/// only go up to 65534, so no font glyph has it; it advances like `.notdef`
+/// and [`glyph_outline`] draws it as a hollow rectangle.
+pub const TOFU: GlyphId = GlyphId(u16::MAX);
+
+/// What a character no font has turns into
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum MissingGlyph {
+    /// Left out, closing up the gap
+    #[default]
+    Skip,
+    /// The font's `.notdef` glyph (glyph 0)
+    Notdef,
+    /// A hollow box the width of `.notdef`
+    Box,
+    /// This character instead, skipped as well when no font has it either
+    Char(char),
+}
+
+impl MissingGlyph {
+    /// Font (0 for `face`, 1.. for `fallbacks`) and glyph standing in for
+    /// missing characters; `None` skips them
+    fn replacement(self, face: &Face<'_>, fallbacks: &[Face<'_>]) -> Option<(usize, GlyphId)> {
+        match self {
+            MissingGlyph::Skip => None,
+            MissingGlyph::Notdef => Some((0, GlyphId(0))),
+            MissingGlyph::Box => Some((0, TOFU)),
+            MissingGlyph::Char(ch) => find_glyph(face, fallbacks, ch),
+        }
+    }
+
+    /// Warning for a character no font has
+    fn warning(self, face: &Face<'_>, fallbacks: &[Face<'_>], ch: char) -> i18n::Message<'static> {
+        match self.replacement(face, fallbacks) {
+            Some(_) => i18n::Message::ReplacedGlyph(ch),
+            None => i18n::Message::MissingGlyph(ch),
+        }
+    }
+}
+
+impl FromStr for MissingGlyph {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "skip" => Ok(MissingGlyph::Skip),
+            "notdef" => Ok(MissingGlyph::Notdef),
+            "box" => Ok(MissingGlyph::Box),
+            _ => {
+                let mut chars = s.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(ch), None) => Ok(MissingGlyph::Char(ch)),
+                    _ => anyhow::bail!(
+                        "expected skip, notdef, box or a single character, got: {}",
+                        s
+                    ),
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for MissingGlyph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MissingGlyph::Skip => f.write_str("skip"),
+            MissingGlyph::Notdef => f.write_str("notdef"),
+            MissingGlyph::Box => f.write_str("box"),
+            MissingGlyph::Char(ch) => write!(f, "{}", ch),
+        }
+    }
+}
+
+/// Distance from one line's baseline to the next (or between the centres of
+/// vertical columns)
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum LineHeight {
+    /// Multiple of the font's own line height (`1.2`, or `120%`)
+    Factor(f32),
+    /// Fixed distance in mm (`14mm`)
+    Mm(f32),
+}
+
+impl Default for LineHeight {
+    fn default() -> Self {
+        LineHeight::Factor(1.0)
+    }
+}
+
+impl LineHeight {
+    /// The distance in mm, given the font's own line height `natural` in mm
+    pub fn resolve(self, natural: f32) -> f32 {
+        match self {
+            LineHeight::Factor(factor) => natural * factor,
+            LineHeight::Mm(mm) => mm,
+        }
+    }
+}
+
+impl FromStr for LineHeight {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let number = |n: &str| {
+            n.trim()
+                .parse::<f32>()
+                .ok()
+                .filter(|v| *v > 0.0 && v.is_finite())
+        };
+        let parsed = if let Some(mm) = s.strip_suffix("mm") {
+            number(mm).map(LineHeight::Mm)
+        } else if let Some(percent) = s.strip_suffix('%') {
+            number(percent).map(|p| LineHeight::Factor(p / 100.0))
+        } else {
+            number(s).map(LineHeight::Factor)
+        };
+        parsed.ok_or_else(|| {
+            anyhow::anyhow!(
+                "expected a positive factor (1.2 or 120%) or a length in mm (14mm), got: {}",
+                s
+            )
+        })
+    }
+}
+
+impl fmt::Display for LineHeight {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LineHeight::Factor(factor) => write!(f, "{}", factor),
+            LineHeight::Mm(mm) => write!(f, "{}mm", mm),
+        }
+    }
+}
+
+/// Horizontal advance of `gid` in font units, [`TOFU`] included
+pub fn hor_advance(face: &Face<'_>, gid: GlyphId) -> Option<u16> {
+    face.glyph_hor_advance(if gid == TOFU { GlyphId(0) } else { gid })
+}
+
+/// Outline `gid` in font units: the tofu box for [`TOFU`], else the glyph
+/// outline or its SVG document
+pub fn glyph_outline(
+    face: &Face<'_>,
+    gid: GlyphId,
+    builder: &mut dyn OutlineBuilder,
+) -> Option<ttf_parser::Rect> {
+    if gid != TOFU {
+        return svg_glyph::outline(face, gid, builder);
+    }
+    let rect = tofu_rect(face);
+    let (x0, y0, x1, y1) = (
+        rect.x_min as f32,
+        rect.y_min as f32,
+        rect.x_max as f32,
+        rect.y_max as f32,
+    );
+    let stroke = face.units_per_em() as f32 * 0.05;
+    builder.move_to(x0, y0);
+    builder.line_to(x1, y0);
+    builder.line_to(x1, y1);
+    builder.line_to(x0, y1);
+    builder.close();
+    // The hole runs the other way round
+    builder.move_to(x0 + stroke, y0 + stroke);
+    builder.line_to(x0 + stroke, y1 - stroke);
+    builder.line_to(x1 - stroke, y1 - stroke);
+    builder.line_to(x1 - stroke, y0 + stroke);
+    builder.close();
+    Some(rect)
+}
+
+/// Bounding box of `gid` in font units, [`TOFU`] and SVG glyphs included
+pub fn glyph_bounds(face: &Face<'_>, gid: GlyphId) -> Option<ttf_parser::Rect> {
+    if gid == TOFU {
+        Some(tofu_rect(face))
+    } else {
+        svg_glyph::bounding_box(face, gid)
+    }
+}
+
+/// The tofu box: inset a tenth of the `.notdef` advance on each side, from
+/// the baseline to the cap height
+fn tofu_rect(face: &Face<'_>) -> ttf_parser::Rect {
+    let width = hor_advance(face, TOFU)
+        .filter(|&advance| advance > 0)
+        .unwrap_or(face.units_per_em() / 2) as f32;
+    let height = crate::font::cap_height(face).unwrap_or(face.ascender() as f32 * 0.7);
+    ttf_parser::Rect {
+        x_min: (width * 0.1).round() as i16,
+        y_min: 0,
+        x_max: (width * 0.9).round() as i16,
+        y_max: height.round() as i16,
+    }
+}
+
+/// Base direction of a line for the Unicode bidi algorithm
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Direction {
+    /// From the first letter with a strong direction
+    #[default]
+    Auto,
+    /// Left to right
+    Ltr,
+    /// Right to left
+    Rtl,
+}
+
+/// Byte ranges of `line` with the same embedding level, in the order they
+/// are drawn left to right, each marked when it reads right to left
+fn bidi_runs(line: &str, direction: Direction) -> Vec<(Range<usize>, bool)> {
+    if line.is_empty() {
+        return Vec::new();
+    }
+    let level = match direction {
+        Direction::Auto => None,
+        Direction::Ltr => Some(Level::ltr()),
+        Direction::Rtl => Some(Level::rtl()),
+    };
+    let info = ParagraphBidiInfo::new(line, level);
+    let (levels, runs) = info.visual_runs(0..line.len());
+    runs.into_iter()
+        .map(|run| {
+            let rtl = levels[run.start].is_rtl();
+            (run, rtl)
+        })
+        .collect()
+}
+
+/// The other half of a paired bracket, which right-to-left text draws
+/// mirrored
+fn mirror(ch: char) -> Option<char> {
+    Some(match ch {
+        '(' => ')',
+        ')' => '(',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '<' => '>',
+        '>' => '<',
+        '«' => '»',
+        '»' => '«',
+        '‹' => '›',
+        '›' => '‹',
+        _ => return None,
+    })
+}
+
+/// Size of the capitals that stand in for small capitals in fonts without
+/// them, as in browsers
+pub const SMALL_CAP_SIZE: f32 = 0.7;
+
+/// How glyphs are chosen and positioned
+#[derive(Clone, Debug, Default)]
+pub struct Shaping {
+    /// Apply kerning when available
+    pub kerning: bool,
+    /// OpenType features to apply
+    pub features: Vec<Tag>,
+    /// Engine doing the work
+    pub shaper: Shaper,
+    /// Base direction of each line
+    pub direction: Direction,
+    /// What characters no font has turn into
+    pub missing_glyph: MissingGlyph,
+    /// Extra space after every glyph, in em
+    pub tracking: f32,
+    /// Extra space after every space character, in em
+    pub word_spacing: f32,
+    /// Fixed advance of every glyph in layout units, the glyph centred in
+    /// it; kerning is then skipped
+    pub monospace: Option<f32>,
+    /// Distance between tab stops in layout units; `None` puts them every
+    /// four em
+    pub tab_width: Option<f32>,
+    /// Size of the capitals drawn for lowercase letters when small
+    /// capitals are synthesized (see [`Shaping::small_caps`])
+    pub small_caps: Option<f32>,
+}
+
+impl Shaping {
+    /// Space `tracking` and `word_spacing` add after `ch`, for an em of
+    /// `em` layout units
+    pub fn extra_space(&self, ch: char, em: f32) -> f32 {
+        let word = match ch {
+            ' ' | '\u{A0}' => self.word_spacing,
+            _ => 0.0,
+        };
+        (self.tracking + word) * em
+    }
+
+    /// Draw lowercase letters as small capitals: with the font's `smcp`
+    /// feature, or as capitals at [`SMALL_CAP_SIZE`] when it has none
+    pub fn small_caps(&mut self, face: &Face<'_>) {
+        let smcp = Tag::from_bytes(b"smcp");
+        if !gsub::has_feature(face, smcp) {
+            self.small_caps = Some(SMALL_CAP_SIZE);
+        } else if !self.features.contains(&smcp) {
+            self.features.push(smcp);
+        }
+    }
+
+    /// The glyph drawn for `ch`, which `face` maps to `gid`, and its size:
+    /// the capital at the `small_caps` size for a lowercase letter no
+    /// feature has substituted, otherwise `gid` itself
+    fn small_cap(&self, face: &Face<'_>, gid: GlyphId, ch: char) -> (GlyphId, f32) {
+        let capital = match self.small_caps {
+            Some(size) if ch.is_lowercase() && face.glyph_index(ch) == Some(gid) => ch
+                .to_uppercase()
+                .next()
+                .and_then(|upper| face.glyph_index(upper))
+                .map(|capital| (capital, size)),
+            _ => None,
+        };
+        capital.unwrap_or((gid, 1.0))
+    }
+
+    /// The first tab stop after `pen`, for an em of `em` layout units
+    pub fn tab_stop(&self, pen: f32, em: f32) -> f32 {
+        let width = self.tab_width.unwrap_or(4.0 * em);
+        ((pen / width).floor() + 1.0) * width
+    }
+
+    /// Where a glyph of `advance` starts within its cell and how far the pen
+    /// moves: centred in the `monospace` advance, or as the font says.
+    /// Marks and other glyphs without an advance keep none.
+    pub fn cell(&self, advance: f32) -> (f32, f32) {
+        match self.monospace {
+            Some(cell) if advance > 0.0 => ((cell - advance) / 2.0, cell),
+            _ => (0.0, advance),
+        }
+    }
+}
+
+/// Whether `ch` belongs to a script whose letters join, reorder or stack
+/// marks (Hebrew, Arabic, the Indic scripts, Thai, ...), which only a full
+/// shaper draws correctly
+pub fn needs_shaping(ch: char) -> bool {
+    matches!(
+        ch as u32,
+        0x0590..=0x08FF // Hebrew, Arabic, Syriac, Thaana, N'Ko, Samaritan, Mandaic
+            | 0x0900..=0x0DFF // Devanagari through Sinhala
+            | 0x0E00..=0x0FFF // Thai, Lao, Tibetan
+            | 0x1000..=0x109F // Myanmar
+            | 0x1780..=0x17FF // Khmer
+            | 0xA8E0..=0xA8FF // Devanagari Extended
+            | 0xFB1D..=0xFDFF // Hebrew and Arabic presentation forms
+            | 0xFE70..=0xFEFC
+    )
+}
+
+/// Characters of a line drawn from the same face (0 for `face`, 1.. for
+/// `fallbacks`) that all do or all do not [`needs_shaping`]. A stand-in
+/// for a missing character is a run of its own and never shaped.
+struct Run {
+    font: usize,
+    complex: bool,
+    replacement: bool,
+    glyphs: Vec<gsub::RunGlyph>,
+}
+
+/// A glyph positioned by a shaper, in font units
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShapedGlyph {
+    pub gid: GlyphId,
+    /// First character of the cluster the glyph belongs to
+    pub ch: char,
+    pub x_advance: i32,
+    pub x_offset: i32,
+    pub y_offset: i32,
+}
+
+/// Splits `line` into [`Run`]s. Characters no face has are returned
+/// separately, and either left out or replaced as `missing_glyph` says.
+fn font_runs(
+    face: &Face<'_>,
+    fallbacks: &[Face<'_>],
+    line: &str,
+    missing_glyph: MissingGlyph,
+) -> (Vec<Run>, Vec<char>) {
+    let mut runs: Vec<Run> = Vec::new();
+    let mut missing = Vec::new();
+    for ch in line.chars() {
+        // A mark stays in the face of the character it goes on, if it can
+        let same_face = runs
+            .last()
+            .filter(|run| is_mark(ch) && !run.replacement)
+            .and_then(|run| {
+                let gid = glyph_face(face, fallbacks, run.font).0.glyph_index(ch)?;
+                Some((run.font, gid))
+            });
+        let Some((font, gid)) = same_face.or_else(|| find_glyph(face, fallbacks, ch)) else {
+            missing.push(ch);
+            if let Some((font, gid)) = missing_glyph.replacement(face, fallbacks) {
+                runs.push(Run {
+                    font,
+                    complex: false,
+                    replacement: true,
+                    glyphs: vec![(gid, ch)],
+                });
+            }
+            continue;
+        };
+        let complex = needs_shaping(ch);
+        match runs.last_mut() {
+            Some(run) if run.font == font && run.complex == complex && !run.replacement => {
+                run.glyphs.push((gid, ch))
+            }
+            _ => runs.push(Run {
+                font,
+                complex,
+                replacement: false,
+                glyphs: vec![(gid, ch)],
+            }),
+        }
+    }
+    (runs, missing)
+}
+
+/// The run shaped by rustybuzz when the harfbuzz shaper is selected or the
+/// run [`needs_shaping`]; `None` leaves it to the builtin path
+#[cfg(feature = "shaping")]
+fn harfbuzz_run(
+    face: &Face<'_>,
+    run: &Run,
+    rtl: bool,
+    shaping: &Shaping,
+) -> Option<Vec<ShapedGlyph>> {
+    if run.replacement || (!run.complex && shaping.shaper != Shaper::Harfbuzz) {
+        return None;
+    }
+    let text: String = run.glyphs.iter().map(|&(_, ch)| ch).collect();
+    Some(crate::shaping::shape(face, &text, rtl, shaping))
+}
+
+#[cfg(not(feature = "shaping"))]
+fn harfbuzz_run(
+    _face: &Face<'_>,
+    _run: &Run,
+    _rtl: bool,
+    _shaping: &Shaping,
+) -> Option<Vec<ShapedGlyph>> {
+    None
+}
+
+/// Glyphs of one line placed from x = 0 on `baseline` in visual order, with
+/// the line's inked width and the characters no face has
+fn place_line(
+    face: &Face<'_>,
+    fallbacks: &[Face<'_>],
+    line: &str,
+    scale: f32,
+    baseline: f32,
+    spacing: f32,
+    shaping: &Shaping,
+) -> (Vec<PlacedGlyph>, f32, Vec<char>) {
+    let mut glyphs = Vec::new();
+    let mut missing = Vec::new();
+    let mut pen_x = 0.0;
+    let mut width = 0.0;
+    let em = face.units_per_em() as f32 * scale;
+
+    for (index, segment) in line.split('\t').enumerate() {
+        if index > 0 {
+            pen_x = shaping.tab_stop(pen_x, em);
+        }
+        for (range, rtl) in bidi_runs(segment, shaping.direction) {
+            let (mut runs, run_missing) =
+                font_runs(face, fallbacks, &segment[range], shaping.missing_glyph);
+            missing.extend(run_missing);
+            if rtl {
+                runs.reverse();
+            }
+
+            for mut run in runs {
+                let font = run.font;
+                let (glyph_face, units) = glyph_face(face, fallbacks, font);
+                let glyph_scale = scale * units;
+
+                if let Some(shaped) = harfbuzz_run(glyph_face, &run, rtl, shaping) {
+                    for shaped in shaped {
+                        let (start, advance) = shaping.cell(shaped.x_advance as f32 * glyph_scale);
+                        glyphs.push(PlacedGlyph {
+                            gid: shaped.gid,
+                            font,
+                            ch: shaped.ch,
+                            x: pen_x + start + shaped.x_offset as f32 * glyph_scale,
+                            y: baseline + shaped.y_offset as f32 * glyph_scale,
+                            size: 1.0,
+                            angle: 0.0,
+                            depth: None,
+                        });
+                        width = pen_x + advance;
+                        pen_x += advance + spacing + shaping.extra_space(shaped.ch, em);
+                    }
+                    continue;
+                }
+
+                gsub::substitute(glyph_face, &shaping.features, &mut run.glyphs);
+                if rtl {
+                    run.glyphs.reverse();
+                    for (gid, ch) in &mut run.glyphs {
+                        if let Some(mirrored) = mirror(*ch).and_then(|m| glyph_face.glyph_index(m))
+                        {
+                            *gid = mirrored;
+                        }
+                    }
+                }
+                let mut prev_gid = None;
+                // The last glyph marks go on and the last mark put on it
+                let mut base = None;
+                let mut above = None;
+                for (gid, ch) in run.glyphs {
+                    if let (Some(base), false) = (base.filter(|_| is_mark(ch)), rtl) {
+                        let mark = attach_mark(
+                            glyph_face,
+                            &glyphs,
+                            base,
+                            &mut above,
+                            (gid, ch),
+                            glyph_scale,
+                        );
+                        glyphs.push(mark);
+                        continue;
+                    }
+                    let (gid, size) = shaping.small_cap(glyph_face, gid, ch);
+                    // Apply kerning relative to previous glyph of the same face,
+                    // which comes after this one in reading order right to left
+                    if shaping.kerning && shaping.monospace.is_none() {
+                        if let Some(prev) = prev_gid {
+                            let (left, right) = if rtl { (gid, prev) } else { (prev, gid) };
+                            if let Some(kern) = kerning_value(glyph_face, left, right) {
+                                pen_x += kern as f32 * glyph_scale;
+                            }
+                        }
+                    }
+
+                    // Advance: glyph advance (or the fixed cell) + spacing (and tracking)
+                    let (start, advance) = shaping.cell(
+                        hor_advance(glyph_face, gid).unwrap_or(0) as f32 * glyph_scale * size,
+                    );
+                    glyphs.push(PlacedGlyph {
+                        gid,
+                        font,
+                        ch,
+                        x: pen_x + start,
+                        y: baseline,
+                        size,
+                        angle: 0.0,
+                        depth: None,
+                    });
+                    (base, above) = (Some(glyphs.len() - 1), None);
+
+                    width = pen_x + advance;
+                    pen_x += advance + spacing + shaping.extra_space(ch, em);
+                    prev_gid = Some(gid);
+                }
+            }
+        }
+    }
+    (glyphs, width, missing)
+}
+
+/// Simple left-to-right layout; returns pen positions for every glyph.
+/// Characters `face` lacks come from the first of the `fallbacks` that has
+/// them, and `shaping` decides which glyphs are drawn and how they are spaced.
+pub fn layout_glyphs(
+    face: &Face<'_>,
+    fallbacks: &[Face<'_>],
+    text: &str,
+    scale: f32,
+    baseline_y: f32,
+    spacing: f32,
+    shaping: &Shaping,
+) -> Layout {
+    let mut layout = Layout::default();
+    let line_advance = face.height() as f32 * scale;
+
+    if !cfg!(feature = "shaping") && text.chars().any(needs_shaping) {
+        eprintln!("⚠️ {}", i18n::Message::UnshapedScript);
+    }
+    for (index, line) in text.split('\n').enumerate() {
+        let baseline = baseline_y - index as f32 * line_advance;
+        let (glyphs, width, missing) =
+            place_line(face, fallbacks, line, scale, baseline, spacing, shaping);
+        for ch in missing {
+            eprintln!("⚠️ {}", shaping.missing_glyph.warning(face, fallbacks, ch));
+            layout.missing.push(ch);
+        }
+        layout.lines.push(LayoutLine {
+            text: line.to_string(),
+            width,
+            baseline,
+            glyph_count: glyphs.len(),
+            offset: 0.0,
+            wrapped: false,
+        });
+        layout.glyphs.extend(glyphs);
+    }
+
+    layout
+}
+
+/// One line of raw glyph ids of `face` from x = 0, bypassing the cmap, so
+/// glyphs without a Unicode mapping can be drawn too. Each glyph advances by
+/// its width plus `spacing`; no substitutions, kerning or fallbacks apply.
+pub fn layout_glyph_ids(
+    face: &Face<'_>,
+    gids: &[GlyphId],
+    scale: f32,
+    baseline_y: f32,
+    spacing: f32,
+) -> Layout {
+    let mut glyphs = Vec::new();
+    let mut pen_x = 0.0;
+    let mut width = 0.0;
+    for &gid in gids {
+        glyphs.push(PlacedGlyph {
+            gid,
+            font: 0,
+            ch: char::REPLACEMENT_CHARACTER,
+            x: pen_x,
+            y: baseline_y,
+            size: 1.0,
+            angle: 0.0,
+            depth: None,
+        });
+        let advance = hor_advance(face, gid).unwrap_or(0) as f32 * scale;
+        width = pen_x + advance;
+        pen_x += advance + spacing;
+    }
+    let text = gids.iter().map(|gid| format!("<{}>", gid.0)).collect();
+    Layout {
+        lines: vec![LayoutLine {
+            text,
+            width,
+            baseline: baseline_y,
+            glyph_count: glyphs.len(),
+            offset: 0.0,
+            wrapped: false,
+        }],
+        glyphs,
+        missing: Vec::new(),
+    }
+}
+
+/// Direction lines are written in
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum WritingMode {
+    /// Left to right, lines stacked downwards
+    #[default]
+    Horizontal,
+    /// Top to bottom (tategaki), columns stacked right to left
+    Vertical,
+}
+
+/// Case change applied to the text before it is laid out
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum TextTransform {
+    /// The text as written
+    #[default]
+    None,
+    /// Every letter in capitals
+    Upper,
+    /// Every letter in lowercase
+    Lower,
+    /// A capital at the start of each word, the rest lowercase
+    Title,
+    /// Lowercase letters as small capitals, from the font's smcp feature,
+    /// else scaled-down capitals
+    Smallcaps,
+}
+
+impl TextTransform {
+    /// `text` in the new case; small capitals leave it to the layout
+    pub fn apply(self, text: &str) -> String {
+        match self {
+            TextTransform::None | TextTransform::Smallcaps => text.to_string(),
+            TextTransform::Upper => text.to_uppercase(),
+            TextTransform::Lower => text.to_lowercase(),
+            TextTransform::Title => {
+                let mut out = String::with_capacity(text.len());
+                let mut word_start = true;
+                for ch in text.chars() {
+                    match word_start {
+                        true => out.extend(ch.to_uppercase()),
+                        false => out.extend(ch.to_lowercase()),
+                    }
+                    word_start = !ch.is_alphanumeric() && ch != '\'' && ch != '’';
+                }
+                out
+            }
+        }
+    }
+}
+
+/// Unicode normalization applied to the text before it is laid out, so
+/// decomposed input (as in macOS file names) draws like composed input
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Normalization {
+    /// The text as written
+    #[default]
+    None,
+    /// Canonical composition: が for か + U+3099, é for e + U+0301
+    Nfc,
+    /// Compatibility composition, which also turns full-width letters,
+    /// half-width kana and ligature characters into their plain forms
+    Nfkc,
+}
+
+impl Normalization {
+    /// `text` in this normalization form
+    pub fn apply(self, text: &str) -> String {
+        use unicode_normalization::UnicodeNormalization;
+
+        match self {
+            Normalization::None => text.to_string(),
+            Normalization::Nfc => text.nfc().collect(),
+            Normalization::Nfkc => text.nfkc().collect(),
+        }
+    }
+}
+
+/// Where each line sits next to the longest one, or within `--max-width`
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Align {
+    /// Flush left (columns flush with the top)
+    #[default]
+    Left,
+    /// Centred
+    Center,
+    /// Flush right (columns flush with the bottom)
+    Right,
+    /// Flush at both ends by widening the word gaps (or, in lines without
+    /// spaces, every gap between characters); the last line stays flush left
+    Justify,
+}
+
+impl Align {
+    /// Share of a line's shortfall from the longest line put before it
+    pub fn factor(self) -> f32 {
+        match self {
+            Align::Left | Align::Justify => 0.0,
+            Align::Center => 0.5,
+            Align::Right => 1.0,
+        }
+    }
+}
+
+/// How far each of a line's `chars` moves to spread `extra` over its word
+/// gaps (spaces with a word on either side), or evenly between all the
+/// characters when there are none
+pub fn justify_shifts(chars: &[char], extra: f32) -> Vec<f32> {
+    let first = chars.iter().position(|ch| !ch.is_whitespace());
+    let last = chars.iter().rposition(|ch| !ch.is_whitespace());
+    let gaps: Vec<usize> = match (first, last) {
+        (Some(first), Some(last)) => (first + 1..last)
+            .filter(|&i| chars[i].is_whitespace() && !chars[i - 1].is_whitespace())
+            .collect(),
+        _ => Vec::new(),
+    };
+    if gaps.is_empty() {
+        // Marks move with the character they go on
+        let letters = chars.iter().filter(|&&ch| !is_mark(ch)).count();
+        let step = extra / letters.saturating_sub(1).max(1) as f32;
+        let mut letter = 0;
+        return chars
+            .iter()
+            .enumerate()
+            .map(|(i, &ch)| {
+                if i > 0 && !is_mark(ch) {
+                    letter += 1;
+                }
+                letter as f32 * step
+            })
+            .collect();
+    }
+    let step = extra / gaps.len() as f32;
+    (0..chars.len())
+        .map(|i| gaps.iter().filter(|&&gap| gap < i).count() as f32 * step)
+        .collect()
+}
+
+/// Slide each line of `layout` along its direction (columns down) so it
+/// sits `align`ed within `width`, or with the longest line when that is
+/// `None`; justified lines are stretched to the full width instead
+pub fn align_lines(
+    layout: &mut Layout,
+    align: Align,
+    width: Option<f32>,
+    writing_mode: WritingMode,
+) {
+    let longest = layout
+        .lines
+        .iter()
+        .map(|line| line.width)
+        .fold(0.0, f32::max);
+    let width = width.unwrap_or(longest);
+    let last = layout.lines.len().saturating_sub(1);
+    let any_wrapped = layout.lines.iter().any(|line| line.wrapped);
+    let mut glyphs = layout.glyphs.iter_mut();
+    for (index, line) in layout.lines.iter_mut().enumerate() {
+        let shortfall = (width - line.width).max(0.0);
+        let glyphs: Vec<&mut PlacedGlyph> = glyphs.by_ref().take(line.glyph_count).collect();
+        // Once wrapping has broken the text, the line ending each paragraph
+        // keeps its natural spacing, as the last line does otherwise
+        let stretch = match any_wrapped {
+            true => line.wrapped,
+            false => index < last,
+        };
+        let shifts = if align == Align::Justify && stretch && glyphs.len() > 1 {
+            let chars: Vec<char> = glyphs.iter().map(|glyph| glyph.ch).collect();
+            line.width += shortfall;
+            justify_shifts(&chars, shortfall)
+        } else {
+            line.offset = shortfall * align.factor();
+            vec![line.offset; glyphs.len()]
+        };
+        for (glyph, shift) in glyphs.into_iter().zip(shifts) {
+            match writing_mode {
+                WritingMode::Horizontal => glyph.x += shift,
+                WritingMode::Vertical => glyph.y -= shift,
+            }
+        }
+    }
+}
+
+/// Move the lines of `layout` (columns, when vertical) from the font's own
+/// line height to `line_height` apart, keeping the first line in place
+pub fn space_lines(
+    face: &Face<'_>,
+    layout: &mut Layout,
+    line_height: LineHeight,
+    scale: f32,
+    writing_mode: WritingMode,
+) {
+    let natural = match writing_mode {
+        WritingMode::Horizontal => face.height() as f32,
+        WritingMode::Vertical => column_pitch(face),
+    } * scale;
+    let step = line_height.resolve(natural) - natural;
+    let count = layout.lines.len();
+    let mut glyphs = layout.glyphs.iter_mut();
+    for (index, line) in layout.lines.iter_mut().enumerate() {
+        // Columns are placed from the left, so they all move with the pitch
+        let shift = match writing_mode {
+            WritingMode::Horizontal => -(index as f32) * step,
+            WritingMode::Vertical => (count - 1 - index) as f32 * step + step / 2.0,
+        };
+        line.baseline += shift;
+        for glyph in glyphs.by_ref().take(line.glyph_count) {
+            match writing_mode {
+                WritingMode::Horizontal => glyph.y += shift,
+                WritingMode::Vertical => glyph.x += shift,
+            }
+        }
+    }
+}
+
+/// Distance between the centres of two columns in font units: the `vhea`
+/// line height, or the horizontal one when the font has no vertical metrics
+fn column_pitch(face: &Face<'_>) -> f32 {
+    match (face.vertical_ascender(), face.vertical_descender()) {
+        (Some(ascender), Some(descender)) if ascender > descender => {
+            ascender as f32 - descender as f32 + face.vertical_line_gap().unwrap_or(0) as f32
+        }
+        _ => face.height() as f32,
+    }
+}
+
+/// Height of the vertical origin of `gid` above its baseline in font units:
+/// from `VORG`, else from the top side bearing in `vmtx`, else the ascender
+fn vertical_origin(face: &Face<'_>, gid: GlyphId) -> f32 {
+    if let Some(y) = face.glyph_y_origin(gid) {
+        return y as f32;
+    }
+    match (
+        face.glyph_ver_side_bearing(gid),
+        face.glyph_bounding_box(gid),
+    ) {
+        (Some(bearing), Some(rect)) => rect.y_max as f32 + bearing as f32,
+        _ => face.ascender() as f32,
+    }
+}
+
+/// Length of `line` laid out on its own: its width, or its height as a
+/// column when `writing_mode` is vertical
+pub fn line_length(
+    face: &Face<'_>,
+    fallbacks: &[Face<'_>],
+    line: &str,
+    scale: f32,
+    spacing: f32,
+    shaping: &Shaping,
+    writing_mode: WritingMode,
+) -> f32 {
+    match writing_mode {
+        WritingMode::Horizontal => {
+            place_line(face, fallbacks, line, scale, 0.0, spacing, shaping).1
+        }
+        WritingMode::Vertical => place_column(face, fallbacks, line, scale, spacing, shaping).1,
+    }
+}
+
+/// Glyphs of one column centred on x = 0, running down from y = 0, with the
+/// column's length and the characters no face has. The `vert` substitutions
+/// are applied along with `features`.
+fn place_column(
+    face: &Face<'_>,
+    fallbacks: &[Face<'_>],
+    line: &str,
+    scale: f32,
+    spacing: f32,
+    shaping: &Shaping,
+) -> (Vec<PlacedGlyph>, f32, Vec<char>) {
+    let features: Vec<Tag> = shaping
+        .features
+        .iter()
+        .copied()
+        .chain([Tag::from_bytes(b"vert")])
+        .collect();
+    let mut glyphs = Vec::new();
+    let mut missing = Vec::new();
+    let mut pen_y = 0.0;
+    let mut length = 0.0;
+    let em = face.units_per_em() as f32 * scale;
+
+    for (index, segment) in line.split('\t').enumerate() {
+        if index > 0 {
+            pen_y = -shaping.tab_stop(-pen_y, em);
+        }
+        let (runs, run_missing) = font_runs(face, fallbacks, segment, shaping.missing_glyph);
+        missing.extend(run_missing);
+        for mut run in runs {
+            let (glyph_face, units) = glyph_face(face, fallbacks, run.font);
+            let glyph_scale = scale * units;
+            gsub::substitute(glyph_face, &features, &mut run.glyphs);
+            let (mut base, mut above) = (None, None);
+            for (gid, ch) in run.glyphs {
+                if let Some(base) = base.filter(|_| is_mark(ch)) {
+                    let mark = attach_mark(
+                        glyph_face,
+                        &glyphs,
+                        base,
+                        &mut above,
+                        (gid, ch),
+                        glyph_scale,
+                    );
+                    glyphs.push(mark);
+                    continue;
+                }
+                let width = hor_advance(glyph_face, gid).unwrap_or(0) as f32;
+                let (start, advance) = shaping.cell(
+                    glyph_face.glyph_ver_advance(gid).map_or(
+                        glyph_face.ascender() as f32 - glyph_face.descender() as f32,
+                        f32::from,
+                    ) * glyph_scale,
+                );
+                glyphs.push(PlacedGlyph {
+                    gid,
+                    font: run.font,
+                    ch,
+                    x: -width / 2.0 * glyph_scale,
+                    y: pen_y - start - vertical_origin(glyph_face, gid) * glyph_scale,
+                    size: 1.0,
+                    angle: 0.0,
+                    depth: None,
+                });
+                (base, above) = (Some(glyphs.len() - 1), None);
+
+                length = advance - pen_y;
+                pen_y -= advance + spacing + shaping.extra_space(ch, em);
+            }
+        }
+    }
+    (glyphs, length, missing)
+}
+
+/// Vertical layout for Japanese signs, separate from [`layout_glyphs`]:
+/// each line becomes a column read top to bottom, the first on the right.
+/// Glyphs advance by their `vmtx` heights from their vertical origins and
+/// take their `vert` forms, so ー, 。 and brackets turn with the text; a
+/// [`LayoutLine`] then holds the column's length as `width` and the x of its
+/// centre as `baseline`. Bidi, kerning and `shaper` do not apply.
+pub fn layout_vertical(
+    face: &Face<'_>,
+    fallbacks: &[Face<'_>],
+    text: &str,
+    scale: f32,
+    top_y: f32,
+    spacing: f32,
+    shaping: &Shaping,
+) -> Layout {
+    let mut layout = Layout::default();
+    let pitch = column_pitch(face) * scale;
+    let columns = text.split('\n').count();
+
+    for (index, line) in text.split('\n').enumerate() {
+        let center_x = (columns - 1 - index) as f32 * pitch + pitch / 2.0;
+        let (glyphs, length, missing) =
+            place_column(face, fallbacks, line, scale, spacing, shaping);
+        for ch in missing {
+            eprintln!("⚠️ {}", shaping.missing_glyph.warning(face, fallbacks, ch));
+            layout.missing.push(ch);
+        }
+        layout.lines.push(LayoutLine {
+            text: line.to_string(),
+            width: length,
+            baseline: center_x,
+            glyph_count: glyphs.len(),
+            offset: 0.0,
+            wrapped: false,
+        });
+        layout
+            .glyphs
+            .extend(glyphs.into_iter().map(|glyph| PlacedGlyph {
+                x: glyph.x + center_x,
+                y: glyph.y + top_y,
+                ..glyph
+            }));
+    }
+
+    layout
+}
+
+/// Height of the inked text in font units (top of the first line's glyphs
+/// to the bottom of the last line's, `line_advance` apart, or of the
+/// longest column's when vertical), `None` when nothing has an outline
+pub fn ink_height(
+    face: &Face<'_>,
+    fallbacks: &[Face<'_>],
+    text: &str,
+    shaping: &Shaping,
+    writing_mode: WritingMode,
+    line_advance: f32,
+) -> Option<f32> {
+    let mut range: Option<(f32, f32)> = None;
+    for (index, line) in text.split('\n').enumerate() {
+        let offset = index as f32 * line_advance;
+        let glyphs = match writing_mode {
+            WritingMode::Horizontal => {
+                place_line(face, fallbacks, line, 1.0, -offset, 0.0, shaping).0
+            }
+            WritingMode::Vertical => place_column(face, fallbacks, line, 1.0, 0.0, shaping).0,
+        };
+        for glyph in glyphs {
+            let (glyph_face, units) = glyph.face(face, fallbacks);
+            let Some(rect) = glyph_bounds(glyph_face, glyph.gid) else {
+                continue;
+            };
+            let (lo, hi) = (
+                rect.y_min as f32 * units + glyph.y,
+                rect.y_max as f32 * units + glyph.y,
+            );
+            range = Some(match range {
+                Some((a, b)) => (a.min(lo), b.max(hi)),
+                None => (lo, hi),
+            });
+        }
+    }
+    range.map(|(lo, hi)| hi - lo).filter(|h| *h > 0.0)
+}
+
+/// Collects the outlines of laid-out glyphs into a path, slanted by `shear`
+/// (see [`LyonOutlineBuilder::shear`]), reporting per-glyph progress
+pub fn glyphs_to_path(
+    face: &Face<'_>,
+    fallbacks: &[Face<'_>],
+    builder: &mut PathBuilder,
+    glyphs: &[PlacedGlyph],
+    scale: f32,
+    shear: f32,
+    progress: ProgressFn,
+) {
+    for (i, glyph) in glyphs.iter().enumerate() {
+        let (glyph_face, units) = glyph.face(face, fallbacks);
+        let mut adapter = LyonOutlineBuilder {
+            builder,
+            offset_x: glyph.x,
+            offset_y: glyph.y,
+            scale: scale * units,
+            shear,
+            angle: glyph.angle,
+        };
+        // Glyphs without an outline (e.g. spaces) only advance the pen
+        glyph_outline(glyph_face, glyph.gid, &mut adapter);
+        progress(Stage::Layout, (i + 1) as f32 / glyphs.len() as f32);
+    }
+    progress(Stage::Layout, 1.0);
+}
+
+/// Adapter: ttf-parser outline -> lyon PathBuilder
+pub struct LyonOutlineBuilder<'a> {
+    pub builder: &'a mut PathBuilder,
+    pub offset_x: f32,
+    pub offset_y: f32,
+    pub scale: f32,
+    /// Horizontal shift per unit of height above the baseline, slanting
+    /// the glyph (the tangent of the slant angle; 0 keeps it upright)
+    pub shear: f32,
+    /// Counter-clockwise turn about the offset in radians (see
+    /// [`PlacedGlyph::angle`])
+    pub angle: f32,
+}
+
+impl LyonOutlineBuilder<'_> {
+    fn point(&self, x: f32, y: f32) -> Point {
+        let (x, y) = ((x + y * self.shear) * self.scale, y * self.scale);
+        let (sin, cos) = self.angle.sin_cos();
+        Point::new(
+            x * cos - y * sin + self.offset_x,
+            x * sin + y * cos + self.offset_y,
+        )
+    }
+}
+
+impl OutlineBuilder for LyonOutlineBuilder<'_> {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.builder.begin(self.point(x, y));
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.builder.line_to(self.point(x, y));
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (ctrl, to) = (self.point(x1, y1), self.point(x, y));
+        self.builder.quadratic_bezier_to(ctrl, to);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (ctrl1, ctrl2, to) = (self.point(x1, y1), self.point(x2, y2), self.point(x, y));
+        self.builder.cubic_bezier_to(ctrl1, ctrl2, to);
+    }
+
+    fn close(&mut self) {
+        self.builder.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font;
+
+    /// The embedded font with its cmap hidden, so it maps no character
+    fn without_cmap() -> Vec<u8> {
+        let mut bytes = font::EMBEDDED_FONT.to_vec();
+        let tables = u16::from_be_bytes([bytes[4], bytes[5]]) as usize;
+        let record = (0..tables)
+            .map(|i| 12 + i * 16)
+            .find(|&record| &bytes[record..record + 4] == b"cmap")
+            .unwrap();
+        bytes[record..record + 4].copy_from_slice(b"xmap");
+        bytes
+    }
+
+    fn kerned() -> Shaping {
+        Shaping {
+            kerning: true,
+            ..Shaping::default()
+        }
+    }
+
+    #[test]
+    fn missing_characters_come_from_the_fallbacks() {
+        let bytes = without_cmap();
+        let bare = font::parse_face(&bytes, 0).unwrap();
+        let full = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+
+        let alone = layout_glyphs(&bare, &[], "Aあ", 0.01, 0.0, 0.0, &kerned());
+        assert!(alone.glyphs.is_empty());
+        assert_eq!(alone.missing, ['A', 'あ']);
+
+        let fallbacks = [font::parse_face(font::EMBEDDED_FONT, 0).unwrap()];
+        let chained = layout_glyphs(&bare, &fallbacks, "Aあ", 0.01, 0.0, 0.0, &kerned());
+        let direct = layout_glyphs(&full, &[], "Aあ", 0.01, 0.0, 0.0, &kerned());
+        assert!(chained.missing.is_empty());
+        assert!(chained.glyphs.iter().all(|glyph| glyph.font == 1));
+        let xs = |layout: &Layout| layout.glyphs.iter().map(|g| g.x).collect::<Vec<_>>();
+        assert_eq!(xs(&chained), xs(&direct));
+        assert_eq!(chained.lines[0].width, direct.lines[0].width);
+        let advance = full.height() as f32;
+        assert_eq!(
+            ink_height(
+                &bare,
+                &fallbacks,
+                "Aあ",
+                &kerned(),
+                WritingMode::Horizontal,
+                advance
+            ),
+            ink_height(
+                &full,
+                &[],
+                "Aあ",
+                &kerned(),
+                WritingMode::Horizontal,
+                advance
+            )
+        );
+    }
+
+    #[test]
+    fn missing_characters_can_be_replaced() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let with = |missing_glyph: MissingGlyph| {
+            let shaping = Shaping {
+                missing_glyph,
+                ..Shaping::default()
+            };
+            layout_glyphs(&face, &[], "A\u{13000}B", 1.0, 0.0, 0.0, &shaping)
+        };
+        let gids = |layout: &Layout| layout.glyphs.iter().map(|g| g.gid).collect::<Vec<_>>();
+        let (a, b) = (
+            face.glyph_index('A').unwrap(),
+            face.glyph_index('B').unwrap(),
+        );
+
+        let skipped = with(MissingGlyph::Skip);
+        assert_eq!(gids(&skipped), [a, b]);
+        assert_eq!(skipped.missing, ['\u{13000}']);
+        assert_eq!(gids(&with(MissingGlyph::Notdef)), [a, GlyphId(0), b]);
+        let question = face.glyph_index('?').unwrap();
+        assert_eq!(gids(&with(MissingGlyph::Char('?'))), [a, question, b]);
+        assert_eq!(gids(&with(MissingGlyph::Char('\u{13001}'))), [a, b]);
+
+        // The box keeps the .notdef spacing and has an outline with a hole
+        let boxed = with(MissingGlyph::Box);
+        assert_eq!(gids(&boxed), [a, TOFU, b]);
+        assert_eq!(boxed.glyphs[2].x, with(MissingGlyph::Notdef).glyphs[2].x);
+        let rect = glyph_bounds(&face, TOFU).unwrap();
+        assert!(rect.x_min > 0 && rect.y_max > 0);
+        let mut builder = lyon_path::Path::builder();
+        glyph_outline(
+            &face,
+            TOFU,
+            &mut LyonOutlineBuilder {
+                builder: &mut builder,
+                offset_x: 0.0,
+                offset_y: 0.0,
+                scale: 1.0,
+                shear: 0.0,
+                angle: 0.0,
+            },
+        );
+        assert_eq!(
+            builder
+                .build()
+                .iter()
+                .filter(|e| matches!(e, lyon_path::Event::Begin { .. }))
+                .count(),
+            2
+        );
+
+        assert_eq!("box".parse::<MissingGlyph>().unwrap(), MissingGlyph::Box);
+        assert_eq!(
+            "・".parse::<MissingGlyph>().unwrap(),
+            MissingGlyph::Char('・')
+        );
+        assert!("tofu".parse::<MissingGlyph>().is_err());
+    }
+
+    #[test]
+    fn glyph_ids_skip_the_cmap() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let a = face.glyph_index('A').unwrap();
+        let layout = layout_glyph_ids(&face, &[a, GlyphId(1)], 0.01, 5.0, 0.0);
+        let text = layout_glyphs(&face, &[], "A", 0.01, 5.0, 0.0, &Shaping::default());
+        assert_eq!(layout.glyphs[0].x, 0.0);
+        assert_eq!(layout.glyphs[1].x, text.lines[0].width);
+        assert_eq!(layout.glyphs[1].gid, GlyphId(1));
+        assert_eq!(layout.lines[0].text, format!("<{}><1>", a.0));
+    }
+
+    #[test]
+    fn tracking_and_word_spacing_scale_with_the_em() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let em = face.units_per_em() as f32 * 0.01;
+        let x = |tracking, word_spacing| {
+            let shaping = Shaping {
+                tracking,
+                word_spacing,
+                ..Shaping::default()
+            };
+            let layout = layout_glyphs(&face, &[], "A B", 0.01, 5.0, 0.0, &shaping);
+            layout
+                .glyphs
+                .iter()
+                .map(|glyph| glyph.x)
+                .collect::<Vec<_>>()
+        };
+        let plain = x(0.0, 0.0);
+        let tracked = x(0.1, 0.0);
+        assert!((tracked[1] - plain[1] - 0.1 * em).abs() < 1e-4);
+        assert!((tracked[2] - plain[2] - 0.2 * em).abs() < 1e-4);
+        let spaced = x(0.0, 0.5);
+        assert_eq!(spaced[1], plain[1]);
+        assert!((spaced[2] - plain[2] - 0.5 * em).abs() < 1e-4);
+    }
+
+    #[test]
+    fn monospace_fixes_every_advance() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let shaping = Shaping {
+            monospace: Some(8.0),
+            ..kerned()
+        };
+        let ones = layout_glyphs(&face, &[], "11:11", 0.01, 5.0, 0.0, &shaping);
+        let eights = layout_glyphs(&face, &[], "88:88", 0.01, 5.0, 0.0, &shaping);
+        assert_eq!(ones.lines[0].width, 40.0);
+        assert_eq!(eights.lines[0].width, 40.0);
+        for (one, eight) in ones.glyphs.iter().zip(&eights.glyphs) {
+            let centre = |glyph: &PlacedGlyph| {
+                glyph.x + hor_advance(&face, glyph.gid).unwrap() as f32 * 0.01 / 2.0
+            };
+            assert!((centre(one) - centre(eight)).abs() < 1e-4);
+        }
+        assert!((ones.glyphs[3].x - ones.glyphs[0].x - 24.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn tabs_advance_to_the_next_stop() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let shaping = Shaping {
+            tab_width: Some(30.0),
+            ..Shaping::default()
+        };
+        let layout = layout_glyphs(&face, &[], "A\tB\n\t\tC", 0.01, 5.0, 0.0, &shaping);
+        assert!(layout.missing.is_empty());
+        assert_eq!(layout.glyphs[1].x, 30.0);
+        assert_eq!(layout.glyphs[2].x, 60.0);
+
+        let em = face.units_per_em() as f32 * 0.01;
+        let layout = layout_glyphs(&face, &[], "\tA", 0.01, 5.0, 0.0, &Shaping::default());
+        assert_eq!(layout.glyphs[0].x, 4.0 * em);
+        assert_eq!(Shaping::default().tab_stop(4.0 * em, em), 8.0 * em);
+    }
+
+    #[test]
+    fn normalization_composes_decomposed_text() {
+        let decomposed = "か\u{3099}e\u{301}ｶﾞＡ";
+        assert_eq!(Normalization::None.apply(decomposed), decomposed);
+        assert_eq!(Normalization::Nfc.apply(decomposed), "がéｶﾞＡ");
+        assert_eq!(Normalization::Nfkc.apply(decomposed), "がéガA");
+    }
+
+    #[test]
+    fn text_transforms_change_the_case() {
+        let text = "hello WORLD, it's straße";
+        assert_eq!(
+            TextTransform::Upper.apply(text),
+            "HELLO WORLD, IT'S STRASSE"
+        );
+        assert_eq!(TextTransform::Lower.apply(text), "hello world, it's straße");
+        assert_eq!(TextTransform::Title.apply(text), "Hello World, It's Straße");
+        assert_eq!(TextTransform::Smallcaps.apply(text), text);
+    }
+
+    #[test]
+    fn small_caps_fall_back_to_smaller_capitals() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let mut shaping = Shaping::default();
+        shaping.small_caps(&face);
+        assert!(!gsub::has_feature(&face, Tag::from_bytes(b"smcp")));
+        assert_eq!(shaping.small_caps, Some(SMALL_CAP_SIZE));
+
+        let layout = layout_glyphs(&face, &[], "Ab1", 0.01, 5.0, 0.0, &shaping);
+        let b = face.glyph_index('B').unwrap();
+        assert_eq!(layout.glyphs[1].gid, b);
+        assert_eq!(layout.glyphs[1].size, SMALL_CAP_SIZE);
+        assert_eq!(layout.glyphs[2].size, 1.0);
+        let advance = |gid| hor_advance(&face, gid).unwrap() as f32 * 0.01;
+        let a = face.glyph_index('A').unwrap();
+        assert!((layout.glyphs[2].x - advance(a) - advance(b) * SMALL_CAP_SIZE).abs() < 1e-4);
+    }
+
+    #[test]
+    fn lines_align_against_the_longest() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let shaping = Shaping::default();
+        let mut layout = layout_glyphs(&face, &[], "WWW\nW", 0.01, 5.0, 0.0, &shaping);
+        let shortfall = layout.lines[0].width - layout.lines[1].width;
+        align_lines(&mut layout, Align::Center, None, WritingMode::Horizontal);
+        assert_eq!(layout.glyphs[0].x, 0.0);
+        assert!((layout.glyphs[3].x - shortfall / 2.0).abs() < 1e-5);
+        assert_eq!(layout.lines[1].offset, layout.glyphs[3].x);
+
+        let mut columns = layout_vertical(&face, &[], "ああ\nあ", 0.01, 5.0, 0.0, &shaping);
+        let top = columns.glyphs[2].y;
+        align_lines(&mut columns, Align::Right, None, WritingMode::Vertical);
+        let length = columns.lines[0].width - columns.lines[1].width;
+        assert!((top - columns.glyphs[2].y - length).abs() < 1e-5);
+    }
+
+    #[test]
+    fn justified_lines_widen_word_gaps_then_letter_gaps() {
+        let chars: Vec<char> = " ab cd  e ".chars().collect();
+        let shifts = justify_shifts(&chars, 2.0);
+        assert_eq!(shifts, [0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 2.0, 2.0, 2.0]);
+        assert_eq!(justify_shifts(&['あ', 'い', 'う'], 1.0), [0.0, 0.5, 1.0]);
+
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let shaping = Shaping::default();
+        let mut layout = layout_glyphs(&face, &[], "A B\nA B", 0.01, 5.0, 0.0, &shaping);
+        let natural = layout.glyphs[2].x;
+        align_lines(
+            &mut layout,
+            Align::Justify,
+            Some(20.0),
+            WritingMode::Horizontal,
+        );
+        assert_eq!(layout.lines[0].width, 20.0);
+        assert_eq!(layout.glyphs[1].x, layout.glyphs[4].x);
+        assert!(layout.glyphs[2].x > natural);
+        // The last line keeps its natural spacing
+        assert_eq!(layout.glyphs[5].x, natural);
+    }
+
+    #[test]
+    fn line_height_sets_the_baseline_distance() {
+        assert_eq!(
+            "1.2".parse::<LineHeight>().unwrap(),
+            LineHeight::Factor(1.2)
+        );
+        assert_eq!(
+            "80%".parse::<LineHeight>().unwrap(),
+            LineHeight::Factor(0.8)
+        );
+        assert_eq!("14mm".parse::<LineHeight>().unwrap(), LineHeight::Mm(14.0));
+        assert!("0".parse::<LineHeight>().is_err());
+        assert!("tight".parse::<LineHeight>().is_err());
+
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let shaping = Shaping::default();
+        let mut layout = layout_glyphs(&face, &[], "A\nA\nA", 0.01, 5.0, 0.0, &shaping);
+        space_lines(
+            &face,
+            &mut layout,
+            LineHeight::Mm(3.0),
+            0.01,
+            WritingMode::Horizontal,
+        );
+        assert_eq!(layout.lines[0].baseline, 5.0);
+        assert!((layout.lines[2].baseline - -1.0).abs() < 1e-5);
+        assert_eq!(layout.glyphs[2].y, layout.lines[2].baseline);
+
+        let mut columns = layout_vertical(&face, &[], "あ\nあ", 0.01, 0.0, 0.0, &shaping);
+        let pitch = columns.lines[0].baseline - columns.lines[1].baseline;
+        let factor = LineHeight::Factor(2.0);
+        space_lines(&face, &mut columns, factor, 0.01, WritingMode::Vertical);
+        let spaced = columns.lines[0].baseline - columns.lines[1].baseline;
+        assert!((spaced - 2.0 * pitch).abs() < 1e-5);
+        assert!((columns.lines[1].baseline - pitch).abs() < 1e-5);
+    }
+
+    #[test]
+    fn joining_and_reordering_scripts_need_shaping() {
+        for ch in ['ب', 'ש', 'क', 'ก', 'ﻻ'] {
+            assert!(needs_shaping(ch), "{}", ch);
+        }
+        for ch in ['A', 'あ', '漢', ' ', '1'] {
+            assert!(!needs_shaping(ch), "{}", ch);
+        }
+    }
+
+    #[test]
+    fn right_to_left_lines_run_in_visual_order() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let chars = |text: &str, direction| {
+            let shaping = Shaping {
+                direction,
+                ..Shaping::default()
+            };
+            let layout = layout_glyphs(&face, &[], text, 1.0, 0.0, 0.0, &shaping);
+            layout
+                .glyphs
+                .iter()
+                .map(|glyph| glyph.ch)
+                .collect::<String>()
+        };
+        assert_eq!(chars("AB!", Direction::Auto), "AB!");
+        assert_eq!(chars("AB!", Direction::Rtl), "!AB");
+
+        assert_eq!(bidi_runs("abc", Direction::Auto), [(0..3, false)]);
+        // Hebrew "shalom" then a Latin word: the Hebrew is drawn last-letter first
+        let runs = bidi_runs("שלום ok", Direction::Ltr);
+        assert_eq!(runs[0], (0..8, true));
+        assert!(!runs.last().unwrap().1);
+        let runs = bidi_runs("שלום ok", Direction::Auto);
+        assert!(!runs.first().unwrap().1);
+        assert_eq!(mirror('('), Some(')'));
+    }
+
+    #[test]
+    fn vertical_columns_run_down_from_the_right() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let shaping = Shaping::default();
+        let layout = layout_vertical(&face, &[], "あー\nい", 0.01, 0.0, 0.0, &shaping);
+        let [a, bar, i] = layout.glyphs[..] else {
+            panic!("{:?}", layout.glyphs);
+        };
+        assert!((a.x - bar.x).abs() < 1e-4 && i.x < a.x);
+        assert!(bar.y < a.y);
+        assert!((a.y - i.y).abs() < 1e-4);
+        assert_ne!(bar.gid, face.glyph_index('ー').unwrap());
+        assert!((layout.lines[0].width - 20.0).abs() < 1e-4);
+        assert!((layout.lines[0].baseline - layout.lines[1].baseline - 15.0).abs() < 1e-4);
+
+        let advance = face.height() as f32;
+        let tall = ink_height(&face, &[], "あい", &shaping, WritingMode::Vertical, advance);
+        let wide = ink_height(
+            &face,
+            &[],
+            "あい",
+            &shaping,
+            WritingMode::Horizontal,
+            advance,
+        );
+        let (tall, wide) = (tall.unwrap(), wide.unwrap());
+        assert!(tall > 1.5 * wide);
+    }
+
+    #[test]
+    fn kerning_comes_from_gpos_pairs() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        assert!(face.tables().kern.is_none());
+        let gid = |ch| face.glyph_index(ch).unwrap();
+        assert!(kerning_value(&face, gid('A'), gid('V')).unwrap() < 0);
+        assert_eq!(kerning_value(&face, gid('H'), gid('H')), None);
+
+        let kerned = layout_glyphs(&face, &[], "AV", 1.0, 0.0, 0.0, &kerned());
+        let plain = layout_glyphs(&face, &[], "AV", 1.0, 0.0, 0.0, &Shaping::default());
+        assert!(kerned.lines[0].width < plain.lines[0].width);
+    }
+
+    #[test]
+    #[cfg(feature = "builtin-serif")]
+    fn combining_marks_sit_on_their_base() {
+        let serif = font::parse_face(font::Builtin::Serif.bytes().unwrap(), 0).unwrap();
+        let shaping = Shaping::default();
+        // DejaVu Serif puts the acute by its mark anchors, taking no room
+        let layout = layout_glyphs(&serif, &[], "e\u{301}x", 1.0, 0.0, 0.0, &shaping);
+        let plain = layout_glyphs(&serif, &[], "ex", 1.0, 0.0, 0.0, &shaping);
+        let (e, acute) = (serif.glyph_index('e').unwrap(), layout.glyphs[1].gid);
+        let (dx, _) = anchor_offset(&serif, e, acute, false).unwrap();
+        assert_eq!(layout.glyphs[1].x, layout.glyphs[0].x + dx);
+        assert_eq!(layout.glyphs[2].x, plain.glyphs[1].x);
+        assert_eq!(justify_shifts(&['e', '\u{301}', 'x'], 1.0), [0.0, 0.0, 1.0]);
+
+        // Noto Sans JP has no anchors for the combining dakuten, whose
+        // full-width glyph then overlaps the kana
+        let jp = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let layout = layout_glyphs(&jp, &[], "か\u{3099}き", 1.0, 0.0, 0.0, &shaping);
+        let xs: Vec<f32> = layout.glyphs.iter().map(|glyph| glyph.x).collect();
+        assert_eq!(xs, [0.0, 0.0, 1000.0]);
+        let column = layout_vertical(&jp, &[], "か\u{3099}き", 1.0, 0.0, 0.0, &shaping);
+        assert_eq!(column.glyphs[1].y, column.glyphs[0].y);
+    }
+}
@@ -0,0 +1,1376 @@
+//! Convert text into extruded triangle meshes (TTF/OTF supported).
+//!
+//! The `wagyan` CLI is a thin wrapper around [`generate`]; embedders can call
+//! the same pipeline directly and abort long jobs with a [`CancelToken`].
+
+use std::borrow::Cow;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use lyon_path::Path;
+use ttf_parser::{Face, GlyphId};
+
+use crate::layout::{PlacedGlyph, Shaping};
+
+pub mod amf;
+pub mod batch;
+pub mod cable_tag;
+pub mod cake_topper;
+pub mod cancel;
+pub mod cnc;
+pub mod color;
+pub mod colr;
+pub mod coverage;
+pub mod curve;
+pub mod doorsign;
+pub mod dry_run;
+pub mod dxf;
+pub mod font;
+pub mod font_info;
+pub mod garden_marker;
+pub mod gltf;
+pub mod glyph_cache;
+#[cfg(feature = "google-fonts")]
+pub mod google_fonts;
+pub mod gsub;
+pub mod hershey;
+pub mod i18n;
+pub mod indexed;
+pub mod jitter;
+pub mod layout;
+pub mod license_plate;
+pub mod measure;
+pub mod mesh;
+pub mod metadata;
+pub mod model_label;
+pub mod nameplate;
+pub mod obj;
+pub mod outline;
+pub mod output;
+pub mod pendant;
+pub mod placements;
+pub mod plaque;
+pub mod ply;
+pub mod preview;
+pub mod printer;
+pub mod progress;
+pub mod runs;
+#[cfg(feature = "shaping")]
+pub mod shaping;
+pub mod slicer;
+pub mod step;
+pub mod stl;
+pub mod svg;
+pub mod svg_glyph;
+pub mod template;
+pub mod threemf;
+pub mod token;
+pub mod tui;
+pub mod warp;
+pub mod woff;
+pub mod wrap;
+
+pub use cancel::{CancelToken, Cancelled};
+pub use color::Color;
+pub use curve::{Arc, ArcSide, TextPath};
+pub use indexed::IndexedMesh;
+pub use layout::{
+    Align, Direction, Layout, LineHeight, MissingGlyph, Normalization, Shaper, TextTransform,
+    WritingMode,
+};
+pub use mesh::{Anchor, Extrusion, Mesh2D, Orientation};
+pub use pendant::HangingLoop;
+pub use progress::{no_progress, ProgressFn, Stage};
+pub use runs::{Run, RunStyle};
+pub use stl_io::Triangle;
+pub use token::Token;
+pub use warp::Warp;
+
+pub const DEFAULT_TOLERANCE: f32 = 0.01;
+pub const DEFAULT_TOLERANCE_SIZE: f32 = 72.0;
+pub const MIN_TOLERANCE: f32 = 0.0005;
+pub const MAX_TOLERANCE: f32 = 0.2;
+
+pub fn resolve_tolerance(size: f32, cli_value: Option<f32>) -> f32 {
+    let scaled = DEFAULT_TOLERANCE * (size / DEFAULT_TOLERANCE_SIZE);
+    let value = cli_value.unwrap_or(scaled);
+    value.clamp(MIN_TOLERANCE, MAX_TOLERANCE)
+}
+
+/// How output units relate to the real world, shown in `--help`
+pub const UNITS_NOTE: &str =
+    "Units: all lengths are millimeters (1 STL unit = 1 mm, as slicers assume). \
+--size is the font's em size, so glyphs are usually smaller than it; use --cap-height-mm or \
+--height-mm to make a physical dimension exact.";
+
+/// Physical dimension the layout is scaled to, overriding the em size
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum SizeTarget {
+    /// Height of capital letters in mm (the font's cap height)
+    CapHeight(f32),
+    /// Height of the inked text in mm, all lines included
+    Height(f32),
+}
+
+/// Generation settings (mirrors the CLI flags)
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct Options {
+    /// Text to render (`\n` separates lines)
+    pub text: String,
+    /// Unicode normalization applied to `text` before layout
+    pub normalize: Normalization,
+    /// Case change applied to `text` before layout
+    pub text_transform: TextTransform,
+    /// Glyph ids to draw in one line instead of `text`, bypassing the cmap
+    pub glyph_ids: Vec<u16>,
+    /// Font file (.ttf/.otf). `None` uses the `builtin` font
+    pub font: Option<PathBuf>,
+    /// Font built into the binary, used when `font` is `None`
+    pub builtin: font::Builtin,
+    /// Face index for font collections (.ttc)
+    pub face_index: u32,
+    /// Fonts tried in order for characters `font` lacks
+    pub fallback_fonts: Vec<PathBuf>,
+    /// Axis settings for a variable `font`, such as `wght=700`
+    pub variations: Vec<font::Variation>,
+    /// Em size in mm
+    pub size: f32,
+    /// Scale to an exact cap or text height instead of `size`
+    pub size_target: Option<SizeTarget>,
+    /// Tessellation tolerance; `None` scales with `size`
+    pub tolerance: Option<f32>,
+    /// Extrusion depth
+    pub depth: f32,
+    /// Additional spacing between glyphs
+    pub spacing: f32,
+    /// Additional spacing between glyphs in em, so it keeps in proportion
+    /// with `size`
+    pub tracking: f32,
+    /// Additional spacing after each space character in em
+    pub word_spacing: f32,
+    /// Grow every outline outward by this many mm (shrink when negative) as
+    /// a synthetic bold
+    pub weight: f32,
+    /// Lean every glyph right by this many degrees (left when negative) as
+    /// a synthetic italic
+    pub slant: f32,
+    /// Fixed advance in mm for every glyph, centred in it, so swapping a
+    /// character leaves the others in place
+    pub monospace: Option<f32>,
+    /// Distance in mm between the tab stops `\t` advances to; `None` puts
+    /// them every four em
+    pub tab_width: Option<f32>,
+    /// Apply kerning when available
+    pub kerning: bool,
+    /// OpenType features whose GSUB substitutions are applied, such as
+    /// `liga`, `smcp` or `ss01`
+    pub features: Vec<String>,
+    /// Engine that shapes the text
+    pub shaper: Shaper,
+    /// Base direction of each line for bidirectional text
+    pub direction: Direction,
+    /// Horizontal lines or vertical columns (tategaki)
+    pub writing_mode: WritingMode,
+    /// Where shorter lines sit next to the longest one, or within `max_width`
+    pub align: Align,
+    /// Width in mm lines are wrapped at and aligned (and justified) within;
+    /// `None` leaves the lines as they are, aligned with the longest
+    pub max_width: Option<f32>,
+    /// Let wrapping break between any two characters, not only at spaces,
+    /// hyphens and around CJK characters
+    pub break_anywhere: bool,
+    /// Baseline-to-baseline distance of lines (column pitch when vertical)
+    pub line_height: LineHeight,
+    /// Radius in mm of a circle to bend the text around
+    pub arc_radius: Option<f32>,
+    /// Angle in degrees the bent text spans, setting the radius if
+    /// `arc_radius` does not
+    pub arc_angle: Option<f32>,
+    /// Half of the circle the bent text reads along
+    pub arc_side: ArcSide,
+    /// SVG file whose first path the text is laid along instead
+    pub path_file: Option<PathBuf>,
+    /// Shape the whole outline is bent into after layout
+    pub warp: Warp,
+    /// Strength of `warp`, as a fraction of the text height
+    pub warp_amount: f32,
+    /// How far each glyph is randomly moved (in em), turned (in radians)
+    /// and resized for a hand-made look; 0 leaves them in place
+    pub jitter: f32,
+    /// Seed of the `jitter`, which gives the same wobble every time
+    pub seed: u64,
+    /// Stretches of `text` in their own size, depth or font, laid out in
+    /// place of it when not empty; `text` then holds their plain text
+    pub runs: Vec<Run>,
+    /// What characters the fonts have no glyph for turn into
+    pub missing_glyph: MissingGlyph,
+    /// Fail instead of skipping characters the fonts have no glyph for
+    pub strict: bool,
+    /// Back plate thickness (0 disables)
+    pub plate: f32,
+    /// Margin to expand the plate
+    pub plate_margin: f32,
+    /// Plane orientation
+    pub orient: Orientation,
+    /// Center the text bounding box on the origin
+    pub center: bool,
+    /// Point of the text `center` puts on the origin
+    pub anchor: Anchor,
+    /// Print layer height in mm; heights are rounded to whole layers
+    pub layer_height: Option<f32>,
+    /// Hanging loop above the text (or the plate) for wearing it as a pendant
+    pub pendant: Option<HangingLoop>,
+    /// Put the text on a disc instead (the plate and loop are then ignored)
+    pub token: Option<Token>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            text: String::new(),
+            normalize: Normalization::None,
+            text_transform: TextTransform::None,
+            glyph_ids: Vec::new(),
+            font: None,
+            builtin: font::Builtin::Sans,
+            face_index: 0,
+            fallback_fonts: Vec::new(),
+            variations: Vec::new(),
+            size: 72.0,
+            size_target: None,
+            tolerance: None,
+            depth: 10.0,
+            spacing: 0.0,
+            tracking: 0.0,
+            word_spacing: 0.0,
+            weight: 0.0,
+            slant: 0.0,
+            monospace: None,
+            tab_width: None,
+            kerning: true,
+            features: Vec::new(),
+            shaper: Shaper::Builtin,
+            direction: Direction::Auto,
+            writing_mode: WritingMode::Horizontal,
+            align: Align::Left,
+            max_width: None,
+            break_anywhere: false,
+            line_height: LineHeight::Factor(1.0),
+            arc_radius: None,
+            arc_angle: None,
+            arc_side: ArcSide::Top,
+            path_file: None,
+            warp: Warp::None,
+            warp_amount: 0.3,
+            jitter: 0.0,
+            seed: 0,
+            runs: Vec::new(),
+            missing_glyph: MissingGlyph::Skip,
+            strict: false,
+            plate: 0.0,
+            plate_margin: 2.0,
+            orient: Orientation::Front,
+            center: true,
+            anchor: Anchor::Center,
+            layer_height: None,
+            pendant: None,
+            token: None,
+        }
+    }
+}
+
+impl Options {
+    /// Layout units (mm) per font unit
+    pub fn scale(&self, face: &Face<'_>) -> f32 {
+        let em_scale = self.size / face.units_per_em() as f32;
+        let (target, font_units) = match self.size_target {
+            None => return em_scale,
+            Some(SizeTarget::CapHeight(mm)) => (mm, font::cap_height(face)),
+            Some(SizeTarget::Height(mm)) => (
+                mm,
+                layout::ink_height(
+                    face,
+                    &font::fallback_faces(&self.fallback_data()),
+                    &self.laid_out_text(),
+                    &self.shaping(),
+                    self.writing_mode,
+                    // An absolute line height is taken at the --size scale
+                    self.line_height.resolve(face.height() as f32 * em_scale) / em_scale,
+                ),
+            ),
+        };
+        font_units.map_or(em_scale, |units| target / units)
+    }
+
+    /// Round a height to a whole number of layers (at least one) when
+    /// `layer_height` is set, so the top layer is never a partial one
+    pub fn snap_to_layers(&self, height: f32) -> f32 {
+        match self.layer_height {
+            Some(layer) if layer > 0.0 && height > 0.0 => (height / layer).round().max(1.0) * layer,
+            _ => height,
+        }
+    }
+
+    /// Extrusion depth after layer snapping
+    pub fn text_depth(&self) -> f32 {
+        self.snap_to_layers(self.depth)
+    }
+
+    /// Plate thickness after layer snapping
+    pub fn plate_thickness(&self) -> f32 {
+        self.snap_to_layers(self.plate)
+    }
+
+    /// Effective em size in mm once `size_target` is applied
+    pub fn em_size(&self, face: &Face<'_>) -> f32 {
+        self.scale(face) * face.units_per_em() as f32
+    }
+
+    /// Effective tessellation tolerance
+    pub fn tolerance(&self, face: &Face<'_>) -> f32 {
+        resolve_tolerance(self.em_size(face), self.tolerance)
+    }
+
+    /// `fallback_fonts`, then the fonts of `runs` not among them
+    pub fn fallback_paths(&self) -> Vec<PathBuf> {
+        let mut paths = self.fallback_fonts.clone();
+        for font in self.runs.iter().filter_map(|run| run.style.font.as_ref()) {
+            if !paths.contains(font) {
+                paths.push(font.clone());
+            }
+        }
+        paths
+    }
+
+    /// Contents of [`Options::fallback_paths`], whose faces
+    /// [`font::fallback_faces`] parses; none when one fails to load, which
+    /// [`font::fallback_fonts`] reports up front
+    pub fn fallback_data(&self) -> Vec<font::FontData> {
+        font::fallback_fonts(&self.fallback_paths()).unwrap_or_default()
+    }
+
+    /// Kerning, features, shaper, direction and em spacing as layout settings;
+    /// features that are no valid tag are skipped
+    pub fn shaping(&self) -> Shaping {
+        Shaping {
+            kerning: self.kerning,
+            features: self
+                .features
+                .iter()
+                .filter_map(|feature| gsub::parse_tag(feature).ok())
+                .collect(),
+            shaper: self.shaper,
+            direction: self.direction,
+            missing_glyph: self.missing_glyph,
+            tracking: self.tracking,
+            word_spacing: self.word_spacing,
+            monospace: self.monospace,
+            tab_width: self.tab_width,
+            small_caps: None,
+        }
+    }
+
+    /// `text` as it is laid out: normalized, then in its new case
+    pub fn laid_out_text(&self) -> String {
+        self.text_transform.apply(&self.normalize.apply(&self.text))
+    }
+
+    /// Horizontal shift per unit of height that leans glyphs by `slant`
+    pub fn shear(&self) -> f32 {
+        self.slant.to_radians().tan()
+    }
+
+    /// Circle `arc_radius` and `arc_angle` bend the text around, if any
+    pub fn arc(&self) -> Option<Arc> {
+        (self.arc_radius.is_some() || self.arc_angle.is_some()).then_some(Arc {
+            radius: self.arc_radius,
+            angle: self.arc_angle.map(f32::to_radians),
+            side: self.arc_side,
+        })
+    }
+
+    /// Path of `path_file`; none when it fails to load, which
+    /// [`TextPath::load`] reports up front
+    pub fn text_path(&self) -> Option<TextPath> {
+        self.path_file
+            .as_deref()
+            .and_then(|path| TextPath::load(path).ok())
+    }
+
+    /// Point of the text `center` puts on the origin: `anchor`, except on a
+    /// token, which keeps its text in the middle of the disc
+    pub fn text_anchor(&self) -> Anchor {
+        match self.token {
+            Some(_) => Anchor::Center,
+            None => self.anchor,
+        }
+    }
+
+    /// Fail when a `glyph_ids` entry is not a glyph of `face`
+    pub fn check_glyph_ids(&self, face: &Face<'_>) -> Result<()> {
+        let count = face.number_of_glyphs();
+        if let Some(&gid) = self.glyph_ids.iter().find(|&&gid| gid >= count) {
+            anyhow::bail!(
+                "glyph id {} is out of range (the font has glyphs 0..={})",
+                gid,
+                count.saturating_sub(1)
+            );
+        }
+        Ok(())
+    }
+
+    /// Parse the selected face of `font_bytes` with `variations` applied
+    pub fn face<'a>(&self, font_bytes: &'a [u8]) -> Result<Face<'a>> {
+        self.shaper.check()?;
+        let mut face = font::parse_face(font_bytes, self.face_index)?;
+        font::apply_variations(&mut face, &self.variations)?;
+        self.check_glyph_ids(&face)?;
+        Ok(face)
+    }
+}
+
+/// Lay out `options.text` (or `options.glyph_ids`) without generating
+/// geometry, laid along [`Options::text_path`] or bent around
+/// [`Options::arc`] if set, then shaken by [`Options::jitter`]. With
+/// `options.center` and [`Anchor::BaselineLeft`], the start of the first
+/// line on its baseline is moved to the origin.
+pub fn layout(face: &Face<'_>, options: &Options) -> Layout {
+    let scale = options.scale(face);
+    let baseline_y = face.ascender() as f32 * scale;
+    let mut layout = if !options.runs.is_empty() {
+        runs::layout_runs(face, options, scale, baseline_y)
+    } else if options.glyph_ids.is_empty() {
+        layout_text(
+            face,
+            &font::fallback_faces(&options.fallback_data()),
+            options,
+            scale,
+            baseline_y,
+        )
+    } else {
+        let gids: Vec<GlyphId> = options.glyph_ids.iter().map(|&gid| GlyphId(gid)).collect();
+        layout::layout_glyph_ids(face, &gids, scale, baseline_y, options.spacing)
+    };
+    if options.writing_mode == WritingMode::Horizontal {
+        if let Some(path) = options.text_path() {
+            curve::follow(
+                face,
+                &font::fallback_faces(&options.fallback_data()),
+                &mut layout,
+                scale,
+                &path,
+            );
+        } else if let Some(arc) = options.arc() {
+            curve::bend(
+                face,
+                &font::fallback_faces(&options.fallback_data()),
+                &mut layout,
+                scale,
+                arc,
+            );
+        }
+    }
+    let em = scale * face.units_per_em() as f32;
+    jitter::apply(&mut layout, options.jitter, options.seed, em);
+    if let (true, Anchor::BaselineLeft, Some(first)) =
+        (options.center, options.text_anchor(), layout.lines.first())
+    {
+        let (dx, dy) = match options.writing_mode {
+            WritingMode::Horizontal => (first.offset, first.baseline),
+            WritingMode::Vertical => (first.baseline, baseline_y - first.offset),
+        };
+        for glyph in &mut layout.glyphs {
+            glyph.x -= dx;
+            glyph.y -= dy;
+        }
+        for line in &mut layout.lines {
+            line.baseline -= match options.writing_mode {
+                WritingMode::Horizontal => dy,
+                WritingMode::Vertical => dx,
+            };
+        }
+    }
+    layout
+}
+
+/// [`layout`] of `options.text`, case-transformed, wrapped, spaced and
+/// aligned
+pub(crate) fn layout_text(
+    face: &Face<'_>,
+    fallbacks: &[Face<'_>],
+    options: &Options,
+    scale: f32,
+    baseline_y: f32,
+) -> Layout {
+    let mut shaping = options.shaping();
+    if options.text_transform == TextTransform::Smallcaps {
+        shaping.small_caps(face);
+    }
+    let text = options.laid_out_text();
+    let lines = match options.max_width {
+        Some(width) => wrap::wrap_lines(&text, width, options.break_anywhere, |line| {
+            layout::line_length(
+                face,
+                fallbacks,
+                line,
+                scale,
+                options.spacing,
+                &shaping,
+                options.writing_mode,
+            )
+        }),
+        None => Vec::new(),
+    };
+    let text = match lines.is_empty() {
+        true => text,
+        false => lines
+            .iter()
+            .map(|line| line.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+    let lay_out = match options.writing_mode {
+        WritingMode::Horizontal => layout::layout_glyphs,
+        WritingMode::Vertical => layout::layout_vertical,
+    };
+    let mut layout = lay_out(
+        face,
+        fallbacks,
+        &text,
+        scale,
+        baseline_y,
+        options.spacing,
+        &shaping,
+    );
+    for (line, text_line) in layout.lines.iter_mut().zip(&lines) {
+        line.wrapped = text_line.wrapped;
+    }
+    layout::space_lines(
+        face,
+        &mut layout,
+        options.line_height,
+        scale,
+        options.writing_mode,
+    );
+    layout::align_lines(
+        &mut layout,
+        options.align,
+        options.max_width,
+        options.writing_mode,
+    );
+    layout
+}
+
+/// Lay out the text and collect all glyph outlines into a single path (mm),
+/// slanted by `options.slant`, emboldened by `options.weight` and bent by
+/// `options.warp`
+pub fn build_path(face: &Face<'_>, options: &Options, progress: ProgressFn) -> Path {
+    let layout = layout(face, options);
+    let path = glyphs_path(face, options, &layout.glyphs, progress);
+    options
+        .warp
+        .apply(&path, options.warp_amount, options.tolerance(face))
+}
+
+/// Outlines of `glyphs` in one path, slanted and emboldened but not warped
+fn glyphs_path(
+    face: &Face<'_>,
+    options: &Options,
+    glyphs: &[PlacedGlyph],
+    progress: ProgressFn,
+) -> Path {
+    let mut path_builder = Path::builder();
+    layout::glyphs_to_path(
+        face,
+        &font::fallback_faces(&options.fallback_data()),
+        &mut path_builder,
+        glyphs,
+        options.scale(face),
+        options.shear(),
+        progress,
+    );
+    let path = path_builder.build();
+    match options.weight {
+        0.0 => path,
+        weight => outline::embolden(&path, weight, options.tolerance(face)),
+    }
+}
+
+/// [`build_path`] placed where [`text_parts`] puts the text, one path per
+/// text part (`text`, then `text_1`, `text_2`, ... for runs with their own
+/// depth): anchored by the bounds of the flattened outlines, as the
+/// tessellated meshes are
+pub fn text_paths(face: &Face<'_>, options: &Options) -> Vec<Path> {
+    use lyon_path::iterator::PathIterator;
+
+    let paths = depth_paths(face, options, &layout(face, options), &no_progress);
+    if !options.center {
+        return paths;
+    }
+    let bounds = paths
+        .iter()
+        .flat_map(|path| path.iter().flattened(options.tolerance(face)))
+        .filter_map(|event| match event {
+            lyon_path::Event::Begin { at } => Some(at),
+            lyon_path::Event::Line { to, .. } => Some(to),
+            _ => None,
+        })
+        .map(|p| (p.x, p.x, p.y, p.y))
+        .reduce(|a, b| (a.0.min(b.0), a.1.max(b.1), a.2.min(b.2), a.3.max(b.3)));
+    match bounds {
+        Some(bounds) => {
+            let (dx, dy) = options.text_anchor().offset(bounds);
+            let translation = lyon_path::math::Transform::translation(dx, dy);
+            paths
+                .into_iter()
+                .map(|path| path.transformed(&translation))
+                .collect()
+        }
+        None => paths,
+    }
+}
+
+/// Depths of the runs in `layout`: `None` (the text depth) first, then each
+/// run depth in the order it first appears
+fn run_depths(layout: &Layout) -> Vec<Option<f32>> {
+    let mut depths: Vec<Option<f32>> = vec![None];
+    for glyph in &layout.glyphs {
+        if !depths.contains(&glyph.depth) {
+            depths.push(glyph.depth);
+        }
+    }
+    depths
+}
+
+/// Outlines of the glyphs of each of [`run_depths`], bent by `options.warp`
+/// together
+fn depth_paths(
+    face: &Face<'_>,
+    options: &Options,
+    layout: &Layout,
+    progress: ProgressFn,
+) -> Vec<Path> {
+    let depths = run_depths(layout);
+    let paths: Vec<Path> = match depths.len() {
+        1 => vec![glyphs_path(face, options, &layout.glyphs, progress)],
+        _ => depths
+            .iter()
+            .map(|&depth| {
+                let glyphs: Vec<PlacedGlyph> = layout
+                    .glyphs
+                    .iter()
+                    .filter(|glyph| glyph.depth == depth)
+                    .copied()
+                    .collect();
+                glyphs_path(face, options, &glyphs, progress)
+            })
+            .collect(),
+    };
+    options
+        .warp
+        .apply_all(&paths, options.warp_amount, options.tolerance(face))
+}
+
+/// Lay out and tessellate the text into a 2D mesh
+pub fn build_mesh(
+    face: &Face<'_>,
+    options: &Options,
+    cancel: &CancelToken,
+    progress: ProgressFn,
+) -> Result<Mesh2D> {
+    let path = build_path(face, options, progress);
+    tessellate_text(face, options, &path, cancel, progress)
+}
+
+/// Tessellate the text outline built by [`build_path`]
+fn tessellate_text(
+    face: &Face<'_>,
+    options: &Options,
+    path: &Path,
+    cancel: &CancelToken,
+    progress: ProgressFn,
+) -> Result<Mesh2D> {
+    cancel.check()?;
+
+    progress(Stage::Tessellate, 0.0);
+    let mesh = mesh::tessellate_path_with_cancel(path, options.tolerance(face), cancel)?;
+    progress(Stage::Tessellate, 1.0);
+    ensure_strokes_left(face, options, [&mesh])?;
+    Ok(mesh)
+}
+
+/// Fail when a negative `options.weight` thinned every stroke of the text
+/// away, which would give a model without letters
+fn ensure_strokes_left<'a>(
+    face: &Face<'_>,
+    options: &Options,
+    meshes: impl IntoIterator<Item = &'a Mesh2D>,
+) -> Result<()> {
+    if options.weight >= 0.0 || meshes.into_iter().any(|mesh| !mesh.indices.is_empty()) {
+        return Ok(());
+    }
+    let unthinned = Options {
+        weight: 0.0,
+        ..options.clone()
+    };
+    anyhow::ensure!(
+        build_path(face, &unthinned, &no_progress)
+            .iter()
+            .next()
+            .is_none(),
+        "{}",
+        i18n::Message::ThinnedAway(options.weight)
+    );
+    Ok(())
+}
+
+/// Lay out and tessellate the text and split the model into parts as
+/// [`model_parts`] does, except that glyphs of runs with their own depth
+/// (see [`Options::runs`]) become parts of their own, `text_1`, `text_2`,
+/// ..., standing on the same plane as the rest of the text
+pub fn text_parts(
+    face: &Face<'_>,
+    options: &Options,
+    cancel: &CancelToken,
+    progress: ProgressFn,
+) -> Result<Vec<Part>> {
+    let layout = layout(face, options);
+    let depths = run_depths(&layout);
+    let paths = depth_paths(face, options, &layout, progress);
+    if let [path] = paths.as_slice() {
+        let mesh = tessellate_text(face, options, path, cancel, progress)?;
+        return Ok(model_parts(mesh, options));
+    }
+    cancel.check()?;
+    let tolerance = options.tolerance(face);
+    progress(Stage::Tessellate, 0.0);
+    let mut text = Vec::new();
+    for (i, path) in paths.iter().enumerate() {
+        let name = match i {
+            0 => "text".to_string(),
+            i => format!("text_{}", i),
+        };
+        let mesh = mesh::tessellate_path_with_cancel(path, tolerance, cancel)?;
+        text.push((name, None, mesh));
+    }
+    progress(Stage::Tessellate, 1.0);
+    ensure_strokes_left(face, options, text.iter().map(|(_, _, mesh)| mesh))?;
+    let base = options.text_depth();
+    let mut parts = assemble_parts(text, options);
+    for part in &mut parts {
+        let run = part.name.strip_prefix("text_");
+        if let Some(Some(depth)) = run.and_then(|i| depths.get(i.parse::<usize>().ok()?)) {
+            part.depth = options.snap_to_layers(*depth);
+            // Runs stand on the plate (or the bottom of the text) and
+            // rise to their own height
+            part.z_offset += (part.depth - base) * 0.5;
+        }
+    }
+    Ok(parts)
+}
+
+/// Center the mesh, add the back plate and extrude into triangles
+pub fn extrude_model(mesh: Mesh2D, options: &Options) -> Vec<Triangle> {
+    extrude_model_iter(mesh, options).collect()
+}
+
+/// One extruded part of a model (the lettering, one letter of it, or the back plate)
+#[derive(Clone)]
+pub struct Part {
+    pub name: String,
+    /// Outline mesh in the text plane
+    pub mesh: Mesh2D,
+    pub depth: f32,
+    /// Offset of the part's center along the extrusion axis
+    pub z_offset: f32,
+    /// Index among the letters when the text is split per glyph
+    pub letter: Option<usize>,
+    /// How far the top outline sits inside the bottom one in mm, sloping
+    /// the side walls (0 for straight walls)
+    pub chamfer: f32,
+    /// Color the part prints in regardless of the color flags, for the
+    /// layers of color glyphs
+    pub color: Option<Color>,
+}
+
+impl Part {
+    /// Whether this is lettering (the whole text, one letter or one color
+    /// of it) rather than a plate, rim or other shape around it
+    pub fn is_text(&self) -> bool {
+        self.name == "text"
+            || self.name.starts_with("text_")
+            || self.letter.is_some()
+            || self.color.is_some()
+    }
+
+    /// This part with every length multiplied by `factor`, for writing in
+    /// units other than mm
+    pub fn scaled(&self, factor: f32) -> Part {
+        let mut part = self.clone();
+        mesh::scale_mesh(&mut part.mesh, factor);
+        part.depth *= factor;
+        part.z_offset *= factor;
+        part.chamfer *= factor;
+        part
+    }
+
+    /// Lazily extrude this part
+    pub fn extrusion(&self, orient: Orientation) -> Extrusion<'_> {
+        Extrusion::new(Cow::Borrowed(&self.mesh), self.depth, orient, self.z_offset)
+            .with_chamfer(self.chamfer)
+    }
+
+    /// Extrude into an indexed mesh with vertex normals; `crease_degrees`
+    /// enables smooth side walls (see [`IndexedMesh::extrude`])
+    pub fn indexed(&self, orient: &Orientation, crease_degrees: Option<f32>) -> IndexedMesh {
+        IndexedMesh::extrude_chamfered(
+            &self.mesh,
+            self.depth,
+            orient,
+            self.z_offset,
+            crease_degrees,
+            self.chamfer,
+        )
+    }
+}
+
+/// Center the mesh and split the model into parts: the plate (if any) first, then the text
+pub fn model_parts(mesh: Mesh2D, options: &Options) -> Vec<Part> {
+    assemble_parts(vec![("text".to_string(), None, mesh)], options)
+}
+
+/// [`model_parts`] with the text as one part per glyph mesh (see
+/// [`glyph_cache::GlyphMeshCache::glyph_meshes`]), named after the character
+/// and its index among the letters: `A_0`, `B_1`, ...
+pub fn letter_parts(letters: Vec<(char, Mesh2D)>, options: &Options) -> Vec<Part> {
+    let text = letters
+        .into_iter()
+        .enumerate()
+        .map(|(i, (ch, mesh))| (format!("{}_{}", ch, i), Some(i), mesh))
+        .collect();
+    assemble_parts(text, options)
+}
+
+/// [`model_parts`] for text with COLR color glyphs (emoji): the plain
+/// glyphs as `text` and each layer color as a `color_rrggbb` part printed in
+/// that color; `None` when the text has no color glyphs
+pub fn color_parts(
+    face: &Face<'_>,
+    options: &Options,
+    cancel: &CancelToken,
+) -> Result<Option<Vec<Part>>> {
+    let fallback_data = options.fallback_data();
+    let fallbacks = font::fallback_faces(&fallback_data);
+    if !colr::has_color_glyphs(face, &fallbacks, &options.laid_out_text()) {
+        return Ok(None);
+    }
+    let layout = layout(face, options);
+    let meshes = colr::color_meshes(
+        face,
+        &fallbacks,
+        &layout.glyphs,
+        options.scale(face),
+        options.shear(),
+        options.tolerance(face),
+        cancel,
+    )?;
+    let name = |color: Option<Color>| match color {
+        Some(color) => format!("color_{}", color.to_string().trim_start_matches('#')),
+        None => "text".to_string(),
+    };
+    let colors: Vec<(String, Color)> = meshes
+        .iter()
+        .filter_map(|(color, _)| Some((name(*color), (*color)?)))
+        .collect();
+    let text = meshes
+        .into_iter()
+        .map(|(color, mesh)| (name(color), None, mesh))
+        .collect();
+    let mut parts = assemble_parts(text, options);
+    for part in &mut parts {
+        part.color = colors
+            .iter()
+            .find(|(name, _)| *name == part.name)
+            .map(|(_, color)| *color);
+    }
+    Ok(Some(parts))
+}
+
+fn assemble_parts(mut text: Vec<(String, Option<usize>, Mesh2D)>, options: &Options) -> Vec<Part> {
+    let bounds = text
+        .iter()
+        .filter_map(|(_, _, mesh)| mesh::mesh_bounds(mesh))
+        .reduce(|a, b| (a.0.min(b.0), a.1.max(b.1), a.2.min(b.2), a.3.max(b.3)));
+    let Some((mut min_x, mut max_x, mut min_y, mut max_y)) = bounds else {
+        return Vec::new();
+    };
+
+    if options.center {
+        let (dx, dy) = options.text_anchor().offset((min_x, max_x, min_y, max_y));
+        for (_, _, mesh) in &mut text {
+            mesh::translate_mesh(mesh, dx, dy);
+        }
+        (min_x, max_x, min_y, max_y) = (min_x + dx, max_x + dx, min_y + dy, max_y + dy);
+    }
+
+    if let Some(token) = &options.token {
+        return token::token_parts(token, text, options);
+    }
+
+    let mut parts = Vec::new();
+
+    let (depth, plate) = (options.text_depth(), options.plate_thickness());
+    let loop_tolerance = options.tolerance.unwrap_or(DEFAULT_TOLERANCE);
+    if plate > 0.0 {
+        let (min_x, max_x, min_y, max_y) = (
+            min_x - options.plate_margin,
+            max_x + options.plate_margin,
+            min_y - options.plate_margin,
+            max_y + options.plate_margin,
+        );
+        let mut mesh = mesh::rectangle_mesh(min_x, max_x, min_y, max_y);
+        // The loop is part of the plate, on its top edge
+        if let Some(hanger) = &options.pendant {
+            let mut builder = Path::builder();
+            builder.add_polygon(lyon_path::Polygon {
+                points: &mesh.vertices,
+                closed: true,
+            });
+            hanger.add_ring(&mut builder, (min_x + max_x) * 0.5, max_y);
+            if let Ok(merged) = mesh::tessellate_path(&builder.build(), loop_tolerance) {
+                mesh = merged;
+            }
+        }
+        parts.push(Part {
+            name: "plate".to_string(),
+            mesh,
+            depth: plate,
+            z_offset: -(depth * 0.5 + plate * 0.5),
+            letter: None,
+            chamfer: 0.0,
+            color: None,
+        });
+    } else if let Some(hanger) = &options.pendant {
+        let meshes: Vec<&Mesh2D> = text.iter().map(|(_, _, mesh)| mesh).collect();
+        if let Ok(Some(mesh)) = hanger.text_loop(&meshes, loop_tolerance) {
+            parts.push(Part {
+                name: "loop".to_string(),
+                mesh,
+                depth,
+                z_offset: 0.0,
+                letter: None,
+                chamfer: 0.0,
+                color: None,
+            });
+        }
+    }
+
+    parts.extend(text.into_iter().map(|(name, letter, mesh)| Part {
+        name,
+        mesh,
+        depth,
+        z_offset: 0.0,
+        letter,
+        chamfer: 0.0,
+        color: None,
+    }));
+    parts
+}
+
+/// Axis-aligned bounds (min, max) of the extruded parts in output coordinates
+pub fn model_bounds(parts: &[Part], orient: &Orientation) -> Option<([f32; 3], [f32; 3])> {
+    parts
+        .iter()
+        .filter_map(|part| {
+            let (min_x, max_x, min_y, max_y) = mesh::mesh_bounds(&part.mesh)?;
+            let z0 = part.z_offset - part.depth * 0.5;
+            let z1 = part.z_offset + part.depth * 0.5;
+            let a = mesh::map_point(lyon_path::math::Point::new(min_x, min_y), z0, orient);
+            let b = mesh::map_point(lyon_path::math::Point::new(max_x, max_y), z1, orient);
+            Some((
+                [a[0].min(b[0]), a[1].min(b[1]), a[2].min(b[2])],
+                [a[0].max(b[0]), a[1].max(b[1]), a[2].max(b[2])],
+            ))
+        })
+        .reduce(|(lo, hi), (a, b)| {
+            (
+                [lo[0].min(a[0]), lo[1].min(a[1]), lo[2].min(a[2])],
+                [hi[0].max(b[0]), hi[1].max(b[1]), hi[2].max(b[2])],
+            )
+        })
+}
+
+/// Triangles of one model, produced lazily part by part
+pub struct ModelTriangles {
+    parts: Vec<Extrusion<'static>>,
+    current: usize,
+}
+
+impl Iterator for ModelTriangles {
+    type Item = Triangle;
+
+    fn next(&mut self) -> Option<Triangle> {
+        while let Some(part) = self.parts.get_mut(self.current) {
+            if let Some(tri) = part.next() {
+                return Some(tri);
+            }
+            self.current += 1;
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.parts[self.current.min(self.parts.len())..]
+            .iter()
+            .map(ExactSizeIterator::len)
+            .sum();
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for ModelTriangles {}
+
+/// Streaming variant of [`extrude_model`]
+pub fn extrude_model_iter(mesh: Mesh2D, options: &Options) -> ModelTriangles {
+    extrude_parts(model_parts(mesh, options), options)
+}
+
+/// Triangles of `parts`, extruded lazily
+fn extrude_parts(parts: Vec<Part>, options: &Options) -> ModelTriangles {
+    let parts = parts
+        .into_iter()
+        .map(|part| {
+            Extrusion::new(
+                Cow::Owned(part.mesh),
+                part.depth,
+                options.orient.clone(),
+                part.z_offset,
+            )
+            .with_chamfer(part.chamfer)
+        })
+        .collect();
+    ModelTriangles { parts, current: 0 }
+}
+
+/// Run the whole pipeline: load the font, lay out, tessellate and extrude
+pub fn generate(options: &Options) -> Result<Vec<Triangle>> {
+    generate_with_cancel(options, &CancelToken::new())
+}
+
+/// Stream the generated triangles instead of collecting them.
+///
+/// The 2D tessellation still happens up front; extrusion is lazy, so the
+/// full triangle soup never has to be held in memory.
+pub fn triangles(options: &Options) -> Result<ModelTriangles> {
+    let font_bytes = font::load_font_bytes(options.font.as_deref(), options.builtin)?;
+    let face = options.face(&font_bytes)?;
+    font::fallback_fonts(&options.fallback_paths())?;
+    coverage::ensure_covered(&face, options)?;
+    let parts = text_parts(&face, options, &CancelToken::new(), &no_progress)?;
+    Ok(extrude_parts(parts, options))
+}
+
+/// [`generate`] that stops with a [`Cancelled`] error once `cancel` fires
+pub fn generate_with_cancel(options: &Options, cancel: &CancelToken) -> Result<Vec<Triangle>> {
+    generate_with_progress(options, cancel, &no_progress)
+}
+
+/// [`generate_with_cancel`] that also reports per-stage progress
+pub fn generate_with_progress(
+    options: &Options,
+    cancel: &CancelToken,
+    progress: ProgressFn,
+) -> Result<Vec<Triangle>> {
+    let font_bytes = font::load_font_bytes(options.font.as_deref(), options.builtin)?;
+    let face = options.face(&font_bytes)?;
+    font::fallback_fonts(&options.fallback_paths())?;
+    coverage::ensure_covered(&face, options)?;
+    let parts = text_parts(&face, options, cancel, progress)?;
+    cancel.check()?;
+
+    let model = extrude_parts(parts, options);
+    let total = model.len();
+    let mut triangles = Vec::with_capacity(total);
+    for tri in model {
+        triangles.push(tri);
+        if triangles
+            .len()
+            .is_multiple_of(progress::EXTRUDE_REPORT_INTERVAL)
+        {
+            cancel.check()?;
+            progress(Stage::Extrude, triangles.len() as f32 / total as f32);
+        }
+    }
+    progress(Stage::Extrude, 1.0);
+    Ok(triangles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tolerance_scales_with_size() {
+        let base = resolve_tolerance(72.0, None);
+        let bigger = resolve_tolerance(144.0, None);
+        let smaller = resolve_tolerance(24.0, None);
+
+        assert!(bigger > base);
+        assert!(smaller < base);
+    }
+
+    #[test]
+    fn tolerance_is_clamped() {
+        let min = resolve_tolerance(1.0, Some(0.00001));
+        let max = resolve_tolerance(10_000.0, Some(10.0));
+
+        assert_eq!(min, MIN_TOLERANCE);
+        assert_eq!(max, MAX_TOLERANCE);
+    }
+
+    #[test]
+    fn cancelled_token_aborts_generation() {
+        let options = Options {
+            text: "HELLO".into(),
+            ..Default::default()
+        };
+        let cancel = CancelToken::new();
+        cancel.cancel();
+
+        let err = generate_with_cancel(&options, &cancel).unwrap_err();
+        assert_eq!(err.downcast_ref::<Cancelled>(), Some(&Cancelled::Requested));
+    }
+
+    #[test]
+    fn streamed_triangles_match_collected_ones() {
+        let options = Options {
+            text: "Hi".into(),
+            plate: 1.0,
+            ..Default::default()
+        };
+        let streamed: Vec<Triangle> = triangles(&options).unwrap().collect();
+        let collected = generate(&options).unwrap();
+        assert_eq!(streamed.len(), collected.len());
+        assert!(!streamed.is_empty());
+    }
+
+    #[test]
+    fn streamed_triangles_report_missing_fallback_fonts() {
+        let options = Options {
+            text: "Hi".into(),
+            fallback_fonts: vec!["/nonexistent/fallback.ttf".into()],
+            ..Default::default()
+        };
+        assert!(triangles(&options).is_err());
+    }
+
+    #[test]
+    fn thinning_every_stroke_away_is_an_error() {
+        let thinned = |weight| {
+            triangles(&Options {
+                text: "Hi".into(),
+                weight,
+                ..Default::default()
+            })
+        };
+        assert!(thinned(-0.2).unwrap().len() > 0);
+        assert!(thinned(-100.0).is_err());
+    }
+
+    #[test]
+    fn progress_reaches_completion_for_every_stage() {
+        use std::cell::RefCell;
+
+        let options = Options {
+            text: "ABC".into(),
+            ..Default::default()
+        };
+        let seen = RefCell::new(Vec::new());
+        generate_with_progress(&options, &CancelToken::new(), &|stage, f| {
+            seen.borrow_mut().push((stage, f))
+        })
+        .unwrap();
+
+        let seen = seen.into_inner();
+        for stage in [Stage::Layout, Stage::Tessellate, Stage::Extrude] {
+            assert!(seen.contains(&(stage, 1.0)), "{:?} never completed", stage);
+        }
+        assert!(seen.iter().all(|&(_, f)| (0.0..=1.0).contains(&f)));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn options_round_trip_through_json() {
+        let options = Options {
+            text: "こんにちは".into(),
+            orient: Orientation::Flat,
+            plate: 2.0,
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&options).unwrap();
+        let back: Options = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.text, options.text);
+        assert_eq!(back.plate, 2.0);
+        assert!(matches!(back.orient, Orientation::Flat));
+
+        // Missing fields fall back to defaults
+        let partial: Options = serde_json::from_str(r#"{"text":"A","orient":"front"}"#).unwrap();
+        assert_eq!(partial.size, 72.0);
+    }
+
+    #[test]
+    fn layer_height_rounds_to_whole_layers() {
+        let options = Options {
+            depth: 2.9,
+            plate: 0.05,
+            layer_height: Some(0.2),
+            ..Default::default()
+        };
+        assert!((options.text_depth() - 3.0).abs() < 1e-5);
+        assert!((options.plate_thickness() - 0.2).abs() < 1e-6);
+
+        let unsnapped = Options {
+            depth: 2.9,
+            ..Default::default()
+        };
+        assert_eq!(unsnapped.text_depth(), 2.9);
+    }
+
+    #[test]
+    fn letter_parts_share_centering_and_plate() {
+        let options = Options {
+            plate: 1.0,
+            ..Default::default()
+        };
+        let letters = vec![
+            ('A', mesh::rectangle_mesh(0.0, 1.0, 0.0, 1.0)),
+            ('A', mesh::rectangle_mesh(3.0, 4.0, 0.0, 2.0)),
+        ];
+        let parts = letter_parts(letters, &options);
+        let names: Vec<&str> = parts.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["plate", "A_0", "A_1"]);
+        assert_eq!(parts[2].letter, Some(1));
+
+        // Centered as a whole, and the plate covers every letter plus the margin
+        assert_eq!(
+            mesh::mesh_bounds(&parts[1].mesh),
+            Some((-2.0, -1.0, -1.0, 0.0))
+        );
+        assert_eq!(
+            mesh::mesh_bounds(&parts[0].mesh),
+            Some((-4.0, 4.0, -3.0, 3.0))
+        );
+    }
+
+    #[test]
+    fn anchors_put_their_point_on_the_origin() {
+        let rect = || mesh::rectangle_mesh(2.0, 10.0, 1.0, 5.0);
+        let bounds = |anchor| {
+            let options = Options {
+                anchor,
+                ..Default::default()
+            };
+            mesh::mesh_bounds(&model_parts(rect(), &options)[0].mesh).unwrap()
+        };
+        assert_eq!(bounds(Anchor::Center), (-4.0, 4.0, -2.0, 2.0));
+        assert_eq!(bounds(Anchor::TopLeft), (0.0, 8.0, -4.0, 0.0));
+        assert_eq!(bounds(Anchor::BottomCenter), (-4.0, 4.0, 0.0, 4.0));
+
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let options = Options {
+            text: "Hi\nthere".into(),
+            align: Align::Right,
+            anchor: Anchor::BaselineLeft,
+            ..Default::default()
+        };
+        let layout = layout(&face, &options);
+        assert_eq!(layout.lines[0].baseline, 0.0);
+        assert_eq!(layout.glyphs[0].x, 0.0);
+        assert_eq!(layout.glyphs[0].y, 0.0);
+        assert!(layout.glyphs[2].x < 0.0);
+    }
+
+    #[test]
+    fn slant_leans_the_glyphs_from_their_baseline() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let upright = Options {
+            text: "I".into(),
+            ..Default::default()
+        };
+        let slanted = Options {
+            slant: 12.0,
+            ..upright.clone()
+        };
+        let bounds = |options: &Options| {
+            let outlines = outline::from_path(&build_path(&face, options, &no_progress), 0.01);
+            let points = outlines.iter().flatten();
+            points.fold((f32::MAX, f32::MIN, f32::MAX, f32::MIN), |b, p| {
+                (b.0.min(p.x), b.1.max(p.x), b.2.min(p.y), b.3.max(p.y))
+            })
+        };
+        let (a, b) = (bounds(&upright), bounds(&slanted));
+        assert_eq!((a.0, a.2, a.3), (b.0, b.2, b.3));
+        let lean = (a.3 - a.2) * 12f32.to_radians().tan();
+        assert!((b.1 - a.1 - lean).abs() < 0.01, "{} vs {}", b.1 - a.1, lean);
+
+        let layout = layout(&face, &slanted);
+        let reported =
+            dry_run::layout_bounds(&face, &[], &layout, slanted.scale(&face), slanted.shear());
+        assert!((reported.unwrap().1 - b.1).abs() < 0.01);
+    }
+
+    #[test]
+    fn pendant_loop_hangs_from_the_plate_or_the_text() {
+        let hanger = HangingLoop::default();
+        let mut options = Options {
+            plate: 1.0,
+            pendant: Some(hanger.clone()),
+            ..Default::default()
+        };
+        let text = || mesh::rectangle_mesh(0.0, 10.0, 0.0, 4.0);
+
+        // Merged into the plate, on top of its upper edge
+        let parts = model_parts(text(), &options);
+        let names: Vec<&str> = parts.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["plate", "text"]);
+        let (.., max_y) = mesh::mesh_bounds(&parts[0].mesh).unwrap();
+        let expected = 2.0 + options.plate_margin - hanger.width * 0.5 + hanger.outer_diameter();
+        assert!((max_y - expected).abs() < 1e-3);
+
+        options.plate = 0.0;
+        let parts = model_parts(text(), &options);
+        let names: Vec<&str> = parts.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["loop", "text"]);
+    }
+
+    #[test]
+    fn size_targets_make_physical_heights_exact() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let mut options = Options {
+            text: "Hg\nHg".into(),
+            center: false,
+            orient: Orientation::Flat,
+            ..Default::default()
+        };
+
+        options.size_target = Some(SizeTarget::CapHeight(10.0));
+        let cap = font::cap_height(&face).unwrap();
+        assert!((cap * options.scale(&face) - 10.0).abs() < 1e-4);
+
+        options.size_target = Some(SizeTarget::Height(25.0));
+        let tris = generate(&options).unwrap();
+        let ys = tris.iter().flat_map(|t| t.vertices.iter().map(|v| v[1]));
+        let (lo, hi) = ys.fold((f32::MAX, f32::MIN), |(lo, hi), y| (lo.min(y), hi.max(y)));
+        assert!((hi - lo - 25.0).abs() < 0.05, "height {}", hi - lo);
+    }
+}
@@ -0,0 +1,149 @@
+//! License-plate preset: a full-size plate with a raised frame, bolt holes
+//! at the standard spacing and large centered lettering
+
+use anyhow::Result;
+use lyon_path::math::Point;
+use ttf_parser::Face;
+
+use crate::cancel::CancelToken;
+use crate::nameplate::{centered_text, raised_parts, Outline};
+use crate::{Options, Part};
+
+/// License-plate settings; defaults are the North American 12 x 6 in plate
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct LicensePlate {
+    /// Plate width in mm
+    pub width: f32,
+    /// Plate height in mm
+    pub height: f32,
+    /// Plate thickness in mm
+    pub thickness: f32,
+    pub corner_radius: f32,
+    /// Width of the raised frame in mm
+    pub frame: f32,
+    /// Number of bolt holes: 0, 2 (top row) or 4
+    pub bolts: u8,
+    /// Center distance between the left and right holes in mm
+    pub bolt_spacing_x: f32,
+    /// Center distance between the top and bottom rows in mm
+    pub bolt_spacing_y: f32,
+    pub bolt_diameter: f32,
+}
+
+impl Default for LicensePlate {
+    fn default() -> Self {
+        Self {
+            width: 304.8,
+            height: 152.4,
+            thickness: 2.0,
+            corner_radius: 6.0,
+            frame: 4.0,
+            bolts: 4,
+            bolt_spacing_x: 177.8,
+            bolt_spacing_y: 120.65,
+            bolt_diameter: 7.0,
+        }
+    }
+}
+
+impl LicensePlate {
+    /// Bolt hole centers, top row first
+    fn bolt_holes(&self) -> Vec<Point> {
+        let (x, y) = (self.bolt_spacing_x * 0.5, self.bolt_spacing_y * 0.5);
+        let rows: &[f32] = match self.bolts {
+            0 => &[],
+            2 => &[y],
+            _ => &[y, -y],
+        };
+        rows.iter()
+            .flat_map(|&y| [Point::new(-x, y), Point::new(x, y)])
+            .collect()
+    }
+}
+
+/// Build the plate, frame and lettering; text too wide for the space
+/// inside the frame is scaled down to fit
+pub fn license_plate_parts(
+    face: &Face<'_>,
+    options: &Options,
+    plate: &LicensePlate,
+    cancel: &CancelToken,
+) -> Result<Vec<Part>> {
+    anyhow::ensure!(
+        matches!(plate.bolts, 0 | 2 | 4),
+        "a license plate has 0, 2 or 4 bolt holes, not {}",
+        plate.bolts
+    );
+    let (mut text, text_w, text_h) = centered_text(face, options, cancel)?;
+
+    // Keep clear of the frame, and stay between the rows of bolt holes
+    let clearance = plate.frame + plate.bolt_diameter;
+    let room_w = plate.width * 0.5 - clearance;
+    let room_h = if plate.bolts > 0 {
+        plate.bolt_spacing_y * 0.5 - plate.bolt_diameter
+    } else {
+        plate.height * 0.5 - clearance
+    };
+    let fit = (room_w / text_w).min(room_h / text_h);
+    if fit < 1.0 {
+        anyhow::ensure!(fit > 0.0, "the plate is too small for its frame");
+        for p in &mut text.vertices {
+            *p = Point::new(p.x * fit, p.y * fit);
+        }
+    }
+
+    let (half_w, half_h) = (plate.width * 0.5, plate.height * 0.5);
+    let outline = Outline {
+        half_w,
+        half_h,
+        radius: plate.corner_radius.min(half_w).min(half_h),
+        holes: plate.bolt_holes(),
+        hole_radius: plate.bolt_diameter * 0.5,
+        slots: Vec::new(),
+    };
+    raised_parts(
+        text,
+        &outline,
+        options.snap_to_layers(plate.thickness),
+        plate.frame,
+        options,
+        options.tolerance(face),
+        cancel,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font;
+    use crate::mesh;
+    use crate::SizeTarget;
+
+    #[test]
+    fn wide_text_is_shrunk_to_fit_inside_the_frame() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let options = Options {
+            text: "ABCDEFGHIJKL".into(),
+            size_target: Some(SizeTarget::Height(70.0)),
+            depth: 1.5,
+            ..Default::default()
+        };
+        let plate = LicensePlate::default();
+        let parts = license_plate_parts(&face, &options, &plate, &CancelToken::new()).unwrap();
+        let names: Vec<&str> = parts.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["plate", "rim", "text"]);
+
+        let (min_x, max_x, ..) = mesh::mesh_bounds(&parts[2].mesh).unwrap();
+        let room = plate.width - 2.0 * (plate.frame + plate.bolt_diameter);
+        assert!((max_x - min_x - room).abs() < 0.01);
+
+        // Four holes in the plate at the standard spacing
+        assert_eq!(plate.bolt_holes().len(), 4);
+        assert_eq!(plate.bolt_holes()[1], Point::new(88.9, 60.325));
+    }
+}
@@ -1,574 +1,2623 @@
 use std::borrow::Cow;
-use std::collections::HashMap;
-use std::fs::{self, File};
-use std::io::{BufWriter, Write};
-use std::path::PathBuf;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::builder::TypedValueParser;
+use clap::{CommandFactory, FromArgMatches, Parser};
 use lyon_path::math::Point;
-use lyon_path::path::Builder as PathBuilder;
-use lyon_path::Path;
-use lyon_tessellation::geometry_builder::VertexBuffers;
-use lyon_tessellation::{BuffersBuilder, FillOptions, FillRule, FillTessellator, FillVertex};
-use stl_io::Triangle;
-use ttf_parser::{Face, GlyphId, OutlineBuilder};
-
-const EMBEDDED_FONT: &[u8] = include_bytes!("../assets/fonts/NotoSansJP-Regular.otf");
-const DEFAULT_TOLERANCE: f32 = 0.01;
-const DEFAULT_TOLERANCE_SIZE: f32 = 72.0;
-const MIN_TOLERANCE: f32 = 0.0005;
-const MAX_TOLERANCE: f32 = 0.2;
+use ttf_parser::{Face, Permissions};
+
+use wagyan::amf::write_amf_to_writer;
+use wagyan::cable_tag::{cable_tag_parts, CableTag};
+use wagyan::cake_topper::{cake_topper_parts, CakeTopper};
+use wagyan::cnc::{self, write_gcode_to_writer, write_heightmap_pgm_to_writer, Machine};
+use wagyan::coverage;
+use wagyan::doorsign::{doorsign_parts, DoorSign, Mount};
+use wagyan::dxf::{self, write_dxf_to_writer, DxfLayer};
+use wagyan::font::{Builtin, FontData, FontStyle, Variation};
+use wagyan::font_info;
+use wagyan::garden_marker::{garden_marker_parts, GardenMarker};
+use wagyan::gltf::{write_glb_to_writer, write_gltf_to_writer};
+use wagyan::glyph_cache::GlyphMeshCache;
+use wagyan::hershey::HersheyFont;
+use wagyan::i18n::{self, Lang, Message};
+use wagyan::indexed::{MeshPart, DEFAULT_CREASE_DEGREES};
+use wagyan::license_plate::{license_plate_parts, LicensePlate};
+use wagyan::measure;
+use wagyan::metadata::{self, GenerationMetadata};
+use wagyan::model_label::{model_label_parts, ModelLabel, RESIN_LAYER_HEIGHT};
+use wagyan::nameplate::{nameplate_parts, Nameplate, NameplateStyle};
+use wagyan::obj::{write_obj_to_writer, ObjOptions};
+use wagyan::output::{self, Compression, Format, OverwritePolicy, Units};
+use wagyan::placements;
+use wagyan::plaque::{plaque_parts, AwardPlaque};
+use wagyan::ply::write_ply_to_writer;
+use wagyan::preview;
+use wagyan::printer::{self, Printer};
+use wagyan::runs;
+use wagyan::slicer::{Slicer, SlicerProject};
+use wagyan::step::{self, write_step_to_writer};
+use wagyan::stl::{write_stl_ascii_to_writer, write_stl_binary_to_writer};
+use wagyan::svg::{self, write_svg_to_writer};
+use wagyan::template::{self, TemplateVars};
+use wagyan::threemf::write_3mf_to_writer;
+use wagyan::tui;
+use wagyan::{
+    dry_run, Align, Anchor, ArcSide, CancelToken, Color, Direction, HangingLoop, Layout,
+    LineHeight, MissingGlyph, Normalization, Options, Orientation, Part, Shaper, SizeTarget,
+    TextPath, TextTransform, Token, Triangle, Warp, WritingMode,
+};
 
 /// Simple CLI that extrudes text into an ASCII STL
 #[derive(Parser, Debug)]
-#[command(author, version, about)]
+#[command(author, version, about, after_help = wagyan::UNITS_NOTE)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
     /// Text to render
-    text: String,
-    /// Font file (.ttf/.otf). Falls back to embedded Noto Sans JP Regular
+    #[arg(required_unless_present_any = ["list_instances", "glyph_id", "codepoint", "runs"])]
+    text: Option<String>,
+    /// Draw these glyph ids of the font in one line instead of text, including
+    /// glyphs no character maps to; repeat or separate with commas
+    #[arg(long, value_name = "ID", value_delimiter = ',', conflicts_with_all = ["text", "codepoint"])]
+    glyph_id: Vec<u16>,
+    /// Draw these code points (U+3042, 0x3042 or 3042) instead of text, with
+    /// no escape or placeholder handling; repeat or separate with commas
+    #[arg(long, value_name = "U+XXXX", value_delimiter = ',', value_parser = codepoint, conflicts_with = "text")]
+    codepoint: Vec<char>,
+    /// Read {size=MM}, {depth=MM} and {font=FILE} tags in the text, closed
+    /// by {/size} and so on, to set parts of it in their own em size,
+    /// extrusion depth or font, and ^{..} and _{..} for superscripts and
+    /// subscripts; {{ and }} are literal braces
+    #[arg(long, conflicts_with_all = ["glyph_id", "codepoint", "stroke_font", "max_width"])]
+    markup: bool,
+    /// Set the text from a JSON list of runs such as {"text": "東京",
+    /// "font": "jp.otf", "size": 48, "depth": 4, "tracking": 0.1}, laid out
+    /// one after another (only "text" is required)
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["text", "glyph_id", "codepoint", "markup", "stroke_font", "max_width"])]
+    runs: Option<PathBuf>,
+    /// Print the named instances of the variable --font with their axis
+    /// values, then exit
+    #[arg(long)]
+    list_instances: bool,
+    #[command(flatten)]
+    font_args: FontArgs,
+    /// Font em size in mm (glyphs are smaller than this)
+    #[arg(long, visible_alias = "size-mm", default_value_t = 72.0)]
+    size: f32,
+    /// Scale so capital letters are exactly this tall in mm (overrides --size)
+    #[arg(long, value_name = "MM", value_parser = positive_f32, conflicts_with = "height_mm")]
+    cap_height_mm: Option<f32>,
+    /// Scale so the whole text, all lines included, is exactly this tall in mm (overrides --size)
+    #[arg(long, value_name = "MM", value_parser = positive_f32)]
+    height_mm: Option<f32>,
+    /// Extrusion depth in mm
+    #[arg(long, default_value_t = 10.0)]
+    depth: f32,
+    /// Back plate thickness (0 disables)
+    #[arg(long, default_value_t = 0.0)]
+    plate: f32,
+    /// Margin to expand the plate
+    #[arg(long, default_value_t = 2.0)]
+    plate_margin: f32,
+    /// Plane orientation (flat: XY floor, front: XZ facing viewer)
+    #[arg(long, value_enum, default_value_t = Orientation::Front)]
+    orient: Orientation,
+    /// Disable auto-centering to origin
+    #[arg(long)]
+    no_center: bool,
+    /// Point of the text placed on the origin: the middle of its bounding
+    /// box, the start of the first line on its baseline, the top-left
+    /// corner or the middle of the bottom edge
+    #[arg(long, value_enum, default_value_t = Anchor::Center, conflicts_with = "no_center")]
+    anchor: Anchor,
+    /// Bend the text around a circle of this radius in mm, each letter
+    /// turned to follow it, as on a coin or medal
+    #[arg(long, value_name = "MM", value_parser = positive_f32, conflicts_with = "stroke_font")]
+    arc_radius: Option<f32>,
+    /// Bend the text around a circle so the longest line spans this many
+    /// degrees (up to 360); with --arc-radius the letters spread to fill it
+    #[arg(long, value_name = "DEGREES", value_parser = arc_angle, conflicts_with = "stroke_font")]
+    arc_angle: Option<f32>,
+    /// Half of the circle the bent text reads along: top (clockwise, letters
+    /// standing outward) or bottom (letters pointing at the centre)
+    #[arg(long, value_enum, default_value_t = ArcSide::Top)]
+    arc_side: ArcSide,
+    /// Lay the text along the first path of this SVG file instead, each
+    /// letter turned to follow it, starting where the path starts
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["arc_radius", "arc_angle", "stroke_font"])]
+    path_file: Option<PathBuf>,
+    /// Bend the whole text into a shape after layout: arch, wave, flag or
+    /// perspective. Letters are merged, so they cannot be split into parts
+    #[arg(long, value_enum, default_value_t = Warp::None, conflicts_with_all = ["stroke_font", "emit_placements", "glyph_groups", "letter_colors", "letter_layers"])]
+    warp: Warp,
+    /// Strength of --warp as a fraction of the text height, from -1 to 1
+    /// (negative values bend the other way)
+    #[arg(long, value_name = "AMOUNT", default_value_t = 0.3, value_parser = warp_amount, allow_hyphen_values = true)]
+    warp_amount: f32,
+    /// Move, turn and resize every glyph by a small random amount for a
+    /// hand-made look: up to this many em, radians and parts of its size
+    /// (0.03 is subtle)
+    #[arg(long, value_name = "AMOUNT", default_value_t = 0.0, value_parser = jitter, conflicts_with = "stroke_font")]
+    jitter: f32,
+    /// Seed of --jitter; the same seed always gives the same wobble
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    seed: u64,
+    /// What to build around the text
+    #[arg(long, value_enum, default_value_t = Mode::Text)]
+    mode: Mode,
+    /// Hole diameter of the pendant loop in mm
+    #[arg(long, value_name = "MM", default_value_t = 3.0)]
+    loop_diameter: f32,
+    /// Ring width of the pendant loop in mm
+    #[arg(long, value_name = "MM", default_value_t = 1.6)]
+    loop_width: f32,
+    /// Do not bridge the gap between the pendant loop and the letters
+    #[arg(long)]
+    no_connector: bool,
+    /// Token disc diameter in mm
+    #[arg(long, value_name = "MM", default_value_t = 20.0)]
+    diameter: f32,
+    /// Token disc thickness in mm (total thickness with --engrave)
+    #[arg(long, value_name = "MM", default_value_t = 3.0)]
+    thickness: f32,
+    /// Cut the text --depth deep into the token instead of raising it
+    #[arg(long)]
+    engrave: bool,
+    /// Repeat the text on the back of the token, mirrored
+    #[arg(long)]
+    back: bool,
+    /// Single-stroke Hershey font (.jhf) used instead of --font: strokes are
+    /// widened to --stroke-width, and .dxf/.svg output engraves their centerlines
+    #[arg(long, value_name = "FILE", conflicts_with = "dry_run")]
+    stroke_font: Option<PathBuf>,
+    /// Tool (or nozzle) width the --stroke-font lines are drawn with, in mm
+    #[arg(long, value_name = "MM", default_value_t = 0.8, value_parser = positive_f32)]
+    stroke_width: f32,
+    /// V-carve the text with a bit of this included angle in degrees; --output
+    /// must end in .nc/.gcode (toolpaths) or .pgm (depth heightmap)
+    #[arg(long, value_name = "DEG", value_parser = vbit_angle, conflicts_with_all = ["dry_run", "stroke_font"])]
+    vcarve: Option<f32>,
+    /// Mill the text with an end mill of this radius in mm: successive inward
+    /// offsets of the outlines, optionally only the first PASSES of them, cut
+    /// --depth deep; --output must end in .dxf or .nc/.gcode
+    #[arg(long, value_name = "RADIUS[,PASSES]", conflicts_with_all = ["dry_run", "stroke_font", "vcarve"])]
+    toolpath_offset: Option<ToolpathOffset>,
+    /// Spacing of V-carve passes in mm, also the heightmap pixel size
+    #[arg(long, value_name = "MM", default_value_t = 0.1, value_parser = positive_f32)]
+    stepover: f32,
+    /// Lay out only and report bounding box, lines and triangle estimate; nothing is written
+    #[arg(long)]
+    dry_run: bool,
+    /// Print the tessellated text as block characters, COLUMNS wide; nothing is written
+    #[arg(long, value_name = "COLUMNS", num_args = 0..=1, default_missing_value = "80", conflicts_with = "dry_run")]
+    preview_term: Option<usize>,
+    #[command(flatten)]
+    out: OutputArgs,
+    /// Also write each glyph's character, id and position, with one shared
+    /// mesh per distinct glyph, to this JSON file for game engines
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["sequence", "dry_run", "preview_term", "stroke_font", "vcarve", "toolpath_offset"])]
+    emit_placements: Option<PathBuf>,
+    /// Value for {counter}. Default: first unused number when the output path uses it, else 1
+    #[arg(long)]
+    counter: Option<u64>,
+    /// Generate one model per number in START..END (inclusive), exposed as the "n" template variable
+    #[arg(long, value_name = "START..END")]
+    sequence: Option<SequenceRange>,
+    /// Language of messages and help (default: from LANG and the other locale variables)
+    #[arg(long, value_enum, global = true)]
+    lang: Option<Lang>,
+}
+
+/// Model built around the text
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    /// The lettering, on a back plate with --plate
+    Text,
+    /// A hanging loop above the text (or plate) for a jump ring
+    Pendant,
+    /// A disc with the text centered on its face (see --diameter, --thickness)
+    Token,
+}
+
+/// Font selection and glyph placement, shared by all commands
+#[derive(clap::Args, Debug)]
+#[command(group = clap::ArgGroup::new("named_font").args(["family", "google_font"]))]
+struct FontArgs {
+    /// Font file (.ttf/.otf/.woff/.woff2). Falls back to the --builtin font
     #[arg(short, long)]
     font: Option<PathBuf>,
+    /// Font built into wagyan to use without --font: sans (Noto Sans JP
+    /// Regular), serif (DejaVu Serif) or mono (DejaVu Sans Mono); serif and
+    /// mono have no Japanese
+    #[arg(long, value_enum, default_value_t = Builtin::Sans, conflicts_with_all = ["font", "family", "google_font"])]
+    builtin: Builtin,
     /// Face index for font collections (.ttc). 0-based.
     #[arg(long, default_value_t = 0)]
     face_index: u32,
-    /// Font size (px-ish units)
-    #[arg(long, default_value_t = 72.0)]
-    size: f32,
+    /// Face of a font collection (.ttc) by name, e.g. "Yu Gothic UI Semibold",
+    /// instead of --face-index; case and spaces do not matter
+    #[arg(long, value_name = "NAME", conflicts_with = "face_index")]
+    face_name: Option<String>,
+    /// Font file (.ttf/.otf) for characters --font lacks, such as Latin
+    /// letters or symbols next to Japanese text. Repeat to try several in order
+    #[arg(long, value_name = "FILE")]
+    fallback_font: Vec<PathBuf>,
+    /// Variable font axis setting such as wght=700; repeat it or separate
+    /// several with commas (wght=700,wdth=85)
+    #[arg(long, value_name = "AXIS=VALUE", value_delimiter = ',')]
+    variation: Vec<Variation>,
+    /// Named instance of a variable --font, e.g. "SemiBold" (see
+    /// --list-instances); --variation settings override its axes
+    #[arg(long, value_name = "NAME")]
+    instance: Option<String>,
+    /// Installed font family to use instead of a font file, e.g. "Noto Serif JP"
+    #[arg(long, conflicts_with_all = ["font", "face_index", "face_name"])]
+    family: Option<String>,
+    /// Google Fonts family to download (into a cache) and use instead of a
+    /// font file, e.g. "Zen Maru Gothic" (needs the `google-fonts` build feature)
+    #[arg(long, value_name = "FAMILY", conflicts_with_all = ["font", "face_index", "face_name", "family"])]
+    google_font: Option<String>,
+    /// Style of the --family or --google-font font; the closest installed
+    /// one is used
+    #[arg(
+        long = "style",
+        id = "font_style",
+        value_name = "STYLE",
+        value_enum,
+        default_value_t = FontStyle::Regular,
+        requires = "named_font"
+    )]
+    font_style: FontStyle,
+    /// Use a font whose embedding permissions (OS/2 fsType) are marked restricted,
+    /// having checked its license allows this use
+    #[arg(long)]
+    ack_license: bool,
     /// Tessellation tolerance (smaller = finer). Default scales with --size.
     #[arg(long)]
     tolerance: Option<f32>,
-    /// Extrusion depth (same units as layout)
-    #[arg(long, default_value_t = 10.0)]
-    depth: f32,
     /// Additional spacing between glyphs
     #[arg(long, default_value_t = 0.0)]
     spacing: f32,
+    /// Additional spacing between glyphs in em, e.g. 0.05em, so it keeps in
+    /// proportion with --size; negative values tighten
+    #[arg(long, value_name = "EM", default_value_t = 0.0, value_parser = em, allow_hyphen_values = true)]
+    tracking: f32,
+    /// Additional spacing after each space in em, e.g. 0.25em
+    #[arg(long, value_name = "EM", default_value_t = 0.0, value_parser = em, allow_hyphen_values = true)]
+    word_spacing: f32,
+    /// Synthetic bold: grow every outline outward by this many mm, e.g.
+    /// +0.5mm (negative values thin the lettering)
+    #[arg(long, value_name = "MM", default_value_t = 0.0, value_parser = mm, allow_hyphen_values = true)]
+    weight: f32,
+    /// Synthetic italic: lean every glyph right by this many degrees, e.g.
+    /// 12 (negative values lean left)
+    #[arg(long, value_name = "DEGREES", default_value_t = 0.0, value_parser = slant, allow_hyphen_values = true)]
+    slant: f32,
+    /// Advance every glyph by this many mm, centred in it, as on a
+    /// monospaced font (kerning is then skipped)
+    #[arg(long, value_name = "MM", value_parser = positive_f32)]
+    monospace: Option<f32>,
+    /// Distance between the tab stops a tab in the text advances to, in mm
+    /// [default: 4 em]
+    #[arg(long, value_name = "MM", value_parser = positive_f32)]
+    tab_width: Option<f32>,
     /// Apply kerning when available (disable with --no-kerning)
     #[arg(long, default_value_t = true, action = clap::ArgAction::SetTrue, conflicts_with = "no_kerning")]
     kerning: bool,
     /// Disable kerning adjustments
     #[arg(long = "no-kerning", action = clap::ArgAction::SetTrue, conflicts_with = "kerning")]
     no_kerning: bool,
-    /// Back plate thickness (0 disables)
-    #[arg(long, default_value_t = 0.0)]
-    plate: f32,
-    /// Margin to expand the plate
-    #[arg(long, default_value_t = 2.0)]
-    plate_margin: f32,
-    /// Plane orientation (flat: XY floor, front: XZ facing viewer)
-    #[arg(long, value_enum, default_value_t = Orientation::Front)]
-    orient: Orientation,
-    /// Keep literal "\\n" (do not convert to newline)
+    /// OpenType features to apply, comma-separated: liga for ligatures, smcp
+    /// for small caps, ss01..ss20 for stylistic sets, ...
+    #[arg(long, value_name = "TAGS", value_delimiter = ',', value_parser = feature_tag)]
+    features: Vec<String>,
+    /// Shaping engine: builtin, or harfbuzz for complex scripts and the
+    /// font's default features (needs the `shaping` build feature)
+    #[arg(long, value_enum, default_value_t = Shaper::Builtin)]
+    shaper: Shaper,
+    /// Base direction of each line: auto follows the first Hebrew, Arabic or
+    /// Latin letter; rtl keeps trailing punctuation on the left
+    #[arg(long, value_enum, default_value_t = Direction::Auto)]
+    direction: Direction,
+    /// vertical writes each line as a column, top to bottom and right to
+    /// left, for Japanese nameplates
+    #[arg(long, value_enum, default_value_t = WritingMode::Horizontal)]
+    writing_mode: WritingMode,
+    /// Where shorter lines sit next to the longest one (or within
+    /// --max-width): left, center, right (columns: top, middle or bottom) or
+    /// justify, which widens the gaps of every line but the last
+    #[arg(long, value_enum, default_value_t = Align::Left)]
+    align: Align,
+    /// Unicode normalization applied before layout: nfc composes decomposed
+    /// text (か + U+3099 becomes が), nfkc also turns full-width and
+    /// half-width forms into plain ones. Default none
+    #[arg(long, value_enum, default_value_t = Normalization::None)]
+    normalize: Normalization,
+    /// Change the case of the text before layout: upper, lower, title
+    /// (capitalized words) or smallcaps, from the font's smcp feature or
+    /// else scaled-down capitals
+    #[arg(long, value_enum, default_value_t = TextTransform::None)]
+    text_transform: TextTransform,
+    /// Wrap lines longer than this many mm at spaces, hyphens and between
+    /// Japanese characters, following kinsoku rules (、。 may hang past it);
+    /// --align places lines within it and justify stretches them to it
+    #[arg(long, value_name = "MM", value_parser = positive_f32)]
+    max_width: Option<f32>,
+    /// Let --max-width wrapping break between any two characters kinsoku
+    /// allows, so long words and unspaced text fill every line
+    #[arg(long, requires = "max_width")]
+    break_anywhere: bool,
+    /// Distance between baselines: a multiple of the font's line height
+    /// (0.9, 120%) or a length (14mm); for vertical text, between columns
+    #[arg(long, value_name = "FACTOR|MM", default_value_t = LineHeight::Factor(1.0))]
+    line_height: LineHeight,
+    /// What characters the fonts lack turn into: skip, notdef (the font's
+    /// .notdef glyph), box (a tofu box) or a single replacement character
+    #[arg(long, value_name = "CHAR|notdef|box|skip", default_value_t = MissingGlyph::Skip)]
+    missing_glyph: MissingGlyph,
+    /// Fail with an error listing the characters the fonts lack, instead of
+    /// skipping them with a warning
+    #[arg(long)]
+    strict: bool,
+    /// Keep literal "\\n" and "\\t" (do not convert to newline and tab)
     #[arg(long)]
     no_escape: bool,
-    /// Disable auto-centering to origin
+}
+
+/// Output file, format and print settings, shared by all commands
+#[derive(clap::Args, Debug)]
+struct OutputArgs {
+    /// Output file (stdout by default). A .glb extension writes binary glTF (.gltf as JSON), .obj a
+    /// Wavefront OBJ with normals, .ply a PLY with faces colored by type, .3mf a 3MF
+    /// with plate and text as separate objects, .amf an AMF with a material per color,
+    /// .dxf 2D outlines for a laser cutter, .svg the same with the glyph curves kept.
+    /// Supports {date}, {counter[:0N]} and {text}. Repeat to write the same
+    /// model in several formats
+    #[arg(short, long)]
+    output: Vec<PathBuf>,
+    /// Overwrite an existing output file
+    #[arg(long, conflicts_with = "backup")]
+    force: bool,
+    /// Once the new output is written, rename the existing file to
+    /// <name>.bak (or <name>.1.bak, ... when that is taken)
     #[arg(long)]
-    no_center: bool,
-    /// Output file (stdout by default)
+    backup: bool,
+    /// Suppress status messages (stdout only ever carries the STL data)
     #[arg(short, long)]
-    output: Option<PathBuf>,
+    quiet: bool,
+    /// Abort when generation takes longer than this many seconds
+    #[arg(long, value_name = "SECONDS")]
+    timeout: Option<f64>,
+    /// Printer preset: sets the default --layer-height and warns when the model
+    /// exceeds the build volume or has strokes too thin to print
+    #[arg(long, value_enum)]
+    printer: Option<Printer>,
+    /// Round heights to whole multiples of this print layer height (mm)
+    #[arg(long, value_name = "MM", value_parser = positive_f32)]
+    layer_height: Option<f32>,
+    /// Material base color for GLB/3MF/AMF output (#rrggbb or #rrggbbaa)
+    #[arg(long, value_name = "COLOR", default_value_t = Color::LIGHT_GRAY)]
+    base_color: Color,
+    /// Color of the lettering for GLB/3MF/AMF output (default: --base-color)
+    #[arg(long, value_name = "COLOR")]
+    text_color: Option<Color>,
+    /// Color of the plate and everything else that is not lettering, for
+    /// GLB/3MF/AMF output (default: --base-color)
+    #[arg(long, value_name = "COLOR")]
+    plate_color: Option<Color>,
+    /// Comma-separated colors assigned to the letters in order (whitespace is
+    /// skipped, the list repeats when shorter), for GLB/3MF/AMF output
+    #[arg(long, value_name = "COLORS", value_delimiter = ',')]
+    letter_colors: Vec<Color>,
+    /// Export every glyph as its own named object (A_0, B_1, ...) next to the
+    /// plate in OBJ/GLB/3MF/AMF output, to select or recolor letters later
+    #[arg(long)]
+    glyph_groups: bool,
+    /// Smooth-shade curved side walls in GLB/OBJ output (corners sharper than 40° stay faceted)
+    #[arg(long)]
+    smooth_normals: bool,
+    /// Smooth-shade side walls like --smooth-normals, keeping corners sharper
+    /// than this many degrees faceted
+    #[arg(long, value_name = "DEGREES", value_parser = crease_angle)]
+    crease_angle: Option<f32>,
+    /// Leave the normals out of OBJ output and let the importer shade the
+    /// welded mesh
+    #[arg(long, conflicts_with_all = ["smooth_normals", "crease_angle"])]
+    no_normals: bool,
+    /// Also write the generation metadata (font, version, reproducibility hash)
+    /// to <output>.json
+    #[arg(long)]
+    sidecar: bool,
+    /// Output format, for files and stdout alike (default: from the --output
+    /// extension, else ASCII STL)
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+    /// Unit of the written coordinates. 3MF, AMF, DXF and SVG declare it and
+    /// OBJ notes it in a comment; STL, PLY and glTF are only scaled. AMF has
+    /// no centimeters
+    #[arg(long, value_enum, default_value_t)]
+    units: Units,
+    /// Model name: the ASCII STL solid name (default: the output file name),
+    /// the 3MF/AMF title and the OBJ object names
+    #[arg(long)]
+    name: Option<String>,
+    /// Compress the output stream (default: gzip for a .gz --output, zstd
+    /// for a .zst one)
+    #[arg(long, value_enum, value_name = "METHOD")]
+    compress: Option<Compression>,
+    /// Add PrusaSlicer or Bambu Studio project data to 3MF output: the model
+    /// centered on the --printer bed (or a typical one), its parts named and on
+    /// the filament of their color, and a filament change at the first layer
+    /// of lettering raised from a plate
+    #[arg(long, value_enum)]
+    slicer: Option<Slicer>,
+    /// Laser kerf width in mm; the DXF CUT layer grows by half of it
+    #[arg(long, value_name = "MM", default_value_t = 0.0, value_parser = non_negative_f32)]
+    kerf: f32,
+    /// Put every letter on its own DXF layer (LETTER-1, LETTER-2, ...)
+    #[arg(long)]
+    letter_layers: bool,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Name plate with sensible defaults: rounded plate, rim, mounting holes
+    Nameplate(NameplateArgs),
+    /// Cake topper: lettering joined by a backing outline, with sticks to push into the cake
+    CakeTopper(CakeTopperArgs),
+    /// License-plate style sign: full-size plate, raised frame, bolt holes, centered text
+    LicensePlate(LicensePlateArgs),
+    /// Door sign: rounded plate with a chamfered edge, centered text, screw holes or a flat back
+    #[command(name = "doorsign")]
+    DoorSign(DoorSignArgs),
+    /// Cable label: small tag with raised text and zip-tie slots at the ends
+    CableTag(CableTagArgs),
+    /// Award plaque: beveled plate, large title, smaller body lines, corner screw holes
+    Plaque(PlaqueArgs),
+    /// Plant label: tag with raised text on a pointed stake, one or a whole --batch list
+    GardenMarker(GardenMarkerArgs),
+    /// Scale-model labels: tiny text thickened for resin printing, on a thin backing strip
+    ModelLabel(ModelLabelArgs),
+    /// Tweak size, depth and plate interactively with a live preview, then print the command
+    Tui(TuiArgs),
+    /// Print line widths, bounding box, ascent/descent and glyph advances; no geometry is built
+    Measure(MeasureArgs),
+    /// List the characters of the text that the font and fallback fonts lack
+    Coverage(CoverageArgs),
+    /// Print font names, metrics, tables, OpenType features and variation axes
+    FontInfo(FontInfoArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct NameplateArgs {
+    /// Name to put on the plate
+    text: String,
+    /// Plate outline
+    #[arg(long, value_enum, default_value_t = NameplateStyle::Rounded)]
+    style: NameplateStyle,
+    /// Height of the lettering in mm, all lines included
+    #[arg(long, value_name = "MM", value_parser = positive_f32, default_value_t = 15.0)]
+    height_mm: f32,
+    /// Height of capital letters in mm (overrides --height-mm)
+    #[arg(long, value_name = "MM", value_parser = positive_f32)]
+    cap_height_mm: Option<f32>,
+    /// How far the lettering is raised (or engraved) in mm
+    #[arg(long, default_value_t = 1.5)]
+    depth: f32,
+    /// Plate thickness in mm
+    #[arg(long, default_value_t = 3.0)]
+    thickness: f32,
+    /// Space between the text and the plate edge in mm
+    #[arg(long, default_value_t = 6.0)]
+    margin: f32,
+    /// Corner radius for --style rounded in mm
+    #[arg(long, default_value_t = 4.0)]
+    corner_radius: f32,
+    /// Width of the raised border in mm (0 disables)
+    #[arg(long, default_value_t = 1.6)]
+    rim: f32,
+    /// Cut the lettering into the plate instead of raising it
+    #[arg(long)]
+    engrave: bool,
+    /// Leave out the two mounting holes
+    #[arg(long)]
+    no_holes: bool,
+    /// Mounting hole diameter in mm
+    #[arg(long, default_value_t = 4.0)]
+    hole_diameter: f32,
+    /// Plane orientation (flat: lying on the bed, ready to print)
+    #[arg(long, value_enum, default_value_t = Orientation::Flat)]
+    orient: Orientation,
+    #[command(flatten)]
+    font_args: FontArgs,
+    #[command(flatten)]
+    out: OutputArgs,
 }
 
-#[derive(clap::ValueEnum, Clone, Debug)]
-enum Orientation {
-    Flat,
-    Front,
+#[derive(clap::Args, Debug)]
+struct CakeTopperArgs {
+    /// Text for the topper (a connected script font looks best)
+    text: String,
+    /// Height of the lettering in mm, all lines included
+    #[arg(long, value_name = "MM", value_parser = positive_f32, default_value_t = 30.0)]
+    height_mm: f32,
+    /// Height of capital letters in mm (overrides --height-mm)
+    #[arg(long, value_name = "MM", value_parser = positive_f32)]
+    cap_height_mm: Option<f32>,
+    /// How far the lettering stands out from the backing in mm
+    #[arg(long, default_value_t = 1.5)]
+    depth: f32,
+    /// Backing thickness in mm
+    #[arg(long, value_parser = positive_f32, default_value_t = 3.0)]
+    thickness: f32,
+    /// How far the backing reaches past the letters in mm
+    #[arg(long, value_name = "MM", value_parser = positive_f32, default_value_t = 2.0)]
+    outline: f32,
+    /// Number of sticks
+    #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u8).range(1..=2))]
+    sticks: u8,
+    /// Stick length below the backing in mm, point included
+    #[arg(long, value_name = "MM", value_parser = positive_f32, default_value_t = 60.0)]
+    stick_length: f32,
+    /// Stick width in mm
+    #[arg(long, value_name = "MM", value_parser = positive_f32, default_value_t = 5.0)]
+    stick_width: f32,
+    /// Plane orientation (flat: lying on the bed, ready to print)
+    #[arg(long, value_enum, default_value_t = Orientation::Flat)]
+    orient: Orientation,
+    #[command(flatten)]
+    font_args: FontArgs,
+    #[command(flatten)]
+    out: OutputArgs,
 }
 
-fn resolve_tolerance(size: f32, cli_value: Option<f32>) -> f32 {
-    let scaled = DEFAULT_TOLERANCE * (size / DEFAULT_TOLERANCE_SIZE);
-    let value = cli_value.unwrap_or(scaled);
-    value.clamp(MIN_TOLERANCE, MAX_TOLERANCE)
+#[derive(clap::Args, Debug)]
+struct LicensePlateArgs {
+    /// Plate text
+    text: String,
+    /// Height of the lettering in mm, all lines included (shrunk when it does not fit)
+    #[arg(long, value_name = "MM", value_parser = positive_f32, default_value_t = 70.0)]
+    height_mm: f32,
+    /// Height of capital letters in mm (overrides --height-mm)
+    #[arg(long, value_name = "MM", value_parser = positive_f32)]
+    cap_height_mm: Option<f32>,
+    /// How far the lettering and frame are raised in mm
+    #[arg(long, default_value_t = 2.0)]
+    depth: f32,
+    /// Plate thickness in mm
+    #[arg(long, default_value_t = 2.0)]
+    thickness: f32,
+    /// Plate width in mm (default: 12 in)
+    #[arg(long, value_name = "MM", default_value_t = 304.8)]
+    width: f32,
+    /// Plate height in mm (default: 6 in)
+    #[arg(long, value_name = "MM", default_value_t = 152.4)]
+    height: f32,
+    /// Corner radius in mm
+    #[arg(long, default_value_t = 6.0)]
+    corner_radius: f32,
+    /// Width of the raised frame in mm (0 disables)
+    #[arg(long, value_name = "MM", default_value_t = 4.0)]
+    frame: f32,
+    /// Bolt holes: none, the top row, or all four
+    #[arg(
+        long,
+        default_value_t = 4,
+        value_parser = clap::builder::PossibleValuesParser::new(["0", "2", "4"])
+            .map(|s| s.parse::<u8>().unwrap_or_default())
+    )]
+    bolts: u8,
+    /// Horizontal distance between bolt hole centers in mm (default: 7 in)
+    #[arg(long, value_name = "MM", default_value_t = 177.8)]
+    bolt_spacing_x: f32,
+    /// Vertical distance between bolt hole centers in mm (default: 4.75 in)
+    #[arg(long, value_name = "MM", default_value_t = 120.65)]
+    bolt_spacing_y: f32,
+    /// Bolt hole diameter in mm
+    #[arg(long, value_name = "MM", default_value_t = 7.0)]
+    bolt_diameter: f32,
+    /// Plane orientation (flat: lying on the bed, ready to print)
+    #[arg(long, value_enum, default_value_t = Orientation::Flat)]
+    orient: Orientation,
+    #[command(flatten)]
+    font_args: FontArgs,
+    #[command(flatten)]
+    out: OutputArgs,
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
-    run(args).context("conversion failed")
+#[derive(clap::Args, Debug)]
+struct DoorSignArgs {
+    /// Room name or other sign text
+    text: String,
+    /// Height of the lettering in mm, all lines included
+    #[arg(long, value_name = "MM", value_parser = positive_f32, default_value_t = 20.0)]
+    height_mm: f32,
+    /// Height of capital letters in mm (overrides --height-mm)
+    #[arg(long, value_name = "MM", value_parser = positive_f32)]
+    cap_height_mm: Option<f32>,
+    /// How far the lettering is raised in mm
+    #[arg(long, default_value_t = 1.5)]
+    depth: f32,
+    /// Plate thickness in mm
+    #[arg(long, default_value_t = 4.0)]
+    thickness: f32,
+    /// Space between the text and the plate edge in mm
+    #[arg(long, default_value_t = 10.0)]
+    margin: f32,
+    /// Corner radius in mm
+    #[arg(long, default_value_t = 5.0)]
+    corner_radius: f32,
+    /// Size of the 45° chamfer along the top edge in mm (0 disables)
+    #[arg(long, value_name = "MM", default_value_t = 1.0)]
+    chamfer: f32,
+    /// Screw holes in the top corners, or a flat back for adhesive pads
+    #[arg(long, value_enum, default_value_t = Mount::Holes)]
+    mount: Mount,
+    /// Screw hole diameter in mm
+    #[arg(long, default_value_t = 4.0)]
+    hole_diameter: f32,
+    /// Plane orientation (flat: lying on the bed, ready to print)
+    #[arg(long, value_enum, default_value_t = Orientation::Flat)]
+    orient: Orientation,
+    #[command(flatten)]
+    font_args: FontArgs,
+    #[command(flatten)]
+    out: OutputArgs,
 }
 
-fn run(args: Args) -> Result<()> {
-    // Load font (fallback to embedded Noto Sans JP Regular)
-    let font_bytes: Cow<[u8]> = if let Some(path) = args.font.as_ref() {
-        Cow::Owned(
-            fs::read(path)
-                .with_context(|| format!("failed to read font file: {}", path.display()))?,
-        )
-    } else {
-        Cow::Borrowed(EMBEDDED_FONT)
-    };
+#[derive(clap::Args, Debug)]
+struct CableTagArgs {
+    /// Label text
+    #[arg(required_unless_present = "batch")]
+    text: Option<String>,
+    /// Make one tag per line of this file (first column of a CSV); --output
+    /// then needs {n} or {text}
+    #[arg(long, value_name = "FILE", conflicts_with = "text")]
+    batch: Option<PathBuf>,
+    /// Height of the lettering in mm, all lines included
+    #[arg(long, value_name = "MM", value_parser = positive_f32, default_value_t = 6.0)]
+    height_mm: f32,
+    /// Height of capital letters in mm (overrides --height-mm)
+    #[arg(long, value_name = "MM", value_parser = positive_f32)]
+    cap_height_mm: Option<f32>,
+    /// How far the lettering is raised in mm
+    #[arg(long, default_value_t = 0.6)]
+    depth: f32,
+    /// Tag thickness in mm
+    #[arg(long, default_value_t = 1.6)]
+    thickness: f32,
+    /// Space around the text and slots in mm
+    #[arg(long, default_value_t = 2.0)]
+    margin: f32,
+    /// Corner radius in mm
+    #[arg(long, default_value_t = 2.0)]
+    corner_radius: f32,
+    /// Slot length in mm: the zip tie width plus clearance
+    #[arg(long, value_name = "MM", default_value_t = 5.0)]
+    slot_width: f32,
+    /// Slot height in mm: the zip tie thickness plus clearance
+    #[arg(long, value_name = "MM", default_value_t = 2.0)]
+    slot_height: f32,
+    /// Number of slots (2: one at each end)
+    #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u8).range(1..=2))]
+    slots: u8,
+    /// Plane orientation (flat: lying on the bed, ready to print)
+    #[arg(long, value_enum, default_value_t = Orientation::Flat)]
+    orient: Orientation,
+    #[command(flatten)]
+    font_args: FontArgs,
+    #[command(flatten)]
+    out: OutputArgs,
+}
 
-    let face_count = ttf_parser::fonts_in_collection(&font_bytes).unwrap_or(1);
-    anyhow::ensure!(face_count > 0, "font file appears to have no faces");
-    anyhow::ensure!(
-        args.face_index < face_count,
-        "face index {} is out of range (available 0..={}; font has {} face{})",
-        args.face_index,
-        face_count - 1,
-        face_count,
-        if face_count == 1 { "" } else { "s" },
-    );
+#[derive(clap::Args, Debug)]
+struct PlaqueArgs {
+    /// Title, then one argument per body line
+    #[arg(required = true)]
+    lines: Vec<String>,
+    /// Cap height of the title in mm
+    #[arg(long, value_name = "MM", default_value_t = 10.0)]
+    title_height: f32,
+    /// Cap height of the body lines in mm
+    #[arg(long, value_name = "MM", default_value_t = 5.0)]
+    body_height: f32,
+    /// Cap heights of the first body lines in mm, comma separated (the rest use --body-height)
+    #[arg(long, value_name = "MM,...", value_delimiter = ',', value_parser = positive_f32)]
+    line_heights: Vec<f32>,
+    /// Space below each line, as a fraction of its cap height
+    #[arg(long, value_name = "FACTOR", default_value_t = 0.8)]
+    line_gap: f32,
+    /// How far the lettering is raised in mm
+    #[arg(long, default_value_t = 1.0)]
+    depth: f32,
+    /// Plate thickness in mm, bevel included
+    #[arg(long, default_value_t = 5.0)]
+    thickness: f32,
+    /// Space between the text and the plate edge in mm
+    #[arg(long, default_value_t = 12.0)]
+    margin: f32,
+    /// Corner radius in mm
+    #[arg(long, default_value_t = 3.0)]
+    corner_radius: f32,
+    /// Size of the 45° bevel along the top edge in mm (0 disables)
+    #[arg(long, value_name = "MM", default_value_t = 1.5)]
+    bevel: f32,
+    /// Leave out the corner screw holes
+    #[arg(long)]
+    no_holes: bool,
+    /// Screw hole diameter in mm
+    #[arg(long, default_value_t = 4.0)]
+    hole_diameter: f32,
+    /// Plane orientation (flat: lying on the bed, ready to print)
+    #[arg(long, value_enum, default_value_t = Orientation::Flat)]
+    orient: Orientation,
+    #[command(flatten)]
+    font_args: FontArgs,
+    #[command(flatten)]
+    out: OutputArgs,
+}
 
-    let face = Face::parse(&font_bytes, args.face_index)
-        .with_context(|| format!("failed to parse font (face index {})", args.face_index))?;
+#[derive(clap::Args, Debug)]
+struct GardenMarkerArgs {
+    /// Plant name
+    #[arg(required_unless_present = "batch")]
+    text: Option<String>,
+    /// Make one marker per line of this file (first column of a CSV); --output
+    /// then needs {n} or {text}
+    #[arg(long, value_name = "FILE", conflicts_with = "text")]
+    batch: Option<PathBuf>,
+    /// Height of the lettering in mm, all lines included
+    #[arg(long, value_name = "MM", value_parser = positive_f32, default_value_t = 12.0)]
+    height_mm: f32,
+    /// Height of capital letters in mm (overrides --height-mm)
+    #[arg(long, value_name = "MM", value_parser = positive_f32)]
+    cap_height_mm: Option<f32>,
+    /// How far the lettering is raised in mm
+    #[arg(long, default_value_t = 0.8)]
+    depth: f32,
+    /// Marker thickness in mm
+    #[arg(long, default_value_t = 2.5)]
+    thickness: f32,
+    /// Space around the text in mm
+    #[arg(long, default_value_t = 3.0)]
+    margin: f32,
+    /// Corner radius of the tag in mm
+    #[arg(long, default_value_t = 3.0)]
+    corner_radius: f32,
+    /// Stake length below the tag in mm, point included
+    #[arg(long, value_name = "MM", default_value_t = 100.0)]
+    stake_length: f32,
+    /// Stake width in mm
+    #[arg(long, value_name = "MM", default_value_t = 10.0)]
+    stake_width: f32,
+    /// Plane orientation (flat: lying on the bed, ready to print)
+    #[arg(long, value_enum, default_value_t = Orientation::Flat)]
+    orient: Orientation,
+    #[command(flatten)]
+    font_args: FontArgs,
+    #[command(flatten)]
+    out: OutputArgs,
+}
 
-    // Unit conversion
-    let units_per_em = face.units_per_em() as f32;
-    let scale = args.size / units_per_em;
-    let baseline_y = face.ascender() as f32 * scale;
-    let tolerance = resolve_tolerance(args.size, args.tolerance);
+#[derive(clap::Args, Debug)]
+struct ModelLabelArgs {
+    /// Room or building name
+    #[arg(required_unless_present = "batch")]
+    text: Option<String>,
+    /// Make one label per line of this file (first column of a CSV); --output
+    /// then needs {n} or {text}
+    #[arg(long, value_name = "FILE", conflicts_with = "text")]
+    batch: Option<PathBuf>,
+    /// Height of capital letters in mm
+    #[arg(long, value_name = "MM", value_parser = positive_f32, default_value_t = 3.0)]
+    cap_height_mm: f32,
+    /// How far the lettering is raised in mm
+    #[arg(long, default_value_t = 0.3)]
+    depth: f32,
+    /// Backing strip thickness in mm
+    #[arg(long, default_value_t = 0.5)]
+    thickness: f32,
+    /// Strip width around the text in mm
+    #[arg(long, default_value_t = 0.5)]
+    margin: f32,
+    /// Thinnest letter stroke in mm; thinner strokes are grown to this
+    #[arg(long, value_name = "MM", default_value_t = 0.3)]
+    min_stroke: f32,
+    /// Plane orientation (flat: lying on the bed, ready to print)
+    #[arg(long, value_enum, default_value_t = Orientation::Flat)]
+    orient: Orientation,
+    #[command(flatten)]
+    font_args: FontArgs,
+    #[command(flatten)]
+    out: OutputArgs,
+}
 
-    // Convert literal "\\n" to newline unless disabled
-    let text = if args.no_escape {
-        args.text.clone()
-    } else {
-        args.text.replace("\\n", "\n")
-    };
+#[derive(clap::Args, Debug)]
+struct TuiArgs {
+    /// Text to start with
+    #[arg(default_value = "wagyan")]
+    text: String,
+    /// Preview width in characters
+    #[arg(long, default_value_t = 80)]
+    columns: usize,
+    #[command(flatten)]
+    font_args: FontArgs,
+}
 
-    let kerning = if args.no_kerning { false } else { args.kerning };
-
-    // Build a single path from all glyph outlines
-    let mut path_builder = Path::builder();
-    layout_text_to_path(
-        &face,
-        &mut path_builder,
-        &text,
-        scale,
-        baseline_y,
-        args.spacing,
-        kerning,
-    )?;
-    let path = path_builder.build();
+#[derive(clap::Args, Debug)]
+struct MeasureArgs {
+    /// Text to measure
+    text: String,
+    /// Font em size in mm
+    #[arg(long, visible_alias = "size-mm", default_value_t = 72.0)]
+    size: f32,
+    /// Scale so capital letters are exactly this tall in mm (overrides --size)
+    #[arg(long, value_name = "MM", value_parser = positive_f32, conflicts_with = "height_mm")]
+    cap_height_mm: Option<f32>,
+    /// Scale so the whole text is exactly this tall in mm (overrides --size)
+    #[arg(long, value_name = "MM", value_parser = positive_f32)]
+    height_mm: Option<f32>,
+    #[command(flatten)]
+    font_args: FontArgs,
+    /// Print JSON instead of the plain report
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct CoverageArgs {
+    /// Text to check
+    text: String,
+    #[command(flatten)]
+    font_args: FontArgs,
+    /// Print JSON instead of the plain report
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct FontInfoArgs {
+    #[command(flatten)]
+    font_args: FontArgs,
+    /// Print JSON instead of the plain report
+    #[arg(long)]
+    json: bool,
+}
+
+fn feature_tag(s: &str) -> Result<String, String> {
+    wagyan::gsub::parse_tag(s).map(|_| s.to_string())
+}
 
-    // Tessellate and extrude
-    let mut mesh = tessellate_path(&path, tolerance)?;
-    if !args.no_center {
-        center_mesh_xy(&mut mesh);
+fn positive_f32(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(v) if v > 0.0 && v.is_finite() => Ok(v),
+        _ => Err(format!("expected a positive number, got '{}'", s)),
     }
+}
 
-    let mut triangles = Vec::new();
+/// A length in em: 0.05em, or a bare 0.05
+fn em(s: &str) -> Result<f32, String> {
+    match s.strip_suffix("em").unwrap_or(s).parse::<f32>() {
+        Ok(v) if v.is_finite() => Ok(v),
+        _ => Err(format!(
+            "expected a length in em such as 0.05em, got '{}'",
+            s
+        )),
+    }
+}
 
-    if args.plate > 0.0 {
-        if let Some((min_x, max_x, min_y, max_y)) = mesh_bounds(&mesh) {
-            let plate_mesh = rectangle_mesh(
-                min_x - args.plate_margin,
-                max_x + args.plate_margin,
-                min_y - args.plate_margin,
-                max_y + args.plate_margin,
-            );
-            let plate_offset = -(args.depth * 0.5 + args.plate * 0.5);
-            triangles.extend(extrude_mesh_with_offset(
-                &plate_mesh,
-                args.plate,
-                args.orient.clone(),
-                plate_offset,
-            ));
-        }
+/// A signed length in mm such as +0.5mm; the unit is optional
+fn mm(s: &str) -> Result<f32, String> {
+    match s.strip_suffix("mm").unwrap_or(s).parse::<f32>() {
+        Ok(v) if v.is_finite() => Ok(v),
+        _ => Err(format!(
+            "expected a length in mm such as +0.5mm, got '{}'",
+            s
+        )),
     }
+}
 
-    triangles.extend(extrude_mesh(&mesh, args.depth, args.orient.clone()));
+/// A slant angle in degrees, steep enough for any italic but short of
+/// flattening the glyphs
+fn slant(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(v) if v.abs() <= 45.0 => Ok(v),
+        _ => Err(format!(
+            "expected an angle from -45 to 45 degrees, got '{}'",
+            s
+        )),
+    }
+}
 
-    // Write STL: default to stdout, file when --output is set
-    if let Some(path) = args.output.as_ref() {
-        write_stl_ascii(path, &triangles)
-            .with_context(|| format!("failed to write ASCII STL: {}", path.display()))?;
-        println!("✅ wrote: {}", path.display());
-    } else {
-        let mut out = BufWriter::new(std::io::stdout().lock());
-        write_stl_ascii_to_writer(&mut out, "mesh", &triangles)
-            .context("failed to write ASCII STL to stdout")?;
+/// An angle of arc in degrees, at most the whole circle
+fn arc_angle(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(v) if v > 0.0 && v <= 360.0 => Ok(v),
+        _ => Err(format!(
+            "expected an angle above 0 and up to 360 degrees, got '{}'",
+            s
+        )),
     }
-    Ok(())
 }
 
-fn kerning_value(face: &Face<'_>, left: GlyphId, right: GlyphId) -> Option<i16> {
-    let kern = face.tables().kern.as_ref()?;
-    for subtable in kern.subtables.into_iter() {
-        if !subtable.horizontal || subtable.has_cross_stream || subtable.has_state_machine {
-            continue;
+/// A --warp strength, at most the text's height either way
+fn warp_amount(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(v) if v.abs() <= 1.0 => Ok(v),
+        _ => Err(format!("expected a number from -1 to 1, got '{}'", s)),
+    }
+}
+
+fn jitter(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(v) if (0.0..=1.0).contains(&v) => Ok(v),
+        _ => Err(format!("expected a number from 0 to 1, got '{}'", s)),
+    }
+}
+
+/// A Unicode scalar value in hex: U+3042, 0x3042 or 3042
+fn codepoint(s: &str) -> Result<char, String> {
+    let hex = s
+        .strip_prefix("U+")
+        .or_else(|| s.strip_prefix("u+"))
+        .or_else(|| s.strip_prefix("0x"))
+        .unwrap_or(s);
+    u32::from_str_radix(hex, 16)
+        .ok()
+        .and_then(char::from_u32)
+        .ok_or_else(|| format!("expected a code point such as U+3042, got '{}'", s))
+}
+
+fn non_negative_f32(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(v) if v >= 0.0 && v.is_finite() => Ok(v),
+        _ => Err(format!("expected a number >= 0, got '{}'", s)),
+    }
+}
+
+fn crease_angle(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(v) if (0.0..=180.0).contains(&v) => Ok(v),
+        _ => Err(format!(
+            "expected a crease angle from 0 to 180 degrees, got '{}'",
+            s
+        )),
+    }
+}
+
+fn vbit_angle(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(v) if v > 0.0 && v < 180.0 => Ok(v),
+        _ => Err(format!(
+            "expected a V-bit angle between 0 and 180 degrees, got '{}'",
+            s
+        )),
+    }
+}
+
+/// Tool radius and optional pass count for --toolpath-offset
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ToolpathOffset {
+    radius: f32,
+    passes: Option<usize>,
+}
+
+impl std::str::FromStr for ToolpathOffset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (radius, passes) = match s.split_once(',') {
+            Some((radius, passes)) => (radius, Some(passes)),
+            None => (s, None),
+        };
+        let radius = positive_f32(radius.trim())?;
+        let passes = passes
+            .map(|passes| match passes.trim().parse::<usize>() {
+                Ok(n) if n > 0 => Ok(n),
+                _ => Err(format!("invalid pass count: '{}'", passes)),
+            })
+            .transpose()?;
+        Ok(Self { radius, passes })
+    }
+}
+
+/// Inclusive number range for --sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SequenceRange {
+    start: u64,
+    end: u64,
+}
+
+impl std::str::FromStr for SequenceRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s
+            .split_once("..")
+            .ok_or_else(|| format!("expected START..END, got '{}'", s))?;
+        let start: u64 = start
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid sequence start: '{}'", start))?;
+        let end: u64 = end
+            .trim_start_matches('=')
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid sequence end: '{}'", end))?;
+        if start > end {
+            return Err(format!("sequence start {} is after end {}", start, end));
         }
-        if let Some(value) = subtable.glyphs_kerning(left, right) {
-            return Some(value);
+        Ok(Self { start, end })
+    }
+}
+
+impl Args {
+    /// Font arguments of the command being run; the TUI picks its own
+    fn font_args_mut(&mut self) -> Option<&mut FontArgs> {
+        match &mut self.command {
+            Some(Command::Nameplate(args)) => Some(&mut args.font_args),
+            Some(Command::CakeTopper(args)) => Some(&mut args.font_args),
+            Some(Command::LicensePlate(args)) => Some(&mut args.font_args),
+            Some(Command::DoorSign(args)) => Some(&mut args.font_args),
+            Some(Command::CableTag(args)) => Some(&mut args.font_args),
+            Some(Command::Plaque(args)) => Some(&mut args.font_args),
+            Some(Command::GardenMarker(args)) => Some(&mut args.font_args),
+            Some(Command::ModelLabel(args)) => Some(&mut args.font_args),
+            Some(Command::Measure(args)) => Some(&mut args.font_args),
+            Some(Command::Coverage(args)) => Some(&mut args.font_args),
+            Some(Command::FontInfo(args)) => Some(&mut args.font_args),
+            Some(Command::Tui(args)) => Some(&mut args.font_args),
+            None => Some(&mut self.font_args),
+        }
+    }
+
+    fn options(&self, text: String) -> Result<Options> {
+        let runs = match (&self.runs, self.markup) {
+            (Some(path), _) => runs::load_runs(path)?,
+            (None, true) => runs::parse_markup(&text)?,
+            (None, false) => Vec::new(),
+        };
+        Ok(Options {
+            text: match runs.is_empty() {
+                true => text,
+                false => runs::plain_text(&runs),
+            },
+            normalize: self.font_args.normalize,
+            text_transform: self.font_args.text_transform,
+            glyph_ids: self.glyph_id.clone(),
+            font: self.font_args.font.clone(),
+            builtin: self.font_args.builtin,
+            face_index: self.font_args.face_index,
+            fallback_fonts: self.font_args.fallback_font.clone(),
+            variations: self.font_args.variation.clone(),
+            size: self.size,
+            size_target: self.size_target(),
+            tolerance: self.font_args.tolerance,
+            depth: self.depth,
+            spacing: self.font_args.spacing,
+            tracking: self.font_args.tracking,
+            word_spacing: self.font_args.word_spacing,
+            weight: self.font_args.weight,
+            slant: self.font_args.slant,
+            monospace: self.font_args.monospace,
+            tab_width: self.font_args.tab_width,
+            kerning: self.font_args.kerning(),
+            features: self.font_args.features.clone(),
+            shaper: self.font_args.shaper,
+            direction: self.font_args.direction,
+            writing_mode: self.font_args.writing_mode,
+            align: self.font_args.align,
+            max_width: self.font_args.max_width,
+            break_anywhere: self.font_args.break_anywhere,
+            line_height: self.font_args.line_height,
+            arc_radius: self.arc_radius,
+            arc_angle: self.arc_angle,
+            arc_side: self.arc_side,
+            path_file: self.path_file.clone(),
+            warp: self.warp,
+            warp_amount: self.warp_amount,
+            jitter: self.jitter,
+            seed: self.seed,
+            runs,
+            missing_glyph: self.font_args.missing_glyph,
+            strict: self.font_args.strict,
+            plate: self.plate,
+            plate_margin: self.plate_margin,
+            orient: self.orient.clone(),
+            center: !self.no_center,
+            anchor: self.anchor,
+            layer_height: self.out.layer_height(),
+            pendant: (self.mode == Mode::Pendant).then_some(HangingLoop {
+                inner_diameter: self.loop_diameter,
+                width: self.loop_width,
+                connector: !self.no_connector,
+            }),
+            token: (self.mode == Mode::Token).then_some(Token {
+                diameter: self.diameter,
+                thickness: self.thickness,
+                engrave: self.engrave,
+                back: self.back,
+                ..Default::default()
+            }),
+        })
+    }
+
+    fn size_target(&self) -> Option<SizeTarget> {
+        match (self.cap_height_mm, self.height_mm) {
+            (Some(mm), _) => Some(SizeTarget::CapHeight(mm)),
+            (None, Some(mm)) => Some(SizeTarget::Height(mm)),
+            (None, None) => None,
         }
     }
-    None
 }
 
-/// Simple left-to-right layout; collects glyph outlines into a path
-fn layout_text_to_path(
-    face: &Face<'_>,
-    builder: &mut PathBuilder,
-    text: &str,
-    scale: f32,
-    baseline_y: f32,
-    spacing: f32,
-    kerning: bool,
-) -> Result<()> {
-    let mut pen_x = 0.0;
-    let mut pen_baseline = baseline_y;
-    let line_advance = face.height() as f32 * scale;
-    let mut prev_gid = None;
-
-    for ch in text.chars() {
-        if ch == '\n' {
-            pen_x = 0.0;
-            pen_baseline -= line_advance;
-            prev_gid = None;
-            continue;
+impl FontArgs {
+    /// Contents of --font, or of the --builtin font without one
+    fn font_bytes(&self) -> Result<Cow<'static, [u8]>> {
+        wagyan::font::load_font_bytes(self.font.as_deref(), self.builtin)
+    }
+
+    /// Point --font and --face-index at the installed file of --family
+    fn resolve_family(&mut self) -> Result<()> {
+        if let Some(family) = &self.family {
+            let (path, index) = wagyan::font::find_system_font(family, self.font_style)?;
+            self.font = Some(path);
+            self.face_index = index;
         }
+        Ok(())
+    }
 
-        let gid = match face.glyph_index(ch) {
-            Some(id) => id,
-            None => {
-                eprintln!("⚠️ Skip missing glyph: '{}'", ch);
-                continue;
-            }
+    /// Point --font at the --google-font file, downloading it the first time
+    fn resolve_google_font(&mut self) -> Result<()> {
+        let Some(family) = &self.google_font else {
+            return Ok(());
         };
+        #[cfg(feature = "google-fonts")]
+        {
+            self.font = Some(wagyan::google_fonts::fetch(family, self.font_style)?);
+            Ok(())
+        }
+        #[cfg(not(feature = "google-fonts"))]
+        anyhow::bail!(
+            "--google-font \"{}\" needs wagyan built with the `google-fonts` feature",
+            family
+        )
+    }
 
-        // Apply kerning relative to previous glyph when available
-        if kerning {
-            if let Some(prev) = prev_gid {
-                if let Some(kern) = kerning_value(face, prev, gid) {
-                    pen_x += kern as f32 * scale;
-                }
+    /// Turn --face-name into the face index it names
+    fn resolve_face_name(&mut self) -> Result<()> {
+        if let Some(name) = &self.face_name {
+            let font_bytes = self.font_bytes()?;
+            self.face_index = wagyan::font::find_face_index(&font_bytes, name)?;
+        }
+        Ok(())
+    }
+
+    /// Put the axis settings of --instance ahead of the --variation ones,
+    /// which then override them
+    fn resolve_instance(&mut self) -> Result<()> {
+        if let Some(name) = &self.instance {
+            let font_bytes = self.font_bytes()?;
+            let face = wagyan::font::parse_face(&font_bytes, self.face_index)?;
+            let instance = wagyan::font::instance_variations(&face, name)?;
+            self.variation.splice(0..0, instance);
+        }
+        Ok(())
+    }
+
+    /// Parse the selected face, refusing restricted-license fonts unless
+    /// acknowledged and warning about preview & print ones; the fallback
+    /// fonts are loaded and checked the same way here too, so a bad one
+    /// fails up front. --variation settings are applied to the face.
+    fn face<'a>(&self, font_bytes: &'a [u8]) -> Result<Face<'a>> {
+        self.shaper.check()?;
+        let mut face = wagyan::font::parse_face(font_bytes, self.face_index)?;
+        wagyan::font::apply_variations(&mut face, &self.variation)?;
+        self.check_license(&face, "the font")?;
+        let fallbacks = wagyan::font::fallback_fonts(&self.fallback_font)?;
+        self.check_fallback_licenses(&self.fallback_font, &fallbacks)?;
+        Ok(face)
+    }
+
+    /// Refuse a restricted-license face unless acknowledged and warn about
+    /// a preview & print one; `file` names it when the font has no name
+    fn check_license(&self, face: &Face<'_>, file: &str) -> Result<()> {
+        let name = || metadata::font_name(face).unwrap_or_else(|| file.to_string());
+        match wagyan::font::embedding_restriction(face) {
+            Some(Permissions::Restricted) if !self.ack_license => {
+                anyhow::bail!("{}", Message::RestrictedFont(&name()))
+            }
+            Some(Permissions::PreviewAndPrint) if !self.ack_license => {
+                eprintln!("⚠️ {}", Message::PreviewAndPrintFont(&name()))
             }
+            _ => {}
         }
+        Ok(())
+    }
 
-        // Add outline to path
-        let mut adapter = LyonOutlineBuilder {
-            builder,
-            offset_x: pen_x,
-            offset_y: pen_baseline,
-            scale,
-        };
-        face.outline_glyph(gid, &mut adapter)
-            .ok_or_else(|| anyhow::anyhow!("failed to get outline for '{}'", ch))?;
+    /// [`FontArgs::check_license`] for fallback fonts loaded from `paths`
+    fn check_fallback_licenses(&self, paths: &[PathBuf], fonts: &[FontData]) -> Result<()> {
+        let faces = wagyan::font::fallback_faces(fonts);
+        for (path, face) in paths.iter().zip(&faces) {
+            self.check_license(face, &path.display().to_string())?;
+        }
+        Ok(())
+    }
 
-        // Advance: glyph advance + spacing
-        let advance = face.glyph_hor_advance(gid).unwrap_or(0) as f32 * scale + spacing;
-        pen_x += advance;
-        prev_gid = Some(gid);
+    fn kerning(&self) -> bool {
+        !self.no_kerning && self.kerning
     }
 
-    Ok(())
+    /// Convert literal "\\n" to newline and "\\t" to tab unless disabled
+    fn unescape(&self, text: &str) -> String {
+        if self.no_escape {
+            text.to_string()
+        } else {
+            text.replace("\\n", "\n").replace("\\t", "\t")
+        }
+    }
+
+    /// Lettering options for the presets, which pick their own size and depth
+    fn preset_options(
+        &self,
+        text: &str,
+        size_target: SizeTarget,
+        depth: f32,
+        orient: &Orientation,
+        out: &OutputArgs,
+    ) -> Options {
+        Options {
+            size_target: Some(size_target),
+            depth,
+            orient: orient.clone(),
+            layer_height: out.layer_height(),
+            ..self.lettering_options(text)
+        }
+    }
+
+    /// The font and layout settings of these flags, every other option at
+    /// its default
+    fn lettering_options(&self, text: &str) -> Options {
+        Options {
+            text: self.unescape(text),
+            normalize: self.normalize,
+            text_transform: self.text_transform,
+            font: self.font.clone(),
+            builtin: self.builtin,
+            face_index: self.face_index,
+            fallback_fonts: self.fallback_font.clone(),
+            variations: self.variation.clone(),
+            tolerance: self.tolerance,
+            spacing: self.spacing,
+            tracking: self.tracking,
+            word_spacing: self.word_spacing,
+            weight: self.weight,
+            slant: self.slant,
+            monospace: self.monospace,
+            tab_width: self.tab_width,
+            kerning: self.kerning(),
+            features: self.features.clone(),
+            shaper: self.shaper,
+            direction: self.direction,
+            writing_mode: self.writing_mode,
+            align: self.align,
+            max_width: self.max_width,
+            break_anywhere: self.break_anywhere,
+            line_height: self.line_height,
+            missing_glyph: self.missing_glyph,
+            strict: self.strict,
+            ..Options::default()
+        }
+    }
 }
 
-/// Adapter: ttf-parser outline -> lyon PathBuilder
-struct LyonOutlineBuilder<'a> {
-    builder: &'a mut PathBuilder,
-    offset_x: f32,
-    offset_y: f32,
-    scale: f32,
+/// Preset lettering size: --cap-height-mm wins over --height-mm
+fn preset_size(height_mm: f32, cap_height_mm: Option<f32>) -> SizeTarget {
+    match cap_height_mm {
+        Some(mm) => SizeTarget::CapHeight(mm),
+        None => SizeTarget::Height(height_mm),
+    }
 }
 
-impl OutlineBuilder for LyonOutlineBuilder<'_> {
-    fn move_to(&mut self, x: f32, y: f32) {
-        self.builder.begin(Point::new(
-            x * self.scale + self.offset_x,
-            y * self.scale + self.offset_y,
-        ));
+impl OutputArgs {
+    fn layer_height(&self) -> Option<f32> {
+        self.layer_height
+            .or(self.printer.map(|p| p.profile().layer_height))
     }
 
-    fn line_to(&mut self, x: f32, y: f32) {
-        self.builder.line_to(Point::new(
-            x * self.scale + self.offset_x,
-            y * self.scale + self.offset_y,
-        ));
+    /// Crease angle of smooth side walls, `None` for flat shading
+    fn crease(&self) -> Option<f32> {
+        self.crease_angle
+            .or(self.smooth_normals.then_some(DEFAULT_CREASE_DEGREES))
     }
 
-    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
-        self.builder.quadratic_bezier_to(
-            Point::new(
-                x1 * self.scale + self.offset_x,
-                y1 * self.scale + self.offset_y,
-            ),
-            Point::new(
-                x * self.scale + self.offset_x,
-                y * self.scale + self.offset_y,
-            ),
-        );
+    /// Format written to `output` (see [`Format::resolve`])
+    fn format(&self, output: Option<&Path>) -> Result<Format> {
+        Format::resolve(self.format, output)
     }
 
-    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
-        self.builder.cubic_bezier_to(
-            Point::new(
-                x1 * self.scale + self.offset_x,
-                y1 * self.scale + self.offset_y,
-            ),
-            Point::new(
-                x2 * self.scale + self.offset_x,
-                y2 * self.scale + self.offset_y,
-            ),
-            Point::new(
-                x * self.scale + self.offset_x,
-                y * self.scale + self.offset_y,
-            ),
+    /// Whether the text is exported as one part per letter (--glyph-groups
+    /// or --letter-colors with OBJ/GLB/3MF/AMF, --letter-layers with DXF) by
+    /// any of the outputs
+    fn splits_letters(&self, outputs: &[PathBuf]) -> Result<bool> {
+        let mut split = false;
+        for output in targets(outputs) {
+            split |= match self.format(output)? {
+                Format::Amf | Format::Glb | Format::Gltf | Format::ThreeMf => {
+                    self.glyph_groups || !self.letter_colors.is_empty()
+                }
+                Format::Obj => self.glyph_groups,
+                Format::Dxf => self.letter_layers,
+                _ => false,
+            };
+        }
+        Ok(split)
+    }
+
+    fn part_color(&self, part: &Part) -> Color {
+        if let Some(color) = part.color {
+            return color;
+        }
+        match part.letter {
+            Some(i) if !self.letter_colors.is_empty() => {
+                self.letter_colors[i % self.letter_colors.len()]
+            }
+            _ if part.is_text() => self.text_color.unwrap_or(self.base_color),
+            _ => self.plate_color.unwrap_or(self.base_color),
+        }
+    }
+
+    /// --slicer setup of the model (in mm), its placement in --units
+    fn slicer_project(&self, options: &Options, parts: &[Part]) -> Option<SlicerProject> {
+        let slicer = self.slicer?;
+        let printer = self.printer.unwrap_or(slicer.default_printer()).profile();
+        let mut project = SlicerProject::new(
+            slicer,
+            &printer,
+            parts,
+            &options.orient,
+            self.layer_height().unwrap_or(printer.layer_height),
+            self.text_color.unwrap_or(self.base_color),
         );
+        project.offset = project.offset.map(|v| v * self.units.per_mm());
+        Some(project)
     }
 
-    fn close(&mut self) {
-        self.builder.close();
+    /// Metadata for embedding, titled with --name when given
+    fn metadata(&self, face: &Face<'_>, options: &Options) -> GenerationMetadata {
+        let metadata = GenerationMetadata::new(face, options);
+        match &self.name {
+            Some(name) => metadata.with_title(name.clone()),
+            None => metadata,
+        }
+    }
+
+    fn cancel_token(&self) -> Result<CancelToken> {
+        match self.timeout {
+            Some(secs) => {
+                let limit = Duration::try_from_secs_f64(secs)
+                    .map_err(|_| anyhow::anyhow!("invalid --timeout: {}", secs))?;
+                Ok(CancelToken::with_timeout(limit))
+            }
+            None => Ok(CancelToken::new()),
+        }
     }
-}
 
-struct Mesh2D {
-    vertices: Vec<Point>,
-    indices: Vec<u16>,
+    fn overwrite_policy(&self) -> OverwritePolicy {
+        if self.force {
+            OverwritePolicy::Force
+        } else if self.backup {
+            OverwritePolicy::Backup
+        } else {
+            OverwritePolicy::Refuse
+        }
+    }
 }
 
-fn center_mesh_xy(mesh: &mut Mesh2D) {
-    let mut min_x = f32::MAX;
-    let mut max_x = f32::MIN;
-    let mut min_y = f32::MAX;
-    let mut max_y = f32::MIN;
+fn main() -> Result<()> {
+    let (lang, args) = Lang::take_from_args(std::env::args());
+    i18n::set_lang(lang);
+    let matches = localized_command(lang).get_matches_from(args);
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    run(args).context(Message::ConversionFailed.to_string())
+}
 
-    for p in &mesh.vertices {
-        min_x = min_x.min(p.x);
-        max_x = max_x.max(p.x);
-        min_y = min_y.min(p.y);
-        max_y = max_y.max(p.y);
+/// The clap command with its help text in `lang`
+fn localized_command(lang: Lang) -> clap::Command {
+    // The name plate's --style is its outline, so the font's is --font-style
+    let command = Args::command().mut_subcommand("nameplate", |sub| {
+        sub.mut_arg("font_style", |arg| arg.long("font-style"))
+    });
+    if lang == Lang::En {
+        return command;
     }
+    let shared = shared_arg_ids();
+    let localize_args = |command: clap::Command, name: Option<&str>| {
+        command.mut_args(
+            |arg| match ja_arg_help(&shared, name, arg.get_id().as_str()) {
+                Some(help) => arg.help(help).long_help(None),
+                None => arg,
+            },
+        )
+    };
+    localize_args(command, None)
+        .about("テキストを押し出して ASCII STL などの 3D モデルにする CLI")
+        .after_help(i18n::JA_UNITS_NOTE)
+        .mut_subcommands(|sub| {
+            let name = sub.get_name().to_string();
+            let sub = localize_args(sub, Some(&name));
+            match i18n::ja_about(&name) {
+                Some(about) => sub.about(about).long_about(None),
+                None => sub,
+            }
+        })
+}
+
+/// Ids of the arguments every command shares: the font and output flags
+/// and --lang
+fn shared_arg_ids() -> Vec<clap::Id> {
+    use clap::Args as _;
 
-    let cx = (min_x + max_x) * 0.5;
-    let cy = (min_y + max_y) * 0.5;
+    let shared = OutputArgs::augment_args(FontArgs::augment_args(clap::Command::new("shared")));
+    shared
+        .get_arguments()
+        .map(|arg| arg.get_id().clone())
+        .chain([clap::Id::from("lang")])
+        .collect()
+}
 
-    for p in &mut mesh.vertices {
-        p.x -= cx;
-        p.y -= cy;
+/// Japanese help of argument `id` of subcommand `command` (the main command
+/// without one); `shared` arguments mean the same everywhere
+fn ja_arg_help(shared: &[clap::Id], command: Option<&str>, id: &str) -> Option<&'static str> {
+    match shared.iter().any(|shared| shared == id) {
+        true => i18n::ja_arg_help(None, id),
+        false => i18n::ja_arg_help(command, id),
     }
 }
 
-fn mesh_bounds(mesh: &Mesh2D) -> Option<(f32, f32, f32, f32)> {
-    if mesh.vertices.is_empty() {
-        return None;
+/// `--list-instances`: one line per named instance, with settings that
+/// --variation takes as well
+fn list_instances(font_args: &FontArgs) -> Result<()> {
+    let font_bytes = font_args.font_bytes()?;
+    let face = wagyan::font::parse_face(&font_bytes, font_args.face_index)?;
+    let instances = wagyan::font::named_instances(&face);
+    anyhow::ensure!(!instances.is_empty(), "the font has no named instances");
+    let width = instances
+        .iter()
+        .map(|i| i.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    for instance in instances {
+        let settings: Vec<_> = instance.variations.iter().map(|v| v.to_string()).collect();
+        println!("{:<width$}  {}", instance.name, settings.join(","));
     }
+    Ok(())
+}
 
-    let mut min_x = f32::MAX;
-    let mut max_x = f32::MIN;
-    let mut min_y = f32::MAX;
-    let mut max_y = f32::MIN;
+fn run(mut args: Args) -> Result<()> {
+    if let Some(font_args) = args.font_args_mut() {
+        font_args.resolve_family()?;
+        font_args.resolve_google_font()?;
+        font_args.resolve_face_name()?;
+        font_args.resolve_instance()?;
+    }
+    if args.list_instances {
+        return list_instances(&args.font_args);
+    }
+    match &args.command {
+        Some(Command::Nameplate(nameplate)) => return run_nameplate(nameplate),
+        Some(Command::CakeTopper(topper)) => return run_cake_topper(topper),
+        Some(Command::LicensePlate(plate)) => return run_license_plate(plate),
+        Some(Command::DoorSign(sign)) => return run_doorsign(sign),
+        Some(Command::CableTag(tag)) => return run_cable_tag(tag),
+        Some(Command::Plaque(plaque)) => return run_plaque(plaque),
+        Some(Command::GardenMarker(marker)) => return run_garden_marker(marker),
+        Some(Command::ModelLabel(label)) => return run_model_label(label),
+        Some(Command::Tui(tui)) => return run_tui(tui),
+        Some(Command::Measure(measure)) => return run_measure(measure),
+        Some(Command::Coverage(coverage)) => return run_coverage(coverage),
+        Some(Command::FontInfo(info)) => return run_font_info(info),
+        None => {}
+    }
+    if args.mode == Mode::Token && args.engrave {
+        let token = Token {
+            thickness: args.thickness,
+            back: args.back,
+            engrave: true,
+            ..Default::default()
+        };
+        anyhow::ensure!(
+            token.core_thickness(args.depth) > 0.0,
+            "engraving {} mm deep needs a token thicker than that (token is {} mm)",
+            args.depth,
+            args.thickness
+        );
+    }
+    anyhow::ensure!(
+        (args.arc_radius.is_none() && args.arc_angle.is_none() && args.path_file.is_none())
+            || args.font_args.writing_mode == WritingMode::Horizontal,
+        "--arc-radius, --arc-angle and --path-file bend horizontal text only"
+    );
+    anyhow::ensure!(
+        (!args.markup && args.runs.is_none())
+            || args.font_args.writing_mode == WritingMode::Horizontal,
+        "--markup and --runs set horizontal text only"
+    );
+    if let Some(path) = &args.path_file {
+        TextPath::load(path)?;
+    }
+    let cancel = args.out.cancel_token()?;
+
+    // Load font (fallback to the --builtin one)
+    let font_bytes = args.font_args.font_bytes()?;
+    let face = args.font_args.face(&font_bytes)?;
+    let stroke_font = args
+        .stroke_font
+        .as_deref()
+        .map(HersheyFont::load)
+        .transpose()?;
+
+    let text_template = if args.codepoint.is_empty() {
+        args.font_args
+            .unescape(args.text.as_deref().unwrap_or_default())
+    } else {
+        args.codepoint.iter().collect()
+    };
+    // Fonts of --markup and --runs runs are loaded as fallbacks after the
+    // --fallback-font ones, which face() has checked already
+    let fallback_paths = args.options(text_template.clone())?.fallback_paths();
+    let fallback_data = wagyan::font::fallback_fonts(&fallback_paths)?;
+    let checked = args.font_args.fallback_font.len();
+    args.font_args
+        .check_fallback_licenses(&fallback_paths[checked..], &fallback_data[checked..])?;
+    let fallbacks = wagyan::font::fallback_faces(&fallback_data);
+
+    let date = template::today();
+
+    let Some(sequence) = args.sequence else {
+        let (text, outputs) = resolve_templates(&args, &text_template, &date, None)?;
+        let options = args.options(text)?;
+        options.check_glyph_ids(&face)?;
+        if args.dry_run {
+            let layout = wagyan::layout(&face, &options);
+            return report_dry_run(&face, &layout, &options, &outputs);
+        }
+        if let Some(columns) = args.preview_term {
+            let mesh = wagyan::build_mesh(&face, &options, &cancel, &wagyan::no_progress)?;
+            print!("{}", preview::render(&mesh, columns));
+            return Ok(());
+        }
+
+        if let Some(font) = &stroke_font {
+            return write_stroke_model(&args, &face, font, &options, &outputs, &cancel);
+        }
+        if args.vcarve.is_some() || args.toolpath_offset.is_some() {
+            return write_toolpaths(&args, &face, &options, &outputs);
+        }
 
-    for p in &mesh.vertices {
-        min_x = min_x.min(p.x);
-        max_x = max_x.max(p.x);
-        min_y = min_y.min(p.y);
-        max_y = max_y.max(p.y);
+        // Color glyphs print as one part per layer color; other text is
+        // tessellated and extruded
+        if let Some(parts) = wagyan::color_parts(&face, &options, &cancel)? {
+            write_model(&args.out, &face, &options, parts, &outputs)?;
+        } else {
+            let parts = if args.out.splits_letters(&outputs)? {
+                let layout = wagyan::layout(&face, &options);
+                let mut cache = GlyphMeshCache::new(options.scale(&face), options.tolerance(&face))
+                    .with_weight(options.weight)
+                    .with_shear(options.shear());
+                wagyan::letter_parts(
+                    cache.glyph_meshes(&face, &fallbacks, &layout.glyphs, &cancel)?,
+                    &options,
+                )
+            } else {
+                wagyan::text_parts(&face, &options, &cancel, &wagyan::no_progress)?
+            };
+            write_text_model(&args.out, &face, &options, parts, &outputs)?;
+        }
+        if let Some(path) = &args.emit_placements {
+            placements::placements(&face, &options, &cancel)?
+                .write(path, args.out.overwrite_policy())?;
+            if !args.out.quiet {
+                eprintln!("✅ {}", Message::Wrote(path));
+            }
+        }
+        return Ok(());
+    };
+
+    for path in &args.out.output {
+        let path = path.to_string_lossy();
+        anyhow::ensure!(
+            ["n", "counter", "text"]
+                .iter()
+                .any(|name| template::uses(&path, name)),
+            "--sequence needs {{n}}, {{counter}} or {{text}} in --output so files do not overwrite each other"
+        );
+    }
+
+    // Shared characters are tessellated once and reused for every model
+    // (--height-mm depends on the text, so the cache restarts when the scale changes)
+    let mut cache: Option<GlyphMeshCache> = None;
+    for n in sequence.start..=sequence.end {
+        let (text, outputs) = resolve_templates(&args, &text_template, &date, Some(n))?;
+        let options = args.options(text)?;
+        options.check_glyph_ids(&face)?;
+        if let Some(font) = &stroke_font {
+            write_stroke_model(&args, &face, font, &options, &outputs, &cancel)?;
+            continue;
+        }
+        if args.vcarve.is_some() || args.toolpath_offset.is_some() {
+            write_toolpaths(&args, &face, &options, &outputs)?;
+            continue;
+        }
+        let layout = wagyan::layout(&face, &options);
+        if args.dry_run {
+            report_dry_run(&face, &layout, &options, &outputs)?;
+            continue;
+        }
+        if let Some(columns) = args.preview_term {
+            let mesh = wagyan::build_mesh(&face, &options, &cancel, &wagyan::no_progress)?;
+            println!("{}", options.text);
+            print!("{}", preview::render(&mesh, columns));
+            continue;
+        }
+        if let Some(parts) = wagyan::color_parts(&face, &options, &cancel)? {
+            write_model(&args.out, &face, &options, parts, &outputs)?;
+            continue;
+        }
+        let scale = options.scale(&face);
+        let cache = match cache.as_mut() {
+            Some(cache) if cache.scale() == scale => cache,
+            _ => cache.insert(
+                GlyphMeshCache::new(scale, options.tolerance(&face))
+                    .with_weight(options.weight)
+                    .with_shear(options.shear()),
+            ),
+        };
+        let parts = if args.out.splits_letters(&outputs)? {
+            wagyan::letter_parts(
+                cache.glyph_meshes(&face, &fallbacks, &layout.glyphs, &cancel)?,
+                &options,
+            )
+        } else {
+            // Emboldened neighbours can touch, warps bend the text as a
+            // whole and runs may have depths of their own, so it is merged,
+            // as are glyphs that overlap
+            let assembled = match options.weight != 0.0
+                || options.warp != Warp::None
+                || !options.runs.is_empty()
+            {
+                true => None,
+                false => cache.assemble(&face, &fallbacks, &layout.glyphs, &cancel)?,
+            };
+            match assembled {
+                Some(mesh) => wagyan::model_parts(mesh, &options),
+                None => wagyan::text_parts(&face, &options, &cancel, &wagyan::no_progress)?,
+            }
+        };
+        write_text_model(&args.out, &face, &options, parts, &outputs)?;
     }
+    Ok(())
+}
 
-    Some((min_x, max_x, min_y, max_y))
+/// Draw the text with the single-stroke font and write it, keeping the
+/// centerlines for DXF and SVG output
+fn write_stroke_model(
+    args: &Args,
+    face: &Face<'_>,
+    font: &HersheyFont,
+    options: &Options,
+    outputs: &[PathBuf],
+    cancel: &CancelToken,
+) -> Result<()> {
+    let (mesh, centerlines) = font.stroke_text(options, args.stroke_width, cancel)?;
+    let parts = wagyan::model_parts(mesh, options);
+    let outlines = Outlines {
+        centerlines: &centerlines,
+        glyphs: &[],
+    };
+    write_model_with_outlines(&args.out, face, options, parts, outputs, outlines)
 }
 
-fn rectangle_mesh(min_x: f32, max_x: f32, min_y: f32, max_y: f32) -> Mesh2D {
-    Mesh2D {
-        vertices: vec![
-            Point::new(min_x, min_y),
-            Point::new(max_x, min_y),
-            Point::new(max_x, max_y),
-            Point::new(min_x, max_y),
-        ],
-        indices: vec![0u16, 1, 2, 0, 2, 3],
+/// Write --vcarve or --toolpath-offset toolpaths (or a V-carve heightmap)
+/// to every output, computing them once
+fn write_toolpaths(
+    args: &Args,
+    face: &Face<'_>,
+    options: &Options,
+    outputs: &[PathBuf],
+) -> Result<()> {
+    let formats = if args.vcarve.is_some() {
+        ".nc, .gcode or .pgm"
+    } else {
+        ".nc, .gcode or .dxf"
+    };
+    anyhow::ensure!(
+        !outputs.is_empty(),
+        "toolpaths need --output ending in {}",
+        formats
+    );
+    coverage::ensure_covered(face, options)?;
+    let outlines = cnc::text_outlines(face, options);
+    let tolerance = options.tolerance(face);
+    let passes = match (args.vcarve, args.toolpath_offset) {
+        (Some(angle), _) => cnc::vcarve_passes(&outlines, angle, args.stepover, tolerance),
+        (None, Some(tool)) => cnc::pocket_passes(
+            &outlines,
+            tool.radius,
+            tool.passes,
+            options.depth,
+            tolerance,
+        ),
+        (None, None) => Vec::new(),
+    };
+    anyhow::ensure!(!passes.is_empty(), "{}", Message::NothingToCut);
+    let policy = args.out.overwrite_policy();
+    output::ensure_distinct(outputs.iter().map(PathBuf::as_path))?;
+    for path in outputs {
+        output::prepare_output(path, policy)?;
     }
+    for path in outputs {
+        match (extension(path).as_deref(), args.vcarve) {
+            (Some("pgm"), Some(angle)) => {
+                let heightmap = cnc::vcarve_heightmap(&outlines, angle, args.stepover);
+                output::write_with_policy(path, policy, |file| {
+                    write_heightmap_pgm_to_writer(BufWriter::new(file), &heightmap)
+                })
+                .with_context(|| Message::WriteFailed("PGM", path).to_string())?;
+            }
+            (Some("dxf"), None) => {
+                let layer = DxfLayer {
+                    name: dxf::TOOLPATH_LAYER.to_string(),
+                    color: 3,
+                    outlines: passes
+                        .iter()
+                        .flat_map(|pass| pass.contours.clone())
+                        .collect(),
+                    paths: Vec::new(),
+                };
+                output::write_with_policy(path, policy, |file| {
+                    write_dxf_to_writer(BufWriter::new(file), &[layer], Units::Mm)
+                })
+                .with_context(|| Message::WriteFailed("DXF", path).to_string())?;
+            }
+            (Some("nc" | "gcode" | "ngc"), _) => {
+                output::write_with_policy(path, policy, |file| {
+                    write_gcode_to_writer(BufWriter::new(file), &passes, &Machine::default())
+                })
+                .with_context(|| Message::WriteFailed("G-code", path).to_string())?;
+            }
+            _ => anyhow::bail!(
+                "toolpaths are written to {} files, not {}",
+                formats,
+                path.display()
+            ),
+        }
+        if !args.out.quiet {
+            eprintln!("✅ {}", Message::Wrote(path));
+        }
+    }
+    Ok(())
 }
 
-fn tessellate_path(path: &Path, tolerance: f32) -> Result<Mesh2D> {
-    let mut buffers: VertexBuffers<Point, u16> = VertexBuffers::new();
-    let mut tess = FillTessellator::new();
-    tess.tessellate_path(
-        path,
-        &FillOptions::default()
-            .with_fill_rule(FillRule::NonZero)
-            .with_tolerance(tolerance),
-        &mut BuffersBuilder::new(&mut buffers, |v: FillVertex| v.position()),
-    )
-    .context("failed to tessellate polygon")?;
-
-    Ok(Mesh2D {
-        vertices: buffers.vertices,
-        indices: buffers.indices,
-    })
+fn report_dry_run(
+    face: &Face<'_>,
+    layout: &Layout,
+    options: &Options,
+    outputs: &[PathBuf],
+) -> Result<()> {
+    let fallback_data = options.fallback_data();
+    let params = dry_run::DryRunParams {
+        scale: options.scale(face),
+        shear: options.shear(),
+        tolerance: options.tolerance(face),
+        depth: options.text_depth(),
+        plate: options.plate_thickness(),
+        plate_margin: options.plate_margin,
+        center: options.center,
+        anchor: options.text_anchor(),
+        outputs,
+        fallbacks: &wagyan::font::fallback_faces(&fallback_data),
+    };
+    let stdout = std::io::stdout();
+    dry_run::report(stdout.lock(), face, layout, &params)
 }
 
-fn extrude_mesh_with_offset(
-    mesh: &Mesh2D,
-    depth: f32,
-    orient: Orientation,
-    z_offset: f32,
-) -> Vec<Triangle> {
-    let mut triangles = Vec::new();
-    let z0 = -depth * 0.5 + z_offset;
-    let z1 = depth * 0.5 + z_offset;
+/// `wagyan measure`: layout metrics only, as a report or JSON on stdout
+fn run_measure(args: &MeasureArgs) -> Result<()> {
+    let font_bytes = args.font_args.font_bytes()?;
+    let face = args.font_args.face(&font_bytes)?;
+    let size_target = match (args.cap_height_mm, args.height_mm) {
+        (Some(mm), _) => Some(SizeTarget::CapHeight(mm)),
+        (None, Some(mm)) => Some(SizeTarget::Height(mm)),
+        (None, None) => None,
+    };
+    let options = Options {
+        size: args.size,
+        size_target,
+        ..args.font_args.lettering_options(&args.text)
+    };
+    coverage::ensure_covered(&face, &options)?;
+    let measurement = measure::measure(&face, &options);
+    let stdout = std::io::stdout();
+    if args.json {
+        serde_json::to_writer_pretty(stdout.lock(), &measurement.to_json())?;
+        println!();
+        Ok(())
+    } else {
+        measurement.write_text(stdout.lock())
+    }
+}
 
-    // Top face
-    for idx in mesh.indices.chunks(3) {
-        let a = mesh.vertices[idx[0] as usize];
-        let b = mesh.vertices[idx[1] as usize];
-        let c = mesh.vertices[idx[2] as usize];
-        triangles.push(triangle_with_normal(
-            map_point(a, z1, &orient),
-            map_point(b, z1, &orient),
-            map_point(c, z1, &orient),
-        ));
+/// `wagyan coverage`: the characters each font draws and those none has;
+/// with --strict, missing ones are an error after the report
+fn run_coverage(args: &CoverageArgs) -> Result<()> {
+    let font_bytes = args.font_args.font_bytes()?;
+    let face = args.font_args.face(&font_bytes)?;
+    let fallback_data = wagyan::font::fallback_fonts(&args.font_args.fallback_font)?;
+    let fallbacks = wagyan::font::fallback_faces(&fallback_data);
+    let text = args.font_args.unescape(&args.text);
+    let report = coverage::coverage(&face, &fallbacks, &text);
+    let stdout = std::io::stdout();
+    if args.json {
+        serde_json::to_writer_pretty(stdout.lock(), &report.to_json())?;
+        println!();
+    } else {
+        report.write_text(stdout.lock())?;
     }
+    anyhow::ensure!(
+        report.is_complete() || !args.font_args.strict,
+        "{}",
+        Message::StrictMissing(report.missing.len())
+    );
+    Ok(())
+}
 
-    // Bottom face (reverse winding so normal points down)
-    for idx in mesh.indices.chunks(3) {
-        let a = mesh.vertices[idx[0] as usize];
-        let b = mesh.vertices[idx[1] as usize];
-        let c = mesh.vertices[idx[2] as usize];
-        triangles.push(triangle_with_normal(
-            map_point(c, z0, &orient),
-            map_point(b, z0, &orient),
-            map_point(a, z0, &orient),
-        ));
+/// `wagyan font-info`: metadata of the selected face, which is read even
+/// when its license is restricted
+fn run_font_info(args: &FontInfoArgs) -> Result<()> {
+    let font_bytes = args.font_args.font_bytes()?;
+    let face = wagyan::font::parse_face(&font_bytes, args.font_args.face_index)?;
+    let info = font_info::font_info(&face);
+    let stdout = std::io::stdout();
+    if args.json {
+        serde_json::to_writer_pretty(stdout.lock(), &info.to_json())?;
+        println!();
+        Ok(())
+    } else {
+        info.write_text(stdout.lock())
     }
+}
+
+/// `wagyan nameplate`: plate, rim, holes and lettering from one short command
+fn run_nameplate(args: &NameplateArgs) -> Result<()> {
+    let cancel = args.out.cancel_token()?;
+    let font_bytes = args.font_args.font_bytes()?;
+    let face = args.font_args.face(&font_bytes)?;
+
+    let options = args.font_args.preset_options(
+        &args.text,
+        preset_size(args.height_mm, args.cap_height_mm),
+        args.depth,
+        &args.orient,
+        &args.out,
+    );
+    let plate = Nameplate {
+        style: args.style,
+        thickness: args.thickness,
+        margin: args.margin,
+        corner_radius: args.corner_radius,
+        rim: args.rim,
+        engrave: args.engrave,
+        holes: !args.no_holes,
+        hole_diameter: args.hole_diameter,
+    };
+    let parts = nameplate_parts(&face, &options, &plate, &cancel)?;
+    write_model(&args.out, &face, &options, parts, &args.out.output)
+}
 
-    // Side faces: detect boundary edges, create quads -> two triangles
-    for (i0, i1) in boundary_edges(&mesh.indices) {
-        let p0 = mesh.vertices[i0 as usize];
-        let p1 = mesh.vertices[i1 as usize];
+/// `wagyan cake-topper`: joined lettering on a backing with sticks
+fn run_cake_topper(args: &CakeTopperArgs) -> Result<()> {
+    let cancel = args.out.cancel_token()?;
+    let font_bytes = args.font_args.font_bytes()?;
+    let face = args.font_args.face(&font_bytes)?;
+
+    let options = args.font_args.preset_options(
+        &args.text,
+        preset_size(args.height_mm, args.cap_height_mm),
+        args.depth,
+        &args.orient,
+        &args.out,
+    );
+    let topper = CakeTopper {
+        outline: args.outline,
+        thickness: args.thickness,
+        sticks: args.sticks,
+        stick_length: args.stick_length,
+        stick_width: args.stick_width,
+    };
+    let parts = cake_topper_parts(&face, &options, &topper, &cancel)?;
+    write_model(&args.out, &face, &options, parts, &args.out.output)
+}
 
-        let top0 = map_point(p0, z1, &orient);
-        let top1 = map_point(p1, z1, &orient);
-        let bot0 = map_point(p0, z0, &orient);
-        let bot1 = map_point(p1, z0, &orient);
+/// `wagyan license-plate`: plate, frame, bolt holes and lettering
+fn run_license_plate(args: &LicensePlateArgs) -> Result<()> {
+    let cancel = args.out.cancel_token()?;
+    let font_bytes = args.font_args.font_bytes()?;
+    let face = args.font_args.face(&font_bytes)?;
+
+    let options = args.font_args.preset_options(
+        &args.text,
+        preset_size(args.height_mm, args.cap_height_mm),
+        args.depth,
+        &args.orient,
+        &args.out,
+    );
+    let plate = LicensePlate {
+        width: args.width,
+        height: args.height,
+        thickness: args.thickness,
+        corner_radius: args.corner_radius,
+        frame: args.frame,
+        bolts: args.bolts,
+        bolt_spacing_x: args.bolt_spacing_x,
+        bolt_spacing_y: args.bolt_spacing_y,
+        bolt_diameter: args.bolt_diameter,
+    };
+    let parts = license_plate_parts(&face, &options, &plate, &cancel)?;
+    write_model(&args.out, &face, &options, parts, &args.out.output)
+}
+
+/// `wagyan doorsign`: chamfered plate, mounting and lettering
+fn run_doorsign(args: &DoorSignArgs) -> Result<()> {
+    let cancel = args.out.cancel_token()?;
+    let font_bytes = args.font_args.font_bytes()?;
+    let face = args.font_args.face(&font_bytes)?;
+
+    let options = args.font_args.preset_options(
+        &args.text,
+        preset_size(args.height_mm, args.cap_height_mm),
+        args.depth,
+        &args.orient,
+        &args.out,
+    );
+    let sign = DoorSign {
+        thickness: args.thickness,
+        margin: args.margin,
+        corner_radius: args.corner_radius,
+        chamfer: args.chamfer,
+        mount: args.mount,
+        hole_diameter: args.hole_diameter,
+    };
+    let parts = doorsign_parts(&face, &options, &sign, &cancel)?;
+    write_model(&args.out, &face, &options, parts, &args.out.output)
+}
 
-        triangles.push(triangle_with_normal(top0, top1, bot1));
-        triangles.push(triangle_with_normal(top0, bot1, bot0));
+/// `wagyan cable-tag`: slotted tags, one or a whole --batch list
+fn run_cable_tag(args: &CableTagArgs) -> Result<()> {
+    let cancel = args.out.cancel_token()?;
+    let font_bytes = args.font_args.font_bytes()?;
+    let face = args.font_args.face(&font_bytes)?;
+
+    let tag = CableTag {
+        thickness: args.thickness,
+        margin: args.margin,
+        corner_radius: args.corner_radius,
+        slot_width: args.slot_width,
+        slot_height: args.slot_height,
+        slots: args.slots,
+    };
+    let jobs = preset_jobs(
+        args.text.as_deref(),
+        args.batch.as_deref(),
+        &args.out.output,
+    )?;
+    for (text, outputs) in jobs {
+        let options = args.font_args.preset_options(
+            &text,
+            preset_size(args.height_mm, args.cap_height_mm),
+            args.depth,
+            &args.orient,
+            &args.out,
+        );
+        let parts = cable_tag_parts(&face, &options, &tag, &cancel)?;
+        write_model(&args.out, &face, &options, parts, &outputs)?;
     }
+    Ok(())
+}
 
-    triangles
+/// `wagyan plaque`: title and body lines at their own sizes on a beveled plate
+fn run_plaque(args: &PlaqueArgs) -> Result<()> {
+    let cancel = args.out.cancel_token()?;
+    let font_bytes = args.font_args.font_bytes()?;
+    let face = args.font_args.face(&font_bytes)?;
+
+    let options = args.font_args.preset_options(
+        &args.lines.join("\n"),
+        SizeTarget::CapHeight(args.title_height),
+        args.depth,
+        &args.orient,
+        &args.out,
+    );
+    let plaque = AwardPlaque {
+        thickness: args.thickness,
+        margin: args.margin,
+        corner_radius: args.corner_radius,
+        bevel: args.bevel,
+        holes: !args.no_holes,
+        hole_diameter: args.hole_diameter,
+        title_height: args.title_height,
+        body_height: args.body_height,
+        line_heights: args.line_heights.clone(),
+        line_gap: args.line_gap,
+    };
+    let parts = plaque_parts(&face, &options, &plaque, &cancel)?;
+    write_model(&args.out, &face, &options, parts, &args.out.output)
 }
 
-fn extrude_mesh(mesh: &Mesh2D, depth: f32, orient: Orientation) -> Vec<Triangle> {
-    extrude_mesh_with_offset(mesh, depth, orient, 0.0)
+/// `wagyan garden-marker`: staked plant labels, one or a whole --batch list
+fn run_garden_marker(args: &GardenMarkerArgs) -> Result<()> {
+    let cancel = args.out.cancel_token()?;
+    let font_bytes = args.font_args.font_bytes()?;
+    let face = args.font_args.face(&font_bytes)?;
+
+    let marker = GardenMarker {
+        thickness: args.thickness,
+        margin: args.margin,
+        corner_radius: args.corner_radius,
+        stake_length: args.stake_length,
+        stake_width: args.stake_width,
+    };
+    let jobs = preset_jobs(
+        args.text.as_deref(),
+        args.batch.as_deref(),
+        &args.out.output,
+    )?;
+    for (text, outputs) in jobs {
+        let options = args.font_args.preset_options(
+            &text,
+            preset_size(args.height_mm, args.cap_height_mm),
+            args.depth,
+            &args.orient,
+            &args.out,
+        );
+        let parts = garden_marker_parts(&face, &options, &marker, &cancel)?;
+        write_model(&args.out, &face, &options, parts, &outputs)?;
+    }
+    Ok(())
 }
 
-/// Return boundary edges (true = edge orientation matches triangle winding)
-fn boundary_edges(indices: &[u16]) -> Vec<(u16, u16)> {
-    let mut counts: HashMap<(u16, u16), u32> = HashMap::new();
-    let mut oriented: HashMap<(u16, u16), (u16, u16)> = HashMap::new();
+/// `wagyan model-label`: resin-ready scale-model labels, one or a whole --batch list
+fn run_model_label(args: &ModelLabelArgs) -> Result<()> {
+    let cancel = args.out.cancel_token()?;
+    let font_bytes = args.font_args.font_bytes()?;
+    let face = args.font_args.face(&font_bytes)?;
 
-    for tri in indices.chunks(3) {
-        let edges = [(tri[0], tri[1]), (tri[1], tri[2]), (tri[2], tri[0])];
-        for &(a, b) in &edges {
-            let key = if a < b { (a, b) } else { (b, a) };
-            *counts.entry(key).or_insert(0) += 1;
-            oriented.entry(key).or_insert((a, b));
+    let label = ModelLabel {
+        thickness: args.thickness,
+        margin: args.margin,
+        min_stroke: args.min_stroke,
+    };
+    let jobs = preset_jobs(
+        args.text.as_deref(),
+        args.batch.as_deref(),
+        &args.out.output,
+    )?;
+    for (text, outputs) in jobs {
+        let mut options = args.font_args.preset_options(
+            &text,
+            SizeTarget::CapHeight(args.cap_height_mm),
+            args.depth,
+            &args.orient,
+            &args.out,
+        );
+        // Snap to resin layers unless a printer or layer height says otherwise
+        options.layer_height = options.layer_height.or(Some(RESIN_LAYER_HEIGHT));
+        let parts = model_label_parts(&face, &options, &label, &cancel)?;
+        write_model(&args.out, &face, &options, parts, &outputs)?;
+    }
+    Ok(())
+}
+
+/// `wagyan tui`: edit the options line by line, redrawing the preview after
+/// each change; prints the equivalent command on exit
+fn run_tui(args: &TuiArgs) -> Result<()> {
+    let mut font_bytes = args.font_args.font_bytes()?;
+    args.font_args.face(&font_bytes)?;
+    let mut session = tui::Session::new(args.font_args.lettering_options(&args.text));
+    let stdin = std::io::stdin();
+    let mut step = tui::Step::Help;
+    loop {
+        match step {
+            tui::Step::Help => {
+                println!(
+                    "Set a field with 'field value' (numbers also take +n/-n); empty line quits."
+                );
+                for (field, about) in tui::FIELDS {
+                    println!("  {:<13}{}", field, about);
+                }
+            }
+            tui::Step::Quit => break,
+            tui::Step::Redraw | tui::Step::ReloadFont => {}
+        }
+        let face = session.options.face(&font_bytes)?;
+        match session.summary(&face, args.columns) {
+            Ok(summary) => print!("{}", summary),
+            Err(e) => eprintln!("⚠️ {:#}", e),
+        }
+        print!("> ");
+        std::io::Write::flush(&mut std::io::stdout())?;
+
+        let mut line = String::new();
+        if stdin.read_line(&mut line)? == 0 {
+            break;
+        }
+        let previous = session.options.clone();
+        step = match session.apply(&line) {
+            Ok(step) => step,
+            Err(e) => {
+                eprintln!("⚠️ {:#}", e);
+                tui::Step::Redraw
+            }
+        };
+        if step == tui::Step::ReloadFont {
+            let options = &session.options;
+            let loaded = wagyan::font::load_font_bytes(options.font.as_deref(), options.builtin)
+                .and_then(|bytes| {
+                    options.face(&bytes)?;
+                    Ok(bytes)
+                });
+            match loaded {
+                Ok(bytes) => font_bytes = bytes,
+                Err(e) => {
+                    eprintln!("⚠️ {:#}", e);
+                    session.options = previous;
+                }
+            }
         }
     }
+    println!("{}", session.command_line());
+    Ok(())
+}
 
-    counts
+/// Labels for a preset: the one on the command line, or one per line of
+/// `batch` with `{n}` (1-based line number), `{text}` and `{date}` expanded
+/// in the output path
+fn preset_jobs(
+    text: Option<&str>,
+    batch: Option<&Path>,
+    outputs: &[PathBuf],
+) -> Result<Vec<(String, Vec<PathBuf>)>> {
+    let Some(batch) = batch else {
+        return Ok(vec![(
+            text.unwrap_or_default().to_string(),
+            outputs.to_vec(),
+        )]);
+    };
+    anyhow::ensure!(
+        !outputs.is_empty(),
+        "--batch needs --output with {{n}} or {{text}} in it"
+    );
+    for output in outputs {
+        let template = output.to_string_lossy();
+        anyhow::ensure!(
+            ["n", "text"]
+                .iter()
+                .any(|name| template::uses(&template, name)),
+            "--batch needs {{n}} or {{text}} in --output so files do not overwrite each other"
+        );
+    }
+    let date = template::today();
+    wagyan::batch::read_labels(batch)?
         .into_iter()
-        .filter(|(_, cnt)| *cnt == 1)
-        .map(|(k, _)| oriented[&k])
+        .enumerate()
+        .map(|(i, label)| {
+            let vars = TemplateVars {
+                date: Some(date.clone()),
+                counter: None,
+                n: Some(i as u64 + 1),
+                text: Some(template::sanitize_file_component(&label)),
+            };
+            let paths = outputs
+                .iter()
+                .map(|output| template::expand_path(output, &vars))
+                .collect::<Result<_>>()?;
+            Ok((label, paths))
+        })
         .collect()
 }
 
-fn triangle_with_normal(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> Triangle {
-    Triangle {
-        normal: calc_normal(a, b, c),
-        vertices: [a, b, c],
-    }
+/// Expand {date}/{counter}/{n} in the text and {date}/{counter}/{n}/{text} in the output path
+fn resolve_templates(
+    args: &Args,
+    text_template: &str,
+    date: &str,
+    n: Option<u64>,
+) -> Result<(String, Vec<PathBuf>)> {
+    let resolve = |counter: u64| {
+        let mut vars = TemplateVars {
+            date: Some(date.to_string()),
+            counter: Some(counter),
+            n,
+            text: None,
+        };
+        let text = template::expand(text_template, &vars)?;
+        vars.text = Some(template::sanitize_file_component(&text));
+        let outputs = args
+            .out
+            .output
+            .iter()
+            .map(|path| template::expand_path(path, &vars))
+            .collect::<Result<Vec<_>>>()?;
+        anyhow::Ok((text, outputs))
+    };
+    // The first output with {counter} picks the value for all of them
+    let counted = args
+        .out
+        .output
+        .iter()
+        .position(|path| template::uses(&path.to_string_lossy(), "counter"));
+    let counter = match (args.counter, counted) {
+        (Some(counter), _) => counter,
+        (None, Some(i)) => {
+            template::next_free_counter(1, |counter| Ok(resolve(counter)?.1.remove(i)))?
+        }
+        _ => 1,
+    };
+    resolve(counter)
 }
 
-fn calc_normal(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> [f32; 3] {
-    let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
-    let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
-    let n = [
-        u[1] * v[2] - u[2] * v[1],
-        u[2] * v[0] - u[0] * v[2],
-        u[0] * v[1] - u[1] * v[0],
-    ];
-    let len = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
-    if len == 0.0 {
-        [0.0, 0.0, 0.0]
+/// Stdout when there are no output files
+fn targets(outputs: &[PathBuf]) -> Vec<Option<&Path>> {
+    if outputs.is_empty() {
+        vec![None]
     } else {
-        [n[0] / len, n[1] / len, n[2] / len]
+        outputs.iter().map(|path| Some(path.as_path())).collect()
     }
 }
 
-fn map_point(p: Point, z: f32, orient: &Orientation) -> [f32; 3] {
-    match orient {
-        Orientation::Flat => [p.x, p.y, z],
-        // Front orientation: keep X, rotate +Z to up, +Y faces viewer
-        // (original +Z normals become +Y; text keeps its vertical sense)
-        Orientation::Front => [p.x, -z, p.y],
+/// Extrude one model and write it to every output file, or to stdout
+fn write_model(
+    args: &OutputArgs,
+    face: &Face<'_>,
+    options: &Options,
+    parts: Vec<Part>,
+    outputs: &[PathBuf],
+) -> Result<()> {
+    coverage::ensure_covered(face, options)?;
+    write_model_with_outlines(args, face, options, parts, outputs, Outlines::default())
+}
+
+/// [`write_model`] for the lettering of the main command, whose glyph
+/// curves a .svg or .step keeps instead of the tessellated outline
+fn write_text_model(
+    args: &OutputArgs,
+    face: &Face<'_>,
+    options: &Options,
+    parts: Vec<Part>,
+    outputs: &[PathBuf],
+) -> Result<()> {
+    coverage::ensure_covered(face, options)?;
+    let mut curves = false;
+    for output in targets(outputs) {
+        curves |= matches!(args.format(output)?, Format::Svg | Format::Step);
     }
+    let glyphs = match curves {
+        true => wagyan::text_paths(face, options),
+        false => Vec::new(),
+    };
+    let outlines = Outlines {
+        centerlines: &[],
+        glyphs: &glyphs,
+    };
+    write_model_with_outlines(args, face, options, parts, outputs, outlines)
 }
 
-fn write_stl_ascii(path: &PathBuf, tris: &[Triangle]) -> Result<()> {
-    let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("mesh");
-    let file = File::create(path)?;
-    let buf = BufWriter::new(file);
-    write_stl_ascii_to_writer(buf, name, tris)
+/// Exact 2D geometry the laser and CAD formats use instead of the tessellated text
+#[derive(Clone, Copy, Default)]
+struct Outlines<'a> {
+    /// Single-stroke centerlines engraved instead of the widened "text" part
+    centerlines: &'a [Vec<Point>],
+    /// Glyph outlines with their curves, placed like the text parts: one
+    /// path for "text", then one for each of "text_1", "text_2", ...
+    glyphs: &'a [lyon_path::Path],
 }
 
-fn write_stl_ascii_to_writer<W: Write>(mut writer: W, name: &str, tris: &[Triangle]) -> Result<()> {
-    writeln!(writer, "solid {}", name)?;
-    for tri in tris {
-        writeln!(
-            writer,
-            "  facet normal {} {} {}",
-            tri.normal[0], tri.normal[1], tri.normal[2]
-        )?;
-        writeln!(writer, "    outer loop")?;
-        for v in &tri.vertices {
-            writeln!(writer, "      vertex {} {} {}", v[0], v[1], v[2])?;
+/// [`write_model`] with the text's exact `outlines`
+fn write_model_with_outlines(
+    args: &OutputArgs,
+    face: &Face<'_>,
+    options: &Options,
+    parts: Vec<Part>,
+    outputs: &[PathBuf],
+    outlines: Outlines<'_>,
+) -> Result<()> {
+    if let Some(printer) = args.printer {
+        let stem = printer::stem_width(face).map(|w| w * options.scale(face));
+        let bounds = wagyan::model_bounds(&parts, &options.orient);
+        for warning in printer::check(&printer.profile(), bounds, stem) {
+            eprintln!("⚠️ {}", warning);
+        }
+    }
+
+    // Every format is known before anything is written
+    let targets = targets(outputs)
+        .into_iter()
+        .map(|output| Ok((output, args.format(output)?)))
+        .collect::<Result<Vec<_>>>()?;
+    // ... and none is refused halfway through the batch
+    output::ensure_distinct(targets.iter().filter_map(|(target, _)| *target))?;
+    for path in targets.iter().filter_map(|(target, _)| *target) {
+        output::prepare_output(path, args.overwrite_policy())?;
+        if args.sidecar {
+            output::prepare_output(&sidecar_path(path), args.overwrite_policy())?;
         }
-        writeln!(writer, "    endloop")?;
-        writeln!(writer, "  endfacet")?;
     }
-    writeln!(writer, "endsolid {}", name)?;
-    writer.flush()?;
+    if args.units == Units::Cm && targets.iter().any(|(_, format)| *format == Format::Amf) {
+        anyhow::bail!("AMF has no centimeter unit; use --units mm or --units inch");
+    }
+    if args.kerf > 0.0 && targets.iter().all(|(_, format)| *format != Format::Dxf) {
+        eprintln!("⚠️ {}", Message::KerfOnlyDxf);
+    }
+    if args.slicer.is_some() && targets.iter().all(|(_, format)| *format != Format::ThreeMf) {
+        eprintln!("⚠️ {}", Message::SlicerOnly3mf);
+    }
+    let model = Model {
+        face,
+        options,
+        parts: &parts,
+        outlines,
+    };
+    for (output, format) in targets {
+        write_output(args, &model, format, output)?;
+    }
     Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Write the model in `format` to one file, or to stdout
+fn write_output(
+    args: &OutputArgs,
+    model: &Model<'_>,
+    format: Format,
+    output: Option<&Path>,
+) -> Result<()> {
+    let compression = Compression::resolve(args.compress, output);
+    let Some(path) = output else {
+        let out = BufWriter::new(std::io::stdout().lock());
+        let name = args.name.as_deref().unwrap_or("mesh");
+        return output::write_compressed(out, compression, |out| {
+            write_format(args, model, format, name, out)
+        })
+        .with_context(|| Message::WriteStdoutFailed(format.label()).to_string());
+    };
 
-    #[test]
-    fn boundary_edges_filters_shared_edges() {
-        let indices = vec![0u16, 1, 2, 2, 1, 3];
-        let edges: std::collections::HashSet<(u16, u16)> =
-            boundary_edges(&indices).into_iter().collect();
+    // sign.stl.gz and sign.stl.zst are named after "sign" too
+    let mut stem = Path::new(path.file_stem().unwrap_or_default());
+    if Compression::from_path(path).is_some() {
+        stem = Path::new(stem.file_stem().unwrap_or_default());
+    }
+    let name = args
+        .name
+        .as_deref()
+        .or(stem.to_str().filter(|s| !s.is_empty()))
+        .unwrap_or("mesh");
+    let sidecar = args.sidecar.then(|| sidecar_path(path));
+    output::write_with_policy(path, args.overwrite_policy(), |file| {
+        output::write_compressed(BufWriter::new(file), compression, |out| {
+            write_format(args, model, format, name, out)
+        })
+    })
+    .with_context(|| Message::WriteFailed(format.label(), path).to_string())?;
+    if let Some(sidecar) = &sidecar {
+        args.metadata(model.face, model.options)
+            .write_sidecar(sidecar, args.overwrite_policy())?;
+    }
+    if !args.quiet {
+        eprintln!("✅ {}", Message::Wrote(path));
+    }
+    Ok(())
+}
 
-        let expected: std::collections::HashSet<(u16, u16)> =
-            [(0, 1), (2, 0), (3, 2), (1, 3)].into_iter().collect();
+/// `--sidecar` metadata file next to the output (`sign.stl` -> `sign.stl.json`)
+fn sidecar_path(path: &Path) -> PathBuf {
+    let mut sidecar = path.as_os_str().to_owned();
+    sidecar.push(".json");
+    PathBuf::from(sidecar)
+}
 
-        assert_eq!(edges, expected);
-    }
+/// What [`write_format`] writes
+struct Model<'a> {
+    face: &'a Face<'a>,
+    options: &'a Options,
+    parts: &'a [Part],
+    outlines: Outlines<'a>,
+}
 
-    #[test]
-    fn calc_normal_returns_unit_z_for_xy_triangles() {
-        let n = calc_normal([0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]);
-        assert!((n[0]).abs() < 1e-6);
-        assert!((n[1]).abs() < 1e-6);
-        assert!((n[2] - 1.0).abs() < 1e-6);
+/// Write the model in `format`; `name` is the ASCII STL solid name
+fn write_format<W: std::io::Write>(
+    args: &OutputArgs,
+    model: &Model<'_>,
+    format: Format,
+    name: &str,
+    mut out: W,
+) -> Result<()> {
+    let Model {
+        face,
+        options,
+        parts,
+        outlines,
+    } = *model;
+    let centerlines = outlines.centerlines;
+    // Built in mm; STL and PLY solids are scaled up front, indexed meshes
+    // and laser layers once built
+    let factor = args.units.per_mm();
+    let solids: Cow<[Part]> = match args.units {
+        Units::Mm => Cow::Borrowed(parts),
+        _ => Cow::Owned(parts.iter().map(|part| part.scaled(factor)).collect()),
+    };
+    let triangles = || -> Vec<Triangle> {
+        solids
+            .iter()
+            .flat_map(|part| part.extrusion(options.orient.clone()))
+            .collect()
+    };
+    let crease = args.crease();
+    match format {
+        Format::Ascii => write_stl_ascii_to_writer(out, name, &triangles()),
+        Format::Binary => {
+            let header = args.metadata(face, options).stl_header();
+            write_stl_binary_to_writer(out, &header, &triangles())
+        }
+        Format::Glb => write_glb_to_writer(out, &mesh_parts(args, options, parts, crease)),
+        Format::Gltf => write_gltf_to_writer(out, &mesh_parts(args, options, parts, crease)),
+        Format::Ply => write_ply_to_writer(out, &solids, &options.orient),
+        Format::Obj => {
+            let obj = ObjOptions {
+                smooth: crease.is_some(),
+                normals: !args.no_normals,
+                units: args.units,
+            };
+            let mut parts = mesh_parts(args, options, parts, crease);
+            if let Some(name) = &args.name {
+                // One object takes the name, several are told apart by part
+                let single = parts.len() == 1;
+                for part in &mut parts {
+                    part.name = match single {
+                        true => name.clone(),
+                        false => format!("{}-{}", name, part.name),
+                    };
+                }
+            }
+            write_obj_to_writer(out, &parts, obj)
+        }
+        Format::Dxf => {
+            let mut cut_parts = parts.to_vec();
+            if !centerlines.is_empty() {
+                cut_parts.retain(|part| part.name != "text");
+            }
+            for warning in dxf::kerf_warnings(&cut_parts, args.kerf) {
+                eprintln!("⚠️ {}", warning);
+            }
+            let mut layers = if args.letter_layers && centerlines.is_empty() {
+                dxf::letter_layers(&cut_parts, args.kerf)
+            } else {
+                let mut layers = dxf::laser_layers(&cut_parts, args.kerf);
+                layers[1].paths = centerlines.to_vec();
+                layers
+            };
+            layers.iter_mut().for_each(|layer| layer.scale(factor));
+            write_dxf_to_writer(out, &layers, args.units)
+        }
+        Format::Svg => {
+            let mut layers = svg::laser_layers(parts, outlines.glyphs, centerlines);
+            layers.iter_mut().for_each(|layer| layer.scale(factor));
+            write_svg_to_writer(out, &layers, args.units)
+        }
+        Format::Step => {
+            let mut solids = step::solids(parts, outlines.glyphs);
+            solids.iter_mut().for_each(|solid| solid.scale(factor));
+            write_step_to_writer(out, &solids, &options.orient, name, args.units)
+        }
+        Format::Amf => {
+            let metadata = args.metadata(face, options);
+            write_amf_to_writer(
+                out,
+                &mesh_parts(args, options, parts, None),
+                Some(&metadata),
+                args.units,
+            )
+        }
+        Format::ThreeMf => {
+            // The zip writer seeks back to patch headers; buffer, then copy
+            let metadata = args.metadata(face, options);
+            let mut zip = std::io::Cursor::new(Vec::new());
+            write_3mf_to_writer(
+                &mut zip,
+                &mesh_parts(args, options, parts, None),
+                Some(&metadata),
+                args.units,
+                args.slicer_project(options, parts).as_ref(),
+            )?;
+            out.write_all(zip.get_ref())?;
+            out.flush()?;
+            Ok(())
+        }
     }
+}
+
+/// Indexed, colored meshes for formats that keep parts apart, in --units
+fn mesh_parts(
+    args: &OutputArgs,
+    options: &Options,
+    parts: &[Part],
+    crease: Option<f32>,
+) -> Vec<MeshPart> {
+    parts
+        .iter()
+        .map(|part| {
+            let mut mesh = part.indexed(&options.orient, crease);
+            mesh.scale(args.units.per_mm());
+            MeshPart {
+                name: part.name.clone(),
+                mesh,
+                color: args.part_color(part),
+            }
+        })
+        .collect()
+}
+
+/// Lowercased file extension
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
     #[test]
-    fn calc_normal_handles_degenerate_triangles() {
-        let n = calc_normal([0.0, 0.0, 0.0], [1.0, 1.0, 1.0], [2.0, 2.0, 2.0]);
-        assert_eq!(n, [0.0, 0.0, 0.0]);
+    fn sequence_range_parses_inclusive_bounds() {
+        let seq: SequenceRange = "1..100".parse().unwrap();
+        assert_eq!(seq, SequenceRange { start: 1, end: 100 });
+        let seq: SequenceRange = "3..=5".parse().unwrap();
+        assert_eq!(seq, SequenceRange { start: 3, end: 5 });
+        assert!("5..1".parse::<SequenceRange>().is_err());
+        assert!("7".parse::<SequenceRange>().is_err());
     }
 
     #[test]
-    fn tolerance_scales_with_size() {
-        let base = resolve_tolerance(72.0, None);
-        let bigger = resolve_tolerance(144.0, None);
-        let smaller = resolve_tolerance(24.0, None);
-
-        assert!(bigger > base);
-        assert!(smaller < base);
+    fn toolpath_offset_parses_radius_and_passes() {
+        let tool: ToolpathOffset = "1.5".parse().unwrap();
+        assert_eq!((tool.radius, tool.passes), (1.5, None));
+        let tool: ToolpathOffset = "0.5, 3".parse().unwrap();
+        assert_eq!((tool.radius, tool.passes), (0.5, Some(3)));
+        assert!("0,3".parse::<ToolpathOffset>().is_err());
+        assert!("1,0".parse::<ToolpathOffset>().is_err());
     }
 
     #[test]
-    fn tolerance_is_clamped() {
-        let min = resolve_tolerance(1.0, Some(0.00001));
-        let max = resolve_tolerance(10_000.0, Some(10.0));
-
-        assert_eq!(min, MIN_TOLERANCE);
-        assert_eq!(max, MAX_TOLERANCE);
+    fn every_argument_has_japanese_help() {
+        let shared = shared_arg_ids();
+        let command = localized_command(Lang::En);
+        let commands = std::iter::once((None, &command)).chain(
+            command
+                .get_subcommands()
+                .map(|sub| (Some(sub.get_name()), sub)),
+        );
+        for (name, command) in commands {
+            for arg in command.get_arguments() {
+                let id = arg.get_id().as_str();
+                assert!(
+                    ja_arg_help(&shared, name, id).is_some(),
+                    "no Japanese help for {} of {}",
+                    id,
+                    name.unwrap_or("wagyan")
+                );
+            }
+        }
     }
 }
@@ -0,0 +1,221 @@
+//! `wagyan measure`: metrics of the laid-out text without generating any
+//! geometry, for tools that size plates or quote prices up front
+
+use std::io::Write;
+
+use anyhow::Result;
+use serde_json::json;
+use ttf_parser::Face;
+
+use crate::layout::hor_advance;
+use crate::{dry_run, font, layout, Options};
+
+/// A glyph's place on its line, in mm
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlyphMetrics {
+    pub ch: char,
+    pub gid: u16,
+    /// Pen position, kerning included
+    pub x: f32,
+    /// Horizontal advance from the font, without --spacing or kerning
+    pub advance: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineMetrics {
+    pub text: String,
+    pub width: f32,
+    pub baseline: f32,
+    pub glyphs: Vec<GlyphMetrics>,
+}
+
+/// Everything `measure` reports; lengths are in mm, in layout coordinates
+/// (y up, the first baseline at y = ascent, before any centering)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Measurement {
+    pub em_size: f32,
+    pub ascent: f32,
+    /// Below the baseline, so usually negative
+    pub descent: f32,
+    pub line_height: f32,
+    pub lines: Vec<LineMetrics>,
+    /// Ink bounding box (min_x, max_x, min_y, max_y), `None` when nothing has an outline
+    pub bounds: Option<(f32, f32, f32, f32)>,
+    pub missing: Vec<char>,
+}
+
+/// Lay out `options.text` and collect its metrics
+pub fn measure(face: &Face<'_>, options: &Options) -> Measurement {
+    let scale = options.scale(face);
+    let fallback_data = options.fallback_data();
+    let fallbacks = font::fallback_faces(&fallback_data);
+    let layout = layout(face, options);
+    let mut placed = layout.glyphs.iter();
+    let lines = layout
+        .lines
+        .iter()
+        .map(|line| {
+            let glyphs = placed
+                .by_ref()
+                .take(line.glyph_count)
+                .map(|glyph| {
+                    let (glyph_face, units) = glyph.face(face, &fallbacks);
+                    GlyphMetrics {
+                        ch: glyph.ch,
+                        gid: glyph.gid.0,
+                        x: glyph.x,
+                        advance: hor_advance(glyph_face, glyph.gid).unwrap_or(0) as f32
+                            * scale
+                            * units,
+                    }
+                })
+                .collect();
+            LineMetrics {
+                text: line.text.clone(),
+                width: line.width,
+                baseline: line.baseline,
+                glyphs,
+            }
+        })
+        .collect();
+    Measurement {
+        em_size: options.em_size(face),
+        ascent: face.ascender() as f32 * scale,
+        descent: face.descender() as f32 * scale,
+        line_height: options.line_height.resolve(face.height() as f32 * scale),
+        lines,
+        bounds: dry_run::layout_bounds(face, &fallbacks, &layout, scale, options.shear()),
+        missing: layout.missing,
+    }
+}
+
+/// f32 lengths as JSON numbers without the f64 widening noise (0.1 µm steps)
+pub(crate) fn mm(value: f32) -> f64 {
+    (value as f64 * 1e4).round() / 1e4
+}
+
+impl Measurement {
+    pub fn to_json(&self) -> serde_json::Value {
+        let lines: Vec<_> = self
+            .lines
+            .iter()
+            .map(|line| {
+                let glyphs: Vec<_> = line
+                    .glyphs
+                    .iter()
+                    .map(|g| {
+                        json!({
+                            "char": g.ch.to_string(),
+                            "gid": g.gid,
+                            "x": mm(g.x),
+                            "advance": mm(g.advance),
+                        })
+                    })
+                    .collect();
+                json!({
+                    "text": line.text,
+                    "width": mm(line.width),
+                    "baseline": mm(line.baseline),
+                    "glyphs": glyphs,
+                })
+            })
+            .collect();
+        let bounds = self.bounds.map(|(min_x, max_x, min_y, max_y)| {
+            json!({
+                "min_x": mm(min_x),
+                "max_x": mm(max_x),
+                "min_y": mm(min_y),
+                "max_y": mm(max_y),
+                "width": mm(max_x - min_x),
+                "height": mm(max_y - min_y),
+            })
+        });
+        let missing: Vec<String> = self.missing.iter().map(char::to_string).collect();
+        json!({
+            "units": "mm",
+            "em_size": mm(self.em_size),
+            "ascent": mm(self.ascent),
+            "descent": mm(self.descent),
+            "line_height": mm(self.line_height),
+            "lines": lines,
+            "bounds": bounds,
+            "missing": missing,
+        })
+    }
+
+    /// Human-readable report
+    pub fn write_text<W: Write>(&self, mut out: W) -> Result<()> {
+        writeln!(
+            out,
+            "em size {:.3} mm, ascent {:.3}, descent {:.3}, line height {:.3}",
+            self.em_size, self.ascent, self.descent, self.line_height
+        )?;
+        match self.bounds {
+            Some((min_x, max_x, min_y, max_y)) => writeln!(
+                out,
+                "bounding box: {:.3} x {:.3} mm (x {:.3}..{:.3}, y {:.3}..{:.3})",
+                max_x - min_x,
+                max_y - min_y,
+                min_x,
+                max_x,
+                min_y,
+                max_y
+            )?,
+            None => writeln!(out, "bounding box: empty (no outlines)")?,
+        }
+        for (i, line) in self.lines.iter().enumerate() {
+            writeln!(
+                out,
+                "line {}: {:?} width {:.3} baseline {:.3}",
+                i + 1,
+                line.text,
+                line.width,
+                line.baseline
+            )?;
+            for glyph in &line.glyphs {
+                writeln!(
+                    out,
+                    "  {:?} gid {} x {:.3} advance {:.3}",
+                    glyph.ch, glyph.gid, glyph.x, glyph.advance
+                )?;
+            }
+        }
+        if !self.missing.is_empty() {
+            let missing: Vec<String> = self.missing.iter().map(|c| format!("'{}'", c)).collect();
+            writeln!(out, "missing glyphs: {}", missing.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font;
+
+    #[test]
+    fn glyphs_line_up_with_their_characters() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let options = Options {
+            text: "AV\n\u{13000}i".into(),
+            size: 40.0,
+            ..Default::default()
+        };
+        let m = measure(&face, &options);
+        assert_eq!(m.em_size, 40.0);
+        assert!(m.ascent > 0.0 && m.descent < 0.0);
+        assert_eq!(m.lines.len(), 2);
+        assert_eq!(m.lines[0].glyphs.len(), 2);
+        assert_eq!(m.lines[1].glyphs[0].ch, 'i');
+        assert_eq!(m.missing, ['\u{13000}']);
+
+        let first = &m.lines[0].glyphs;
+        assert_eq!(first[0].x, 0.0);
+        assert!(first[0].advance > 0.0);
+        assert!((m.lines[1].baseline - (m.lines[0].baseline - m.line_height)).abs() < 1e-4);
+
+        let json = m.to_json();
+        assert_eq!(json["lines"][0]["glyphs"][1]["char"], "V");
+        assert!(json["bounds"]["width"].as_f64().unwrap() > 0.0);
+    }
+}
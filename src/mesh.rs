@@ -0,0 +1,587 @@
+//! 2D tessellation and extrusion into triangles
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use lyon_path::math::Point;
+use lyon_path::Path;
+use lyon_tessellation::geometry_builder::{
+    FillGeometryBuilder, GeometryBuilder, GeometryBuilderError, VertexBuffers,
+};
+use lyon_tessellation::{
+    BuffersBuilder, FillOptions, FillRule, FillTessellator, FillVertex, VertexId,
+};
+use stl_io::Triangle;
+
+use crate::cancel::CancelToken;
+
+/// Plane orientation (flat: XY floor, front: XZ facing viewer)
+#[derive(clap::ValueEnum, Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Orientation {
+    Flat,
+    Front,
+}
+
+/// Point of the text placed on the origin when centering
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum Anchor {
+    /// Middle of the bounding box
+    #[default]
+    Center,
+    /// Start of the first line on its baseline (top of the first column
+    /// in vertical text)
+    BaselineLeft,
+    /// Top-left corner of the bounding box
+    TopLeft,
+    /// Middle of the bottom edge of the bounding box
+    BottomCenter,
+}
+
+impl Anchor {
+    /// Translation that brings this point of `bounds` (min x, max x, min y,
+    /// max y) to the origin. `BaselineLeft` needs none: the layout already
+    /// starts there (see [`crate::layout()`]).
+    pub fn offset(self, bounds: (f32, f32, f32, f32)) -> (f32, f32) {
+        let (min_x, max_x, min_y, max_y) = bounds;
+        match self {
+            Anchor::Center => (-(min_x + max_x) * 0.5, -(min_y + max_y) * 0.5),
+            Anchor::BaselineLeft => (0.0, 0.0),
+            Anchor::TopLeft => (-min_x, -max_y),
+            Anchor::BottomCenter => (-(min_x + max_x) * 0.5, -min_y),
+        }
+    }
+}
+
+/// Tessellated 2D triangles (indexed) before extrusion
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mesh2D {
+    pub vertices: Vec<Point>,
+    pub indices: Vec<u16>,
+}
+
+pub fn center_mesh_xy(mesh: &mut Mesh2D) {
+    let mut min_x = f32::MAX;
+    let mut max_x = f32::MIN;
+    let mut min_y = f32::MAX;
+    let mut max_y = f32::MIN;
+
+    for p in &mesh.vertices {
+        min_x = min_x.min(p.x);
+        max_x = max_x.max(p.x);
+        min_y = min_y.min(p.y);
+        max_y = max_y.max(p.y);
+    }
+
+    let cx = (min_x + max_x) * 0.5;
+    let cy = (min_y + max_y) * 0.5;
+
+    for p in &mut mesh.vertices {
+        p.x -= cx;
+        p.y -= cy;
+    }
+}
+
+pub fn translate_mesh(mesh: &mut Mesh2D, dx: f32, dy: f32) {
+    for p in &mut mesh.vertices {
+        p.x += dx;
+        p.y += dy;
+    }
+}
+
+pub fn scale_mesh(mesh: &mut Mesh2D, factor: f32) {
+    for p in &mut mesh.vertices {
+        p.x *= factor;
+        p.y *= factor;
+    }
+}
+
+pub fn mesh_bounds(mesh: &Mesh2D) -> Option<(f32, f32, f32, f32)> {
+    if mesh.vertices.is_empty() {
+        return None;
+    }
+
+    let mut min_x = f32::MAX;
+    let mut max_x = f32::MIN;
+    let mut min_y = f32::MAX;
+    let mut max_y = f32::MIN;
+
+    for p in &mesh.vertices {
+        min_x = min_x.min(p.x);
+        max_x = max_x.max(p.x);
+        min_y = min_y.min(p.y);
+        max_y = max_y.max(p.y);
+    }
+
+    Some((min_x, max_x, min_y, max_y))
+}
+
+pub fn rectangle_mesh(min_x: f32, max_x: f32, min_y: f32, max_y: f32) -> Mesh2D {
+    Mesh2D {
+        vertices: vec![
+            Point::new(min_x, min_y),
+            Point::new(max_x, min_y),
+            Point::new(max_x, max_y),
+            Point::new(min_x, max_y),
+        ],
+        indices: vec![0u16, 1, 2, 0, 2, 3],
+    }
+}
+
+pub fn tessellate_path(path: &Path, tolerance: f32) -> Result<Mesh2D> {
+    tessellate_path_with_cancel(path, tolerance, &CancelToken::new())
+}
+
+/// Vertices between deadline checks while tessellating
+const CANCEL_CHECK_INTERVAL: u32 = 256;
+
+/// Wraps a fill builder and aborts the tessellation once the token fires
+struct CancellableBuilder<'a, B> {
+    inner: B,
+    cancel: &'a CancelToken,
+    vertices: u32,
+}
+
+impl<B: GeometryBuilder> GeometryBuilder for CancellableBuilder<'_, B> {
+    fn begin_geometry(&mut self) {
+        self.inner.begin_geometry();
+    }
+
+    fn end_geometry(&mut self) {
+        self.inner.end_geometry();
+    }
+
+    fn add_triangle(&mut self, a: VertexId, b: VertexId, c: VertexId) {
+        self.inner.add_triangle(a, b, c);
+    }
+
+    fn abort_geometry(&mut self) {
+        self.inner.abort_geometry();
+    }
+}
+
+impl<B: FillGeometryBuilder> FillGeometryBuilder for CancellableBuilder<'_, B> {
+    fn add_fill_vertex(&mut self, vertex: FillVertex) -> Result<VertexId, GeometryBuilderError> {
+        self.vertices += 1;
+        if self.vertices.is_multiple_of(CANCEL_CHECK_INTERVAL) && self.cancel.is_cancelled() {
+            return Err(GeometryBuilderError::InvalidVertex);
+        }
+        self.inner.add_fill_vertex(vertex)
+    }
+}
+
+/// Tessellate, checking the cancel token periodically so runaway jobs can be aborted
+pub fn tessellate_path_with_cancel(
+    path: &Path,
+    tolerance: f32,
+    cancel: &CancelToken,
+) -> Result<Mesh2D> {
+    cancel.check()?;
+
+    let mut buffers: VertexBuffers<Point, u16> = VertexBuffers::new();
+    let mut tess = FillTessellator::new();
+    let result = tess.tessellate_path(
+        path,
+        &FillOptions::default()
+            .with_fill_rule(FillRule::NonZero)
+            .with_tolerance(tolerance),
+        &mut CancellableBuilder {
+            inner: BuffersBuilder::new(&mut buffers, |v: FillVertex| v.position()),
+            cancel,
+            vertices: 0,
+        },
+    );
+    cancel.check()?;
+    result.context("failed to tessellate polygon")?;
+
+    let mut mesh = Mesh2D {
+        vertices: buffers.vertices,
+        indices: buffers.indices,
+    };
+    make_counter_clockwise(&mut mesh);
+    Ok(mesh)
+}
+
+/// Lyon emits triangles clockwise in our Y-up space; extrusion expects
+/// counter-clockwise ones so cap and side normals point outward. Zero-area
+/// triangles (lyon joins collinear edges with them) are flipped too, or
+/// their edges would not pair up with their neighbours'
+fn make_counter_clockwise(mesh: &mut Mesh2D) {
+    for tri in mesh.indices.chunks_mut(3) {
+        let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| mesh.vertices[i as usize]);
+        if (b - a).cross(c - a) <= 0.0 {
+            tri.swap(1, 2);
+        }
+    }
+}
+
+/// Lazy extrusion of a 2D mesh: top faces, bottom faces, then side walls.
+/// Triangles are produced on demand so callers can stream them.
+pub struct Extrusion<'a> {
+    mesh: Cow<'a, Mesh2D>,
+    /// Top cap vertices when they differ from the bottom (chamfer)
+    top: Option<Vec<Point>>,
+    edges: Vec<(u16, u16)>,
+    orient: Orientation,
+    z0: f32,
+    z1: f32,
+    next: usize,
+}
+
+impl<'a> Extrusion<'a> {
+    pub fn new(mesh: Cow<'a, Mesh2D>, depth: f32, orient: Orientation, z_offset: f32) -> Self {
+        let edges = boundary_edges(&mesh.indices);
+        Self {
+            mesh,
+            top: None,
+            edges,
+            orient,
+            z0: -depth * 0.5 + z_offset,
+            z1: depth * 0.5 + z_offset,
+            next: 0,
+        }
+    }
+
+    /// Slope the side walls: the top outline sits `inset` inside the bottom one
+    pub fn with_chamfer(mut self, inset: f32) -> Self {
+        self.top = (inset > 0.0).then(|| inset_boundary(&self.mesh, inset));
+        self
+    }
+
+    fn top_vertex(&self, i: u16) -> Point {
+        self.top.as_ref().unwrap_or(&self.mesh.vertices)[i as usize]
+    }
+
+    fn face_count(&self) -> usize {
+        self.mesh.indices.len() / 3
+    }
+
+    /// Total number of triangles this extrusion yields
+    pub fn triangle_count(&self) -> usize {
+        self.face_count() * 2 + self.edges.len() * 2
+    }
+
+    fn triangle(&self, i: usize) -> Triangle {
+        let (mesh, orient) = (&self.mesh, &self.orient);
+        let faces = self.face_count();
+
+        if i < faces * 2 {
+            let idx = &mesh.indices[(i % faces) * 3..(i % faces) * 3 + 3];
+            return if i < faces {
+                // Top face
+                triangle_with_normal(
+                    map_point(self.top_vertex(idx[0]), self.z1, orient),
+                    map_point(self.top_vertex(idx[1]), self.z1, orient),
+                    map_point(self.top_vertex(idx[2]), self.z1, orient),
+                )
+            } else {
+                let a = mesh.vertices[idx[0] as usize];
+                let b = mesh.vertices[idx[1] as usize];
+                let c = mesh.vertices[idx[2] as usize];
+                // Bottom face (reverse winding so normal points down)
+                triangle_with_normal(
+                    map_point(c, self.z0, orient),
+                    map_point(b, self.z0, orient),
+                    map_point(a, self.z0, orient),
+                )
+            };
+        }
+
+        // Side faces: boundary edge quads -> two triangles
+        let side = i - faces * 2;
+        let (i0, i1) = self.edges[side / 2];
+        let top0 = map_point(self.top_vertex(i0), self.z1, orient);
+        let bot1 = map_point(mesh.vertices[i1 as usize], self.z0, orient);
+        if side.is_multiple_of(2) {
+            let top1 = map_point(self.top_vertex(i1), self.z1, orient);
+            triangle_with_normal(bot1, top1, top0)
+        } else {
+            let bot0 = map_point(mesh.vertices[i0 as usize], self.z0, orient);
+            triangle_with_normal(bot0, bot1, top0)
+        }
+    }
+}
+
+impl Iterator for Extrusion<'_> {
+    type Item = Triangle;
+
+    fn next(&mut self) -> Option<Triangle> {
+        if self.next >= self.triangle_count() {
+            return None;
+        }
+        let tri = self.triangle(self.next);
+        self.next += 1;
+        Some(tri)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.triangle_count().saturating_sub(self.next);
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for Extrusion<'_> {}
+
+pub fn extrude_mesh_with_offset(
+    mesh: &Mesh2D,
+    depth: f32,
+    orient: Orientation,
+    z_offset: f32,
+) -> Vec<Triangle> {
+    Extrusion::new(Cow::Borrowed(mesh), depth, orient, z_offset).collect()
+}
+
+pub fn extrude_mesh(mesh: &Mesh2D, depth: f32, orient: Orientation) -> Vec<Triangle> {
+    extrude_mesh_with_offset(mesh, depth, orient, 0.0)
+}
+
+/// Vertex positions with the outline moved `inset` into the filled area
+/// (outer contours shrink, holes grow); interior vertices stay put
+pub fn inset_boundary(mesh: &Mesh2D, inset: f32) -> Vec<Point> {
+    let mut vertices = mesh.vertices.clone();
+    let mut incoming: HashMap<u16, u16> = HashMap::new();
+    let mut outgoing: HashMap<u16, u16> = HashMap::new();
+    for (a, b) in boundary_edges(&mesh.indices) {
+        outgoing.entry(a).or_insert(b);
+        incoming.entry(b).or_insert(a);
+    }
+    // The filled side is to the left of counter-clockwise boundary edges
+    let left = |from: u16, to: u16| {
+        let d = mesh.vertices[to as usize] - mesh.vertices[from as usize];
+        lyon_path::math::vector(-d.y, d.x).normalize()
+    };
+    for (&v, &next) in &outgoing {
+        let Some(&prev) = incoming.get(&v) else {
+            continue;
+        };
+        let (n_in, n_out) = (left(prev, v), left(v, next));
+        let sum = n_in + n_out;
+        if sum.square_length() < 1e-12 {
+            continue;
+        }
+        let dir = sum.normalize();
+        // Miter length, limited at sharp corners
+        let cos = dir.dot(n_in).max(0.25);
+        vertices[v as usize] = mesh.vertices[v as usize] + dir * (inset / cos);
+    }
+    vertices
+}
+
+/// Return boundary edges (true = edge orientation matches triangle winding)
+pub fn boundary_edges(indices: &[u16]) -> Vec<(u16, u16)> {
+    let mut counts: HashMap<(u16, u16), u32> = HashMap::new();
+    let mut oriented: HashMap<(u16, u16), (u16, u16)> = HashMap::new();
+
+    for tri in indices.chunks(3) {
+        let edges = [(tri[0], tri[1]), (tri[1], tri[2]), (tri[2], tri[0])];
+        for &(a, b) in &edges {
+            let key = if a < b { (a, b) } else { (b, a) };
+            *counts.entry(key).or_insert(0) += 1;
+            oriented.entry(key).or_insert((a, b));
+        }
+    }
+
+    counts
+        .into_iter()
+        .filter(|(_, cnt)| *cnt == 1)
+        .map(|(k, _)| oriented[&k])
+        .collect()
+}
+
+/// Chain the boundary edges into closed outlines. With counter-clockwise
+/// triangles, outer contours run counter-clockwise and holes clockwise.
+pub fn boundary_loops(mesh: &Mesh2D) -> Vec<Vec<Point>> {
+    let mut next: HashMap<u16, Vec<u16>> = HashMap::new();
+    for (a, b) in boundary_edges(&mesh.indices) {
+        next.entry(a).or_default().push(b);
+    }
+
+    let mut starts: Vec<u16> = next.keys().copied().collect();
+    starts.sort_unstable();
+    let mut loops = Vec::new();
+    for start in starts {
+        while let Some(mut current) = next.get_mut(&start).and_then(Vec::pop) {
+            let mut outline = vec![mesh.vertices[start as usize]];
+            while current != start {
+                outline.push(mesh.vertices[current as usize]);
+                match next.get_mut(&current).and_then(Vec::pop) {
+                    Some(v) => current = v,
+                    None => break,
+                }
+            }
+            if outline.len() >= 3 {
+                loops.push(outline);
+            }
+        }
+    }
+    loops
+}
+
+pub fn triangle_with_normal(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> Triangle {
+    Triangle {
+        normal: calc_normal(a, b, c),
+        vertices: [a, b, c],
+    }
+}
+
+pub fn calc_normal(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> [f32; 3] {
+    let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+    let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+    let n = [
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ];
+    let len = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+    if len == 0.0 {
+        [0.0, 0.0, 0.0]
+    } else {
+        [n[0] / len, n[1] / len, n[2] / len]
+    }
+}
+
+pub fn map_point(p: Point, z: f32, orient: &Orientation) -> [f32; 3] {
+    match orient {
+        Orientation::Flat => [p.x, p.y, z],
+        // Front orientation: keep X, rotate +Z to up, +Y faces viewer
+        // (original +Z normals become +Y; text keeps its vertical sense)
+        Orientation::Front => [p.x, -z, p.y],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boundary_edges_filters_shared_edges() {
+        let indices = vec![0u16, 1, 2, 2, 1, 3];
+        let edges: std::collections::HashSet<(u16, u16)> =
+            boundary_edges(&indices).into_iter().collect();
+
+        let expected: std::collections::HashSet<(u16, u16)> =
+            [(0, 1), (2, 0), (3, 2), (1, 3)].into_iter().collect();
+
+        assert_eq!(edges, expected);
+    }
+
+    #[test]
+    fn calc_normal_returns_unit_z_for_xy_triangles() {
+        let n = calc_normal([0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]);
+        assert!((n[0]).abs() < 1e-6);
+        assert!((n[1]).abs() < 1e-6);
+        assert!((n[2] - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn calc_normal_handles_degenerate_triangles() {
+        let n = calc_normal([0.0, 0.0, 0.0], [1.0, 1.0, 1.0], [2.0, 2.0, 2.0]);
+        assert_eq!(n, [0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn extrusion_yields_caps_and_side_walls() {
+        let square = rectangle_mesh(0.0, 1.0, 0.0, 1.0);
+        let extrusion = Extrusion::new(Cow::Owned(square), 2.0, Orientation::Flat, 0.0);
+        // 2 top + 2 bottom + 4 edges * 2
+        assert_eq!(extrusion.len(), 12);
+
+        let tris: Vec<Triangle> = extrusion.collect();
+        assert!(tris[..2].iter().all(|t| t.normal == [0.0, 0.0, 1.0]));
+        assert!(tris[2..4].iter().all(|t| t.normal == [0.0, 0.0, -1.0]));
+        assert!(tris[4..].iter().all(|t| t.normal[2] == 0.0));
+
+        // Side walls face away from the square's center
+        for t in &tris[4..] {
+            let mid = t
+                .vertices
+                .iter()
+                .fold([0.0; 2], |m, v| [m[0] + v[0], m[1] + v[1]]);
+            let outward = [mid[0] / 3.0 - 0.5, mid[1] / 3.0 - 0.5];
+            assert!(t.normal[0] * outward[0] + t.normal[1] * outward[1] > 0.0);
+        }
+    }
+
+    #[test]
+    fn collinear_joins_keep_every_edge_paired() {
+        // A stake under a wider tag: lyon bridges the shared edge with zero-area triangles
+        let mut builder = Path::builder();
+        builder.add_rounded_rectangle(
+            &lyon_path::math::Box2D::new(Point::new(-15.0, -8.0), Point::new(15.0, 8.0)),
+            &lyon_path::builder::BorderRadii::new(3.0),
+            lyon_path::Winding::Positive,
+        );
+        let stake = [
+            (-5.0, -5.0),
+            (-5.0, -90.0),
+            (0.0, -100.0),
+            (5.0, -90.0),
+            (5.0, -5.0),
+        ];
+        builder.add_polygon(lyon_path::Polygon {
+            points: &stake.map(|(x, y)| Point::new(x, y)),
+            closed: true,
+        });
+        let mesh = tessellate_path(&builder.build(), 0.01).unwrap();
+
+        // Each directed edge at most once, so inner edges pair up with their reverse
+        let mut edges = std::collections::HashSet::new();
+        for t in mesh.indices.chunks(3) {
+            for edge in [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])] {
+                assert!(edges.insert(edge), "edge {:?} repeated", edge);
+            }
+        }
+        assert_eq!(boundary_loops(&mesh).len(), 1);
+    }
+
+    #[test]
+    fn chamfer_insets_the_top_and_tilts_the_walls_up() {
+        let square = rectangle_mesh(0.0, 4.0, 0.0, 4.0);
+        for p in inset_boundary(&square, 1.0) {
+            assert!(((p.x - 2.0).abs() - 1.0).abs() < 1e-5);
+            assert!(((p.y - 2.0).abs() - 1.0).abs() < 1e-5);
+        }
+
+        let tris: Vec<Triangle> = Extrusion::new(Cow::Owned(square), 1.0, Orientation::Flat, 0.0)
+            .with_chamfer(1.0)
+            .collect();
+        // 45° walls
+        assert!(tris[4..]
+            .iter()
+            .all(|t| (t.normal[2] - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-5));
+    }
+
+    #[test]
+    fn boundary_loops_trace_outlines() {
+        let square = rectangle_mesh(0.0, 1.0, 0.0, 1.0);
+        let loops = boundary_loops(&square);
+        assert_eq!(loops.len(), 1);
+        assert_eq!(loops[0].len(), 4);
+    }
+
+    #[test]
+    fn tessellated_triangles_are_counter_clockwise() {
+        let mut builder = Path::builder();
+        builder.begin(Point::new(0.0, 0.0));
+        builder.line_to(Point::new(0.0, 1.0));
+        builder.line_to(Point::new(1.0, 1.0));
+        builder.line_to(Point::new(1.0, 0.0));
+        builder.end(true);
+        let mesh = tessellate_path(&builder.build(), 0.01).unwrap();
+        for tri in mesh.indices.chunks(3) {
+            let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| mesh.vertices[i as usize]);
+            assert!((b - a).cross(c - a) > 0.0);
+        }
+    }
+}
@@ -0,0 +1,252 @@
+//! Generation metadata embedded in output files (source text, font, version,
+//! options and reproducibility hashes)
+
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use ttf_parser::name_id;
+use ttf_parser::Face;
+
+use crate::i18n::Message;
+use crate::output::{self, OverwritePolicy};
+use crate::Options;
+
+/// Size of the binary STL header
+pub const STL_HEADER_LEN: usize = 80;
+
+/// What produced a file, so settings can be traced back later
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenerationMetadata {
+    pub text: String,
+    /// Model title, the text unless set with [`Self::with_title`]
+    pub title: String,
+    pub font_name: String,
+    /// The font's own version string (name table), "unknown" when missing
+    pub font_version: String,
+    pub version: String,
+    /// FNV-1a hash of the generation options, as 16 hex digits
+    pub options_hash: String,
+    /// [`build_hash`] as 16 hex digits: equal on any machine for identical
+    /// inputs, so artifacts can be checked against each other
+    pub build_hash: String,
+}
+
+impl GenerationMetadata {
+    pub fn new(face: &Face<'_>, options: &Options) -> Self {
+        Self {
+            text: options.text.clone(),
+            title: options.text.clone(),
+            font_name: font_name(face).unwrap_or_else(|| "unknown".to_string()),
+            font_version: font_version(face).unwrap_or_else(|| "unknown".to_string()),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            options_hash: format!("{:016x}", options_hash(options)),
+            build_hash: format!("{:016x}", build_hash(face, options)),
+        }
+    }
+
+    /// Name the model something other than its text
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    /// Key/value pairs for formats with a metadata table (e.g. 3MF)
+    pub fn entries(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("Application", format!("wagyan {}", self.version)),
+            ("Title", self.title.clone()),
+            ("wagyan:Text", self.text.clone()),
+            ("wagyan:Font", self.font_name.clone()),
+            ("wagyan:FontVersion", self.font_version.clone()),
+            ("wagyan:Version", self.version.clone()),
+            ("wagyan:OptionsHash", self.options_hash.clone()),
+            ("wagyan:BuildHash", self.build_hash.clone()),
+        ]
+    }
+
+    /// Write [`Self::entries`] as a JSON object to `path`, a sidecar for
+    /// formats without a metadata table, under the overwrite `policy`
+    pub fn write_sidecar(&self, path: &Path, policy: OverwritePolicy) -> Result<()> {
+        let object: serde_json::Map<String, serde_json::Value> = self
+            .entries()
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.into()))
+            .collect();
+        let json = serde_json::to_string_pretty(&object)?;
+        output::write_with_policy(path, policy, |file| {
+            Ok(file.write_all((json + "\n").as_bytes())?)
+        })
+        .with_context(|| Message::WriteFailed("metadata", path).to_string())
+    }
+
+    /// Binary STL header. It must not start with "solid" (readers would take
+    /// the file for ASCII STL) and is truncated to 80 bytes on a char boundary.
+    pub fn stl_header(&self) -> [u8; STL_HEADER_LEN] {
+        let text = format!(
+            "wagyan {} opts={} font={} text={}",
+            self.version,
+            self.options_hash,
+            self.font_name,
+            self.text.replace('\n', "\\n")
+        );
+        let mut end = text.len().min(STL_HEADER_LEN);
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        let mut header = [0u8; STL_HEADER_LEN];
+        header[..end].copy_from_slice(&text.as_bytes()[..end]);
+        header
+    }
+}
+
+/// Full font name (falls back to the family name)
+pub fn font_name(face: &Face<'_>) -> Option<String> {
+    [name_id::FULL_NAME, name_id::FAMILY]
+        .iter()
+        .find_map(|&id| name_string(face, id))
+}
+
+/// Version string from the name table, e.g. "Version 2.004"
+pub fn font_version(face: &Face<'_>) -> Option<String> {
+    name_string(face, name_id::VERSION)
+}
+
+/// First Unicode string with name ID `id` in the name table
+pub(crate) fn name_string(face: &Face<'_>, id: u16) -> Option<String> {
+    face.names()
+        .into_iter()
+        .filter(|name| name.name_id == id && name.is_unicode())
+        .find_map(|name| name.to_string())
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// Stable 64-bit FNV-1a hash (std's `DefaultHasher` may change between releases)
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    fnv1a_extend(FNV_OFFSET_BASIS, bytes)
+}
+
+/// Continue an FNV-1a hash with more bytes
+fn fnv1a_extend(mut hash: u64, bytes: &[u8]) -> u64 {
+    for &b in bytes {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+/// Hash of the options that shape the geometry
+pub fn options_hash(options: &Options) -> u64 {
+    fnv1a(format!("{:?}", options).as_bytes())
+}
+
+/// Hash of everything that determines the output: the options, the bytes
+/// of the font and its fallbacks, the path the text is laid along and the
+/// wagyan version. File paths are left out, so the same files kept
+/// elsewhere give the same hash.
+pub fn build_hash(face: &Face<'_>, options: &Options) -> u64 {
+    let mut normalized = Options {
+        font: None,
+        fallback_fonts: Vec::new(),
+        path_file: None,
+        ..options.clone()
+    };
+    // Run fonts are hashed with the fallbacks, among which they are loaded
+    for run in &mut normalized.runs {
+        run.style.font = None;
+    }
+    let inputs = format!(
+        "{:?}\n{:?}\n{}\n",
+        normalized,
+        options.text_path(),
+        env!("CARGO_PKG_VERSION")
+    );
+    let hash = fnv1a_extend(fnv1a(inputs.as_bytes()), face.raw_face().data);
+    options
+        .fallback_data()
+        .iter()
+        .fold(hash, |hash, fallback| fnv1a_extend(hash, fallback))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> GenerationMetadata {
+        GenerationMetadata {
+            text: "こんにちは世界".repeat(10),
+            title: "こんにちは世界".repeat(10),
+            font_name: "Noto Sans JP Regular".into(),
+            font_version: "Version 2.004".into(),
+            version: "0.1.2".into(),
+            options_hash: "0123456789abcdef".into(),
+            build_hash: "fedcba9876543210".into(),
+        }
+    }
+
+    #[test]
+    fn stl_header_is_truncated_on_char_boundary() {
+        let header = sample().stl_header();
+        assert!(!header.starts_with(b"solid"));
+        let end = header
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(STL_HEADER_LEN);
+        assert!(std::str::from_utf8(&header[..end]).is_ok());
+    }
+
+    #[test]
+    fn options_hash_changes_with_options() {
+        let a = Options::default();
+        let b = Options {
+            depth: 3.0,
+            ..Default::default()
+        };
+        assert_eq!(options_hash(&a), options_hash(&a.clone()));
+        assert_ne!(options_hash(&a), options_hash(&b));
+    }
+
+    #[test]
+    fn build_hash_ignores_the_font_path_but_not_the_font() {
+        let face = crate::font::parse_face(crate::font::EMBEDDED_FONT, 0).unwrap();
+        let a = Options::default();
+        let moved = Options {
+            font: Some("/elsewhere/NotoSansJP-Regular.otf".into()),
+            ..Default::default()
+        };
+        assert_eq!(build_hash(&face, &a), build_hash(&face, &moved));
+
+        let mut bytes = crate::font::EMBEDDED_FONT.to_vec();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        let edited = crate::font::parse_face(&bytes, 0).unwrap();
+        assert_ne!(build_hash(&face, &a), build_hash(&edited, &a));
+    }
+
+    #[test]
+    fn build_hash_follows_the_path_file_content_not_its_location() {
+        let face = crate::font::parse_face(crate::font::EMBEDDED_FONT, 0).unwrap();
+        let dir = std::env::temp_dir().join(format!("wagyan-path-hash-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let svg = |name: &str, d: &str| {
+            let path = dir.join(name);
+            let doc = format!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg"><path d="{}" stroke="black"/></svg>"#,
+                d
+            );
+            std::fs::write(&path, doc).unwrap();
+            Options {
+                path_file: Some(path),
+                ..Default::default()
+            }
+        };
+        let a = svg("a.svg", "M0 0 L100 0");
+        let moved = svg("b.svg", "M0 0 L100 0");
+        assert_eq!(build_hash(&face, &a), build_hash(&face, &moved));
+        let edited = svg("a.svg", "M0 0 L100 50");
+        assert_ne!(build_hash(&face, &moved), build_hash(&face, &edited));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,130 @@
+//! Scale-model label preset: tiny room or building names on a thin backing
+//! strip, with strokes thickened to what a resin printer can resolve
+
+use anyhow::{Context, Result};
+use ttf_parser::Face;
+
+use crate::cancel::CancelToken;
+use crate::mesh;
+use crate::{build_path, no_progress, outline, printer, Options, Part};
+
+/// Layer height the defaults are tuned for, in mm
+pub const RESIN_LAYER_HEIGHT: f32 = 0.05;
+
+/// Model label settings; the lettering itself comes from [`Options`]
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct ModelLabel {
+    /// Backing strip thickness in mm
+    pub thickness: f32,
+    /// Strip width around the text in mm
+    pub margin: f32,
+    /// Thinnest letter stroke in mm; thinner strokes are grown to this
+    pub min_stroke: f32,
+}
+
+impl Default for ModelLabel {
+    fn default() -> Self {
+        Self {
+            thickness: 0.5,
+            margin: 0.5,
+            min_stroke: 0.3,
+        }
+    }
+}
+
+impl ModelLabel {
+    /// How far each side of a stroke is pushed out, in mm (0 when the
+    /// font's stems are already thick enough at this size)
+    pub fn thickening(&self, face: &Face<'_>, options: &Options) -> f32 {
+        printer::stem_width(face)
+            .map(|stem| stem * options.scale(face))
+            .map_or(0.0, |stem| ((self.min_stroke - stem) * 0.5).max(0.0))
+    }
+}
+
+/// Build the backing strip and the (thickened) lettering
+pub fn model_label_parts(
+    face: &Face<'_>,
+    options: &Options,
+    label: &ModelLabel,
+    cancel: &CancelToken,
+) -> Result<Vec<Part>> {
+    let tolerance = options.tolerance(face);
+    let path = build_path(face, options, &no_progress);
+    let grow = label.thickening(face, options);
+    let mut text = if grow > 0.0 {
+        let letters = outline::from_path(&path, tolerance);
+        outline::fill(
+            &outline::offset(&letters, grow, tolerance),
+            tolerance,
+            cancel,
+        )?
+    } else {
+        mesh::tessellate_path_with_cancel(&path, tolerance, cancel)?
+    };
+    let (min_x, max_x, min_y, max_y) =
+        mesh::mesh_bounds(&text).context("the text has no visible glyphs")?;
+    mesh::translate_mesh(&mut text, -(min_x + max_x) * 0.5, -(min_y + max_y) * 0.5);
+    let half_w = (max_x - min_x) * 0.5 + label.margin;
+    let half_h = (max_y - min_y) * 0.5 + label.margin;
+
+    let depth = options.text_depth();
+    let thickness = options.snap_to_layers(label.thickness);
+    Ok(vec![
+        Part {
+            name: "backing".to_string(),
+            mesh: mesh::rectangle_mesh(-half_w, half_w, -half_h, half_h),
+            depth: thickness,
+            z_offset: -(depth * 0.5 + thickness * 0.5),
+            letter: None,
+            chamfer: 0.0,
+            color: None,
+        },
+        Part {
+            name: "text".to_string(),
+            mesh: text,
+            depth,
+            z_offset: 0.0,
+            letter: None,
+            chamfer: 0.0,
+            color: None,
+        },
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font;
+    use crate::SizeTarget;
+
+    #[test]
+    fn thin_strokes_are_grown_to_the_minimum() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let mut options = Options {
+            text: "I".into(),
+            size_target: Some(SizeTarget::CapHeight(2.0)),
+            depth: 0.3,
+            ..Default::default()
+        };
+        let label = ModelLabel::default();
+        let grow = label.thickening(&face, &options);
+        assert!(grow > 0.0);
+
+        // The single stem of an 'I' comes out exactly min_stroke wide
+        let parts = model_label_parts(&face, &options, &label, &CancelToken::new()).unwrap();
+        let (min_x, max_x, ..) = mesh::mesh_bounds(&parts[1].mesh).unwrap();
+        assert!((max_x - min_x - label.min_stroke).abs() < 0.01);
+        let (strip_min_x, strip_max_x, ..) = mesh::mesh_bounds(&parts[0].mesh).unwrap();
+        assert!((strip_max_x - strip_min_x - label.min_stroke - 2.0 * label.margin).abs() < 0.01);
+
+        // Large text is left alone
+        options.size_target = Some(SizeTarget::CapHeight(20.0));
+        assert_eq!(label.thickening(&face, &options), 0.0);
+    }
+}
@@ -0,0 +1,330 @@
+//! Nameplate preset: a plate sized around the text, with rounded corners,
+//! a rim, mounting holes and raised or engraved lettering
+
+use anyhow::{Context, Result};
+use lyon_path::builder::BorderRadii;
+use lyon_path::math::{Box2D, Point};
+use lyon_path::path::Builder;
+use lyon_path::{Path, Polygon, Winding};
+use ttf_parser::Face;
+
+use crate::cancel::CancelToken;
+use crate::mesh::{self, Mesh2D};
+use crate::{build_mesh, no_progress, Options, Part};
+
+/// Plate outline
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum NameplateStyle {
+    /// Sharp corners
+    Rect,
+    /// Rounded corners
+    Rounded,
+    /// Fully rounded ends
+    Pill,
+}
+
+/// Nameplate settings; the lettering itself (font, size, relief depth) comes from [`Options`]
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct Nameplate {
+    pub style: NameplateStyle,
+    /// Plate thickness in mm (total thickness when engraving)
+    pub thickness: f32,
+    /// Space between the text and the plate edge in mm
+    pub margin: f32,
+    /// Corner radius for [`NameplateStyle::Rounded`] in mm
+    pub corner_radius: f32,
+    /// Width of the raised border in mm (0 disables; raised lettering only)
+    pub rim: f32,
+    /// Cut the lettering into the plate instead of raising it
+    pub engrave: bool,
+    /// Add a mounting hole on each side of the text
+    pub holes: bool,
+    pub hole_diameter: f32,
+}
+
+impl Default for Nameplate {
+    fn default() -> Self {
+        Self {
+            style: NameplateStyle::Rounded,
+            thickness: 3.0,
+            margin: 6.0,
+            corner_radius: 4.0,
+            rim: 1.6,
+            engrave: false,
+            holes: true,
+            hole_diameter: 4.0,
+        }
+    }
+}
+
+/// Plate geometry in the centered text frame
+pub(crate) struct Outline {
+    pub(crate) half_w: f32,
+    pub(crate) half_h: f32,
+    pub(crate) radius: f32,
+    /// Hole centers and radius
+    pub(crate) holes: Vec<Point>,
+    pub(crate) hole_radius: f32,
+    /// Stadium-shaped slots (fully rounded ends)
+    pub(crate) slots: Vec<Box2D>,
+}
+
+impl Outline {
+    /// Add the plate outline shrunk by `inset`
+    fn add(&self, builder: &mut Builder, inset: f32, winding: Winding) {
+        let rect = Box2D::new(
+            Point::new(-self.half_w + inset, -self.half_h + inset),
+            Point::new(self.half_w - inset, self.half_h - inset),
+        );
+        let radius = (self.radius - inset).max(0.0);
+        builder.add_rounded_rectangle(&rect, &BorderRadii::new(radius), winding);
+    }
+
+    fn add_holes(&self, builder: &mut Builder) {
+        for center in &self.holes {
+            builder.add_circle(*center, self.hole_radius, Winding::Negative);
+        }
+        for slot in &self.slots {
+            let radius = slot.width().min(slot.height()) * 0.5;
+            builder.add_rounded_rectangle(slot, &BorderRadii::new(radius), Winding::Negative);
+        }
+    }
+}
+
+fn fill(tolerance: f32, cancel: &CancelToken, add: impl FnOnce(&mut Builder)) -> Result<Mesh2D> {
+    let mut builder = Path::builder();
+    add(&mut builder);
+    mesh::tessellate_path_with_cancel(&builder.build(), tolerance, cancel)
+}
+
+/// The text mesh centered on the origin, with its half width and height
+pub(crate) fn centered_text(
+    face: &Face<'_>,
+    options: &Options,
+    cancel: &CancelToken,
+) -> Result<(Mesh2D, f32, f32)> {
+    let mut text = build_mesh(face, options, cancel, &no_progress)?;
+    let (min_x, max_x, min_y, max_y) =
+        mesh::mesh_bounds(&text).context("the text has no visible glyphs")?;
+    mesh::translate_mesh(&mut text, -(min_x + max_x) * 0.5, -(min_y + max_y) * 0.5);
+    Ok((text, (max_x - min_x) * 0.5, (max_y - min_y) * 0.5))
+}
+
+/// The plate with its holes, below lettering `depth` deep
+fn plate_part(
+    outline: &Outline,
+    thickness: f32,
+    depth: f32,
+    tolerance: f32,
+    cancel: &CancelToken,
+) -> Result<Part> {
+    Ok(Part {
+        name: "plate".to_string(),
+        mesh: fill(tolerance, cancel, |b| {
+            outline.add(b, 0.0, Winding::Positive);
+            outline.add_holes(b);
+        })?,
+        depth: thickness,
+        z_offset: -(depth * 0.5 + thickness * 0.5),
+        letter: None,
+        chamfer: 0.0,
+        color: None,
+    })
+}
+
+/// Plate (with its holes), then the raised border of width `rim` and the lettering
+pub(crate) fn raised_parts(
+    text: Mesh2D,
+    outline: &Outline,
+    thickness: f32,
+    rim: f32,
+    options: &Options,
+    tolerance: f32,
+    cancel: &CancelToken,
+) -> Result<Vec<Part>> {
+    let depth = options.text_depth();
+    let mut parts = vec![plate_part(outline, thickness, depth, tolerance, cancel)?];
+    if rim > 0.0 {
+        parts.push(Part {
+            name: "rim".to_string(),
+            mesh: fill(tolerance, cancel, |b| {
+                outline.add(b, 0.0, Winding::Positive);
+                outline.add(b, rim, Winding::Negative);
+            })?,
+            depth,
+            z_offset: 0.0,
+            letter: None,
+            chamfer: 0.0,
+            color: None,
+        });
+    }
+    parts.push(Part {
+        name: "text".to_string(),
+        mesh: text,
+        depth,
+        z_offset: 0.0,
+        letter: None,
+        chamfer: 0.0,
+        color: None,
+    });
+    Ok(parts)
+}
+
+/// Split the plate (`parts[0]`) into a straight base and a top layer
+/// `chamfer` thick with sloped walls, keeping the total thickness
+pub(crate) fn chamfer_plate(parts: &mut Vec<Part>, chamfer: f32, depth: f32) {
+    if chamfer <= 0.0 {
+        return;
+    }
+    let mut top = parts[0].clone();
+    top.name = "chamfer".to_string();
+    top.depth = chamfer;
+    top.z_offset = -(depth * 0.5 + chamfer * 0.5);
+    top.chamfer = chamfer;
+    parts[0].z_offset -= chamfer;
+    parts.insert(1, top);
+}
+
+/// Build the nameplate parts: plate first, then the rim and the lettering
+/// (or, when engraving, the top layer with the text cut out)
+pub fn nameplate_parts(
+    face: &Face<'_>,
+    options: &Options,
+    plate: &Nameplate,
+    cancel: &CancelToken,
+) -> Result<Vec<Part>> {
+    let (text, text_w, text_h) = centered_text(face, options, cancel)?;
+
+    let hole_radius = plate.hole_diameter * 0.5;
+    let hole_space = if plate.holes {
+        plate.hole_diameter + plate.margin
+    } else {
+        0.0
+    };
+    let half_w = text_w + plate.margin + hole_space;
+    let half_h = text_h + plate.margin;
+    let hole_x = text_w + plate.margin + hole_radius;
+    let outline = Outline {
+        half_w,
+        half_h,
+        radius: match plate.style {
+            NameplateStyle::Rect => 0.0,
+            NameplateStyle::Rounded => plate.corner_radius.min(half_h).min(half_w),
+            NameplateStyle::Pill => half_h.min(half_w),
+        },
+        holes: if plate.holes {
+            vec![Point::new(-hole_x, 0.0), Point::new(hole_x, 0.0)]
+        } else {
+            Vec::new()
+        },
+        hole_radius,
+        slots: Vec::new(),
+    };
+
+    let tolerance = options.tolerance(face);
+    let depth = options.text_depth();
+    let thickness = options.snap_to_layers(plate.thickness);
+
+    if plate.engrave {
+        anyhow::ensure!(
+            thickness > depth,
+            "engraving {} mm deep needs a plate thicker than that (plate is {} mm)",
+            depth,
+            thickness
+        );
+        // Outlines of the lettering, reversed so they cut holes into the top layer
+        let cutouts = mesh::boundary_loops(&text);
+        let face_layer = fill(tolerance, cancel, |b| {
+            outline.add(b, 0.0, Winding::Positive);
+            outline.add_holes(b);
+            for mut points in cutouts {
+                points.reverse();
+                b.add_polygon(Polygon {
+                    points: &points,
+                    closed: true,
+                });
+            }
+        })?;
+        return Ok(vec![
+            plate_part(&outline, thickness - depth, depth, tolerance, cancel)?,
+            Part {
+                name: "face".to_string(),
+                mesh: face_layer,
+                depth,
+                z_offset: 0.0,
+                letter: None,
+                chamfer: 0.0,
+                color: None,
+            },
+        ]);
+    }
+
+    raised_parts(
+        text, &outline, thickness, plate.rim, options, tolerance, cancel,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font;
+
+    fn area(mesh: &Mesh2D) -> f32 {
+        mesh.indices
+            .chunks(3)
+            .map(|t| {
+                let [a, b, c] = [t[0], t[1], t[2]].map(|i| mesh.vertices[i as usize]);
+                (b - a).cross(c - a) * 0.5
+            })
+            .sum()
+    }
+
+    fn build(plate: &Nameplate) -> Vec<Part> {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let options = Options {
+            text: "Io".into(),
+            size: 20.0,
+            depth: 1.0,
+            ..Default::default()
+        };
+        nameplate_parts(&face, &options, plate, &CancelToken::new()).unwrap()
+    }
+
+    #[test]
+    fn raised_nameplate_has_plate_rim_and_text() {
+        let parts = build(&Nameplate::default());
+        let names: Vec<&str> = parts.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["plate", "rim", "text"]);
+
+        // The mounting holes are cut out of the plate
+        let (min_x, max_x, min_y, max_y) = mesh::mesh_bounds(&parts[0].mesh).unwrap();
+        let full = (max_x - min_x) * (max_y - min_y);
+        assert!(area(&parts[0].mesh) < full - 2.0 * 3.0);
+    }
+
+    #[test]
+    fn engraved_nameplate_cuts_the_text_out_of_the_top_layer() {
+        let plate = Nameplate {
+            engrave: true,
+            holes: false,
+            ..Default::default()
+        };
+        let parts = build(&plate);
+        let names: Vec<&str> = parts.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["plate", "face"]);
+
+        let text = build(&Nameplate::default()).pop().unwrap();
+        let expected = area(&parts[0].mesh) - area(&text.mesh);
+        assert!((area(&parts[1].mesh) - expected).abs() < 1.0);
+    }
+}
@@ -0,0 +1,152 @@
+//! Wavefront OBJ writer: one object per part. Positions are welded so each
+//! part imports as one connected mesh; texture coordinates and normals keep
+//! their own indices, so seams and crisp edges survive the welding.
+
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::indexed::{to_y_up, MeshPart};
+use crate::output::{self, Units};
+
+/// OBJ writer settings
+#[derive(Debug, Clone, Copy)]
+pub struct ObjOptions {
+    /// Mark the normals as smoothed so importers keep them instead of
+    /// recomputing flat shading
+    pub smooth: bool,
+    /// Write `vn` normals; without them the importer shades the welded mesh
+    pub normals: bool,
+    /// Unit of the coordinates, noted in a comment since OBJ has no field for it
+    pub units: Units,
+}
+
+impl Default for ObjOptions {
+    fn default() -> Self {
+        Self {
+            smooth: false,
+            normals: true,
+            units: Units::Mm,
+        }
+    }
+}
+
+/// Write the parts Y-up (the OBJ convention)
+pub fn write_obj(path: &Path, parts: &[MeshPart], options: ObjOptions) -> Result<()> {
+    output::write_atomically(path, |file| {
+        write_obj_to_writer(BufWriter::new(file), parts, options)
+    })
+}
+
+pub fn write_obj_to_writer<W: Write>(
+    mut writer: W,
+    parts: &[MeshPart],
+    options: ObjOptions,
+) -> Result<()> {
+    writeln!(writer, "# wagyan {}", env!("CARGO_PKG_VERSION"))?;
+    writeln!(writer, "# units: {}", options.units.name())?;
+    // OBJ indices are 1-based and global across objects, separately for
+    // positions and for texture coordinates/normals
+    let (mut position_base, mut vertex_base) = (1, 1);
+    for part in parts {
+        let mesh = &part.mesh;
+        let (positions, welded) = mesh.shared_positions();
+        writeln!(writer, "o {}", part.name)?;
+        for p in &positions {
+            let [x, y, z] = to_y_up(*p);
+            writeln!(writer, "v {} {} {}", x, y, z)?;
+        }
+        for [u, v] in &mesh.uvs {
+            writeln!(writer, "vt {} {}", u, v)?;
+        }
+        if options.normals {
+            for n in &mesh.normals {
+                let [x, y, z] = to_y_up(*n);
+                writeln!(writer, "vn {} {} {}", x, y, z)?;
+            }
+        }
+        writeln!(writer, "s {}", if options.smooth { "1" } else { "off" })?;
+        for (t, welded) in mesh.indices.chunks(3).zip(welded.chunks(3)) {
+            write!(writer, "f")?;
+            for (&i, &w) in t.iter().zip(welded) {
+                let p = w as usize + position_base;
+                let v = i as usize + vertex_base;
+                if options.normals {
+                    write!(writer, " {p}/{v}/{v}")?;
+                } else {
+                    write!(writer, " {p}/{v}")?;
+                }
+            }
+            writeln!(writer)?;
+        }
+        position_base += positions.len();
+        vertex_base += mesh.positions.len();
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::indexed::IndexedMesh;
+    use crate::mesh::{rectangle_mesh, Orientation};
+
+    #[test]
+    fn positions_are_welded_and_normals_optional() {
+        let square = rectangle_mesh(0.0, 1.0, 0.0, 1.0);
+        let parts = [MeshPart {
+            name: "text".into(),
+            mesh: IndexedMesh::extrude(&square, 1.0, &Orientation::Flat, 0.0, None),
+            color: Color::LIGHT_GRAY,
+        }];
+        let options = ObjOptions {
+            normals: false,
+            units: Units::Inch,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        write_obj_to_writer(&mut out, &parts, options).unwrap();
+        let obj = String::from_utf8(out).unwrap();
+        // A box: 8 corners shared by 12 triangles, 24 corners with seams
+        assert_eq!(obj.lines().filter(|l| l.starts_with("v ")).count(), 8);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("vt ")).count(), 24);
+        assert!(!obj.contains("\nvn "));
+        assert!(obj.contains("\n# units: inch\n"));
+        let face = obj.lines().find(|l| l.starts_with("f ")).unwrap();
+        assert_eq!(face.split(' ').nth(1).unwrap().split('/').count(), 2);
+    }
+
+    #[test]
+    fn objects_index_vertices_across_parts() {
+        let square = rectangle_mesh(0.0, 1.0, 0.0, 1.0);
+        let part = |name: &str| MeshPart {
+            name: name.into(),
+            mesh: IndexedMesh::extrude(&square, 1.0, &Orientation::Flat, 0.0, None),
+            color: Color::LIGHT_GRAY,
+        };
+        let mut out = Vec::new();
+        let parts = [part("plate"), part("text")];
+        write_obj_to_writer(&mut out, &parts, ObjOptions::default()).unwrap();
+        let obj = String::from_utf8(out).unwrap();
+
+        let vertices = obj.lines().filter(|l| l.starts_with("vt ")).count();
+        let normals = obj.lines().filter(|l| l.starts_with("vn ")).count();
+        assert_eq!(normals, vertices);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("f ")).count(), 24);
+        // The second object's faces start after the first object's vertices
+        let second = obj.split("o text\n").nth(1).unwrap();
+        let first_face = second.lines().find(|l| l.starts_with("f ")).unwrap();
+        let index: Vec<usize> = first_face[2..]
+            .split(' ')
+            .next()
+            .unwrap()
+            .split('/')
+            .map(|i| i.parse().unwrap())
+            .collect();
+        assert!(index[0] > 8 && index[1] > vertices / 2);
+        assert!(obj.contains("\ns off\n"));
+    }
+}
@@ -0,0 +1,244 @@
+//! Polygon operations on closed outlines: growing/shrinking and merging.
+//!
+//! Outlines follow the tessellator's convention: outer contours run
+//! counter-clockwise, holes clockwise (see [`mesh::boundary_loops`]).
+
+use anyhow::Result;
+use i_overlay::core::fill_rule::FillRule;
+use i_overlay::core::overlay_rule::OverlayRule;
+use i_overlay::float::simplify::SimplifyShape;
+use i_overlay::float::single::SingleFloatOverlay;
+use i_overlay::mesh::outline::offset::OutlineOffset;
+use i_overlay::mesh::stroke::offset::StrokeOffset;
+use i_overlay::mesh::style::{LineCap, LineJoin, OutlineStyle, StrokeStyle};
+use lyon_path::iterator::PathIterator;
+use lyon_path::math::Point;
+use lyon_path::{Event, Path, Polygon};
+
+use crate::cancel::CancelToken;
+use crate::mesh::{self, Mesh2D};
+
+type Contour = Vec<[f32; 2]>;
+
+fn to_contours(outlines: &[Vec<Point>]) -> Vec<Contour> {
+    outlines
+        .iter()
+        .map(|points| points.iter().map(|p| [p.x, p.y]).collect())
+        .collect()
+}
+
+/// Smallest contour kept from a polygon operation, in mm²; anything less
+/// is a rounding sliver
+const MIN_AREA: f32 = 1e-3;
+
+fn from_shapes(shapes: Vec<Vec<Contour>>) -> Vec<Vec<Point>> {
+    shapes
+        .into_iter()
+        .flatten()
+        .map(|contour| -> Vec<Point> {
+            contour.into_iter().map(|[x, y]| Point::new(x, y)).collect()
+        })
+        .filter(|points| area(points).abs() > MIN_AREA)
+        .collect()
+}
+
+/// Flatten a glyph path into clean outlines: overlapping contours are
+/// merged and every contour gets the outer/hole orientation
+pub fn from_path(path: &Path, tolerance: f32) -> Vec<Vec<Point>> {
+    let mut contours: Vec<Contour> = Vec::new();
+    for event in path.iter().flattened(tolerance) {
+        match event {
+            Event::Begin { at } => contours.push(vec![[at.x, at.y]]),
+            Event::Line { to, .. } => {
+                if let Some(contour) = contours.last_mut() {
+                    contour.push([to.x, to.y]);
+                }
+            }
+            _ => {}
+        }
+    }
+    from_shapes(contours.simplify_shape(FillRule::NonZero))
+}
+
+/// Outlines of the area a triangle mesh covers; unlike
+/// [`mesh::boundary_loops`] this does not depend on how the triangles share
+/// vertices
+pub fn from_mesh(mesh: &Mesh2D) -> Vec<Vec<Point>> {
+    let triangles: Vec<Contour> = mesh
+        .indices
+        .chunks(3)
+        .map(|t| {
+            t.iter()
+                .map(|&i| mesh.vertices[i as usize])
+                .map(|p| [p.x, p.y])
+                .collect()
+        })
+        .collect();
+    from_shapes(triangles.simplify_shape(FillRule::NonZero))
+}
+
+pub fn translate(outlines: &mut [Vec<Point>], dx: f32, dy: f32) {
+    for point in outlines.iter_mut().flatten() {
+        point.x += dx;
+        point.y += dy;
+    }
+}
+
+/// Move every edge outward by `distance` (inward when negative), with
+/// round corners that stay within `tolerance` of a true arc
+pub fn offset(outlines: &[Vec<Point>], distance: f32, tolerance: f32) -> Vec<Vec<Point>> {
+    if distance == 0.0 {
+        return outlines.to_vec();
+    }
+    let radius = distance.abs();
+    let step = 2.0 * (1.0 - (tolerance / radius).min(1.0)).acos();
+    let style = OutlineStyle::new(distance).line_join(LineJoin::Round(step));
+    from_shapes(to_contours(outlines).outline(&style))
+}
+
+/// Outline of open polylines drawn with a round pen `width` wide
+pub fn stroke(paths: &[Vec<Point>], width: f32, tolerance: f32) -> Vec<Vec<Point>> {
+    let radius = width * 0.5;
+    let step = 2.0 * (1.0 - (tolerance / radius).min(1.0)).acos();
+    let style = StrokeStyle::new(width)
+        .start_cap(LineCap::Round(step))
+        .end_cap(LineCap::Round(step))
+        .line_join(LineJoin::Round(step));
+    from_shapes(to_contours(paths).stroke(style, false))
+}
+
+/// Merge two sets of outlines into one non-overlapping set
+pub fn union(a: &[Vec<Point>], b: &[Vec<Point>]) -> Vec<Vec<Point>> {
+    let shapes = to_contours(a).overlay(&to_contours(b), OverlayRule::Union, FillRule::NonZero);
+    from_shapes(shapes)
+}
+
+/// The area of `a` that `b` does not cover
+pub fn difference(a: &[Vec<Point>], b: &[Vec<Point>]) -> Vec<Vec<Point>> {
+    let shapes =
+        to_contours(a).overlay(&to_contours(b), OverlayRule::Difference, FillRule::NonZero);
+    from_shapes(shapes)
+}
+
+/// Counter-clockwise rectangle outline
+pub fn rectangle(min_x: f32, max_x: f32, min_y: f32, max_y: f32) -> Vec<Point> {
+    vec![
+        Point::new(min_x, min_y),
+        Point::new(max_x, min_y),
+        Point::new(max_x, max_y),
+        Point::new(min_x, max_y),
+    ]
+}
+
+/// Outlines as a path of closed polygons
+pub fn to_path(outlines: &[Vec<Point>]) -> Path {
+    let mut builder = Path::builder();
+    for points in outlines {
+        builder.add_polygon(Polygon {
+            points,
+            closed: true,
+        });
+    }
+    builder.build()
+}
+
+/// Tessellate outlines into a fillable mesh
+pub fn fill(outlines: &[Vec<Point>], tolerance: f32, cancel: &CancelToken) -> Result<Mesh2D> {
+    mesh::tessellate_path_with_cancel(&to_path(outlines), tolerance, cancel)
+}
+
+/// `path` with every outline moved outward by `weight` (inward when
+/// negative) to embolden or thin it. The contours are merged before the
+/// offset, so overlapping and thickened strokes never self-intersect.
+pub fn embolden(path: &Path, weight: f32, tolerance: f32) -> Path {
+    to_path(&offset(&from_path(path, tolerance), weight, tolerance))
+}
+
+/// Signed area of an outline (positive for counter-clockwise)
+pub fn area(points: &[Point]) -> f32 {
+    let n = points.len();
+    (0..n)
+        .map(|i| points[i].to_vector().cross(points[(i + 1) % n].to_vector()))
+        .sum::<f32>()
+        * 0.5
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn total_area(outlines: &[Vec<Point>]) -> f32 {
+        outlines.iter().map(|o| area(o)).sum()
+    }
+
+    #[test]
+    fn offset_grows_and_shrinks_a_square() {
+        let square = [rectangle(0.0, 10.0, 0.0, 10.0)];
+        let grown = offset(&square, 1.0, 0.01);
+        assert_eq!(grown.len(), 1);
+        // 12 x 12 minus the four corners rounded off: 144 - (4 - PI)
+        let expected = 144.0 - (4.0 - std::f32::consts::PI);
+        assert!((total_area(&grown) - expected).abs() < 0.1);
+
+        let shrunk = offset(&square, -1.0, 0.01);
+        assert!((total_area(&shrunk) - 64.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn mesh_outlines_merge_the_triangles() {
+        let mut builder = Path::builder();
+        builder.add_polygon(Polygon {
+            points: &rectangle(0.0, 6.0, 0.0, 6.0),
+            closed: true,
+        });
+        let mut hole = rectangle(2.0, 4.0, 2.0, 4.0);
+        hole.reverse();
+        builder.add_polygon(Polygon {
+            points: &hole,
+            closed: true,
+        });
+        let mesh = mesh::tessellate_path(&builder.build(), 0.01).unwrap();
+        let outlines = from_mesh(&mesh);
+        assert_eq!(outlines.len(), 2);
+        assert!((total_area(&outlines) - 32.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn stroke_widens_open_paths_with_round_ends() {
+        let line = vec![Point::new(0.0, 0.0), Point::new(10.0, 0.0)];
+        let stroked = stroke(&[line], 2.0, 0.001);
+        assert_eq!(stroked.len(), 1);
+        // 10 x 2 band plus two half discs
+        let expected = 20.0 + std::f32::consts::PI;
+        assert!((total_area(&stroked) - expected).abs() < 0.01);
+    }
+
+    #[test]
+    fn embolden_merges_strokes_that_grow_together() {
+        let squares = to_path(&[
+            rectangle(0.0, 10.0, 0.0, 10.0),
+            rectangle(11.0, 21.0, 0.0, 10.0),
+        ]);
+        let bold = from_path(&embolden(&squares, 1.0, 0.01), 0.01);
+        assert_eq!(bold.len(), 1);
+        assert!(total_area(&bold) > 12.0 * 23.0 - 1.0);
+
+        let thin = from_path(&embolden(&squares, -1.0, 0.01), 0.01);
+        assert_eq!(thin.len(), 2);
+        assert!((total_area(&thin) - 128.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn union_merges_overlaps_and_keeps_holes() {
+        let a = [rectangle(0.0, 10.0, 0.0, 10.0)];
+        let b = [rectangle(5.0, 15.0, 0.0, 10.0)];
+        let merged = union(&a, &b);
+        assert_eq!(merged.len(), 1);
+        assert!((total_area(&merged) - 150.0).abs() < 0.01);
+
+        let mut hole = rectangle(2.0, 4.0, 2.0, 4.0);
+        hole.reverse();
+        let framed = union(&[rectangle(0.0, 6.0, 0.0, 6.0), hole], &[]);
+        assert!((total_area(&framed) - 32.0).abs() < 0.01);
+    }
+}
@@ -0,0 +1,422 @@
+//! Output file handling shared by all writers
+
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::i18n::Message;
+
+/// What to do when the output file already exists
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    /// Refuse with an error
+    Refuse,
+    /// Replace the existing file
+    Force,
+    /// Rename the existing file to `<name>.bak` (or the first free
+    /// `<name>.<n>.bak`) once the new one is written
+    Backup,
+}
+
+/// Model file format, chosen with `--format` or by the output extension
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// ASCII STL
+    #[default]
+    #[value(alias = "stl")]
+    Ascii,
+    /// Binary STL, about a fifth of the size
+    Binary,
+    /// 3MF with indexed meshes, plate and text as separate parts
+    #[value(name = "3mf")]
+    ThreeMf,
+    /// AMF XML, one volume and material per part color
+    Amf,
+    /// Binary glTF
+    Glb,
+    /// glTF JSON with the buffer embedded
+    Gltf,
+    /// Wavefront OBJ
+    Obj,
+    /// PLY with faces colored by type
+    Ply,
+    /// 2D outlines for laser cutters
+    Dxf,
+    /// 2D outlines for laser cutters, glyph curves kept as Béziers
+    Svg,
+    /// STEP B-rep solids for CAD, glyph curves kept as Béziers
+    #[value(alias = "stp")]
+    Step,
+}
+
+impl Format {
+    /// Format implied by the file extension (the one before `.gz` or `.zst`
+    /// for compressed files), `None` when it names none
+    pub fn from_path(path: &Path) -> Option<Format> {
+        let path = match Compression::from_path(path) {
+            Some(_) => Path::new(path.file_stem()?),
+            None => path,
+        };
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        Some(match ext.as_str() {
+            "stl" => Format::Ascii,
+            "3mf" => Format::ThreeMf,
+            "amf" => Format::Amf,
+            "glb" => Format::Glb,
+            "gltf" => Format::Gltf,
+            "obj" => Format::Obj,
+            "ply" => Format::Ply,
+            "dxf" => Format::Dxf,
+            "svg" => Format::Svg,
+            "step" | "stp" => Format::Step,
+            _ => return None,
+        })
+    }
+
+    /// `--format` when given, else the output extension, else ASCII STL; an
+    /// extension that names no format is an error rather than a silent STL
+    pub fn resolve(requested: Option<Format>, output: Option<&Path>) -> Result<Format> {
+        if let Some(format) = requested {
+            return Ok(format);
+        }
+        let Some(path) = output.filter(|p| p.extension().is_some()) else {
+            return Ok(Format::default());
+        };
+        Format::from_path(path).with_context(|| Message::UnknownExtension(path).to_string())
+    }
+
+    /// Name for messages
+    pub fn label(self) -> &'static str {
+        match self {
+            Format::Ascii => "ASCII STL",
+            Format::Binary => "binary STL",
+            Format::ThreeMf => "3MF",
+            Format::Amf => "AMF",
+            Format::Glb => "GLB",
+            Format::Gltf => "glTF",
+            Format::Obj => "OBJ",
+            Format::Ply => "PLY",
+            Format::Dxf => "DXF",
+            Format::Svg => "SVG",
+            Format::Step => "STEP",
+        }
+    }
+}
+
+/// Stream compression, chosen with `--compress` or by a `.gz` or `.zst`
+/// extension
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    /// gzip, which gunzip and most tools read
+    Gzip,
+    /// Zstandard, smaller and faster than gzip
+    Zstd,
+}
+
+impl Compression {
+    /// Compression implied by the file extension
+    pub fn from_path(path: &Path) -> Option<Compression> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "gz" => Some(Compression::Gzip),
+            "zst" => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    /// `--compress` when given, else the output extension
+    pub fn resolve(requested: Option<Compression>, output: Option<&Path>) -> Option<Compression> {
+        requested.or_else(|| output.and_then(Compression::from_path))
+    }
+}
+
+/// Length unit of the written coordinates, chosen with `--units`; models
+/// are built in millimeters and scaled on the way out
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Units {
+    #[default]
+    Mm,
+    Cm,
+    #[value(alias = "in")]
+    Inch,
+}
+
+impl Units {
+    /// Output units per millimeter
+    pub fn per_mm(self) -> f32 {
+        match self {
+            Units::Mm => 1.0,
+            Units::Cm => 0.1,
+            Units::Inch => 1.0 / 25.4,
+        }
+    }
+
+    /// Name in the 3MF and AMF `unit` attributes
+    pub fn name(self) -> &'static str {
+        match self {
+            Units::Mm => "millimeter",
+            Units::Cm => "centimeter",
+            Units::Inch => "inch",
+        }
+    }
+
+    /// Suffix of SVG lengths
+    pub fn abbreviation(self) -> &'static str {
+        match self {
+            Units::Mm => "mm",
+            Units::Cm => "cm",
+            Units::Inch => "in",
+        }
+    }
+}
+
+/// Run `write` against `out`, through `compression` when there is one; the
+/// compressed stream is finished before returning
+pub fn write_compressed<W: Write>(
+    out: W,
+    compression: Option<Compression>,
+    write: impl FnOnce(&mut dyn Write) -> Result<()>,
+) -> Result<()> {
+    match compression {
+        None => {
+            let mut out = out;
+            write(&mut out)?;
+            out.flush()?;
+        }
+        Some(Compression::Gzip) => {
+            let mut gz = flate2::write::GzEncoder::new(out, flate2::Compression::default());
+            write(&mut gz)?;
+            gz.finish()?.flush()?;
+        }
+        Some(Compression::Zstd) => {
+            let mut zst = zstd::stream::write::Encoder::new(out, 0)?;
+            write(&mut zst)?;
+            zst.finish()?.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Backup path for an existing output (`sign.stl` -> `sign.stl.bak`), or
+/// `sign.stl.1.bak`, `sign.stl.2.bak`, ... when earlier backups are in the way
+pub fn backup_path(path: &Path) -> PathBuf {
+    let name = path.file_name().map(OsString::from).unwrap_or_default();
+    (0..)
+        .map(|n| {
+            let mut name = name.clone();
+            if n > 0 {
+                name.push(format!(".{}", n));
+            }
+            name.push(".bak");
+            path.with_file_name(name)
+        })
+        .find(|backup| !backup.exists())
+        .expect("some backup name is free")
+}
+
+/// Hidden temporary file next to `path` that [`write_atomically`] renames
+/// over it (`dir/sign.stl` -> `dir/.sign.stl.<pid>.tmp`)
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(".{}.tmp", std::process::id()));
+    path.with_file_name(name)
+}
+
+/// Create `path` through a temporary file in the same directory that only
+/// replaces it once `write` has succeeded and the data is on disk, so a
+/// crash or Ctrl-C never leaves a truncated file behind. Missing parent
+/// directories are created first.
+pub fn write_atomically(path: &Path, write: impl FnOnce(&mut File) -> Result<()>) -> Result<()> {
+    write_with_policy(path, OverwritePolicy::Force, write)
+}
+
+/// [`write_atomically`] under the overwrite `policy`: an existing `path` is
+/// refused, replaced, or moved to its [`backup_path`] once the new file is
+/// complete, so a failed write leaves it where it was
+pub fn write_with_policy(
+    path: &Path,
+    policy: OverwritePolicy,
+    write: impl FnOnce(&mut File) -> Result<()>,
+) -> Result<()> {
+    prepare_output(path, policy)?;
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .with_context(|| Message::CreateDirectoryFailed(parent).to_string())?;
+    }
+    let temp = temp_path(path);
+    let result = File::create(&temp)
+        .map_err(anyhow::Error::from)
+        .and_then(|mut file| {
+            write(&mut file)?;
+            file.sync_all()?;
+            Ok(())
+        })
+        .and_then(|()| {
+            if policy == OverwritePolicy::Backup && path.exists() {
+                back_up(path)?;
+            }
+            Ok(fs::rename(&temp, path)?)
+        });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Move `path` to its [`backup_path`]
+fn back_up(path: &Path) -> Result<()> {
+    let backup = backup_path(path);
+    fs::rename(path, &backup).with_context(|| Message::BackupFailed(path, &backup).to_string())
+}
+
+/// Refuse outputs that name the same file twice, which would overwrite (or
+/// back up) the first of them with the second
+pub fn ensure_distinct<'a>(paths: impl IntoIterator<Item = &'a Path>) -> Result<()> {
+    let mut seen = std::collections::HashSet::new();
+    for path in paths {
+        anyhow::ensure!(seen.insert(path), "{}", Message::RepeatedOutput(path));
+    }
+    Ok(())
+}
+
+/// Check the output path against the overwrite policy before writing, so
+/// a batch of outputs can be refused before any of them is written
+pub fn prepare_output(path: &Path, policy: OverwritePolicy) -> Result<()> {
+    if policy == OverwritePolicy::Refuse && path.exists() {
+        anyhow::bail!("{}", Message::RefusedOverwrite(path));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_flag_wins_over_the_extension() {
+        let path = Some(Path::new("sign.3MF"));
+        assert_eq!(Format::resolve(None, path).unwrap(), Format::ThreeMf);
+        assert_eq!(
+            Format::resolve(Some(Format::Obj), path).unwrap(),
+            Format::Obj
+        );
+        let unknown = Some(Path::new("sign.txt"));
+        assert!(Format::resolve(None, unknown).is_err());
+        assert_eq!(
+            Format::resolve(Some(Format::Binary), unknown).unwrap(),
+            Format::Binary
+        );
+        assert_eq!(
+            Format::resolve(None, Some(Path::new("sign"))).unwrap(),
+            Format::Ascii
+        );
+        assert_eq!(Format::resolve(None, None).unwrap(), Format::Ascii);
+    }
+
+    #[test]
+    fn gz_outputs_are_compressed_and_keep_their_format() {
+        let path = Path::new("sign.STL.gz");
+        assert_eq!(Format::from_path(path), Some(Format::Ascii));
+        assert_eq!(
+            Compression::resolve(None, Some(path)),
+            Some(Compression::Gzip)
+        );
+        assert_eq!(
+            Compression::resolve(None, Some(Path::new("sign.stl"))),
+            None
+        );
+        assert!(Format::resolve(None, Some(Path::new("sign.gz"))).is_err());
+
+        let mut out = Vec::new();
+        write_compressed(&mut out, Some(Compression::Gzip), |w| {
+            Ok(w.write_all(b"solid mesh\nendsolid mesh\n")?)
+        })
+        .unwrap();
+        let mut text = String::new();
+        std::io::Read::read_to_string(&mut flate2::read::GzDecoder::new(&out[..]), &mut text)
+            .unwrap();
+        assert_eq!(text, "solid mesh\nendsolid mesh\n");
+    }
+
+    #[test]
+    fn zst_outputs_round_trip() {
+        let path = Path::new("sign.obj.zst");
+        assert_eq!(Format::from_path(path), Some(Format::Obj));
+        assert_eq!(
+            Compression::resolve(None, Some(path)),
+            Some(Compression::Zstd)
+        );
+
+        let mut out = Vec::new();
+        write_compressed(&mut out, Some(Compression::Zstd), |w| {
+            Ok(w.write_all(b"solid mesh\nendsolid mesh\n")?)
+        })
+        .unwrap();
+        assert_eq!(
+            zstd::stream::decode_all(&out[..]).unwrap(),
+            b"solid mesh\nendsolid mesh\n"
+        );
+    }
+
+    #[test]
+    fn backup_path_appends_suffix() {
+        assert_eq!(
+            backup_path(Path::new("out/sign.stl")),
+            PathBuf::from("out/sign.stl.bak")
+        );
+    }
+
+    #[test]
+    fn backups_never_replace_earlier_ones() {
+        let dir = std::env::temp_dir().join(format!("wagyan-backup-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("sign.stl");
+        let write = |text: &'static str| {
+            write_with_policy(&path, OverwritePolicy::Backup, |file| {
+                Ok(file.write_all(text.as_bytes())?)
+            })
+        };
+        write("first").unwrap();
+        write("second").unwrap();
+        write("third").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "third");
+        assert_eq!(
+            fs::read_to_string(dir.join("sign.stl.bak")).unwrap(),
+            "first"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("sign.stl.1.bak")).unwrap(),
+            "second"
+        );
+
+        let failed = write_with_policy(&path, OverwritePolicy::Backup, |_| {
+            anyhow::bail!("interrupted")
+        });
+        assert!(failed.is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "third");
+        assert!(!dir.join("sign.stl.2.bak").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failed_writes_leave_the_old_file_alone() {
+        let dir = std::env::temp_dir().join(format!("wagyan-atomic-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("deep/dir/sign.stl");
+        write_atomically(&path, |file| Ok(file.write_all(b"solid old\n")?)).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"solid old\n");
+
+        let failed = write_atomically(&path, |file| {
+            file.write_all(b"solid new\n")?;
+            anyhow::bail!("interrupted")
+        });
+        assert!(failed.is_err());
+        assert_eq!(fs::read(&path).unwrap(), b"solid old\n");
+        assert!(!temp_path(&path).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,137 @@
+//! Pendant mode: a hanging loop above the text for a jump ring or chain
+
+use anyhow::Result;
+use lyon_path::math::Point;
+use lyon_path::path::Builder;
+use lyon_path::{Path, Winding};
+
+use crate::mesh::{self, Mesh2D};
+use crate::outline;
+
+/// Loop settings; the defaults take jump rings with wire up to about 1.2 mm
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct HangingLoop {
+    /// Hole diameter in mm
+    pub inner_diameter: f32,
+    /// Ring width in mm
+    pub width: f32,
+    /// Bridge the gap between the loop and the lettering below it
+    pub connector: bool,
+}
+
+impl Default for HangingLoop {
+    fn default() -> Self {
+        Self {
+            inner_diameter: 3.0,
+            width: 1.6,
+            connector: true,
+        }
+    }
+}
+
+impl HangingLoop {
+    pub fn outer_diameter(&self) -> f32 {
+        self.inner_diameter + 2.0 * self.width
+    }
+
+    /// Add the ring so that it overlaps `top` by half its width at `x`;
+    /// the hole stays clear of anything below `top`
+    pub fn add_ring(&self, builder: &mut Builder, x: f32, top: f32) {
+        let inner = self.inner_diameter * 0.5;
+        let center = Point::new(x, top + inner + self.width * 0.5);
+        builder.add_circle(center, inner + self.width, Winding::Positive);
+        builder.add_circle(center, inner, Winding::Negative);
+    }
+
+    /// The loop hanging from the lettering: the ring sits just above the
+    /// top of the text and, with `connector`, a bar runs down to the ink
+    pub fn text_loop(&self, text: &[&Mesh2D], tolerance: f32) -> Result<Option<Mesh2D>> {
+        let Some((x, ink_top, max_y)) = anchor(text) else {
+            return Ok(None);
+        };
+        let mut builder = Path::builder();
+        self.add_ring(&mut builder, x, max_y);
+        let ring_bottom = max_y - self.width * 0.5;
+        if self.connector && ink_top < ring_bottom {
+            // Overlap both ends a little so the bar fuses with the ring and the glyph
+            let half = self.width * 0.5;
+            let bar = outline::rectangle(x - half, x + half, ink_top - half, max_y);
+            builder.add_polygon(lyon_path::Polygon {
+                points: &bar,
+                closed: true,
+            });
+        }
+        Ok(Some(mesh::tessellate_path(&builder.build(), tolerance)?))
+    }
+}
+
+/// Top of the ink where a vertical line at `x` crosses the mesh
+fn ink_top(mesh: &Mesh2D, x: f32) -> Option<f32> {
+    mesh.indices
+        .chunks(3)
+        .flat_map(|t| {
+            let [a, b, c] = [t[0], t[1], t[2]].map(|i| mesh.vertices[i as usize]);
+            [(a, b), (b, c), (c, a)]
+        })
+        .filter_map(|(a, b)| {
+            let (lo, hi) = if a.x <= b.x { (a, b) } else { (b, a) };
+            if x < lo.x || x > hi.x {
+                return None;
+            }
+            if hi.x == lo.x {
+                return Some(lo.y.max(hi.y));
+            }
+            Some(lo.y + (hi.y - lo.y) * (x - lo.x) / (hi.x - lo.x))
+        })
+        .reduce(f32::max)
+}
+
+/// Where the loop hangs: the inked column nearest the middle of the text,
+/// the ink top there and the top of the text
+fn anchor(text: &[&Mesh2D]) -> Option<(f32, f32, f32)> {
+    let (min_x, max_x, _, max_y) = text
+        .iter()
+        .filter_map(|m| mesh::mesh_bounds(m))
+        .reduce(|a, b| (a.0.min(b.0), a.1.max(b.1), a.2.min(b.2), a.3.max(b.3)))?;
+    let middle = (min_x + max_x) * 0.5;
+    let top_at = |x: f32| text.iter().filter_map(|m| ink_top(m, x)).reduce(f32::max);
+    if let Some(top) = top_at(middle) {
+        return Some((middle, top, max_y));
+    }
+    // Nothing right in the middle (a space): use the nearest vertex column
+    let x = text
+        .iter()
+        .flat_map(|m| m.vertices.iter().map(|p| p.x))
+        .min_by(|a, b| (a - middle).abs().total_cmp(&(b - middle).abs()))?;
+    Some((x, top_at(x)?, max_y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loop_hangs_above_the_text_and_reaches_down_to_it() {
+        // Two blocks with a gap in the middle; the left one is shorter
+        let mut text = mesh::rectangle_mesh(0.0, 4.0, 0.0, 6.0);
+        let right = mesh::rectangle_mesh(6.0, 10.0, 0.0, 10.0);
+        let offset = text.vertices.len() as u16;
+        text.vertices.extend(right.vertices);
+        text.indices
+            .extend(right.indices.iter().map(|i| i + offset));
+
+        let hanger = HangingLoop::default();
+        let ring = hanger.text_loop(&[&text], 0.01).unwrap().unwrap();
+        let (min_x, max_x, min_y, max_y) = mesh::mesh_bounds(&ring).unwrap();
+        // Snapped to the nearest inked column (x = 4), above the taller block,
+        // and bridged down to the top of the shorter one
+        assert!(((min_x + max_x) * 0.5 - 4.0).abs() < 0.01);
+        assert!((max_y - (10.0 - 0.8 + hanger.outer_diameter())).abs() < 0.01);
+        assert!((min_y - (6.0 - 0.8)).abs() < 0.01);
+    }
+}
@@ -0,0 +1,206 @@
+//! `--emit-placements`: every laid-out glyph with its transform and a
+//! reference to one shared mesh per distinct glyph, so game engines can
+//! instance and animate letters instead of importing one fused mesh
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use lyon_path::math::Point;
+use serde_json::{json, Value};
+use ttf_parser::Face;
+
+use crate::cancel::CancelToken;
+use crate::glyph_cache::GlyphMeshCache;
+use crate::i18n::Message;
+use crate::indexed::IndexedMesh;
+use crate::measure::mm;
+use crate::mesh::map_point;
+use crate::output::{self, OverwritePolicy};
+use crate::{dry_run, font, layout, Options};
+
+/// One glyph instance; its mesh is stored once per glyph id
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placement {
+    pub ch: char,
+    /// Face the glyph comes from (see [`crate::layout::PlacedGlyph::font`])
+    pub font: usize,
+    pub gid: u16,
+    /// Pen position on the baseline in model coordinates (mm, Z up), where
+    /// the mesh's origin goes
+    pub position: [f32; 3],
+    /// Rotation of the mesh as an `[x, y, z, w]` quaternion: none, but
+    /// about the text plane's normal for text bent along a curve
+    pub rotation: [f32; 4],
+    /// Scale of the mesh along each axis: 1, but smaller in the text plane
+    /// for capitals standing in for small capitals (see
+    /// [`crate::layout::PlacedGlyph::size`])
+    pub scale: [f32; 3],
+}
+
+/// Glyph instances plus their shared meshes. Meshes are built at the
+/// layout scale, so every instance has, but for text bent along a curve, no
+/// rotation and, but for synthesized small capitals, unit scale.
+#[derive(Debug)]
+pub struct Placements {
+    pub glyphs: Vec<Placement>,
+    /// Extruded glyph meshes around their own pen position, by face and
+    /// glyph id
+    pub meshes: BTreeMap<(usize, u16), (char, IndexedMesh)>,
+}
+
+/// Name a placement uses to refer to its glyph's mesh; glyphs of fallback
+/// fonts carry the font's index too
+pub fn mesh_name(font: usize, gid: u16) -> String {
+    match font {
+        0 => format!("glyph-{}", gid),
+        _ => format!("glyph-{}-{}", font, gid),
+    }
+}
+
+/// Place the glyphs of `options.text` exactly where the lettering of the
+/// built model sits (centering and orientation included); glyphs without
+/// an outline, like spaces, are left out
+pub fn placements(face: &Face<'_>, options: &Options, cancel: &CancelToken) -> Result<Placements> {
+    let scale = options.scale(face);
+    let fallback_data = options.fallback_data();
+    let fallbacks = font::fallback_faces(&fallback_data);
+    let layout = layout(face, options);
+    let (dx, dy) = match dry_run::layout_bounds(face, &fallbacks, &layout, scale, options.shear()) {
+        Some(bounds) if options.center => options.text_anchor().offset(bounds),
+        _ => (0.0, 0.0),
+    };
+    let mut cache = GlyphMeshCache::new(scale, options.tolerance(face))
+        .with_weight(options.weight)
+        .with_shear(options.shear());
+    let mut result = Placements {
+        glyphs: Vec::new(),
+        meshes: BTreeMap::new(),
+    };
+    for glyph in &layout.glyphs {
+        let mesh = cache.glyph_mesh(face, &fallbacks, glyph.font, glyph.gid, 1.0, cancel)?;
+        if mesh.indices.is_empty() {
+            continue;
+        }
+        result
+            .meshes
+            .entry((glyph.font, glyph.gid.0))
+            .or_insert_with(|| {
+                let mesh =
+                    IndexedMesh::extrude(mesh, options.text_depth(), &options.orient, 0.0, None);
+                (glyph.ch, mesh)
+            });
+        result.glyphs.push(Placement {
+            ch: glyph.ch,
+            font: glyph.font,
+            gid: glyph.gid.0,
+            position: map_point(Point::new(glyph.x + dx, glyph.y + dy), 0.0, &options.orient),
+            rotation: {
+                let (sin, cos) = (glyph.angle / 2.0).sin_cos();
+                let [x, y, z] = map_point(Point::zero(), sin, &options.orient);
+                [x, y, z, cos]
+            },
+            scale: map_point(Point::new(glyph.size, glyph.size), 1.0, &options.orient)
+                .map(f32::abs),
+        });
+    }
+    Ok(result)
+}
+
+impl Placements {
+    pub fn to_json(&self) -> Value {
+        let vec3 = |v: &[f32; 3]| json!([mm(v[0]), mm(v[1]), mm(v[2])]);
+        let meshes: serde_json::Map<String, Value> = self
+            .meshes
+            .iter()
+            .map(|(&(font, gid), (ch, mesh))| {
+                let mesh = json!({
+                    "char": ch.to_string(),
+                    "font": font,
+                    "gid": gid,
+                    "positions": mesh.positions.iter().map(vec3).collect::<Vec<_>>(),
+                    "normals": mesh.normals.iter().map(vec3).collect::<Vec<_>>(),
+                    "indices": mesh.indices,
+                });
+                (mesh_name(font, gid), mesh)
+            })
+            .collect();
+        let glyphs: Vec<Value> = self
+            .glyphs
+            .iter()
+            .enumerate()
+            .map(|(index, glyph)| {
+                json!({
+                    "index": index,
+                    "char": glyph.ch.to_string(),
+                    "font": glyph.font,
+                    "gid": glyph.gid,
+                    "mesh": mesh_name(glyph.font, glyph.gid),
+                    "position": vec3(&glyph.position),
+                    "rotation": glyph.rotation,
+                    "scale": glyph.scale,
+                })
+            })
+            .collect();
+        json!({
+            "units": "mm",
+            "up": "z",
+            "glyphs": glyphs,
+            "meshes": meshes,
+        })
+    }
+
+    pub fn write(&self, path: &Path, policy: OverwritePolicy) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.to_json())?;
+        output::write_with_policy(path, policy, |file| {
+            Ok(file.write_all((json + "\n").as_bytes())?)
+        })
+        .with_context(|| Message::WriteFailed("placements", path).to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font;
+    use crate::mesh::Orientation;
+
+    #[test]
+    fn repeated_glyphs_share_one_mesh() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let options = Options {
+            text: "AA B".into(),
+            size: 10.0,
+            orient: Orientation::Flat,
+            ..Default::default()
+        };
+        let placed = placements(&face, &options, &CancelToken::new()).unwrap();
+        // The space has no outline
+        assert_eq!(placed.glyphs.len(), 3);
+        assert_eq!(placed.meshes.len(), 2);
+        assert_eq!(placed.glyphs[0].gid, placed.glyphs[1].gid);
+        assert!(placed.glyphs[1].position[0] > placed.glyphs[0].position[0]);
+        assert_eq!(placed.glyphs[0].position[1], placed.glyphs[2].position[1]);
+
+        // Instances land where the fused model puts the letters
+        let mesh =
+            crate::build_mesh(&face, &options, &CancelToken::new(), &crate::no_progress).unwrap();
+        let parts = crate::model_parts(mesh, &options);
+        let (lo, _) = crate::model_bounds(&parts, &options.orient).unwrap();
+        let first = &placed.meshes[&(0, placed.glyphs[0].gid)].1;
+        let min_x = first
+            .positions
+            .iter()
+            .map(|p| p[0] + placed.glyphs[0].position[0])
+            .fold(f32::MAX, f32::min);
+        assert!((min_x - lo[0]).abs() < 0.05, "{} vs {}", min_x, lo[0]);
+
+        let json = placed.to_json();
+        assert_eq!(
+            json["glyphs"][2]["mesh"],
+            mesh_name(0, placed.glyphs[2].gid)
+        );
+        assert!(json["meshes"][mesh_name(0, placed.glyphs[2].gid)]["indices"].is_array());
+    }
+}
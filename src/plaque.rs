@@ -0,0 +1,197 @@
+//! Award plaque preset: a beveled plate with a large title line, smaller
+//! body lines (each with its own size) and a screw hole in every corner
+
+use anyhow::{Context, Result};
+use lyon_path::math::{Point, Transform};
+use lyon_path::Path;
+use ttf_parser::Face;
+
+use crate::cancel::CancelToken;
+use crate::mesh::{self, Mesh2D};
+use crate::nameplate::{chamfer_plate, raised_parts, Outline};
+use crate::{build_path, no_progress, outline, Options, Part, SizeTarget};
+
+/// Plaque settings; the first line of [`Options::text`] is the title, the
+/// rest are body lines
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct AwardPlaque {
+    /// Plate thickness in mm, bevel included
+    pub thickness: f32,
+    /// Space between the text and the plate edge in mm
+    pub margin: f32,
+    pub corner_radius: f32,
+    /// Size of the 45° bevel along the top edge in mm (0 disables)
+    pub bevel: f32,
+    /// Add a screw hole in each corner
+    pub holes: bool,
+    pub hole_diameter: f32,
+    /// Cap height of the title in mm
+    pub title_height: f32,
+    /// Cap height of the body lines in mm
+    pub body_height: f32,
+    /// Cap heights of the first body lines in mm, overriding `body_height`
+    pub line_heights: Vec<f32>,
+    /// Space below each line, as a fraction of its cap height
+    pub line_gap: f32,
+}
+
+impl Default for AwardPlaque {
+    fn default() -> Self {
+        Self {
+            thickness: 5.0,
+            margin: 12.0,
+            corner_radius: 3.0,
+            bevel: 1.5,
+            holes: true,
+            hole_diameter: 4.0,
+            title_height: 10.0,
+            body_height: 5.0,
+            line_heights: Vec::new(),
+            line_gap: 0.8,
+        }
+    }
+}
+
+impl AwardPlaque {
+    /// Cap height of line `index` (0 is the title)
+    pub fn line_height(&self, index: usize) -> f32 {
+        match index {
+            0 => self.title_height,
+            _ => self
+                .line_heights
+                .get(index - 1)
+                .copied()
+                .unwrap_or(self.body_height),
+        }
+    }
+}
+
+/// Every line at its own size, centered horizontally and stacked from the
+/// title down; the block is centered on the origin and returned with its
+/// half width and height
+fn stacked_text(
+    face: &Face<'_>,
+    options: &Options,
+    plaque: &AwardPlaque,
+    cancel: &CancelToken,
+) -> Result<(Mesh2D, f32, f32)> {
+    let tolerance = options.tolerance(face);
+    let mut builder = Path::builder();
+    let mut baseline = 0.0;
+    for (index, line) in options.text.split('\n').enumerate() {
+        cancel.check()?;
+        let height = plaque.line_height(index);
+        let line_options = Options {
+            text: line.to_string(),
+            size_target: Some(SizeTarget::CapHeight(height)),
+            center: false,
+            ..options.clone()
+        };
+        baseline -= height;
+        let path = build_path(face, &line_options, &no_progress);
+        let xs = outline::from_path(&path, tolerance)
+            .into_iter()
+            .flatten()
+            .map(|p| p.x);
+        let (min_x, max_x) = xs.fold((f32::MAX, f32::MIN), |(lo, hi), x| (lo.min(x), hi.max(x)));
+        if min_x <= max_x {
+            // Layout puts the baseline one ascender below y = 0
+            let ascender = face.ascender() as f32 * line_options.scale(face);
+            let shift = Transform::translation(-(min_x + max_x) * 0.5, baseline - ascender);
+            builder.extend_from_paths(&[path.transformed(&shift).as_slice()]);
+        }
+        baseline -= height * plaque.line_gap;
+    }
+
+    let mut text = mesh::tessellate_path_with_cancel(&builder.build(), tolerance, cancel)?;
+    let (min_x, max_x, min_y, max_y) =
+        mesh::mesh_bounds(&text).context("the text has no visible glyphs")?;
+    mesh::translate_mesh(&mut text, -(min_x + max_x) * 0.5, -(min_y + max_y) * 0.5);
+    Ok((text, (max_x - min_x) * 0.5, (max_y - min_y) * 0.5))
+}
+
+/// Build the plate (straight base, then the beveled top layer) and the lettering
+pub fn plaque_parts(
+    face: &Face<'_>,
+    options: &Options,
+    plaque: &AwardPlaque,
+    cancel: &CancelToken,
+) -> Result<Vec<Part>> {
+    let thickness = options.snap_to_layers(plaque.thickness);
+    let bevel = options.snap_to_layers(plaque.bevel);
+    anyhow::ensure!(
+        bevel < thickness,
+        "a {} mm bevel needs a plate thicker than that (plate is {} mm)",
+        bevel,
+        thickness
+    );
+    let (text, text_w, text_h) = stacked_text(face, options, plaque, cancel)?;
+
+    let (half_w, half_h) = (text_w + plaque.margin, text_h + plaque.margin);
+    let (hole_x, hole_y) = (half_w - plaque.margin * 0.5, half_h - plaque.margin * 0.5);
+    let outline = Outline {
+        half_w,
+        half_h,
+        radius: plaque.corner_radius.min(half_w).min(half_h),
+        holes: if plaque.holes {
+            vec![
+                Point::new(-hole_x, hole_y),
+                Point::new(hole_x, hole_y),
+                Point::new(-hole_x, -hole_y),
+                Point::new(hole_x, -hole_y),
+            ]
+        } else {
+            Vec::new()
+        },
+        hole_radius: plaque.hole_diameter * 0.5,
+        slots: Vec::new(),
+    };
+    let mut parts = raised_parts(
+        text,
+        &outline,
+        thickness - bevel,
+        0.0,
+        options,
+        options.tolerance(face),
+        cancel,
+    )?;
+    chamfer_plate(&mut parts, bevel, options.text_depth());
+    Ok(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font;
+
+    #[test]
+    fn lines_get_their_own_cap_heights() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let plaque = AwardPlaque {
+            line_heights: vec![8.0],
+            ..Default::default()
+        };
+        assert_eq!(plaque.line_height(0), 10.0);
+        assert_eq!(plaque.line_height(1), 8.0);
+        assert_eq!(plaque.line_height(2), 5.0);
+
+        // Flat-topped capitals only, so the ink spans exactly the cap heights and gaps
+        let options = Options {
+            text: "HEH\nHH\nH".into(),
+            depth: 1.0,
+            ..Default::default()
+        };
+        let parts = plaque_parts(&face, &options, &plaque, &CancelToken::new()).unwrap();
+        let names: Vec<&str> = parts.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["plate", "chamfer", "text"]);
+        let (.., min_y, max_y) = mesh::mesh_bounds(&parts[2].mesh).unwrap();
+        let expected = 10.0 + 8.0 + 5.0 + (10.0 + 8.0) * plaque.line_gap;
+        assert!((max_y - min_y - expected).abs() < 0.05, "{}", max_y - min_y);
+        assert_eq!(mesh::boundary_loops(&parts[0].mesh).len(), 5);
+    }
+}
@@ -0,0 +1,135 @@
+//! ASCII PLY writer with faces colored by type, for visual QA in MeshLab:
+//! a cap that shows the wrong color is inverted, a missing wall shows as a gap
+
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::Result;
+use lyon_path::math::Point;
+
+use crate::color::Color;
+use crate::mesh::{calc_normal, map_point, Orientation};
+use crate::output;
+use crate::Part;
+
+/// Upward-facing cap of the text
+pub const TOP_CAP_COLOR: Color = Color::rgb(0x4c, 0xaf, 0x50);
+/// Downward-facing cap of the text
+pub const BOTTOM_CAP_COLOR: Color = Color::rgb(0x21, 0x96, 0xf3);
+/// Side walls (straight or chamfered) of the text
+pub const WALL_COLOR: Color = Color::rgb(0xff, 0x98, 0x00);
+/// Every face of the plate the text stands on
+pub const PLATE_COLOR: Color = Color::LIGHT_GRAY;
+
+/// Color of one triangle: caps are told apart by their winding, so a
+/// flipped cap takes the other cap's color
+fn face_color(normal: [f32; 3], up: [f32; 3]) -> Color {
+    let dot: f32 = (0..3).map(|k| normal[k] * up[k]).sum();
+    if dot > 0.999 {
+        TOP_CAP_COLOR
+    } else if dot < -0.999 {
+        BOTTOM_CAP_COLOR
+    } else {
+        WALL_COLOR
+    }
+}
+
+/// The first part is a plate when the others stand on it
+fn is_plate(parts: &[Part], index: usize) -> bool {
+    let bottom = |part: &Part| part.z_offset - part.depth * 0.5;
+    index == 0
+        && parts.len() > 1
+        && parts[1..]
+            .iter()
+            .all(|part| bottom(part) > bottom(&parts[0]) + 1e-4)
+}
+
+pub fn write_ply(path: &Path, parts: &[Part], orient: &Orientation) -> Result<()> {
+    output::write_atomically(path, |file| {
+        write_ply_to_writer(BufWriter::new(file), parts, orient)
+    })
+}
+
+pub fn write_ply_to_writer<W: Write>(
+    mut writer: W,
+    parts: &[Part],
+    orient: &Orientation,
+) -> Result<()> {
+    let up = map_point(Point::new(0.0, 0.0), 1.0, orient);
+    let mut positions = Vec::new();
+    let mut faces = Vec::new();
+    for (index, part) in parts.iter().enumerate() {
+        let (part_positions, part_indices) = part.indexed(orient, None).shared_positions();
+        let base = positions.len() as u32;
+        let plate = is_plate(parts, index);
+        for t in part_indices.chunks(3) {
+            let [a, b, c] = [t[0], t[1], t[2]].map(|i| part_positions[i as usize]);
+            let color = if plate {
+                PLATE_COLOR
+            } else {
+                face_color(calc_normal(a, b, c), up)
+            };
+            faces.push(([t[0] + base, t[1] + base, t[2] + base], color));
+        }
+        positions.extend(part_positions);
+    }
+
+    writeln!(writer, "ply")?;
+    writeln!(writer, "format ascii 1.0")?;
+    writeln!(writer, "comment wagyan {}", env!("CARGO_PKG_VERSION"))?;
+    writeln!(writer, "element vertex {}", positions.len())?;
+    writeln!(writer, "property float x")?;
+    writeln!(writer, "property float y")?;
+    writeln!(writer, "property float z")?;
+    writeln!(writer, "element face {}", faces.len())?;
+    writeln!(writer, "property list uchar int vertex_indices")?;
+    writeln!(writer, "property uchar red")?;
+    writeln!(writer, "property uchar green")?;
+    writeln!(writer, "property uchar blue")?;
+    writeln!(writer, "end_header")?;
+    for [x, y, z] in &positions {
+        writeln!(writer, "{} {} {}", x, y, z)?;
+    }
+    for ([a, b, c], color) in &faces {
+        writeln!(
+            writer,
+            "3 {} {} {} {} {} {}",
+            a, b, c, color.r, color.g, color.b
+        )?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::rectangle_mesh;
+    use crate::{model_parts, Options};
+
+    #[test]
+    fn faces_are_colored_by_type() {
+        let options = Options {
+            plate: 1.0,
+            orient: Orientation::Front,
+            ..Default::default()
+        };
+        let parts = model_parts(rectangle_mesh(0.0, 10.0, 0.0, 4.0), &options);
+        let mut out = Vec::new();
+        write_ply_to_writer(&mut out, &parts, &options.orient).unwrap();
+        let ply = String::from_utf8(out).unwrap();
+
+        let color_count = |color: Color| {
+            let suffix = format!(" {} {} {}", color.r, color.g, color.b);
+            ply.lines()
+                .filter(|l| l.starts_with("3 ") && l.ends_with(&suffix))
+                .count()
+        };
+        // A box each: two triangles per cap, eight on the walls
+        assert_eq!(color_count(PLATE_COLOR), 12);
+        assert_eq!(color_count(TOP_CAP_COLOR), 2);
+        assert_eq!(color_count(BOTTOM_CAP_COLOR), 2);
+        assert_eq!(color_count(WALL_COLOR), 8);
+        assert!(ply.contains("element vertex 16\n"));
+    }
+}
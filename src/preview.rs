@@ -0,0 +1,79 @@
+//! `--preview-term`: the tessellated layout as block characters, for a
+//! quick look over SSH before exporting
+
+use lyon_path::math::Point;
+
+use crate::mesh::{mesh_bounds, Mesh2D};
+
+/// Rasterize the mesh seen from above into `columns` characters per line.
+/// Each character holds two square pixels stacked (`▀`, `▄`, `█`), since
+/// terminal cells are about twice as tall as wide.
+pub fn render(mesh: &Mesh2D, columns: usize) -> String {
+    let Some((min_x, max_x, min_y, max_y)) = mesh_bounds(mesh) else {
+        return String::new();
+    };
+    let columns = columns.max(1);
+    let pixel = ((max_x - min_x) / columns as f32).max(f32::EPSILON);
+    let rows = (((max_y - min_y) / pixel).ceil() as usize).max(1);
+    let rows = rows + rows % 2;
+
+    let mut filled = vec![false; columns * rows];
+    for t in mesh.indices.chunks(3) {
+        let [a, b, c] = [t[0], t[1], t[2]].map(|i| mesh.vertices[i as usize]);
+        let column_of = |x: f32| ((x - min_x) / pixel).floor().max(0.0) as usize;
+        let row_of = |y: f32| ((max_y - y) / pixel).floor().max(0.0) as usize;
+        let (x0, x1) = (a.x.min(b.x).min(c.x), a.x.max(b.x).max(c.x));
+        let (y0, y1) = (a.y.min(b.y).min(c.y), a.y.max(b.y).max(c.y));
+        for row in row_of(y1)..=row_of(y0).min(rows - 1) {
+            for column in column_of(x0)..=column_of(x1).min(columns - 1) {
+                let x = min_x + (column as f32 + 0.5) * pixel;
+                let y = max_y - (row as f32 + 0.5) * pixel;
+                // Same side of all three edges, whatever the winding
+                let side = |p: Point, q: Point| (q.x - p.x) * (y - p.y) - (q.y - p.y) * (x - p.x);
+                let (d0, d1, d2) = (side(a, b), side(b, c), side(c, a));
+                let inside =
+                    (d0 >= 0.0 && d1 >= 0.0 && d2 >= 0.0) || (d0 <= 0.0 && d1 <= 0.0 && d2 <= 0.0);
+                if inside {
+                    filled[row * columns + column] = true;
+                }
+            }
+        }
+    }
+
+    let mut out = String::new();
+    for pair in filled.chunks(columns * 2) {
+        let (upper, lower) = pair.split_at(columns);
+        let line: String = upper
+            .iter()
+            .zip(lower)
+            .map(|(&upper, &lower)| match (upper, lower) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            })
+            .collect();
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::rectangle_mesh;
+
+    #[test]
+    fn blocks_follow_the_shape() {
+        // An L: a 4 x 1 bar with a 1 x 3 post on its left end
+        let mut mesh = rectangle_mesh(0.0, 4.0, 0.0, 1.0);
+        let post = rectangle_mesh(0.0, 1.0, 1.0, 4.0);
+        let base = mesh.vertices.len() as u16;
+        mesh.vertices.extend(post.vertices);
+        mesh.indices.extend(post.indices.iter().map(|i| i + base));
+
+        assert_eq!(render(&mesh, 4), "█\n█▄▄▄\n");
+        assert_eq!(render(&mesh, 8), "██\n██\n██\n████████\n");
+    }
+}
@@ -0,0 +1,113 @@
+//! Printer presets and the print-oriented checks they configure
+
+use ttf_parser::Face;
+
+/// Common FDM printers with a 0.4 mm nozzle
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Printer {
+    /// Prusa MK4
+    Mk4,
+    /// Bambu Lab A1 mini
+    A1mini,
+    /// Bambu Lab P1S
+    P1s,
+    /// Creality Ender-3
+    Ender3,
+}
+
+/// What a preset bundles
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PrinterProfile {
+    pub name: &'static str,
+    /// Build volume in mm (x, y, z)
+    pub build_volume: [f32; 3],
+    /// Thinnest stroke that still prints cleanly, in mm (two nozzle widths)
+    pub min_feature: f32,
+    /// Default layer height in mm
+    pub layer_height: f32,
+}
+
+impl Printer {
+    pub fn profile(self) -> PrinterProfile {
+        let (name, build_volume) = match self {
+            Printer::Mk4 => ("Prusa MK4", [250.0, 210.0, 220.0]),
+            Printer::A1mini => ("Bambu Lab A1 mini", [180.0, 180.0, 180.0]),
+            Printer::P1s => ("Bambu Lab P1S", [256.0, 256.0, 256.0]),
+            Printer::Ender3 => ("Creality Ender-3", [220.0, 220.0, 250.0]),
+        };
+        PrinterProfile {
+            name,
+            build_volume,
+            min_feature: 0.8,
+            layer_height: 0.2,
+        }
+    }
+}
+
+/// Approximate stem width in font units: the ink width of a straight
+/// vertical glyph ('I', 'l' or '|')
+pub fn stem_width(face: &Face<'_>) -> Option<f32> {
+    ['I', 'l', '|'].iter().find_map(|&ch| {
+        let rect = face.glyph_bounding_box(face.glyph_index(ch)?)?;
+        Some(f32::from(rect.x_max - rect.x_min)).filter(|w| *w > 0.0)
+    })
+}
+
+/// Warnings for a model that does not suit the printer
+pub fn check(
+    profile: &PrinterProfile,
+    bounds: Option<([f32; 3], [f32; 3])>,
+    stem_mm: Option<f32>,
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    if let Some((lo, hi)) = bounds {
+        let size = [hi[0] - lo[0], hi[1] - lo[1], hi[2] - lo[2]];
+        if size
+            .iter()
+            .zip(profile.build_volume)
+            .any(|(size, max)| *size > max)
+        {
+            warnings.push(format!(
+                "model is {:.1} x {:.1} x {:.1} mm, larger than the {} build volume ({} x {} x {} mm)",
+                size[0],
+                size[1],
+                size[2],
+                profile.name,
+                profile.build_volume[0],
+                profile.build_volume[1],
+                profile.build_volume[2]
+            ));
+        }
+    }
+
+    if let Some(stem) = stem_mm.filter(|stem| *stem < profile.min_feature) {
+        warnings.push(format!(
+            "letter strokes are about {:.2} mm wide, below the {:.1} mm the {} prints cleanly; increase the size",
+            stem, profile.min_feature, profile.name
+        ));
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_flags_oversized_models_and_thin_strokes() {
+        let profile = Printer::A1mini.profile();
+        let fits = Some(([0.0; 3], [100.0, 50.0, 5.0]));
+        assert!(check(&profile, fits, Some(2.0)).is_empty());
+
+        let too_wide = Some(([-100.0, 0.0, 0.0], [100.0, 50.0, 5.0]));
+        let warnings = check(&profile, too_wide, Some(0.3));
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("build volume"));
+    }
+}
@@ -0,0 +1,21 @@
+//! Progress reporting for embedding hosts (GUI progress bars etc.)
+
+/// Pipeline stage reported to progress callbacks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Glyph outlines collected (fraction of glyphs done)
+    Layout,
+    /// 2D tessellation (reported at start and end)
+    Tessellate,
+    /// Extrusion (fraction of triangles emitted)
+    Extrude,
+}
+
+/// Progress callback: stage and completion in `0.0..=1.0`
+pub type ProgressFn<'a> = &'a dyn Fn(Stage, f32);
+
+/// Callback that ignores all progress
+pub fn no_progress(_: Stage, _: f32) {}
+
+/// Triangles between extrusion progress reports
+pub const EXTRUDE_REPORT_INTERVAL: usize = 1024;
@@ -0,0 +1,654 @@
+//! Styled runs: stretches of the text with their own em size, extrusion
+//! depth or font, laid out one after another on shared baselines.
+//!
+//! `--markup` reads them from tags in the text, such as
+//! `{size=48}big{/size}`, `{depth=2}` or `{font=serif.ttf}`. Tags nest and
+//! close in reverse order; `{{` and `}}` stand for literal braces.
+//! `^{2}` and `_{2}` raise or lower what they enclose as a superscript or
+//! subscript (see [`Script`]). `--runs`
+//! reads them from a JSON list instead (see [`parse_runs_json`]). Each run
+//! is shaped on its own, so kerning stops at run boundaries. Lines are set
+//! apart by the deepest descent above and the tallest ascent below, which
+//! for text in one size is the font's own line height.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use ttf_parser::Face;
+
+use crate::gsub::{self, RunGlyph};
+use crate::layout::{self, Align, Layout, LayoutLine, WritingMode};
+use crate::{font, Options};
+
+/// Share of the em a superscript or subscript is drawn at when the font
+/// gives no size for them
+const SCRIPT_SIZE: f32 = 0.6;
+/// How far superscripts are raised, and subscripts lowered, in em when the
+/// font does not say
+const SUPERSCRIPT_RISE: f32 = 0.35;
+const SUBSCRIPT_DROP: f32 = 0.15;
+
+/// A run set above or below the baseline, for "m²" or "H₂O": in the font's
+/// `sups` or `subs` forms when it has them for every character, else as
+/// smaller glyphs moved as far as its OS/2 table says
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Script {
+    Super,
+    Sub,
+}
+
+impl Script {
+    /// The OpenType feature with the font's own forms
+    fn feature(self) -> &'static str {
+        match self {
+            Script::Super => "sups",
+            Script::Sub => "subs",
+        }
+    }
+
+    /// How `text` is set in `face`: the feature to apply, or else the size
+    /// relative to the run and the rise in font units
+    fn placement(self, face: &Face<'_>, text: &str) -> (Option<&'static str>, f32, f32) {
+        if has_forms(face, text, self.feature()) {
+            return (Some(self.feature()), 1.0, 0.0);
+        }
+        let em = face.units_per_em() as f32;
+        let metrics = match self {
+            Script::Super => face.superscript_metrics(),
+            Script::Sub => face.subscript_metrics(),
+        };
+        let size = metrics
+            .filter(|metrics| metrics.y_size > 0)
+            .map_or(SCRIPT_SIZE, |metrics| metrics.y_size as f32 / em);
+        // OS/2 measures the subscript offset downwards
+        let offset = metrics
+            .map(|metrics| metrics.y_offset as f32)
+            .filter(|&offset| offset > 0.0);
+        let rise = match self {
+            Script::Super => offset.unwrap_or(SUPERSCRIPT_RISE * em),
+            Script::Sub => -offset.unwrap_or(SUBSCRIPT_DROP * em),
+        };
+        (None, size, rise)
+    }
+}
+
+/// Whether the GSUB `feature` of `face` replaces every character of `text`
+/// but spaces
+fn has_forms(face: &Face<'_>, text: &str, feature: &str) -> bool {
+    let Ok(tag) = gsub::parse_tag(feature) else {
+        return false;
+    };
+    let plain: Option<Vec<RunGlyph>> = text
+        .chars()
+        .filter(|ch| !ch.is_whitespace())
+        .map(|ch| Some((face.glyph_index(ch)?, ch)))
+        .collect();
+    let Some(plain) = plain.filter(|plain| !plain.is_empty()) else {
+        return false;
+    };
+    let mut forms = plain.clone();
+    gsub::substitute(face, &[tag], &mut forms);
+    forms.len() == plain.len()
+        && forms
+            .iter()
+            .zip(&plain)
+            .all(|(form, glyph)| form.0 != glyph.0)
+}
+
+/// How a run differs from the rest of the text; `None` keeps the setting
+/// of [`Options`]
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct RunStyle {
+    /// Em size in mm
+    pub size: Option<f32>,
+    /// Extrusion depth in mm
+    pub depth: Option<f32>,
+    /// Font file; characters it lacks still come from the other fonts
+    pub font: Option<PathBuf>,
+    /// Additional spacing between glyphs in em
+    pub tracking: Option<f32>,
+    /// Raised or lowered as a superscript or subscript
+    pub script: Option<Script>,
+}
+
+/// A stretch of text in one style
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Run {
+    pub text: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub style: RunStyle,
+}
+
+/// A positive length in mm: 48, or 48mm
+fn length(tag: &str, value: &str) -> Result<f32> {
+    match value
+        .trim()
+        .strip_suffix("mm")
+        .unwrap_or(value.trim())
+        .parse::<f32>()
+    {
+        Ok(v) if v > 0.0 && v.is_finite() => Ok(v),
+        _ => anyhow::bail!(
+            "{{{}={}}} needs a positive length in mm, such as {{{}=12}}",
+            tag,
+            value,
+            tag
+        ),
+    }
+}
+
+/// How the tag `name` is written where it opens, for messages
+fn opening(name: &str) -> String {
+    match name {
+        "^" | "_" => format!("{}{{", name),
+        _ => format!("{{{}=...}}", name),
+    }
+}
+
+/// Split text with `{size=..}`, `{depth=..}` and `{font=..}` tags and
+/// `^{..}` and `_{..}` scripts into runs; neighbouring runs in the same
+/// style are merged
+pub fn parse_markup(text: &str) -> Result<Vec<Run>> {
+    let mut runs: Vec<Run> = Vec::new();
+    let mut open: Vec<(&str, RunStyle)> = Vec::new();
+    let mut style = RunStyle::default();
+    let mut current = String::new();
+    let mut rest = text;
+    let flush = |runs: &mut Vec<Run>, current: &mut String, style: &RunStyle| {
+        if current.is_empty() {
+            return;
+        }
+        match runs.last_mut() {
+            Some(last) if last.style == *style => last.text.push_str(current),
+            _ => runs.push(Run {
+                text: current.clone(),
+                style: style.clone(),
+            }),
+        }
+        current.clear();
+    };
+    while let Some(at) = rest.find(['{', '}', '^', '_']) {
+        current.push_str(&rest[..at]);
+        let tail = &rest[at..];
+        if let Some(brace) = ["{{", "}}"].iter().find(|brace| tail.starts_with(**brace)) {
+            current.push_str(&brace[1..]);
+            rest = &tail[2..];
+            continue;
+        }
+        if let Some(script) = ["^{", "_{"]
+            .iter()
+            .find(|script| tail.starts_with(**script))
+        {
+            flush(&mut runs, &mut current, &style);
+            open.push((&script[..1], style.clone()));
+            style.script = Some(match &script[..1] {
+                "^" => Script::Super,
+                _ => Script::Sub,
+            });
+            rest = &tail[2..];
+            continue;
+        }
+        if tail.starts_with(['^', '_']) {
+            current.push_str(&tail[..1]);
+            rest = &tail[1..];
+            continue;
+        }
+        if let Some(after) = tail.strip_prefix('}') {
+            let (name, outer) = open
+                .pop()
+                .context("markup has a '}' that closes no script (write '}}' for a brace)")?;
+            anyhow::ensure!(
+                matches!(name, "^" | "_"),
+                "markup closes a script while {} is still open",
+                opening(name)
+            );
+            flush(&mut runs, &mut current, &style);
+            style = outer;
+            rest = after;
+            continue;
+        }
+        let end = tail
+            .find('}')
+            .context("markup has a '{' that is never closed (write '{{' for a brace)")?;
+        let tag = &tail[1..end];
+        rest = &tail[end + 1..];
+        flush(&mut runs, &mut current, &style);
+        if let Some(name) = tag.strip_prefix('/') {
+            let (opened, outer) = open
+                .pop()
+                .with_context(|| format!("markup closes {{/{}}} without opening it", name))?;
+            anyhow::ensure!(
+                opened == name,
+                "markup closes {{/{}}} while {} is still open",
+                name,
+                opening(opened)
+            );
+            style = outer;
+            continue;
+        }
+        let (name, value) = tag.split_once('=').with_context(|| {
+            format!("markup tag {{{}}} needs a value, such as {{size=48}}", tag)
+        })?;
+        open.push((name, style.clone()));
+        match name {
+            "size" => style.size = Some(length(name, value)?),
+            "depth" => style.depth = Some(length(name, value)?),
+            "font" => style.font = Some(PathBuf::from(value)),
+            _ => anyhow::bail!("unknown markup tag {{{}}}: use size, depth or font", tag),
+        }
+    }
+    current.push_str(rest);
+    flush(&mut runs, &mut current, &style);
+    if let Some((name, _)) = open.last() {
+        anyhow::bail!("markup opens {} without closing it", opening(name));
+    }
+    Ok(runs)
+}
+
+/// Runs from a JSON list of objects such as
+/// `{"text": "東京", "font": "jp.otf", "size": 48, "depth": 4, "tracking": 0.1}`,
+/// in which only `text` is required; sizes and depths are in mm and
+/// tracking in em, as with the flags, and `"script"` is `"super"` or `"sub"`
+pub fn parse_runs_json(json: &str) -> Result<Vec<Run>> {
+    let value: Value = serde_json::from_str(json).context("runs are not valid JSON")?;
+    let list = value
+        .as_array()
+        .context("runs must be a JSON list of {\"text\": ...} objects")?;
+    let mut runs = Vec::new();
+    for (i, item) in list.iter().enumerate() {
+        let object = item
+            .as_object()
+            .with_context(|| format!("run {} is not an object", i))?;
+        let number = |key: &str| -> Result<Option<f32>> {
+            match object.get(key) {
+                None | Some(Value::Null) => Ok(None),
+                Some(value) => match value.as_f64() {
+                    Some(v) => Ok(Some(v as f32)),
+                    None => anyhow::bail!("\"{}\" of run {} is not a number", key, i),
+                },
+            }
+        };
+        let string = |key: &str| -> Result<Option<&str>> {
+            match object.get(key) {
+                None | Some(Value::Null) => Ok(None),
+                Some(value) => match value.as_str() {
+                    Some(s) => Ok(Some(s)),
+                    None => anyhow::bail!("\"{}\" of run {} is not a string", key, i),
+                },
+            }
+        };
+        if let Some(key) = object.keys().find(|key| {
+            !["text", "font", "size", "depth", "tracking", "script"].contains(&key.as_str())
+        }) {
+            anyhow::bail!(
+                "run {} has an unknown key \"{}\": use text, font, size, depth, tracking or script",
+                i,
+                key
+            );
+        }
+        let text = string("text")?.with_context(|| format!("run {} has no \"text\"", i))?;
+        let style = RunStyle {
+            size: number("size")?,
+            depth: number("depth")?,
+            font: string("font")?.map(PathBuf::from),
+            tracking: number("tracking")?,
+            script: match string("script")? {
+                None => None,
+                Some("super") => Some(Script::Super),
+                Some("sub") => Some(Script::Sub),
+                Some(other) => anyhow::bail!(
+                    "\"script\" of run {} is \"{}\": use \"super\" or \"sub\"",
+                    i,
+                    other
+                ),
+            },
+        };
+        for (key, value) in [("size", style.size), ("depth", style.depth)] {
+            if let Some(v) = value {
+                anyhow::ensure!(
+                    v > 0.0 && v.is_finite(),
+                    "\"{}\" of run {} must be a positive length in mm",
+                    key,
+                    i
+                );
+            }
+        }
+        runs.push(Run {
+            text: text.to_string(),
+            style,
+        });
+    }
+    Ok(runs)
+}
+
+/// [`parse_runs_json`] of a file; relative font paths are taken from the
+/// file's directory
+pub fn load_runs(path: &Path) -> Result<Vec<Run>> {
+    let json = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read runs from {}", path.display()))?;
+    let mut runs =
+        parse_runs_json(&json).with_context(|| format!("bad runs in {}", path.display()))?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    for font in runs.iter_mut().filter_map(|run| run.style.font.as_mut()) {
+        if font.is_relative() {
+            *font = dir.join(&*font);
+        }
+    }
+    Ok(runs)
+}
+
+/// The text of `runs` without their styles
+pub fn plain_text(runs: &[Run]) -> String {
+    runs.iter().map(|run| run.text.as_str()).collect()
+}
+
+/// Runs of each line: `runs` split at their `\n`s
+fn lines(runs: &[Run]) -> Vec<Vec<Run>> {
+    let mut lines = vec![Vec::new()];
+    for run in runs {
+        for (i, text) in run.text.split('\n').enumerate() {
+            if i > 0 {
+                lines.push(Vec::new());
+            }
+            if !text.is_empty() {
+                lines.last_mut().unwrap().push(Run {
+                    text: text.to_string(),
+                    style: run.style.clone(),
+                });
+            }
+        }
+    }
+    lines
+}
+
+/// `metrics` (ascent, descent) grown to fit `face` at `scale`
+fn max_metrics(metrics: (f32, f32), face: &Face<'_>, scale: f32) -> (f32, f32) {
+    (
+        metrics.0.max(face.ascender() as f32 * scale),
+        metrics.1.max(-face.descender() as f32 * scale),
+    )
+}
+
+/// [`crate::layout`] of `options.runs`: each run laid out as a line of its
+/// own in its size and font, then set after the one before; lines are
+/// aligned as usual but never wrapped
+pub(crate) fn layout_runs(
+    face: &Face<'_>,
+    options: &Options,
+    scale: f32,
+    baseline_y: f32,
+) -> Layout {
+    let paths = options.fallback_paths();
+    let fallback_data = options.fallback_data();
+    let fallbacks = font::fallback_faces(&fallback_data);
+    // Each run is laid out in its own font with every face as a fallback,
+    // so its glyphs' font indices are one past the shared ones
+    let faces: Vec<Face<'_>> = std::iter::once(face.clone())
+        .chain(fallbacks.iter().cloned())
+        .collect();
+    let mut result = Layout::default();
+    let mut baseline = baseline_y;
+    // Descent of the line above, which the next one is set below
+    let mut descent_above = 0.0;
+    for (index, line) in lines(&options.runs).into_iter().enumerate() {
+        let mut laid = Vec::new();
+        // Ascent and descent of the line's tallest runs
+        let mut metrics = (0.0f32, 0.0f32);
+        for run in &line {
+            let font = run
+                .style
+                .font
+                .as_ref()
+                .and_then(|font| paths.iter().position(|path| path == font))
+                .map_or(0, |i| i + 1);
+            let run_face = &faces[font];
+            let size = run
+                .style
+                .size
+                .map_or(1.0, |size| size / (scale * face.units_per_em() as f32));
+            let full_scale =
+                scale * size * face.units_per_em() as f32 / run_face.units_per_em() as f32;
+            let mut run_options = Options {
+                text: run.text.clone(),
+                tracking: run.style.tracking.unwrap_or(options.tracking),
+                max_width: None,
+                align: Align::Left,
+                ..options.clone()
+            };
+            let (forms, shrink, rise) = match run.style.script {
+                Some(script) => script.placement(run_face, &run.text),
+                None => (None, 1.0, 0.0),
+            };
+            run_options.features.extend(forms.map(str::to_string));
+            let run_scale = full_scale * shrink;
+            let run_layout = crate::layout_text(run_face, &faces, &run_options, run_scale, 0.0);
+            // Scripts keep the line as tall as their text would be
+            metrics = max_metrics(metrics, run_face, full_scale);
+            laid.push((run, font, size * shrink, rise * full_scale, run_layout));
+        }
+        if line.is_empty() {
+            metrics = max_metrics(metrics, face, scale);
+        }
+        let (ascent, descent) = metrics;
+        if index > 0 {
+            baseline -= options.line_height.resolve(descent_above + ascent);
+        }
+        descent_above = descent;
+        let mut line_layout = LayoutLine {
+            text: String::new(),
+            width: 0.0,
+            baseline,
+            glyph_count: 0,
+            offset: 0.0,
+            wrapped: false,
+        };
+        for (run, font, size, rise, run_layout) in laid {
+            let pen = line_layout.width;
+            for mut glyph in run_layout.glyphs {
+                glyph.font = match glyph.font {
+                    0 => font,
+                    i => i - 1,
+                };
+                glyph.size *= size;
+                glyph.x += pen;
+                glyph.y += baseline + rise;
+                glyph.depth = run.style.depth;
+                result.glyphs.push(glyph);
+                line_layout.glyph_count += 1;
+            }
+            if let Some(run_line) = run_layout.lines.first() {
+                line_layout.width += run_line.width;
+            }
+            line_layout.text.push_str(&run.text);
+            for ch in run_layout.missing {
+                if !result.missing.contains(&ch) {
+                    result.missing.push(ch);
+                }
+            }
+        }
+        result.lines.push(line_layout);
+    }
+    layout::align_lines(&mut result, options.align, None, WritingMode::Horizontal);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font;
+
+    fn run(text: &str, style: RunStyle) -> Run {
+        Run {
+            text: text.to_string(),
+            style,
+        }
+    }
+
+    #[test]
+    fn tags_nest_and_braces_escape() {
+        let runs = parse_markup("a{size=48}b{depth=2mm}c{/depth}{{d}}{/size}").unwrap();
+        let big = RunStyle {
+            size: Some(48.0),
+            ..RunStyle::default()
+        };
+        let deep = RunStyle {
+            depth: Some(2.0),
+            ..big.clone()
+        };
+        assert_eq!(
+            runs,
+            [
+                run("a", RunStyle::default()),
+                run("b", big.clone()),
+                run("c", deep),
+                run("{d}", big),
+            ]
+        );
+        assert_eq!(plain_text(&runs), "abc{d}");
+        for bad in [
+            "{size=48}a",
+            "a{/size}",
+            "{size=0}a{/size}",
+            "{colour=red}a{/colour}",
+            "{size=1}a{/depth}",
+            "a}b",
+            "{size",
+        ] {
+            assert!(parse_markup(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn runs_share_the_baseline_at_their_own_size() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let plain = Options {
+            text: "HH".to_string(),
+            center: false,
+            ..Options::default()
+        };
+        let styled = Options {
+            runs: parse_markup("H{size=144}H\nH{/size}").unwrap(),
+            ..plain.clone()
+        };
+        let plain = crate::layout(&face, &plain);
+        let styled = crate::layout(&face, &styled);
+        assert_eq!(styled.lines.len(), 2);
+        let (small, big) = (&styled.glyphs[0], &styled.glyphs[1]);
+        assert_eq!((small.size, big.size), (1.0, 2.0));
+        assert_eq!(small.y, big.y);
+        assert_eq!(big.x, plain.glyphs[1].x);
+        // The big H is twice as wide, and big lines are twice as far apart
+        let advance = plain.lines[0].width - plain.glyphs[1].x;
+        assert!((styled.lines[0].width - (big.x + 2.0 * advance)).abs() < 1e-3);
+        let height = face.height() as f32 * Options::default().scale(&face);
+        assert!((styled.lines[0].baseline - styled.lines[1].baseline - 2.0 * height).abs() < 1e-3);
+    }
+
+    #[test]
+    fn deeper_runs_are_parts_standing_on_the_plate() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let options = Options {
+            runs: parse_markup("H{depth=4}I{/depth}").unwrap(),
+            depth: 2.0,
+            plate: 1.0,
+            ..Options::default()
+        };
+        let parts = crate::text_parts(
+            &face,
+            &options,
+            &crate::CancelToken::new(),
+            &crate::no_progress,
+        )
+        .unwrap();
+        let names: Vec<&str> = parts.iter().map(|part| part.name.as_str()).collect();
+        assert_eq!(names, ["plate", "text", "text_1"]);
+        let bottom = |part: &crate::Part| part.z_offset - part.depth * 0.5;
+        assert_eq!((parts[1].depth, parts[2].depth), (2.0, 4.0));
+        assert_eq!(bottom(&parts[1]), bottom(&parts[2]));
+        assert_eq!(bottom(&parts[0]) + parts[0].depth, bottom(&parts[1]));
+        assert!(parts[2].is_text());
+    }
+
+    #[test]
+    fn json_runs_carry_their_style() {
+        let runs = parse_runs_json(
+            r#"[{"text": "Tokyo "}, {"text": "東京", "font": "jp.otf", "size": 48, "tracking": -0.05}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            runs[1],
+            run(
+                "東京",
+                RunStyle {
+                    size: Some(48.0),
+                    font: Some(PathBuf::from("jp.otf")),
+                    tracking: Some(-0.05),
+                    ..RunStyle::default()
+                }
+            )
+        );
+        assert_eq!(plain_text(&runs), "Tokyo 東京");
+        for bad in [
+            r#"{"text": "a"}"#,
+            r#"[{"size": 12}]"#,
+            r#"[{"text": "a", "size": -1}]"#,
+            r#"[{"text": "a", "colour": "red"}]"#,
+            r#"[{"text": 1}]"#,
+        ] {
+            assert!(parse_runs_json(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn scripts_open_with_a_caret_or_underscore() {
+        let runs = parse_markup("m^{2} H_{2}O a^b_c").unwrap();
+        let script = |script| RunStyle {
+            script: Some(script),
+            ..RunStyle::default()
+        };
+        assert_eq!(
+            runs,
+            [
+                run("m", RunStyle::default()),
+                run("2", script(Script::Super)),
+                run(" H", RunStyle::default()),
+                run("2", script(Script::Sub)),
+                run("O a^b_c", RunStyle::default()),
+            ]
+        );
+        for bad in ["^{2", "^{2{/size}", "{size=9}^{2{/size}}", "x}"] {
+            assert!(parse_markup(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn scripts_without_forms_shrink_and_move_by_the_os2_metrics() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let options = Options {
+            runs: parse_markup("m^{2}H_{2}").unwrap(),
+            center: false,
+            ..Options::default()
+        };
+        let layout = crate::layout(&face, &options);
+        let scale = options.scale(&face);
+        let (base, sup, sub) = (&layout.glyphs[0], &layout.glyphs[1], &layout.glyphs[3]);
+        let metrics = face.superscript_metrics().unwrap();
+        let size = metrics.y_size as f32 / face.units_per_em() as f32;
+        assert_eq!((sup.size, sub.size), (size, size));
+        assert!((sup.y - base.y - metrics.y_offset as f32 * scale).abs() < 1e-3);
+        let drop = face.subscript_metrics().unwrap().y_offset as f32 * scale;
+        assert!((base.y - sub.y - drop).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,97 @@
+//! HarfBuzz-compatible shaping through rustybuzz (the `shaping` feature)
+
+use ttf_parser::{Face, GlyphId};
+
+use crate::layout::{ShapedGlyph, Shaping};
+
+/// Shape `text`, all of which `face` has glyphs for, in visual order. The
+/// user's features are turned on for the whole run and `kern` is turned off
+/// without kerning.
+pub fn shape(face: &Face<'_>, text: &str, rtl: bool, shaping: &Shaping) -> Vec<ShapedGlyph> {
+    let face = rustybuzz::Face::from_face(face.clone());
+    let mut buffer = rustybuzz::UnicodeBuffer::new();
+    buffer.push_str(text);
+    buffer.guess_segment_properties();
+    buffer.set_direction(if rtl {
+        rustybuzz::Direction::RightToLeft
+    } else {
+        rustybuzz::Direction::LeftToRight
+    });
+
+    let mut features: Vec<rustybuzz::Feature> = shaping
+        .features
+        .iter()
+        .map(|&tag| rustybuzz::Feature::new(tag, 1, ..))
+        .collect();
+    if !shaping.kerning {
+        features.push(rustybuzz::Feature::new(
+            ttf_parser::Tag::from_bytes(b"kern"),
+            0,
+            ..,
+        ));
+    }
+
+    let shaped = rustybuzz::shape(&face, &features, buffer);
+    shaped
+        .glyph_infos()
+        .iter()
+        .zip(shaped.glyph_positions())
+        .map(|(info, pos)| ShapedGlyph {
+            gid: GlyphId(info.glyph_id as u16),
+            ch: text[info.cluster as usize..]
+                .chars()
+                .next()
+                .unwrap_or('\u{FFFD}'),
+            x_advance: pos.x_advance,
+            x_offset: pos.x_offset,
+            y_offset: pos.y_offset,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font;
+    use crate::layout::{layout_glyphs, Shaper};
+
+    fn harfbuzz(kerning: bool) -> Shaping {
+        Shaping {
+            kerning,
+            shaper: Shaper::Harfbuzz,
+            ..Shaping::default()
+        }
+    }
+
+    #[test]
+    fn clusters_map_back_to_characters() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let glyphs = shape(&face, "Aあ", false, &harfbuzz(true));
+        let chars: Vec<char> = glyphs.iter().map(|glyph| glyph.ch).collect();
+        assert_eq!(chars, ['A', 'あ']);
+        assert_eq!(glyphs[0].gid, face.glyph_index('A').unwrap());
+    }
+
+    #[test]
+    fn harfbuzz_matches_the_builtin_kerning() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let builtin = Shaping {
+            kerning: true,
+            ..Shaping::default()
+        };
+        let width = |shaping: &Shaping| {
+            layout_glyphs(&face, &[], "AV", 1.0, 0.0, 0.0, shaping).lines[0].width
+        };
+        assert_eq!(width(&harfbuzz(true)), width(&builtin));
+        assert!(width(&harfbuzz(true)) < width(&harfbuzz(false)));
+    }
+
+    #[test]
+    fn harfbuzz_applies_default_ligatures() {
+        let face = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let shaped = layout_glyphs(&face, &[], "ffi", 1.0, 0.0, 0.0, &harfbuzz(true));
+        let plain = layout_glyphs(&face, &[], "ffi", 1.0, 0.0, 0.0, &Shaping::default());
+        assert!(shaped.glyphs.len() < plain.glyphs.len());
+        assert_eq!(shaped.glyphs[0].ch, 'f');
+    }
+}
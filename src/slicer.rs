@@ -0,0 +1,327 @@
+//! `--slicer`: project metadata PrusaSlicer and Bambu Studio read from a 3MF,
+//! so the model opens centered on the bed with its parts named, each on the
+//! filament of its color, and a filament change where the lettering starts
+
+use std::fmt::Write as _;
+
+use crate::color::Color;
+use crate::mesh::Orientation;
+use crate::printer::{Printer, PrinterProfile};
+use crate::threemf::escape;
+use crate::{model_bounds, Part};
+
+/// Slicer whose project files are added to 3MF output
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Slicer {
+    /// PrusaSlicer
+    Prusa,
+    /// Bambu Studio (and OrcaSlicer)
+    Bambu,
+}
+
+impl Slicer {
+    /// Printer whose bed the model is centered on without `--printer`
+    pub fn default_printer(self) -> Printer {
+        match self {
+            Slicer::Prusa => Printer::Mk4,
+            Slicer::Bambu => Printer::P1s,
+        }
+    }
+}
+
+/// Pause for a filament change before the layer whose top is at `print_z`
+/// (mm above the bed)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorChange {
+    pub print_z: f32,
+    pub color: Color,
+}
+
+/// How the model is set up in the slicer
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlicerProject {
+    pub slicer: Slicer,
+    /// Translation of the build item that puts the model on the bed
+    pub offset: [f32; 3],
+    pub color_change: Option<ColorChange>,
+}
+
+impl SlicerProject {
+    /// Center `parts` on the bed of `printer`, standing on it. Lettering
+    /// raised from a plate lying flat gets a change to `text_color` at its
+    /// first layer, so one extruder prints it in another color.
+    pub fn new(
+        slicer: Slicer,
+        printer: &PrinterProfile,
+        parts: &[Part],
+        orient: &Orientation,
+        layer_height: f32,
+        text_color: Color,
+    ) -> SlicerProject {
+        let (lo, hi) = model_bounds(parts, orient).unwrap_or_default();
+        let [bed_x, bed_y, _] = printer.build_volume;
+        let offset = [
+            bed_x * 0.5 - (lo[0] + hi[0]) * 0.5,
+            bed_y * 0.5 - (lo[1] + hi[1]) * 0.5,
+            -lo[2],
+        ];
+
+        let bottom = |part: &&Part| part.z_offset - part.depth * 0.5;
+        let (text, others): (Vec<&Part>, Vec<&Part>) =
+            parts.iter().partition(|part| part.is_text());
+        let text_bottom = text.iter().map(bottom).reduce(f32::min);
+        let others_bottom = others.iter().map(bottom).reduce(f32::min);
+        let color_change = match (orient, text_bottom, others_bottom) {
+            (Orientation::Flat, Some(text), Some(others)) if text > others + 1e-4 => {
+                Some(ColorChange {
+                    print_z: ((text - lo[2] + layer_height) * 1000.0).round() / 1000.0,
+                    color: text_color,
+                })
+            }
+            _ => None,
+        };
+        SlicerProject {
+            slicer,
+            offset,
+            color_change,
+        }
+    }
+}
+
+/// One part as the slicer configs refer to it
+pub(crate) struct Volume<'a> {
+    /// Id of the part's 3MF object
+    pub object_id: usize,
+    pub name: &'a str,
+    /// 1-based filament slot
+    pub extruder: usize,
+    pub triangles: usize,
+}
+
+/// Slicers take colors without alpha
+fn rgb(color: Color) -> String {
+    format!("#{:02X}{:02X}{:02X}", color.r, color.g, color.b)
+}
+
+/// Extra files for the 3MF archive: (path, contents). `parent_id` is the
+/// object grouping the `volumes`, named `title`.
+pub(crate) fn project_files(
+    project: &SlicerProject,
+    title: &str,
+    parent_id: usize,
+    volumes: &[Volume<'_>],
+) -> Vec<(&'static str, String)> {
+    match project.slicer {
+        Slicer::Prusa => prusa_files(project, title, parent_id, volumes),
+        Slicer::Bambu => bambu_files(project, title, parent_id, volumes),
+    }
+}
+
+/// PrusaSlicer merges the parts into one mesh and tells its volumes apart
+/// by triangle ranges
+fn prusa_files(
+    project: &SlicerProject,
+    title: &str,
+    parent_id: usize,
+    volumes: &[Volume<'_>],
+) -> Vec<(&'static str, String)> {
+    let mut config = String::new();
+    let _ = writeln!(config, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(config, "<config>");
+    let _ = writeln!(
+        config,
+        r#" <object id="{}" instances_count="1">"#,
+        parent_id
+    );
+    let _ = writeln!(
+        config,
+        r#"  <metadata type="object" key="name" value="{}"/>"#,
+        escape(title)
+    );
+    let mut first = 0;
+    for volume in volumes {
+        let _ = writeln!(
+            config,
+            r#"  <volume firstid="{}" lastid="{}">"#,
+            first,
+            first + volume.triangles - 1
+        );
+        let _ = writeln!(
+            config,
+            r#"   <metadata type="volume" key="name" value="{}"/>"#,
+            escape(volume.name)
+        );
+        let _ = writeln!(
+            config,
+            r#"   <metadata type="volume" key="extruder" value="{}"/>"#,
+            volume.extruder
+        );
+        let _ = writeln!(config, "  </volume>");
+        first += volume.triangles;
+    }
+    let _ = writeln!(config, " </object>\n</config>");
+    let mut files = vec![("Metadata/Slic3r_PE_model.config", config)];
+
+    if let Some(change) = project.color_change {
+        let mut xml = String::new();
+        let _ = writeln!(xml, r#"<?xml version="1.0" encoding="utf-8"?>"#);
+        let _ = writeln!(xml, "<custom_gcodes_per_print_z>");
+        let _ = writeln!(
+            xml,
+            r#"<code print_z="{}" type="0" extruder="1" color="{}" extra="" gcode="M600"/>"#,
+            change.print_z,
+            rgb(change.color)
+        );
+        let _ = writeln!(xml, r#"<mode value="SingleExtruder"/>"#);
+        let _ = writeln!(xml, "</custom_gcodes_per_print_z>");
+        files.push(("Metadata/Prusa_Slicer_custom_gcode_per_print_z.xml", xml));
+    }
+    files
+}
+
+/// Bambu Studio keeps the parts as components and puts the object on plate 1
+fn bambu_files(
+    project: &SlicerProject,
+    title: &str,
+    parent_id: usize,
+    volumes: &[Volume<'_>],
+) -> Vec<(&'static str, String)> {
+    let mut config = String::new();
+    let _ = writeln!(config, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(config, "<config>");
+    let _ = writeln!(config, r#"  <object id="{}">"#, parent_id);
+    let _ = writeln!(
+        config,
+        r#"    <metadata key="name" value="{}"/>"#,
+        escape(title)
+    );
+    let _ = writeln!(config, r#"    <metadata key="extruder" value="1"/>"#);
+    for volume in volumes {
+        let _ = writeln!(
+            config,
+            r#"    <part id="{}" subtype="normal_part">"#,
+            volume.object_id
+        );
+        let _ = writeln!(
+            config,
+            r#"      <metadata key="name" value="{}"/>"#,
+            escape(volume.name)
+        );
+        let _ = writeln!(
+            config,
+            r#"      <metadata key="extruder" value="{}"/>"#,
+            volume.extruder
+        );
+        let _ = writeln!(config, "    </part>");
+    }
+    let _ = writeln!(config, "  </object>");
+    let _ = writeln!(config, "  <plate>");
+    let _ = writeln!(config, r#"    <metadata key="plater_id" value="1"/>"#);
+    let _ = writeln!(config, "    <model_instance>");
+    let _ = writeln!(
+        config,
+        r#"      <metadata key="object_id" value="{}"/>"#,
+        parent_id
+    );
+    let _ = writeln!(config, r#"      <metadata key="instance_id" value="0"/>"#);
+    let _ = writeln!(config, "    </model_instance>\n  </plate>\n</config>");
+    let mut files = vec![("Metadata/model_settings.config", config)];
+
+    if let Some(change) = project.color_change {
+        let mut xml = String::new();
+        let _ = writeln!(xml, r#"<?xml version="1.0" encoding="utf-8"?>"#);
+        let _ = writeln!(xml, "<custom_gcodes_per_layer>\n<plate>");
+        let _ = writeln!(xml, r#"<plate_info id="1"/>"#);
+        let _ = writeln!(
+            xml,
+            r#"<layer top_z="{}" type="0" extruder="1" color="{}" extra="" gcode="M600"/>"#,
+            change.print_z,
+            rgb(change.color)
+        );
+        let _ = writeln!(xml, r#"<mode value="SingleExtruder"/>"#);
+        let _ = writeln!(xml, "</plate>\n</custom_gcodes_per_layer>");
+        files.push(("Metadata/custom_gcode_per_layer.xml", xml));
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mesh, model_parts, Options};
+
+    #[test]
+    fn model_stands_centered_on_the_bed_with_a_change_at_the_text() {
+        let options = Options {
+            plate: 2.0,
+            orient: Orientation::Flat,
+            ..Default::default()
+        };
+        let parts = model_parts(mesh::rectangle_mesh(0.0, 10.0, 0.0, 4.0), &options);
+        let printer = Printer::Mk4.profile();
+        let project = SlicerProject::new(
+            Slicer::Prusa,
+            &printer,
+            &parts,
+            &options.orient,
+            0.2,
+            Color::rgb(0xff, 0, 0),
+        );
+        let (lo, hi) = model_bounds(&parts, &options.orient).unwrap();
+        assert!((lo[0] + hi[0] + 2.0 * project.offset[0] - 250.0).abs() < 1e-3);
+        assert!((lo[1] + hi[1] + 2.0 * project.offset[1] - 210.0).abs() < 1e-3);
+        assert!((lo[2] + project.offset[2]).abs() < 1e-6);
+
+        // The text starts on top of the plate
+        let plate = &parts[0];
+        let plate_top = plate.z_offset + plate.depth * 0.5 - lo[2];
+        let change = project.color_change.unwrap();
+        assert!((change.print_z - (plate_top + 0.2)).abs() < 1e-3);
+
+        let volumes = [
+            Volume {
+                object_id: 2,
+                name: "plate",
+                extruder: 1,
+                triangles: 12,
+            },
+            Volume {
+                object_id: 3,
+                name: "text",
+                extruder: 2,
+                triangles: 12,
+            },
+        ];
+        let files = project_files(&project, "sign", 4, &volumes);
+        assert_eq!(files[0].0, "Metadata/Slic3r_PE_model.config");
+        assert!(files[0].1.contains(r#"<volume firstid="12" lastid="23">"#));
+        assert!(files[1]
+            .1
+            .contains(r##"color="#FF0000" extra="" gcode="M600""##));
+
+        let bambu = SlicerProject {
+            slicer: Slicer::Bambu,
+            ..project
+        };
+        let files = project_files(&bambu, "sign", 4, &volumes);
+        assert!(files[0]
+            .1
+            .contains(r#"<part id="3" subtype="normal_part">"#));
+        assert_eq!(files[1].0, "Metadata/custom_gcode_per_layer.xml");
+
+        // Text alone has nothing to change color on
+        let alone = model_parts(
+            mesh::rectangle_mesh(0.0, 10.0, 0.0, 4.0),
+            &Options::default(),
+        );
+        let project = SlicerProject::new(
+            Slicer::Bambu,
+            &printer,
+            &alone,
+            &Orientation::Flat,
+            0.2,
+            Color::LIGHT_GRAY,
+        );
+        assert_eq!(project.color_change, None);
+    }
+}
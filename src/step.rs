@@ -0,0 +1,759 @@
+//! STEP (ISO 10303-21, AP214) writer: every part as a B-rep solid whose
+//! walls sweep the outline itself. Glyph curves stay quadratic and cubic
+//! Béziers (B-spline edges and extruded B-spline surfaces) instead of
+//! tessellated facets, so CAD software can fillet and edit the lettering.
+
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::Result;
+use lyon_path::math::Point;
+use lyon_path::{Event, Path as GlyphPath};
+
+use crate::mesh::{map_point, Orientation};
+use crate::output::{self, Units};
+use crate::{outline, Part};
+
+/// One piece of a closed contour in the text plane
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Segment {
+    Line(Point, Point),
+    Quadratic(Point, Point, Point),
+    Cubic(Point, Point, Point, Point),
+}
+
+impl Segment {
+    /// Defining points from start to end: the ends of a line, the control
+    /// polygon of a curve
+    fn points(&self) -> Vec<Point> {
+        match *self {
+            Segment::Line(a, b) => vec![a, b],
+            Segment::Quadratic(a, b, c) => vec![a, b, c],
+            Segment::Cubic(a, b, c, d) => vec![a, b, c, d],
+        }
+    }
+
+    fn from(&self) -> Point {
+        self.points()[0]
+    }
+
+    fn reversed(&self) -> Segment {
+        match *self {
+            Segment::Line(a, b) => Segment::Line(b, a),
+            Segment::Quadratic(a, b, c) => Segment::Quadratic(c, b, a),
+            Segment::Cubic(a, b, c, d) => Segment::Cubic(d, c, b, a),
+        }
+    }
+
+    fn map(&self, f: impl Fn(Point) -> Point) -> Segment {
+        match *self {
+            Segment::Line(a, b) => Segment::Line(f(a), f(b)),
+            Segment::Quadratic(a, b, c) => Segment::Quadratic(f(a), f(b), f(c)),
+            Segment::Cubic(a, b, c, d) => Segment::Cubic(f(a), f(b), f(c), f(d)),
+        }
+    }
+
+    /// Points along the segment, end excluded, for areas and containment
+    fn samples(&self) -> Vec<Point> {
+        const STEPS: usize = 8;
+        match *self {
+            Segment::Line(a, _) => vec![a],
+            Segment::Quadratic(a, b, c) => (0..STEPS)
+                .map(|i| {
+                    let t = i as f32 / STEPS as f32;
+                    let s = 1.0 - t;
+                    (a.to_vector() * (s * s)
+                        + b.to_vector() * (2.0 * s * t)
+                        + c.to_vector() * (t * t))
+                        .to_point()
+                })
+                .collect(),
+            Segment::Cubic(a, b, c, d) => (0..STEPS)
+                .map(|i| {
+                    let t = i as f32 / STEPS as f32;
+                    let s = 1.0 - t;
+                    (a.to_vector() * (s * s * s)
+                        + b.to_vector() * (3.0 * s * s * t)
+                        + c.to_vector() * (3.0 * s * t * t)
+                        + d.to_vector() * (t * t * t))
+                        .to_point()
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Closed loop of segments, each starting where the previous one ends
+pub type Contour = Vec<Segment>;
+
+/// Area to extrude: a counter-clockwise outer contour and its clockwise holes
+#[derive(Debug, Clone)]
+pub struct Region {
+    pub outer: Contour,
+    pub holes: Vec<Contour>,
+}
+
+/// Regions extruded from `z0` to `z1` along the extrusion axis
+#[derive(Debug, Clone)]
+pub struct StepSolid {
+    pub name: String,
+    pub regions: Vec<Region>,
+    pub z0: f32,
+    pub z1: f32,
+}
+
+impl StepSolid {
+    /// Multiply every length by `factor`, e.g. to write in other units than mm
+    pub fn scale(&mut self, factor: f32) {
+        let contours = self
+            .regions
+            .iter_mut()
+            .flat_map(|region| std::iter::once(&mut region.outer).chain(&mut region.holes));
+        for segment in contours.flatten() {
+            *segment = segment.map(|p| p * factor);
+        }
+        self.z0 *= factor;
+        self.z1 *= factor;
+    }
+}
+
+/// Contours of a glyph path with its curves; zero-length lines are dropped
+/// and open subpaths closed
+pub fn path_contours(path: &GlyphPath) -> Vec<Contour> {
+    let mut contours = Vec::new();
+    let mut current: Contour = Vec::new();
+    for event in path.iter() {
+        match event {
+            Event::Begin { .. } => current = Vec::new(),
+            Event::Line { from, to } if from != to => current.push(Segment::Line(from, to)),
+            Event::Line { .. } => {}
+            Event::Quadratic { from, ctrl, to } => current.push(Segment::Quadratic(from, ctrl, to)),
+            Event::Cubic {
+                from,
+                ctrl1,
+                ctrl2,
+                to,
+            } => current.push(Segment::Cubic(from, ctrl1, ctrl2, to)),
+            Event::End { last, first, .. } => {
+                if last != first {
+                    current.push(Segment::Line(last, first));
+                }
+                if !current.is_empty() {
+                    contours.push(std::mem::take(&mut current));
+                }
+            }
+        }
+    }
+    contours
+}
+
+/// Closed polylines as contours of line segments
+pub fn polyline_contours(lines: &[Vec<Point>]) -> Vec<Contour> {
+    lines
+        .iter()
+        .map(|points| {
+            (0..points.len())
+                .map(|i| Segment::Line(points[i], points[(i + 1) % points.len()]))
+                .filter(|segment| !matches!(segment, Segment::Line(a, b) if a == b))
+                .collect::<Contour>()
+        })
+        .filter(|contour| contour.len() > 2)
+        .collect()
+}
+
+/// Even-odd test against a sampled contour
+fn contains(polygon: &[Point], p: Point) -> bool {
+    let mut inside = false;
+    for i in 0..polygon.len() {
+        let (a, b) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+        if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Group contours into regions. Whichever way the font winds its contours,
+/// the largest one is an outer contour and the ones wound against it are
+/// holes, each put into the smallest outer contour around it.
+pub fn regions(contours: Vec<Contour>) -> Vec<Region> {
+    let sampled: Vec<(Contour, Vec<Point>, f32)> = contours
+        .into_iter()
+        .map(|contour| {
+            let polygon: Vec<Point> = contour.iter().flat_map(Segment::samples).collect();
+            let area = outline::area(&polygon);
+            (contour, polygon, area)
+        })
+        .filter(|(_, _, area)| area.abs() > 1e-6)
+        .collect();
+    let Some(outer_sign) = sampled
+        .iter()
+        .map(|(_, _, area)| *area)
+        .reduce(|a, b| if b.abs() > a.abs() { b } else { a })
+        .map(f32::signum)
+    else {
+        return Vec::new();
+    };
+    // Outer contours counter-clockwise, holes clockwise
+    let orient = |contour: Contour| -> Contour {
+        match outer_sign > 0.0 {
+            true => contour,
+            false => contour.iter().rev().map(Segment::reversed).collect(),
+        }
+    };
+
+    let (outers, holes): (Vec<_>, Vec<_>) = sampled
+        .into_iter()
+        .partition(|(_, _, area)| area.signum() == outer_sign);
+    let mut regions: Vec<(Region, Vec<Point>, f32)> = outers
+        .into_iter()
+        .map(|(contour, polygon, area)| {
+            let region = Region {
+                outer: orient(contour),
+                holes: Vec::new(),
+            };
+            (region, polygon, area.abs())
+        })
+        .collect();
+    for (hole, polygon, _) in holes {
+        let around = regions
+            .iter_mut()
+            .filter(|(_, outer, _)| contains(outer, polygon[0]))
+            .min_by(|a, b| a.2.total_cmp(&b.2));
+        if let Some((region, _, _)) = around {
+            region.holes.push(orient(hole));
+        }
+    }
+    regions.into_iter().map(|(region, _, _)| region).collect()
+}
+
+/// Solids for the parts: the lettering from `glyphs` when given, one path
+/// per text part as [`crate::text_paths`] returns them, everything else
+/// from the outlines of its mesh. Chamfers are not modeled; the walls come
+/// out straight.
+pub fn solids(parts: &[Part], glyphs: &[GlyphPath]) -> Vec<StepSolid> {
+    let solid = |name: &str, part: &Part, contours| StepSolid {
+        name: name.to_string(),
+        regions: regions(contours),
+        z0: part.z_offset - part.depth * 0.5,
+        z1: part.z_offset + part.depth * 0.5,
+    };
+    let mut solids = Vec::new();
+    let mut runs_done = Vec::new();
+    for part in parts {
+        if !glyphs.is_empty() && part.is_text() {
+            // Letters and color layers share the outline of the whole text
+            let run = part
+                .name
+                .strip_prefix("text_")
+                .and_then(|i| i.parse::<usize>().ok())
+                .unwrap_or(0);
+            if runs_done.contains(&run) {
+                continue;
+            }
+            runs_done.push(run);
+            let name = match run {
+                0 => "text",
+                _ => &part.name,
+            };
+            if let Some(path) = glyphs.get(run) {
+                solids.push(solid(name, part, path_contours(path)));
+            }
+        } else {
+            let contours = polyline_contours(&outline::from_mesh(&part.mesh));
+            solids.push(solid(&part.name, part, contours));
+        }
+    }
+    solids.retain(|solid| !solid.regions.is_empty());
+    solids
+}
+
+/// STEP reals always carry a decimal point
+fn real(value: f32) -> String {
+    let value = (value as f64 * 1e6).round() / 1e6 + 0.0;
+    let text = value.to_string();
+    match text.contains('.') {
+        true => text,
+        false => text + ".",
+    }
+}
+
+/// Quoted STEP string; non-ASCII characters use the \X2\ encoding
+fn string(text: &str) -> String {
+    let mut out = String::from("'");
+    for ch in text.chars() {
+        match ch {
+            '\'' => out.push_str("''"),
+            '\\' => out.push_str("\\\\"),
+            ' '..='~' => out.push(ch),
+            _ => {
+                let mut units = [0u16; 2];
+                out.push_str("\\X2\\");
+                for unit in ch.encode_utf16(&mut units) {
+                    out.push_str(&format!("{:04X}", unit));
+                }
+                out.push_str("\\X0\\");
+            }
+        }
+    }
+    out.push('\'');
+    out
+}
+
+/// Entity instances of the DATA section, numbered from #1
+struct Entities {
+    lines: Vec<String>,
+}
+
+impl Entities {
+    fn add(&mut self, entity: String) -> usize {
+        self.lines.push(entity);
+        self.lines.len()
+    }
+
+    fn point(&mut self, p: [f32; 3]) -> usize {
+        self.add(format!(
+            "CARTESIAN_POINT('',({},{},{}))",
+            real(p[0]),
+            real(p[1]),
+            real(p[2])
+        ))
+    }
+
+    fn direction(&mut self, d: [f32; 3]) -> usize {
+        let len = (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt();
+        self.add(format!(
+            "DIRECTION('',({},{},{}))",
+            real(d[0] / len),
+            real(d[1] / len),
+            real(d[2] / len)
+        ))
+    }
+
+    fn placement(&mut self, origin: [f32; 3], axis: [f32; 3], x: [f32; 3]) -> usize {
+        let (origin, axis, x) = (self.point(origin), self.direction(axis), self.direction(x));
+        self.add(format!(
+            "AXIS2_PLACEMENT_3D('',#{},#{},#{})",
+            origin, axis, x
+        ))
+    }
+
+    fn line(&mut self, from: [f32; 3], to: [f32; 3]) -> usize {
+        let d = [to[0] - from[0], to[1] - from[1], to[2] - from[2]];
+        let len = (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt();
+        let (start, dir) = (self.point(from), self.direction(d));
+        let vector = self.add(format!("VECTOR('',#{},{})", dir, real(len)));
+        self.add(format!("LINE('',#{},#{})", start, vector))
+    }
+
+    /// A Bézier as a B-spline with a single span
+    fn bezier(&mut self, points: &[[f32; 3]]) -> usize {
+        let ids: Vec<String> = points
+            .iter()
+            .map(|&p| format!("#{}", self.point(p)))
+            .collect();
+        let degree = points.len() - 1;
+        self.add(format!(
+            "B_SPLINE_CURVE_WITH_KNOTS('',{},({}),.UNSPECIFIED.,.F.,.F.,({},{}),(0.,1.),.UNSPECIFIED.)",
+            degree,
+            ids.join(","),
+            points.len(),
+            points.len()
+        ))
+    }
+
+    fn edge(&mut self, from: usize, to: usize, curve: usize) -> usize {
+        self.add(format!("EDGE_CURVE('',#{},#{},#{},.T.)", from, to, curve))
+    }
+
+    /// Face bounded by `loops` of (edge, forward) pairs, the first one outer
+    fn face(&mut self, loops: &[Vec<(usize, bool)>], surface: usize, same_sense: bool) -> usize {
+        let flag = |b: bool| if b { ".T." } else { ".F." };
+        let mut bounds = Vec::new();
+        for (i, edges) in loops.iter().enumerate() {
+            let oriented: Vec<String> = edges
+                .iter()
+                .map(|&(edge, forward)| {
+                    let id = self.add(format!("ORIENTED_EDGE('',*,*,#{},{})", edge, flag(forward)));
+                    format!("#{}", id)
+                })
+                .collect();
+            let edge_loop = self.add(format!("EDGE_LOOP('',({}))", oriented.join(",")));
+            let kind = if i == 0 {
+                "FACE_OUTER_BOUND"
+            } else {
+                "FACE_BOUND"
+            };
+            bounds.push(format!(
+                "#{}",
+                self.add(format!("{}('',#{},.T.)", kind, edge_loop))
+            ));
+        }
+        self.add(format!(
+            "ADVANCED_FACE('',({}),#{},{})",
+            bounds.join(","),
+            surface,
+            flag(same_sense)
+        ))
+    }
+}
+
+/// Edges of one contour at both heights
+struct ContourEdges {
+    bottom: Vec<usize>,
+    top: Vec<usize>,
+}
+
+/// Faces of one extruded region
+fn region_faces(
+    entities: &mut Entities,
+    region: &Region,
+    z0: f32,
+    z1: f32,
+    orient: &Orientation,
+) -> Vec<usize> {
+    let at = |p: Point, z: f32| map_point(p, z, orient);
+    let up = map_point(Point::new(0.0, 0.0), 1.0, orient);
+    let depth = z1 - z0;
+    let mut faces = Vec::new();
+    let mut edges = Vec::new();
+    for contour in std::iter::once(&region.outer).chain(&region.holes) {
+        let n = contour.len();
+        let bottom_vertices: Vec<usize> = contour
+            .iter()
+            .map(|segment| {
+                let p = entities.point(at(segment.from(), z0));
+                entities.add(format!("VERTEX_POINT('',#{})", p))
+            })
+            .collect();
+        let top_vertices: Vec<usize> = contour
+            .iter()
+            .map(|segment| {
+                let p = entities.point(at(segment.from(), z1));
+                entities.add(format!("VERTEX_POINT('',#{})", p))
+            })
+            .collect();
+        let verticals: Vec<usize> = (0..n)
+            .map(|i| {
+                let from = contour[i].from();
+                let line = entities.line(at(from, z0), at(from, z1));
+                entities.edge(bottom_vertices[i], top_vertices[i], line)
+            })
+            .collect();
+        let mut contour_edges = ContourEdges {
+            bottom: Vec::new(),
+            top: Vec::new(),
+        };
+        for (i, segment) in contour.iter().enumerate() {
+            let next = (i + 1) % n;
+            let curve = |entities: &mut Entities, z: f32| {
+                let points: Vec<[f32; 3]> = segment.points().iter().map(|&p| at(p, z)).collect();
+                match segment {
+                    Segment::Line(..) => entities.line(points[0], points[1]),
+                    _ => entities.bezier(&points),
+                }
+            };
+            let bottom_curve = curve(entities, z0);
+            let bottom = entities.edge(bottom_vertices[i], bottom_vertices[next], bottom_curve);
+            let top_curve = curve(entities, z1);
+            let top = entities.edge(top_vertices[i], top_vertices[next], top_curve);
+
+            // The wall faces outward, to the right of the direction of travel
+            let surface = match *segment {
+                Segment::Line(a, b) => {
+                    let d = b - a;
+                    let normal = map_point(Point::new(d.y, -d.x), 0.0, orient);
+                    let along = map_point(d.to_point(), 0.0, orient);
+                    let placement = entities.placement(at(a, z0), normal, along);
+                    entities.add(format!("PLANE('',#{})", placement))
+                }
+                _ => {
+                    let dir = entities.direction(up);
+                    let vector = entities.add(format!("VECTOR('',#{},{})", dir, real(depth)));
+                    entities.add(format!(
+                        "SURFACE_OF_LINEAR_EXTRUSION('',#{},#{})",
+                        bottom_curve, vector
+                    ))
+                }
+            };
+            let wall = [
+                (bottom, true),
+                (verticals[next], true),
+                (top, false),
+                (verticals[i], false),
+            ];
+            faces.push(entities.face(&[wall.to_vec()], surface, true));
+            contour_edges.bottom.push(bottom);
+            contour_edges.top.push(top);
+        }
+        edges.push(contour_edges);
+    }
+
+    // Seen from outside the bottom cap runs the other way round
+    let x = map_point(Point::new(1.0, 0.0), 0.0, orient);
+    let top_loops: Vec<Vec<(usize, bool)>> = edges
+        .iter()
+        .map(|e| e.top.iter().map(|&edge| (edge, true)).collect())
+        .collect();
+    let top_plane = entities.placement(at(Point::new(0.0, 0.0), z1), up, x);
+    let top_plane = entities.add(format!("PLANE('',#{})", top_plane));
+    faces.push(entities.face(&top_loops, top_plane, true));
+    let bottom_loops: Vec<Vec<(usize, bool)>> = edges
+        .iter()
+        .map(|e| e.bottom.iter().rev().map(|&edge| (edge, false)).collect())
+        .collect();
+    let bottom_plane = entities.placement(at(Point::new(0.0, 0.0), z0), up, x);
+    let bottom_plane = entities.add(format!("PLANE('',#{})", bottom_plane));
+    faces.push(entities.face(&bottom_loops, bottom_plane, false));
+    faces
+}
+
+/// Length unit entity for the representation context
+fn length_unit(entities: &mut Entities, units: Units) -> usize {
+    match units {
+        Units::Mm => entities.add("(LENGTH_UNIT()NAMED_UNIT(*)SI_UNIT(.MILLI.,.METRE.))".into()),
+        Units::Cm => entities.add("(LENGTH_UNIT()NAMED_UNIT(*)SI_UNIT(.CENTI.,.METRE.))".into()),
+        Units::Inch => {
+            let mm = entities.add("(LENGTH_UNIT()NAMED_UNIT(*)SI_UNIT(.MILLI.,.METRE.))".into());
+            let measure = entities.add(format!(
+                "LENGTH_MEASURE_WITH_UNIT(LENGTH_MEASURE(25.4),#{})",
+                mm
+            ));
+            let exponents = entities.add("DIMENSIONAL_EXPONENTS(1.,0.,0.,0.,0.,0.,0.)".into());
+            entities.add(format!(
+                "(CONVERSION_BASED_UNIT('INCH',#{})LENGTH_UNIT()NAMED_UNIT(#{}))",
+                measure, exponents
+            ))
+        }
+    }
+}
+
+pub fn write_step(
+    path: &Path,
+    solids: &[StepSolid],
+    orient: &Orientation,
+    name: &str,
+    units: Units,
+) -> Result<()> {
+    output::write_atomically(path, |file| {
+        write_step_to_writer(BufWriter::new(file), solids, orient, name, units)
+    })
+}
+
+/// One product `name` whose shape is the `solids`, coordinates in `units`
+pub fn write_step_to_writer<W: Write>(
+    mut writer: W,
+    solids: &[StepSolid],
+    orient: &Orientation,
+    name: &str,
+    units: Units,
+) -> Result<()> {
+    let mut entities = Entities { lines: Vec::new() };
+    let application = entities.add("APPLICATION_CONTEXT('automotive design')".into());
+    entities.add(format!(
+        "APPLICATION_PROTOCOL_DEFINITION('international standard','automotive_design',2000,#{})",
+        application
+    ));
+    let product_context =
+        entities.add(format!("PRODUCT_CONTEXT('',#{},'mechanical')", application));
+    let product = entities.add(format!(
+        "PRODUCT({0},{0},'',(#{1}))",
+        string(name),
+        product_context
+    ));
+    entities.add(format!(
+        "PRODUCT_RELATED_PRODUCT_CATEGORY('part',$,(#{}))",
+        product
+    ));
+    let formation = entities.add(format!("PRODUCT_DEFINITION_FORMATION('','',#{})", product));
+    let definition_context = entities.add(format!(
+        "PRODUCT_DEFINITION_CONTEXT('part definition',#{},'design')",
+        application
+    ));
+    let definition = entities.add(format!(
+        "PRODUCT_DEFINITION('design','',#{},#{})",
+        formation, definition_context
+    ));
+    let shape = entities.add(format!("PRODUCT_DEFINITION_SHAPE('','',#{})", definition));
+
+    let length = length_unit(&mut entities, units);
+    let angle = entities.add("(NAMED_UNIT(*)PLANE_ANGLE_UNIT()SI_UNIT($,.RADIAN.))".into());
+    let solid_angle =
+        entities.add("(NAMED_UNIT(*)SI_UNIT($,.STERADIAN.)SOLID_ANGLE_UNIT())".into());
+    let uncertainty = entities.add(format!(
+        "UNCERTAINTY_MEASURE_WITH_UNIT(LENGTH_MEASURE(1.E-06),#{},'distance_accuracy_value','confusion accuracy')",
+        length
+    ));
+    let context = entities.add(format!(
+        "(GEOMETRIC_REPRESENTATION_CONTEXT(3)GLOBAL_UNCERTAINTY_ASSIGNED_CONTEXT((#{}))GLOBAL_UNIT_ASSIGNED_CONTEXT((#{},#{},#{}))REPRESENTATION_CONTEXT('',''))",
+        uncertainty, length, angle, solid_angle
+    ));
+
+    let mut items = vec![format!(
+        "#{}",
+        entities.placement([0.0; 3], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0])
+    )];
+    // A manifold solid needs a connected shell, so each region is one
+    for solid in solids {
+        for region in &solid.regions {
+            let faces: Vec<String> =
+                region_faces(&mut entities, region, solid.z0, solid.z1, orient)
+                    .iter()
+                    .map(|face| format!("#{}", face))
+                    .collect();
+            let shell = entities.add(format!("CLOSED_SHELL('',({}))", faces.join(",")));
+            let brep = entities.add(format!(
+                "MANIFOLD_SOLID_BREP({},#{})",
+                string(&solid.name),
+                shell
+            ));
+            items.push(format!("#{}", brep));
+        }
+    }
+    let representation = entities.add(format!(
+        "ADVANCED_BREP_SHAPE_REPRESENTATION({},({}),#{})",
+        string(name),
+        items.join(","),
+        context
+    ));
+    entities.add(format!(
+        "SHAPE_DEFINITION_REPRESENTATION(#{},#{})",
+        shape, representation
+    ));
+
+    writeln!(writer, "ISO-10303-21;")?;
+    writeln!(writer, "HEADER;")?;
+    writeln!(writer, "FILE_DESCRIPTION(('wagyan text model'),'2;1');")?;
+    writeln!(
+        writer,
+        "FILE_NAME({},'',(''),(''),'wagyan {}','wagyan','');",
+        string(name),
+        env!("CARGO_PKG_VERSION")
+    )?;
+    writeln!(
+        writer,
+        "FILE_SCHEMA(('AUTOMOTIVE_DESIGN {{ 1 0 10303 214 1 1 1 1 }}'));"
+    )?;
+    writeln!(writer, "ENDSEC;")?;
+    writeln!(writer, "DATA;")?;
+    for (i, line) in entities.lines.iter().enumerate() {
+        writeln!(writer, "#{}={};", i + 1, line)?;
+    }
+    writeln!(writer, "ENDSEC;")?;
+    writeln!(writer, "END-ISO-10303-21;")?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn square(x0: f32, y0: f32, x1: f32, y1: f32) -> Vec<Point> {
+        vec![
+            Point::new(x0, y0),
+            Point::new(x1, y0),
+            Point::new(x1, y1),
+            Point::new(x0, y1),
+        ]
+    }
+
+    #[test]
+    fn holes_go_into_the_smallest_outline_around_them() {
+        // Wound the other way round, as TrueType fonts do
+        let mut lines = vec![
+            square(0.0, 0.0, 10.0, 10.0),
+            square(2.0, 2.0, 8.0, 8.0),
+            square(3.0, 3.0, 7.0, 7.0),
+            square(20.0, 0.0, 22.0, 2.0),
+        ];
+        lines[0].reverse();
+        lines[2].reverse();
+        lines[3].reverse();
+        let regions = regions(polyline_contours(&lines));
+        assert_eq!(regions.len(), 3);
+        let holes: Vec<usize> = regions.iter().map(|r| r.holes.len()).collect();
+        assert_eq!(holes, [1, 0, 0]);
+        let polygon = |c: &Contour| c.iter().map(Segment::from).collect::<Vec<_>>();
+        assert!(outline::area(&polygon(&regions[0].outer)) > 0.0);
+        assert!(outline::area(&polygon(&regions[0].holes[0])) < 0.0);
+        assert!(outline::area(&polygon(&regions[1].outer)) > 0.0);
+    }
+
+    #[test]
+    fn every_edge_is_shared_by_two_faces_in_opposite_directions() {
+        let mut path = GlyphPath::builder();
+        path.begin(Point::new(0.0, 0.0));
+        path.line_to(Point::new(10.0, 0.0));
+        path.quadratic_bezier_to(Point::new(12.0, 5.0), Point::new(10.0, 10.0));
+        path.cubic_bezier_to(
+            Point::new(7.0, 12.0),
+            Point::new(3.0, 12.0),
+            Point::new(0.0, 10.0),
+        );
+        path.close();
+        let hole = square(3.0, 3.0, 6.0, 6.0).into_iter().rev().collect();
+        let mut contours = path_contours(&path.build());
+        contours.extend(polyline_contours(&[hole]));
+        let solid = StepSolid {
+            name: "text".into(),
+            regions: regions(contours),
+            z0: 0.0,
+            z1: 2.0,
+        };
+
+        let mut out = Vec::new();
+        write_step_to_writer(&mut out, &[solid], &Orientation::Front, "sign", Units::Inch).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with("ISO-10303-21;"));
+        assert!(text.contains("CONVERSION_BASED_UNIT('INCH'"));
+        assert_eq!(text.matches("MANIFOLD_SOLID_BREP('text'").count(), 1);
+        // 4 + 4 walls, the closing line included, and the two caps
+        assert_eq!(text.matches("ADVANCED_FACE").count(), 10);
+        assert_eq!(text.matches("B_SPLINE_CURVE_WITH_KNOTS('',2,").count(), 2);
+        assert_eq!(text.matches("B_SPLINE_CURVE_WITH_KNOTS('',3,").count(), 2);
+        assert_eq!(text.matches("SURFACE_OF_LINEAR_EXTRUSION").count(), 2);
+
+        let mut uses: HashMap<&str, Vec<&str>> = HashMap::new();
+        for line in text.lines().filter(|l| l.contains("ORIENTED_EDGE")) {
+            let args = line.split("*,*,").nth(1).unwrap();
+            let (edge, sense) = args.trim_end_matches(");").split_once(',').unwrap();
+            uses.entry(edge).or_default().push(sense);
+        }
+        assert_eq!(uses.len(), 8 * 3);
+        for senses in uses.values() {
+            let mut senses = senses.clone();
+            senses.sort();
+            assert_eq!(senses, [".F.", ".T."]);
+        }
+    }
+
+    #[test]
+    fn runs_with_their_own_depth_get_solids_of_their_own() {
+        let glyph_path = |x0: f32| {
+            let mut path = GlyphPath::builder();
+            path.add_polygon(lyon_path::Polygon {
+                points: &square(x0, 0.0, x0 + 5.0, 5.0),
+                closed: true,
+            });
+            path.build()
+        };
+        let part = |name: &str, depth: f32, z_offset: f32| Part {
+            name: name.to_string(),
+            mesh: crate::mesh::rectangle_mesh(0.0, 5.0, 0.0, 5.0),
+            depth,
+            z_offset,
+            letter: None,
+            chamfer: 0.0,
+            color: None,
+        };
+        let parts = [part("text", 2.0, 0.0), part("text_1", 4.0, 1.0)];
+        let solids = solids(&parts, &[glyph_path(0.0), glyph_path(10.0)]);
+        let spans: Vec<(&str, f32, f32)> = solids
+            .iter()
+            .map(|solid| (solid.name.as_str(), solid.z0, solid.z1))
+            .collect();
+        assert_eq!(spans, [("text", -1.0, 1.0), ("text_1", -1.0, 3.0)]);
+    }
+}
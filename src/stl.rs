@@ -0,0 +1,102 @@
+//! STL writers, ASCII and binary
+
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::Result;
+use stl_io::Triangle;
+
+use crate::metadata::STL_HEADER_LEN;
+use crate::output;
+
+pub fn write_stl_ascii(path: &Path, tris: &[Triangle]) -> Result<()> {
+    let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("mesh");
+    output::write_atomically(path, |file| {
+        write_stl_ascii_to_writer(BufWriter::new(file), name, tris)
+    })
+}
+
+pub fn write_stl_ascii_to_writer<W: Write>(
+    mut writer: W,
+    name: &str,
+    tris: &[Triangle],
+) -> Result<()> {
+    writeln!(writer, "solid {}", name)?;
+    for tri in tris {
+        writeln!(
+            writer,
+            "  facet normal {} {} {}",
+            tri.normal[0], tri.normal[1], tri.normal[2]
+        )?;
+        writeln!(writer, "    outer loop")?;
+        for v in &tri.vertices {
+            writeln!(writer, "      vertex {} {} {}", v[0], v[1], v[2])?;
+        }
+        writeln!(writer, "    endloop")?;
+        writeln!(writer, "  endfacet")?;
+    }
+    writeln!(writer, "endsolid {}", name)?;
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn write_stl_binary(
+    path: &Path,
+    header: &[u8; STL_HEADER_LEN],
+    tris: &[Triangle],
+) -> Result<()> {
+    output::write_atomically(path, |file| {
+        write_stl_binary_to_writer(BufWriter::new(file), header, tris)
+    })
+}
+
+/// Binary STL: the 80-byte header, the triangle count, then per triangle
+/// the normal and three vertices as little-endian f32 and a zero attribute
+pub fn write_stl_binary_to_writer<W: Write>(
+    mut writer: W,
+    header: &[u8; STL_HEADER_LEN],
+    tris: &[Triangle],
+) -> Result<()> {
+    let count = u32::try_from(tris.len())
+        .map_err(|_| anyhow::anyhow!("too many triangles for binary STL ({})", tris.len()))?;
+    writer.write_all(header)?;
+    writer.write_all(&count.to_le_bytes())?;
+    for tri in tris {
+        let values = tri
+            .normal
+            .iter()
+            .chain(tri.vertices.iter().flat_map(|v| v.iter()));
+        for value in values {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        writer.write_all(&0u16.to_le_bytes())?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_stl_reads_back() {
+        let tris = vec![Triangle {
+            normal: [0.0, 0.0, 1.0],
+            vertices: [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+        }];
+        let mut header = [0u8; STL_HEADER_LEN];
+        header[..6].copy_from_slice(b"wagyan");
+        let mut out = Vec::new();
+        write_stl_binary_to_writer(&mut out, &header, &tris).unwrap();
+        assert_eq!(out.len(), 84 + 50);
+        assert_eq!(&out[..6], b"wagyan");
+
+        let mesh = stl_io::read_stl(&mut std::io::Cursor::new(out)).unwrap();
+        assert_eq!(mesh.faces.len(), 1);
+        assert_eq!(
+            mesh.vertices[mesh.faces[0].vertices[1]],
+            tris[0].vertices[1]
+        );
+    }
+}
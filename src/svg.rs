@@ -0,0 +1,225 @@
+//! SVG writer for laser cutters: outlines as paths in mm, curves kept as
+//! Béziers, one stroked group per layer
+
+use std::fmt::Write as _;
+use std::io::{BufWriter, Write};
+
+use anyhow::Result;
+use lyon_path::iterator::PathIterator;
+use lyon_path::math::{Point, Transform};
+use lyon_path::{Event, Path};
+
+use crate::color::Color;
+use crate::dxf::{CUT_LAYER, ENGRAVE_LAYER};
+use crate::output::{self, Units};
+use crate::threemf::escape;
+use crate::{outline, Part};
+
+/// Flattening tolerance for the document bounds only; the paths keep their curves
+const BOUNDS_TOLERANCE: f32 = 0.01;
+
+/// Named group of paths; laser software maps stroke colors to settings
+#[derive(Debug, Clone)]
+pub struct SvgLayer {
+    pub name: String,
+    pub color: Color,
+    pub paths: Vec<Path>,
+}
+
+impl SvgLayer {
+    /// Multiply every coordinate by `factor`, e.g. to write in other units than mm
+    pub fn scale(&mut self, factor: f32) {
+        let transform = Transform::scale(factor, factor);
+        for path in &mut self.paths {
+            *path = path.clone().transformed(&transform);
+        }
+    }
+}
+
+/// Polylines as a path, each closed into a polygon when `closed`
+pub fn polylines_path(lines: &[Vec<Point>], closed: bool) -> Path {
+    let mut builder = Path::builder();
+    for line in lines.iter().filter(|l| l.len() > 1) {
+        builder.add_polygon(lyon_path::Polygon {
+            points: line,
+            closed,
+        });
+    }
+    builder.build()
+}
+
+/// Split the parts for a laser like [`crate::dxf::laser_layers`]: the plate
+/// and other shapes are cut, the text is engraved (or cut when it stands
+/// alone). The text keeps its curves when `glyphs` has its outlines placed
+/// like the text parts; single-stroke `centerlines` replace it as open paths.
+pub fn laser_layers(parts: &[Part], glyphs: &[Path], centerlines: &[Vec<Point>]) -> Vec<SvgLayer> {
+    let (text, shapes): (Vec<&Part>, Vec<&Part>) = parts.iter().partition(|part| part.is_text());
+    let outlines = |parts: &[&Part]| -> Vec<Vec<Point>> {
+        parts
+            .iter()
+            .flat_map(|part| outline::from_mesh(&part.mesh))
+            .collect()
+    };
+    let lettering = match glyphs {
+        _ if !centerlines.is_empty() => vec![polylines_path(centerlines, false)],
+        [] => vec![polylines_path(&outlines(&text), true)],
+        glyphs => glyphs.to_vec(),
+    };
+    let engrave = SvgLayer {
+        name: ENGRAVE_LAYER.to_string(),
+        color: Color::rgb(0, 0, 0xff),
+        paths: lettering,
+    };
+    if shapes.is_empty() {
+        return vec![SvgLayer {
+            name: CUT_LAYER.to_string(),
+            color: Color::rgb(0xff, 0, 0),
+            ..engrave
+        }];
+    }
+    vec![
+        SvgLayer {
+            name: CUT_LAYER.to_string(),
+            color: Color::rgb(0xff, 0, 0),
+            paths: vec![polylines_path(&outlines(&shapes), true)],
+        },
+        engrave,
+    ]
+}
+
+/// Model coordinates are y up, SVG's are y down
+fn coords(p: Point) -> String {
+    format!("{} {}", round(p.x), round(-p.y))
+}
+
+/// A thousandth of the unit (a micrometer in mm) is plenty for a cutter and
+/// keeps files small
+fn round(value: f32) -> f32 {
+    let value = (value * 1000.0).round() / 1000.0;
+    if value == 0.0 {
+        0.0
+    } else {
+        value
+    }
+}
+
+/// Path data (`d`) with lines, quadratic and cubic curves as in the path
+fn path_data(path: &Path) -> String {
+    let mut d = String::new();
+    for event in path.iter() {
+        let _ = match event {
+            Event::Begin { at } => write!(d, "M{}", coords(at)),
+            Event::Line { to, .. } => write!(d, "L{}", coords(to)),
+            Event::Quadratic { ctrl, to, .. } => write!(d, "Q{} {}", coords(ctrl), coords(to)),
+            Event::Cubic {
+                ctrl1, ctrl2, to, ..
+            } => write!(d, "C{} {} {}", coords(ctrl1), coords(ctrl2), coords(to)),
+            Event::End { close: true, .. } => write!(d, "Z"),
+            Event::End { close: false, .. } => Ok(()),
+        };
+    }
+    d
+}
+
+/// (min_x, max_x, min_y, max_y) of everything drawn, in model coordinates
+fn bounds(layers: &[SvgLayer]) -> Option<(f32, f32, f32, f32)> {
+    layers
+        .iter()
+        .flat_map(|layer| &layer.paths)
+        .flat_map(|path| path.iter().flattened(BOUNDS_TOLERANCE))
+        .filter_map(|event| match event {
+            Event::Begin { at } => Some(at),
+            Event::Line { to, .. } => Some(to),
+            _ => None,
+        })
+        .map(|p| (p.x, p.x, p.y, p.y))
+        .reduce(|a, b| (a.0.min(b.0), a.1.max(b.1), a.2.min(b.2), a.3.max(b.3)))
+}
+
+pub fn write_svg(path: &std::path::Path, layers: &[SvgLayer], units: Units) -> Result<()> {
+    output::write_atomically(path, |file| {
+        write_svg_to_writer(BufWriter::new(file), layers, units)
+    })
+}
+
+/// `units` names the unit of the coordinates, which become the document's
+/// user units
+pub fn write_svg_to_writer<W: Write>(
+    mut writer: W,
+    layers: &[SvgLayer],
+    units: Units,
+) -> Result<()> {
+    let (min_x, max_x, min_y, max_y) = bounds(layers).unwrap_or_default();
+    let (width, height) = (round(max_x - min_x), round(max_y - min_y));
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        writer,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}{unit}" height="{}{unit}" viewBox="{} {} {} {}">"#,
+        width,
+        height,
+        round(min_x),
+        round(-max_y),
+        width,
+        height,
+        unit = units.abbreviation()
+    )?;
+    // A 0.1 mm hairline whatever the unit
+    let stroke_width = round(0.1 * units.per_mm());
+    for layer in layers.iter().filter(|l| !l.paths.is_empty()) {
+        writeln!(
+            writer,
+            r#"  <g id="{}" fill="none" stroke="{}" stroke-width="{}">"#,
+            escape(&layer.name),
+            layer.color,
+            stroke_width
+        )?;
+        for path in &layer.paths {
+            writeln!(writer, r#"    <path d="{}"/>"#, path_data(path))?;
+        }
+        writeln!(writer, "  </g>")?;
+    }
+    writeln!(writer, "</svg>")?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curves_survive_and_y_points_down() {
+        let mut builder = Path::builder();
+        builder.begin(Point::new(0.0, 0.0));
+        builder.quadratic_bezier_to(Point::new(5.0, 10.0), Point::new(10.0, 0.0));
+        builder.close();
+        let layers = [
+            SvgLayer {
+                name: "CUT".into(),
+                color: Color::rgb(0xff, 0, 0),
+                paths: vec![polylines_path(
+                    &[crate::outline::rectangle(-1.0, 11.0, -1.0, 6.0)],
+                    true,
+                )],
+            },
+            SvgLayer {
+                name: "ENGRAVE".into(),
+                color: Color::rgb(0, 0, 0xff),
+                paths: vec![builder.build()],
+            },
+        ];
+        let mut out = Vec::new();
+        write_svg_to_writer(&mut out, &layers, Units::Mm).unwrap();
+        let svg = String::from_utf8(out).unwrap();
+
+        assert!(svg.contains(r#"<path d="M0 0Q5 -10 10 0Z"/>"#), "{}", svg);
+        assert!(svg.contains(r#"width="12mm" height="7mm" viewBox="-1 -6 12 7""#));
+        assert!(svg.contains(r##"<g id="CUT" fill="none" stroke="#ff0000" stroke-width="0.1">"##));
+
+        let mut out = Vec::new();
+        write_svg_to_writer(&mut out, &layers, Units::Cm).unwrap();
+        let svg = String::from_utf8(out).unwrap();
+        assert!(svg.contains(r#"width="12cm" height="7cm""#));
+        assert!(svg.contains(r#"stroke-width="0.01""#));
+    }
+}
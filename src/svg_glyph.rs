@@ -0,0 +1,186 @@
+//! Glyphs drawn only in the OpenType `SVG ` table (icon and emoji fonts)
+
+use ttf_parser::{Face, GlyphId, OutlineBuilder, Rect};
+use usvg::tiny_skia_path::PathSegment;
+use usvg::{Group, Node};
+
+/// Passes points on with the SVG y axis (down) turned into the font's (up)
+/// and tracks their bounds
+struct Flipped<'b> {
+    builder: &'b mut dyn OutlineBuilder,
+    bounds: Option<(f32, f32, f32, f32)>,
+}
+
+impl Flipped<'_> {
+    fn point(&mut self, x: f32, y: f32) -> (f32, f32) {
+        let y = -y;
+        self.bounds = Some(match self.bounds {
+            Some((x0, x1, y0, y1)) => (x0.min(x), x1.max(x), y0.min(y), y1.max(y)),
+            None => (x, x, y, y),
+        });
+        (x, y)
+    }
+
+    /// Filled paths of `group` and its descendants; strokes, gradients and
+    /// images are ignored, as a print has one color per glyph
+    fn group(&mut self, group: &Group) {
+        for node in group.children() {
+            self.node(node);
+        }
+    }
+
+    fn node(&mut self, node: &Node) {
+        match node {
+            Node::Group(group) => self.group(group),
+            Node::Path(path) if path.is_visible() && path.fill().is_some() => {
+                let Some(data) = path.data().clone().transform(path.abs_transform()) else {
+                    return;
+                };
+                for segment in data.segments() {
+                    match segment {
+                        PathSegment::MoveTo(p) => {
+                            let (x, y) = self.point(p.x, p.y);
+                            self.builder.move_to(x, y);
+                        }
+                        PathSegment::LineTo(p) => {
+                            let (x, y) = self.point(p.x, p.y);
+                            self.builder.line_to(x, y);
+                        }
+                        PathSegment::QuadTo(p1, p) => {
+                            let (x1, y1) = self.point(p1.x, p1.y);
+                            let (x, y) = self.point(p.x, p.y);
+                            self.builder.quad_to(x1, y1, x, y);
+                        }
+                        PathSegment::CubicTo(p1, p2, p) => {
+                            let (x1, y1) = self.point(p1.x, p1.y);
+                            let (x2, y2) = self.point(p2.x, p2.y);
+                            let (x, y) = self.point(p.x, p.y);
+                            self.builder.curve_to(x1, y1, x2, y2, x, y);
+                        }
+                        PathSegment::Close => self.builder.close(),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Outline glyph `gid` in font units: its `glyf`/`CFF` outline, else the
+/// filled paths of its SVG document (the element `glyph<gid>` when the
+/// document holds several glyphs). `None` when it has neither.
+pub fn outline(face: &Face<'_>, gid: GlyphId, builder: &mut dyn OutlineBuilder) -> Option<Rect> {
+    if let Some(rect) = face.outline_glyph(gid, builder) {
+        return Some(rect);
+    }
+    let document = face.glyph_svg_image(gid)?;
+    let tree = usvg::Tree::from_data(document.data, &usvg::Options::default()).ok()?;
+    let mut flipped = Flipped {
+        builder,
+        bounds: None,
+    };
+    if document.start_glyph_id == document.end_glyph_id {
+        flipped.group(tree.root());
+    } else {
+        flipped.node(tree.node_by_id(&format!("glyph{}", gid.0))?);
+    }
+    let (x_min, x_max, y_min, y_max) = flipped.bounds?;
+    Some(Rect {
+        x_min: x_min.floor() as i16,
+        y_min: y_min.floor() as i16,
+        x_max: x_max.ceil() as i16,
+        y_max: y_max.ceil() as i16,
+    })
+}
+
+/// Bounding box of glyph `gid` in font units, SVG glyphs included
+pub fn bounding_box(face: &Face<'_>, gid: GlyphId) -> Option<Rect> {
+    face.glyph_bounding_box(gid).or_else(|| {
+        struct Discard;
+        impl OutlineBuilder for Discard {
+            fn move_to(&mut self, _: f32, _: f32) {}
+            fn line_to(&mut self, _: f32, _: f32) {}
+            fn quad_to(&mut self, _: f32, _: f32, _: f32, _: f32) {}
+            fn curve_to(&mut self, _: f32, _: f32, _: f32, _: f32, _: f32, _: f32) {}
+            fn close(&mut self) {}
+        }
+        outline(face, gid, &mut Discard)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font;
+    use crate::woff::{with_tables, Table};
+
+    /// SVG table with one document per (first glyph, last glyph, svg)
+    fn svg_table(documents: &[(u16, u16, &str)]) -> Vec<u8> {
+        let mut table = Vec::new();
+        table.extend(0u16.to_be_bytes());
+        table.extend(10u32.to_be_bytes());
+        table.extend(0u32.to_be_bytes());
+        table.extend((documents.len() as u16).to_be_bytes());
+        let mut offset = 2 + 12 * documents.len();
+        for (first, last, svg) in documents {
+            table.extend(first.to_be_bytes());
+            table.extend(last.to_be_bytes());
+            table.extend((offset as u32).to_be_bytes());
+            table.extend((svg.len() as u32).to_be_bytes());
+            offset += svg.len();
+        }
+        for (_, _, svg) in documents {
+            table.extend(svg.as_bytes());
+        }
+        table
+    }
+
+    fn with_svg(documents: &[(u16, u16, &str)]) -> Vec<u8> {
+        with_tables(
+            font::EMBEDDED_FONT,
+            vec![Table {
+                tag: *b"SVG ",
+                data: svg_table(documents),
+            }],
+        )
+    }
+
+    #[test]
+    fn glyphs_without_outlines_come_from_their_svg_documents() {
+        let plain = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let space = plain.glyph_index(' ').unwrap().0;
+        let a = plain.glyph_index('A').unwrap().0;
+        let square = r#"<svg xmlns="http://www.w3.org/2000/svg"><rect x="100" y="-700" width="400" height="700"/></svg>"#;
+        let bytes = with_svg(&[(space, space, square), (a, a, square)]);
+        let face = font::parse_face(&bytes, 0).unwrap();
+
+        let rect = bounding_box(&face, GlyphId(space)).unwrap();
+        assert_eq!(
+            (rect.x_min, rect.y_min, rect.x_max, rect.y_max),
+            (100, 0, 500, 700)
+        );
+        // Glyph outlines still win over SVG documents
+        assert_eq!(
+            bounding_box(&face, GlyphId(a)),
+            plain.glyph_bounding_box(GlyphId(a))
+        );
+    }
+
+    #[test]
+    fn shared_documents_draw_the_glyph_element() {
+        let plain = font::parse_face(font::EMBEDDED_FONT, 0).unwrap();
+        let space = plain.glyph_index(' ').unwrap().0;
+        let shared = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><g id="glyph{}"><rect width="9" height="9"/></g><g id="glyph{}"><path d="M0 0 L300 -300 L300 0 Z"/></g></svg>"#,
+            space - 1,
+            space,
+        );
+        let bytes = with_svg(&[(space - 1, space, &shared)]);
+        let face = font::parse_face(&bytes, 0).unwrap();
+        let rect = bounding_box(&face, GlyphId(space)).unwrap();
+        assert_eq!(
+            (rect.x_min, rect.y_min, rect.x_max, rect.y_max),
+            (0, 0, 300, 300)
+        );
+    }
+}
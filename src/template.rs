@@ -0,0 +1,180 @@
+//! Placeholder expansion for text and output paths (`{date}`, `{counter:03}`, `{n}`, `{text}`)
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+/// Upper bound when searching for an unused `{counter}` value
+const MAX_AUTO_COUNTER: u64 = 1_000_000;
+
+/// Widest zero padding a `{counter:0N}` spec may ask for
+const MAX_PAD_WIDTH: usize = 32;
+
+/// Values available to templates. `None` leaves the placeholder untouched.
+#[derive(Debug, Default, Clone)]
+pub struct TemplateVars {
+    pub date: Option<String>,
+    pub counter: Option<u64>,
+    pub n: Option<u64>,
+    pub text: Option<String>,
+}
+
+impl TemplateVars {
+    fn lookup(&self, name: &str, spec: Option<&str>) -> Result<Option<String>> {
+        Ok(match name {
+            "date" => self.date.clone(),
+            "counter" => self.counter.map(|n| format_number(n, spec)).transpose()?,
+            "n" => self.n.map(|n| format_number(n, spec)).transpose()?,
+            "text" => self.text.clone(),
+            _ => None,
+        })
+    }
+}
+
+/// Today's local date as `YYYY-MM-DD`
+pub fn today() -> String {
+    chrono::Local::now().format("%Y-%m-%d").to_string()
+}
+
+/// Format a number with an optional zero-pad width spec (`03` -> `007`)
+fn format_number(n: u64, spec: Option<&str>) -> Result<String> {
+    let width = spec
+        .map(|s| {
+            s.parse::<usize>()
+                .map_err(|_| anyhow::anyhow!("padding width must be a number, got: {}", s))
+        })
+        .transpose()?;
+    match width {
+        Some(width) => {
+            anyhow::ensure!(
+                width <= MAX_PAD_WIDTH,
+                "padding width {} is too wide (at most {})",
+                width,
+                MAX_PAD_WIDTH
+            );
+            Ok(format!("{:0width$}", n, width = width))
+        }
+        None => Ok(n.to_string()),
+    }
+}
+
+/// Expand `{name}` / `{name:spec}` placeholders. Unknown names and stray
+/// braces are kept literally so ordinary text is never mangled. A padding
+/// that is not a number, or wider than 32 digits, is an error.
+pub fn expand(template: &str, vars: &TemplateVars) -> Result<String> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('}') else {
+            out.push_str(&rest[start..]);
+            return Ok(out);
+        };
+
+        let inner = &after[..end];
+        if inner.contains('{') {
+            // Nested brace: emit this one literally and rescan from the next
+            out.push('{');
+            rest = after;
+            continue;
+        }
+        let (name, spec) = match inner.split_once(':') {
+            Some((name, spec)) => (name, Some(spec)),
+            None => (inner, None),
+        };
+
+        match vars.lookup(name, spec)? {
+            Some(value) => out.push_str(&value),
+            None => out.push_str(&rest[start..start + end + 2]),
+        }
+        rest = &after[end + 1..];
+    }
+
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// True when the template references the given placeholder
+pub fn uses(template: &str, name: &str) -> bool {
+    template.contains(&format!("{{{}}}", name)) || template.contains(&format!("{{{}:", name))
+}
+
+/// Make text safe to embed in a file name (path separators, control chars)
+pub fn sanitize_file_component(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() || c.is_whitespace() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// Expand an output path template
+pub fn expand_path(template: &Path, vars: &TemplateVars) -> Result<PathBuf> {
+    Ok(PathBuf::from(expand(&template.to_string_lossy(), vars)?))
+}
+
+/// Pick the first counter value (from `start`) whose resolved path does not exist
+pub fn next_free_counter(start: u64, resolve: impl Fn(u64) -> Result<PathBuf>) -> Result<u64> {
+    for n in start..start.saturating_add(MAX_AUTO_COUNTER) {
+        if !resolve(n)?.exists() {
+            return Ok(n);
+        }
+    }
+    anyhow::bail!("no free {{counter}} value found starting at {}", start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_known_placeholders_with_padding() {
+        let vars = TemplateVars {
+            date: Some("2024-01-02".into()),
+            counter: Some(7),
+            n: None,
+            text: Some("HELLO".into()),
+        };
+        assert_eq!(
+            expand("tag_{counter:03}_{text}_{date}.stl", &vars).unwrap(),
+            "tag_007_HELLO_2024-01-02.stl"
+        );
+    }
+
+    #[test]
+    fn rejects_oversized_padding() {
+        let vars = TemplateVars {
+            counter: Some(7),
+            n: Some(1),
+            ..Default::default()
+        };
+        assert!(expand("{counter:99999999999}", &vars).is_err());
+        assert!(expand("{n:33}", &vars).is_err());
+        assert_eq!(expand("{n:32}", &vars).unwrap().len(), 32);
+    }
+
+    #[test]
+    fn rejects_padding_that_is_not_a_number() {
+        let vars = TemplateVars {
+            n: Some(1),
+            ..Default::default()
+        };
+        assert!(expand("t{n:xyz}.stl", &vars).is_err());
+        assert!(expand("t{n:}.stl", &vars).is_err());
+        assert_eq!(expand("t{n:03}.stl", &vars).unwrap(), "t001.stl");
+    }
+
+    #[test]
+    fn keeps_unknown_placeholders_and_stray_braces() {
+        let vars = TemplateVars {
+            counter: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(expand("{foo} {counter} {", &vars).unwrap(), "{foo} 1 {");
+        assert_eq!(expand("{text}", &vars).unwrap(), "{text}");
+    }
+}
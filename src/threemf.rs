@@ -0,0 +1,307 @@
+//! 3MF writer: every part is its own object, grouped under one build item
+//! so slicers load them as parts of a single model that can get their own filament
+
+use std::fmt::Write as _;
+use std::io::{Seek, Write};
+use std::path::Path;
+
+use anyhow::Result;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::color::Color;
+use crate::indexed::MeshPart;
+use crate::metadata::GenerationMetadata;
+use crate::output::{self, Units};
+use crate::slicer::{self, SlicerProject, Volume};
+
+const CORE_NAMESPACE: &str = "http://schemas.microsoft.com/3dmanufacturing/core/2015/02";
+const WAGYAN_NAMESPACE: &str = "https://github.com/dlwr/wagyan";
+const MODEL_PATH: &str = "3D/3dmodel.model";
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
+  <Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>
+  <Default Extension="model" ContentType="application/vnd.ms-package.3dmanufacturing-3dmodel+xml"/>
+</Types>
+"#;
+
+const RELS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+  <Relationship Target="/3D/3dmodel.model" Id="rel0" Type="http://schemas.microsoft.com/3dmanufacturing/2013/01/3dmodel"/>
+</Relationships>
+"#;
+
+/// Escape text for XML attribute values and content
+pub(crate) fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\n' => out.push_str("&#10;"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+/// 3MF colors are `#RRGGBBAA`
+fn display_color(color: Color) -> String {
+    format!(
+        "#{:02X}{:02X}{:02X}{:02X}",
+        color.r, color.g, color.b, color.a
+    )
+}
+
+/// Parts with triangles, and their distinct colors in order of appearance
+fn parts_and_colors(parts: &[MeshPart]) -> (Vec<&MeshPart>, Vec<Color>) {
+    let parts: Vec<&MeshPart> = parts
+        .iter()
+        .filter(|p| !p.mesh.indices.is_empty())
+        .collect();
+    let mut colors: Vec<Color> = Vec::new();
+    for part in &parts {
+        if !colors.contains(&part.color) {
+            colors.push(part.color);
+        }
+    }
+    (parts, colors)
+}
+
+/// Object ids: the base materials come first, then the parts, then their parent
+const MATERIALS_ID: usize = 1;
+
+/// The `3D/3dmodel.model` XML document; a slicer `project` places the build item
+fn model_xml(
+    parts: &[MeshPart],
+    metadata: Option<&GenerationMetadata>,
+    units: Units,
+    project: Option<&SlicerProject>,
+) -> String {
+    let mut xml = String::new();
+    let _ = writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(
+        xml,
+        r#"<model unit="{}" xml:lang="en-US" xmlns="{}" xmlns:wagyan="{}">"#,
+        units.name(),
+        CORE_NAMESPACE,
+        WAGYAN_NAMESPACE
+    );
+    if let Some(metadata) = metadata {
+        for (name, value) in metadata.entries() {
+            let _ = writeln!(
+                xml,
+                r#"  <metadata name="{}">{}</metadata>"#,
+                name,
+                escape(&value)
+            );
+        }
+    }
+    let _ = writeln!(xml, "  <resources>");
+
+    // One base material per distinct color, referenced by index
+    let (parts, colors) = parts_and_colors(parts);
+    let materials_id = MATERIALS_ID;
+    let _ = writeln!(xml, r#"    <basematerials id="{}">"#, materials_id);
+    for color in &colors {
+        let _ = writeln!(
+            xml,
+            r#"      <base name="{}" displaycolor="{}"/>"#,
+            color,
+            display_color(*color)
+        );
+    }
+    let _ = writeln!(xml, "    </basematerials>");
+
+    let first_object = materials_id + 1;
+    for (i, part) in parts.iter().enumerate() {
+        let pindex = colors.iter().position(|c| *c == part.color).unwrap_or(0);
+        let _ = writeln!(
+            xml,
+            r#"    <object id="{}" type="model" name="{}" pid="{}" pindex="{}">"#,
+            first_object + i,
+            escape(&part.name),
+            materials_id,
+            pindex
+        );
+        let (positions, indices) = part.mesh.shared_positions();
+        let _ = writeln!(xml, "      <mesh>\n        <vertices>");
+        for p in &positions {
+            let _ = writeln!(
+                xml,
+                r#"          <vertex x="{}" y="{}" z="{}"/>"#,
+                p[0], p[1], p[2]
+            );
+        }
+        let _ = writeln!(xml, "        </vertices>\n        <triangles>");
+        for tri in indices.chunks(3) {
+            let _ = writeln!(
+                xml,
+                r#"          <triangle v1="{}" v2="{}" v3="{}"/>"#,
+                tri[0], tri[1], tri[2]
+            );
+        }
+        let _ = writeln!(xml, "        </triangles>\n      </mesh>\n    </object>");
+    }
+
+    // Parent object: the parts share its placement
+    let parent = first_object + parts.len();
+    let name = metadata.map_or("wagyan".to_string(), |m| m.title.clone());
+    let _ = writeln!(
+        xml,
+        r#"    <object id="{}" type="model" name="{}">"#,
+        parent,
+        escape(&name)
+    );
+    let _ = writeln!(xml, "      <components>");
+    for i in 0..parts.len() {
+        let _ = writeln!(
+            xml,
+            r#"        <component objectid="{}"/>"#,
+            first_object + i
+        );
+    }
+    let _ = writeln!(xml, "      </components>\n    </object>");
+    let _ = writeln!(xml, "  </resources>");
+    let _ = writeln!(xml, r#"  <build>"#);
+    match project {
+        Some(project) => {
+            let [x, y, z] = project.offset;
+            let _ = writeln!(
+                xml,
+                r#"    <item objectid="{}" transform="1 0 0 0 1 0 0 0 1 {} {} {}" printable="1"/>"#,
+                parent, x, y, z
+            );
+        }
+        None => {
+            let _ = writeln!(xml, r#"    <item objectid="{}"/>"#, parent);
+        }
+    }
+    let _ = writeln!(xml, "  </build>\n</model>");
+    xml
+}
+
+pub fn write_3mf(
+    path: &Path,
+    parts: &[MeshPart],
+    metadata: Option<&GenerationMetadata>,
+    units: Units,
+    project: Option<&SlicerProject>,
+) -> Result<()> {
+    output::write_atomically(path, |file| {
+        write_3mf_to_writer(file, parts, metadata, units, project)
+    })
+}
+
+pub fn write_3mf_to_writer<W: Write + Seek>(
+    writer: W,
+    parts: &[MeshPart],
+    metadata: Option<&GenerationMetadata>,
+    units: Units,
+    project: Option<&SlicerProject>,
+) -> Result<()> {
+    let mut zip = ZipWriter::new(writer);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    zip.start_file("[Content_Types].xml", options)?;
+    zip.write_all(CONTENT_TYPES.as_bytes())?;
+    zip.start_file("_rels/.rels", options)?;
+    zip.write_all(RELS.as_bytes())?;
+    zip.start_file(MODEL_PATH, options)?;
+    zip.write_all(model_xml(parts, metadata, units, project).as_bytes())?;
+    if let Some(project) = project {
+        let (parts, colors) = parts_and_colors(parts);
+        let volumes: Vec<Volume<'_>> = parts
+            .iter()
+            .enumerate()
+            .map(|(i, part)| Volume {
+                object_id: MATERIALS_ID + 1 + i,
+                name: &part.name,
+                extruder: colors.iter().position(|c| *c == part.color).unwrap_or(0) + 1,
+                triangles: part.mesh.indices.len() / 3,
+            })
+            .collect();
+        let title = metadata.map_or("wagyan", |m| m.title.as_str());
+        let parent = MATERIALS_ID + 1 + parts.len();
+        for (name, contents) in slicer::project_files(project, title, parent, &volumes) {
+            zip.start_file(name, options)?;
+            zip.write_all(contents.as_bytes())?;
+        }
+    }
+    zip.finish()?.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexed::IndexedMesh;
+    use crate::mesh::{rectangle_mesh, Orientation};
+
+    fn part(name: &str, color: Color) -> MeshPart {
+        let square = rectangle_mesh(0.0, 1.0, 0.0, 1.0);
+        MeshPart {
+            name: name.into(),
+            mesh: IndexedMesh::extrude(&square, 1.0, &Orientation::Flat, 0.0, None),
+            color,
+        }
+    }
+
+    #[test]
+    fn parts_become_components_of_one_build_item() {
+        let xml = model_xml(
+            &[
+                part("plate", Color::LIGHT_GRAY),
+                part("text", Color::rgb(255, 0, 0)),
+            ],
+            None,
+            Units::Cm,
+            None,
+        );
+        assert!(xml.contains(r#"<model unit="centimeter" "#));
+        assert!(xml.contains(r#"<object id="2" type="model" name="plate" pid="1" pindex="0">"#));
+        assert!(xml.contains(r#"<object id="3" type="model" name="text" pid="1" pindex="1">"#));
+        assert!(xml.contains(r#"<component objectid="2"/>"#));
+        assert!(xml.contains(r#"<component objectid="3"/>"#));
+        assert!(xml.contains(r#"<item objectid="4"/>"#));
+        assert!(xml.contains(r##"displaycolor="#FF0000FF""##));
+        // A closed box: 8 shared corners, 12 triangles
+        assert_eq!(xml.matches("<vertex ").count(), 16);
+        assert_eq!(xml.matches("<triangle ").count(), 24);
+    }
+
+    #[test]
+    fn metadata_is_escaped() {
+        let metadata = GenerationMetadata {
+            text: "A&B <1>".into(),
+            title: "A&B <1>".into(),
+            font_name: "Test".into(),
+            font_version: "1.0".into(),
+            version: "0.0.0".into(),
+            options_hash: "0".into(),
+            build_hash: "0".into(),
+        };
+        let xml = model_xml(
+            &[part("text", Color::LIGHT_GRAY)],
+            Some(&metadata),
+            Units::Mm,
+            None,
+        );
+        assert!(xml.contains(r#"<metadata name="Title">A&amp;B &lt;1&gt;</metadata>"#));
+
+        let titled = metadata.with_title("sign");
+        let xml = model_xml(
+            &[part("text", Color::LIGHT_GRAY)],
+            Some(&titled),
+            Units::Mm,
+            None,
+        );
+        assert!(xml.contains(r#"<metadata name="Title">sign</metadata>"#));
+        assert!(xml.contains(r#"<metadata name="wagyan:Text">A&amp;B &lt;1&gt;</metadata>"#));
+        assert!(xml.contains(r#"type="model" name="sign">"#));
+    }
+}
@@ -0,0 +1,221 @@
+//! Token mode: a disc with the text centered on its face, for game
+//! counters and markers
+
+use lyon_path::math::Point;
+use lyon_path::{Path, Polygon, Winding};
+
+use crate::mesh::{self, Mesh2D};
+use crate::{Options, Part, DEFAULT_TOLERANCE};
+
+/// Token settings; the relief depth comes from [`Options::depth`]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct Token {
+    /// Disc diameter in mm
+    pub diameter: f32,
+    /// Disc thickness in mm (total thickness when engraving)
+    pub thickness: f32,
+    /// Space between the text and the rim in mm; larger text is scaled down
+    pub margin: f32,
+    /// Cut the text into the disc instead of raising it
+    pub engrave: bool,
+    /// Repeat the text on the back, mirrored so it reads from that side
+    pub back: bool,
+}
+
+impl Default for Token {
+    fn default() -> Self {
+        Self {
+            diameter: 20.0,
+            thickness: 3.0,
+            margin: 2.0,
+            engrave: false,
+            back: false,
+        }
+    }
+}
+
+impl Token {
+    /// Disc thickness left under the engraving, on one or both faces
+    pub fn core_thickness(&self, depth: f32) -> f32 {
+        if !self.engrave {
+            return self.thickness;
+        }
+        self.thickness - depth * if self.back { 2.0 } else { 1.0 }
+    }
+}
+
+/// The text mirrored left to right about `x`, triangles rewound to stay CCW
+fn mirrored(mesh: &Mesh2D, x: f32) -> Mesh2D {
+    Mesh2D {
+        vertices: mesh
+            .vertices
+            .iter()
+            .map(|p| Point::new(2.0 * x - p.x, p.y))
+            .collect(),
+        indices: mesh
+            .indices
+            .chunks(3)
+            .flat_map(|t| [t[0], t[2], t[1]])
+            .collect(),
+    }
+}
+
+/// The disc at `center`, with the outlines of `text` cut out of it
+fn disc(center: Point, radius: f32, text: &[Mesh2D], tolerance: f32) -> Option<Mesh2D> {
+    let mut builder = Path::builder();
+    builder.add_circle(center, radius, Winding::Positive);
+    for mut points in text.iter().flat_map(mesh::boundary_loops) {
+        points.reverse();
+        builder.add_polygon(Polygon {
+            points: &points,
+            closed: true,
+        });
+    }
+    mesh::tessellate_path(&builder.build(), tolerance).ok()
+}
+
+/// Build the token: the disc first, then the lettering on the face (and the
+/// back), or the face layers with the lettering cut out when engraving
+pub(crate) fn token_parts(
+    token: &Token,
+    mut text: Vec<(String, Option<usize>, Mesh2D)>,
+    options: &Options,
+) -> Vec<Part> {
+    let bounds = text
+        .iter()
+        .filter_map(|(_, _, mesh)| mesh::mesh_bounds(mesh))
+        .reduce(|a, b| (a.0.min(b.0), a.1.max(b.1), a.2.min(b.2), a.3.max(b.3)));
+    let Some((min_x, max_x, min_y, max_y)) = bounds else {
+        return Vec::new();
+    };
+
+    // Keep the corners of the text box inside the margin
+    let center = Point::new((min_x + max_x) * 0.5, (min_y + max_y) * 0.5);
+    let radius = token.diameter * 0.5;
+    let room = radius - token.margin;
+    let half_diagonal = (max_x - min_x).hypot(max_y - min_y) * 0.5;
+    if half_diagonal > room && room > 0.0 {
+        let fit = room / half_diagonal;
+        for (_, _, mesh) in &mut text {
+            for p in &mut mesh.vertices {
+                *p = center + (*p - center) * fit;
+            }
+        }
+    }
+
+    let tolerance = options.tolerance.unwrap_or(DEFAULT_TOLERANCE);
+    let depth = options.text_depth();
+    let core = options.snap_to_layers(token.core_thickness(depth)).max(0.0);
+    let back_z = -(depth + core);
+    let mut parts = Vec::new();
+    let part = |name: String, letter, mesh, z_offset| Part {
+        name,
+        mesh,
+        depth,
+        z_offset,
+        letter,
+        chamfer: 0.0,
+        color: None,
+    };
+
+    if let Some(mesh) = disc(center, radius, &[], tolerance) {
+        parts.push(Part {
+            name: "token".to_string(),
+            mesh,
+            depth: core,
+            z_offset: -(depth * 0.5 + core * 0.5),
+            letter: None,
+            chamfer: 0.0,
+            color: None,
+        });
+    }
+
+    if token.engrave {
+        let meshes: Vec<Mesh2D> = text.into_iter().map(|(_, _, mesh)| mesh).collect();
+        if let Some(mesh) = disc(center, radius, &meshes, tolerance) {
+            parts.push(part("face".to_string(), None, mesh, 0.0));
+        }
+        if token.back {
+            let back: Vec<Mesh2D> = meshes.iter().map(|m| mirrored(m, center.x)).collect();
+            if let Some(mesh) = disc(center, radius, &back, tolerance) {
+                parts.push(part("back".to_string(), None, mesh, back_z));
+            }
+        }
+        return parts;
+    }
+
+    let back: Vec<Part> = if token.back {
+        text.iter()
+            .map(|(name, letter, mesh)| {
+                let name = format!("back-{}", name);
+                part(name, *letter, mirrored(mesh, center.x), back_z)
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    parts.extend(
+        text.into_iter()
+            .map(|(name, letter, mesh)| part(name, letter, mesh, 0.0)),
+    );
+    parts.extend(back);
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model_parts;
+
+    #[test]
+    fn text_is_fitted_inside_the_disc_and_mirrored_on_the_back() {
+        let token = Token {
+            back: true,
+            ..Default::default()
+        };
+        let options = Options {
+            depth: 1.0,
+            token: Some(token.clone()),
+            ..Default::default()
+        };
+        let parts = model_parts(mesh::rectangle_mesh(0.0, 30.0, 0.0, 10.0), &options);
+        let names: Vec<&str> = parts.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["token", "text", "back-text"]);
+
+        // The text box corners touch the margin circle
+        let (min_x, max_x, min_y, max_y) = mesh::mesh_bounds(&parts[1].mesh).unwrap();
+        let room = token.diameter * 0.5 - token.margin;
+        assert!(((max_x - min_x).hypot(max_y - min_y) * 0.5 - room).abs() < 1e-3);
+        let (disc_min_x, disc_max_x, ..) = mesh::mesh_bounds(&parts[0].mesh).unwrap();
+        assert!((disc_max_x - disc_min_x - token.diameter).abs() < 0.01);
+
+        // Back text sits under the disc, still wound CCW
+        let bottom = |p: &Part| p.z_offset - p.depth * 0.5;
+        let top = |p: &Part| p.z_offset + p.depth * 0.5;
+        assert!((top(&parts[2]) - bottom(&parts[0])).abs() < 1e-5);
+        assert!((bottom(&parts[0]) - (-0.5 - token.thickness)).abs() < 1e-5);
+        let back = &parts[2].mesh;
+        let t = &back.indices[..3];
+        let [a, b, c] = [t[0], t[1], t[2]].map(|i| back.vertices[i as usize]);
+        assert!((b - a).cross(c - a) > 0.0);
+
+        // Engraving both faces leaves the core between the two face layers
+        let options = Options {
+            token: Some(Token {
+                engrave: true,
+                ..token
+            }),
+            ..options
+        };
+        let parts = model_parts(mesh::rectangle_mesh(0.0, 4.0, 0.0, 4.0), &options);
+        let names: Vec<&str> = parts.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["token", "face", "back"]);
+        assert!((parts[0].depth - 1.0).abs() < 1e-5);
+        assert_eq!(mesh::boundary_loops(&parts[1].mesh).len(), 2);
+    }
+}
@@ -0,0 +1,271 @@
+//! `wagyan tui`: tweak the main options one line at a time with a live
+//! block-character preview, then print the equivalent command
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use ttf_parser::Face;
+
+use crate::cancel::CancelToken;
+use crate::font::Builtin;
+use crate::mesh::Orientation;
+use crate::{build_mesh, model_bounds, model_parts, no_progress, preview, Options, SizeTarget};
+
+/// Fields that can be set, with a short description for `help`
+pub const FIELDS: &[(&str, &str)] = &[
+    ("text", "text to render (\\n starts a new line)"),
+    (
+        "font",
+        "font file, or sans, serif or mono for a builtin font",
+    ),
+    ("face", "face index in a font collection (.ttc)"),
+    ("size", "em size in mm"),
+    ("cap-height", "capital letter height in mm, or 'off'"),
+    ("depth", "extrusion depth in mm"),
+    ("plate", "back plate thickness in mm (0 disables)"),
+    ("plate-margin", "plate margin around the text in mm"),
+    ("spacing", "extra space between glyphs in mm"),
+    ("orient", "flat or front"),
+];
+
+/// What the caller should do after a line of input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// Options changed: show the preview again
+    Redraw,
+    /// The font changed and must be reloaded before redrawing
+    ReloadFont,
+    Help,
+    Quit,
+}
+
+/// Options being edited
+#[derive(Debug, Clone, Default)]
+pub struct Session {
+    pub options: Options,
+}
+
+/// Parse `value` as a number, or as `+n` / `-n` relative to `current`
+fn number(value: &str, current: f32) -> Result<f32> {
+    let parsed = |s: &str| -> Result<f32> {
+        s.trim()
+            .parse::<f32>()
+            .with_context(|| format!("'{}' is not a number", value))
+    };
+    let result = match value.as_bytes().first() {
+        Some(b'+') => current + parsed(&value[1..])?,
+        Some(b'-') if value.len() > 1 => current - parsed(&value[1..])?,
+        _ => parsed(value)?,
+    };
+    anyhow::ensure!(
+        result.is_finite() && result >= 0.0,
+        "{} is out of range",
+        result
+    );
+    Ok(result)
+}
+
+/// [`number`] above zero, for the sizes the command line requires to be positive
+fn positive(value: &str, current: f32) -> Result<f32> {
+    let result = number(value, current)?;
+    anyhow::ensure!(result > 0.0, "{} is out of range", result);
+    Ok(result)
+}
+
+impl Session {
+    pub fn new(options: Options) -> Self {
+        Self { options }
+    }
+
+    /// Apply one line: `field value` or `field=value` (numbers may be
+    /// `+n`/`-n` to nudge), `help`, or `quit` / an empty line
+    pub fn apply(&mut self, line: &str) -> Result<Step> {
+        let line = line.trim();
+        if matches!(line, "" | "q" | "quit" | "exit") {
+            return Ok(Step::Quit);
+        }
+        if matches!(line, "?" | "h" | "help") {
+            return Ok(Step::Help);
+        }
+        let (field, value) = line
+            .split_once(['=', ' '])
+            .map(|(field, value)| (field.trim(), value.trim()))
+            .with_context(|| format!("expected 'field value', got '{}'", line))?;
+        let options = &mut self.options;
+        match field {
+            "text" => options.text = value.replace("\\n", "\n"),
+            "font" => {
+                match Builtin::from_str(value, true) {
+                    Ok(builtin) => {
+                        options.font = None;
+                        options.builtin = builtin;
+                    }
+                    Err(_) if value == "default" => options.font = None,
+                    Err(_) => options.font = Some(value.into()),
+                }
+                options.face_index = 0;
+                return Ok(Step::ReloadFont);
+            }
+            "face" => {
+                options.face_index = value
+                    .parse()
+                    .with_context(|| format!("'{}' is not a face index", value))?;
+                return Ok(Step::ReloadFont);
+            }
+            "size" => options.size = positive(value, options.size)?,
+            "cap-height" => {
+                options.size_target = match value {
+                    "off" => None,
+                    _ => {
+                        let current = match options.size_target {
+                            Some(SizeTarget::CapHeight(mm)) => mm,
+                            _ => 0.0,
+                        };
+                        Some(SizeTarget::CapHeight(positive(value, current)?))
+                    }
+                }
+            }
+            "depth" => options.depth = number(value, options.depth)?,
+            "plate" => options.plate = number(value, options.plate)?,
+            "plate-margin" => options.plate_margin = number(value, options.plate_margin)?,
+            "spacing" => options.spacing = number(value, options.spacing)?,
+            "orient" => {
+                options.orient = match value {
+                    "flat" => Orientation::Flat,
+                    "front" => Orientation::Front,
+                    _ => anyhow::bail!("orient is flat or front, not '{}'", value),
+                }
+            }
+            _ => anyhow::bail!("unknown field '{}'; type 'help' for the list", field),
+        }
+        Ok(Step::Redraw)
+    }
+
+    /// Preview and size of the model as it stands
+    pub fn summary(&self, face: &Face<'_>, columns: usize) -> Result<String> {
+        let mesh = build_mesh(face, &self.options, &CancelToken::new(), &no_progress)?;
+        let mut out = preview::render(&mesh, columns);
+        let triangles = mesh.indices.len() / 3;
+        let parts = model_parts(mesh, &self.options);
+        if let Some((lo, hi)) = model_bounds(&parts, &self.options.orient) {
+            out.push_str(&format!(
+                "{:.1} x {:.1} x {:.1} mm, {} triangles per cap\n",
+                hi[0] - lo[0],
+                hi[1] - lo[1],
+                hi[2] - lo[2],
+                triangles
+            ));
+        }
+        Ok(out)
+    }
+
+    /// The `wagyan` command that builds the same model; only options that
+    /// differ from the defaults are spelled out
+    pub fn command_line(&self) -> String {
+        let defaults = Options::default();
+        let options = &self.options;
+        let mut args = vec!["wagyan".to_string()];
+        match &options.font {
+            Some(font) => args.push(format!("--font {}", shell_quote(&font.to_string_lossy()))),
+            None if options.builtin != defaults.builtin => {
+                if let Some(value) = options.builtin.to_possible_value() {
+                    args.push(format!("--builtin {}", value.get_name()));
+                }
+            }
+            None => {}
+        }
+        if options.face_index != defaults.face_index {
+            args.push(format!("--face-index {}", options.face_index));
+        }
+        if !options.variations.is_empty() {
+            let variations: Vec<String> =
+                options.variations.iter().map(|v| v.to_string()).collect();
+            args.push(format!("--variation {}", variations.join(",")));
+        }
+        if let Some(SizeTarget::CapHeight(mm)) = options.size_target {
+            args.push(format!("--cap-height-mm {}", mm));
+        } else if options.size != defaults.size {
+            args.push(format!("--size {}", options.size));
+        }
+        let numbers = [
+            ("--depth", options.depth, defaults.depth),
+            ("--plate", options.plate, defaults.plate),
+            (
+                "--plate-margin",
+                options.plate_margin,
+                defaults.plate_margin,
+            ),
+            ("--spacing", options.spacing, defaults.spacing),
+        ];
+        for (flag, value, default) in numbers {
+            if value != default && (flag != "--plate-margin" || options.plate > 0.0) {
+                args.push(format!("{} {}", flag, value));
+            }
+        }
+        if matches!(options.orient, Orientation::Flat) {
+            args.push("--orient flat".to_string());
+        }
+        args.push(shell_quote(&options.text.replace('\n', "\\n")));
+        args.join(" ")
+    }
+}
+
+/// Single-quote for POSIX shells
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_become_a_command_line() {
+        let mut session = Session::new(Options {
+            text: "Hi".into(),
+            ..Default::default()
+        });
+        assert_eq!(session.apply("size 40").unwrap(), Step::Redraw);
+        assert_eq!(session.apply("size +5").unwrap(), Step::Redraw);
+        session.apply("plate=2").unwrap();
+        session.apply("text it's\\nme").unwrap();
+        session.apply("orient flat").unwrap();
+        assert!(session.apply("depth -20").is_err());
+        assert!(session.apply("color red").is_err());
+        assert_eq!(session.apply("font default").unwrap(), Step::ReloadFont);
+        assert_eq!(session.apply("").unwrap(), Step::Quit);
+
+        assert_eq!(session.options.size, 45.0);
+        assert_eq!(
+            session.command_line(),
+            r"wagyan --size 45 --plate 2 --orient flat 'it'\''s\nme'"
+        );
+    }
+
+    #[test]
+    fn sizes_stay_positive() {
+        let mut session = Session::new(Options::default());
+        assert!(session.apply("size 0").is_err());
+        assert!(session.apply("size -72").is_err());
+        assert!(session.apply("cap-height 0").is_err());
+        assert_eq!(session.options.size, 72.0);
+        assert_eq!(session.options.size_target, None);
+    }
+
+    #[test]
+    fn face_settings_reach_the_command_line() {
+        let mut session = Session::new(Options {
+            text: "Hi".into(),
+            ..Default::default()
+        });
+        assert_eq!(session.apply("font mono").unwrap(), Step::ReloadFont);
+        assert_eq!(session.apply("face 1").unwrap(), Step::ReloadFont);
+        assert!(session.apply("face first").is_err());
+        assert_eq!(
+            session.command_line(),
+            "wagyan --builtin mono --face-index 1 'Hi'"
+        );
+        session.apply("font fonts/a.ttc").unwrap();
+        assert_eq!(session.options.face_index, 0);
+        assert_eq!(session.command_line(), "wagyan --font 'fonts/a.ttc' 'Hi'");
+    }
+}
@@ -0,0 +1,203 @@
+//! WordArt-style warps (`--warp`): the laid-out outline is bent as a whole,
+//! point by point, before it is tessellated.
+//!
+//! Curves are flattened first and long straight edges split, so the stems
+//! of letters bend along with everything else. Each warp is measured
+//! against the bounds of the text: `amount` is a fraction of its height.
+
+use lyon_path::iterator::PathIterator;
+use lyon_path::math::{vector, Point};
+use lyon_path::{Event, Path};
+
+/// Pieces each side of the text's bounds is split into, at least, so
+/// straight edges follow the warp
+const SEGMENTS: f32 = 100.0;
+
+/// Shape the text is bent into
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Warp {
+    /// Left as laid out
+    #[default]
+    None,
+    /// Rising to a hump in the middle (sagging for a negative amount)
+    Arch,
+    /// One full wave, up then down
+    Wave,
+    /// A wave growing from nothing at the left edge, like a flag on its pole
+    Flag,
+    /// Shrinking towards the right edge as if seen at an angle (towards the
+    /// left for a negative amount)
+    Perspective,
+}
+
+impl Warp {
+    /// Where `p` goes in text spanning `bounds` (min_x, max_x, min_y, max_y)
+    fn map(self, p: Point, amount: f32, bounds: (f32, f32, f32, f32)) -> Point {
+        let (x0, x1, y0, y1) = bounds;
+        let height = y1 - y0;
+        let u = if x1 > x0 { (p.x - x0) / (x1 - x0) } else { 0.5 };
+        let wave = (u * std::f32::consts::TAU).sin();
+        let rise = match self {
+            Warp::None => 0.0,
+            Warp::Arch => 4.0 * u * (1.0 - u),
+            Warp::Wave => wave,
+            Warp::Flag => u * wave,
+            Warp::Perspective => {
+                let middle = (y0 + y1) / 2.0;
+                return Point::new(
+                    p.x,
+                    middle + (p.y - middle) * (1.0 - amount * (2.0 * u - 1.0)),
+                );
+            }
+        };
+        p + vector(0.0, amount * height * rise)
+    }
+
+    /// `path` bent by this warp, `amount` strong: curves are flattened to
+    /// `tolerance` and the result is made of straight lines
+    pub fn apply(self, path: &Path, amount: f32, tolerance: f32) -> Path {
+        self.apply_all(std::slice::from_ref(path), amount, tolerance)
+            .remove(0)
+    }
+
+    /// [`Warp::apply`] to `paths` as if they were one, so pieces of the
+    /// text kept apart bend together
+    pub fn apply_all(self, paths: &[Path], amount: f32, tolerance: f32) -> Vec<Path> {
+        if self == Warp::None || amount == 0.0 {
+            return paths.to_vec();
+        }
+        let contours: Vec<Vec<(Vec<Point>, bool)>> =
+            paths.iter().map(|path| contours(path, tolerance)).collect();
+        let Some(bounds) = contours
+            .iter()
+            .flatten()
+            .flat_map(|(points, _)| points)
+            .map(|p| (p.x, p.x, p.y, p.y))
+            .reduce(|a, b| (a.0.min(b.0), a.1.max(b.1), a.2.min(b.2), a.3.max(b.3)))
+        else {
+            return paths.to_vec();
+        };
+        let step = (bounds.1 - bounds.0).max(bounds.3 - bounds.2) / SEGMENTS;
+        contours
+            .into_iter()
+            .map(|contours| {
+                let mut builder = Path::builder();
+                for (points, closed) in contours {
+                    builder.begin(self.map(points[0], amount, bounds));
+                    let mut edges: Vec<(Point, Point)> =
+                        points.windows(2).map(|w| (w[0], w[1])).collect();
+                    if closed {
+                        edges.push((points[points.len() - 1], points[0]));
+                    }
+                    for (from, to) in edges {
+                        let pieces = ((to - from).length() / step).ceil().max(1.0);
+                        for i in 1..=pieces as usize {
+                            let at = from.lerp(to, i as f32 / pieces);
+                            builder.line_to(self.map(at, amount, bounds));
+                        }
+                    }
+                    builder.end(closed);
+                }
+                builder.build()
+            })
+            .collect()
+    }
+}
+
+/// Contours of `path` flattened to `tolerance`, and whether each is closed
+fn contours(path: &Path, tolerance: f32) -> Vec<(Vec<Point>, bool)> {
+    let mut contours: Vec<(Vec<Point>, bool)> = Vec::new();
+    for event in path.iter().flattened(tolerance) {
+        match event {
+            Event::Begin { at } => contours.push((vec![at], false)),
+            Event::Line { to, .. } => {
+                if let Some((points, _)) = contours.last_mut() {
+                    points.push(to);
+                }
+            }
+            Event::End { close, .. } => {
+                if let Some((_, closed)) = contours.last_mut() {
+                    *closed = close;
+                }
+            }
+            _ => {}
+        }
+    }
+    contours
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 100 × 10 rectangle from the origin
+    fn bar() -> Path {
+        let mut builder = Path::builder();
+        builder.begin(Point::new(0.0, 0.0));
+        builder.line_to(Point::new(100.0, 0.0));
+        builder.line_to(Point::new(100.0, 10.0));
+        builder.line_to(Point::new(0.0, 10.0));
+        builder.end(true);
+        builder.build()
+    }
+
+    fn points(path: &Path) -> Vec<Point> {
+        path.iter()
+            .filter_map(|event| match event {
+                Event::Begin { at } => Some(at),
+                Event::Line { to, .. } => Some(to),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Lowest point of `path` at `x`
+    fn bottom_at(path: &Path, x: f32) -> f32 {
+        points(path)
+            .into_iter()
+            .filter(|p| (p.x - x).abs() < 1e-3)
+            .map(|p| p.y)
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    #[test]
+    fn warps_bend_long_edges_by_a_fraction_of_the_height() {
+        let arch = Warp::Arch.apply(&bar(), 0.5, 0.01);
+        assert!(bottom_at(&arch, 0.0).abs() < 1e-3);
+        assert!((bottom_at(&arch, 50.0) - 5.0).abs() < 1e-3);
+        assert!((bottom_at(&arch, 25.0) - 3.75).abs() < 1e-3);
+
+        let wave = Warp::Wave.apply(&bar(), 0.5, 0.01);
+        assert!((bottom_at(&wave, 25.0) - 5.0).abs() < 1e-3);
+        assert!((bottom_at(&wave, 75.0) + 5.0).abs() < 1e-3);
+
+        let flag = Warp::Flag.apply(&bar(), 0.5, 0.01);
+        assert!((bottom_at(&flag, 25.0) - 1.25).abs() < 1e-3);
+        assert!((bottom_at(&flag, 75.0) + 3.75).abs() < 1e-3);
+    }
+
+    #[test]
+    fn perspective_shrinks_the_far_edge_about_the_middle() {
+        let path = Warp::Perspective.apply(&bar(), 0.5, 0.01);
+        let height = |x: f32| {
+            let ys: Vec<f32> = points(&path)
+                .into_iter()
+                .filter(|p| (p.x - x).abs() < 1e-3)
+                .map(|p| p.y)
+                .collect();
+            let top = ys.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+            let bottom = ys.iter().copied().fold(f32::INFINITY, f32::min);
+            ((top - bottom) * 1e3).round() / 1e3
+        };
+        assert_eq!(
+            (height(0.0), height(50.0), height(100.0)),
+            (15.0, 10.0, 5.0)
+        );
+        assert_eq!(points(&Warp::None.apply(&bar(), 0.5, 0.01)), points(&bar()));
+    }
+}
@@ -0,0 +1,709 @@
+//! WOFF and WOFF2 web fonts, unpacked into the plain sfnt (TrueType or
+//! OpenType) data ttf-parser reads. WOFF2's transformed `glyf`, `loca` and
+//! `hmtx` tables are rebuilt as the spec's reference decoder does.
+
+use std::io::Read;
+
+use anyhow::{bail, ensure, Context, Result};
+
+/// The sfnt font inside `data` when it is a WOFF or WOFF2 file, `None` for
+/// anything else
+pub fn decode(data: &[u8]) -> Result<Option<Vec<u8>>> {
+    match data.get(..4) {
+        Some(b"wOFF") => decode_woff(data).context("invalid WOFF font").map(Some),
+        Some(b"wOF2") => decode_woff2(data).context("invalid WOFF2 font").map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// Big-endian cursor over a table or stream
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data, pos: 0 }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .context("unexpected end of data")?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn i16(&mut self) -> Result<i16> {
+        Ok(self.u16()? as i16)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// WOFF2 `UIntBase128`: 7 bits per byte, high bit set on all but the last
+    fn base128(&mut self) -> Result<u32> {
+        let mut value: u32 = 0;
+        for i in 0..5 {
+            let byte = self.u8()?;
+            ensure!(i > 0 || byte != 0x80, "UIntBase128 with leading zeros");
+            ensure!(value >> 25 == 0, "UIntBase128 overflows 32 bits");
+            value = (value << 7) | u32::from(byte & 0x7f);
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        bail!("UIntBase128 longer than 5 bytes")
+    }
+
+    /// WOFF2 `255UInt16`
+    fn u255(&mut self) -> Result<u16> {
+        Ok(match self.u8()? {
+            253 => self.u16()?,
+            254 => u16::from(self.u8()?) + 506,
+            255 => u16::from(self.u8()?) + 253,
+            code => u16::from(code),
+        })
+    }
+}
+
+pub(crate) struct Table {
+    pub(crate) tag: [u8; 4],
+    pub(crate) data: Vec<u8>,
+}
+
+/// Table checksum: the sum of its big-endian words, zero-padded
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+/// Assemble an sfnt from its tables, sorted by tag and 4-byte aligned
+pub(crate) fn sfnt(flavor: u32, mut tables: Vec<Table>) -> Vec<u8> {
+    tables.sort_by_key(|table| table.tag);
+    // In u32, as 16 times 4096 tables or more no longer fits the u16 fields
+    let count = tables.len() as u32;
+    let entry_selector = 31 - count.max(1).leading_zeros();
+    let search_range = 16u32 << entry_selector;
+    let mut out = Vec::new();
+    out.extend(flavor.to_be_bytes());
+    out.extend((count as u16).to_be_bytes());
+    out.extend((search_range as u16).to_be_bytes());
+    out.extend((entry_selector as u16).to_be_bytes());
+    out.extend(((count * 16 - search_range) as u16).to_be_bytes());
+    let mut offset = 12 + 16 * tables.len();
+    for table in &tables {
+        out.extend(table.tag);
+        out.extend(checksum(&table.data).to_be_bytes());
+        out.extend((offset as u32).to_be_bytes());
+        out.extend((table.data.len() as u32).to_be_bytes());
+        offset += table.data.len().next_multiple_of(4);
+    }
+    for table in &tables {
+        out.extend(&table.data);
+        out.resize(out.len().next_multiple_of(4), 0);
+    }
+    out
+}
+
+/// WOFF 1.0: a table directory over tables zlib-compressed one by one
+fn decode_woff(data: &[u8]) -> Result<Vec<u8>> {
+    let mut header = Reader::new(data);
+    header.bytes(4)?;
+    let flavor = header.u32()?;
+    header.u32()?;
+    let count = header.u16()?;
+    let mut directory = Reader::new(data);
+    directory.pos = 44;
+    let mut tables = Vec::new();
+    for _ in 0..count {
+        let tag: [u8; 4] = directory.bytes(4)?.try_into()?;
+        let offset = directory.u32()? as usize;
+        let compressed_len = directory.u32()? as usize;
+        let len = directory.u32()? as usize;
+        directory.u32()?;
+        let stored = data
+            .get(offset..offset + compressed_len)
+            .context("table data out of bounds")?;
+        let data = if compressed_len < len {
+            let mut table = Vec::with_capacity(len);
+            flate2::read::ZlibDecoder::new(stored).read_to_end(&mut table)?;
+            table
+        } else {
+            stored.to_vec()
+        };
+        ensure!(
+            data.len() == len,
+            "table {} has the wrong length",
+            String::from_utf8_lossy(&tag)
+        );
+        tables.push(Table { tag, data });
+    }
+    Ok(sfnt(flavor, tables))
+}
+
+/// Tags WOFF2 refers to by index in its table directory
+const KNOWN_TAGS: [&[u8; 4]; 63] = [
+    b"cmap", b"head", b"hhea", b"hmtx", b"maxp", b"name", b"OS/2", b"post", b"cvt ", b"fpgm",
+    b"glyf", b"loca", b"prep", b"CFF ", b"VORG", b"EBDT", b"EBLC", b"gasp", b"hdmx", b"kern",
+    b"LTSH", b"PCLT", b"VDMX", b"vhea", b"vmtx", b"BASE", b"GDEF", b"GPOS", b"GSUB", b"EBSC",
+    b"JSTF", b"MATH", b"CBDT", b"CBLC", b"COLR", b"CPAL", b"SVG ", b"sbix", b"acnt", b"avar",
+    b"bdat", b"bloc", b"bsln", b"cvar", b"fdsc", b"feat", b"fmtx", b"fvar", b"gvar", b"hsty",
+    b"just", b"lcar", b"mort", b"morx", b"opbd", b"prop", b"trak", b"Zapf", b"Silf", b"Glat",
+    b"Gloc", b"Feat", b"Sill",
+];
+
+/// WOFF2: one Brotli stream holding every table, `glyf`/`loca` and `hmtx`
+/// possibly in their transformed forms
+fn decode_woff2(data: &[u8]) -> Result<Vec<u8>> {
+    let mut header = Reader::new(data);
+    header.bytes(4)?;
+    let flavor = header.u32()?;
+    ensure!(
+        &flavor.to_be_bytes() != b"ttcf",
+        "WOFF2 font collections are not supported"
+    );
+    header.u32()?;
+    let count = header.u16()?;
+    header.u16()?;
+    header.u32()?;
+    let compressed_len = header.u32()? as usize;
+
+    let mut directory = Reader::new(data);
+    directory.pos = 48;
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        let flags = directory.u8()?;
+        let tag: [u8; 4] = match flags & 0x3f {
+            0x3f => directory.bytes(4)?.try_into()?,
+            index => *KNOWN_TAGS[index as usize],
+        };
+        let version = flags >> 6;
+        let len = directory.base128()? as usize;
+        let transformed = match &tag {
+            b"glyf" | b"loca" => version != 3,
+            b"hmtx" => version == 1,
+            _ => {
+                ensure!(
+                    version == 0,
+                    "unknown transform of table {}",
+                    String::from_utf8_lossy(&tag)
+                );
+                false
+            }
+        };
+        let stored_len = match transformed {
+            true => directory.base128()? as usize,
+            false => len,
+        };
+        entries.push((tag, transformed, stored_len));
+    }
+
+    let compressed = data
+        .get(directory.pos..directory.pos + compressed_len)
+        .context("compressed data out of bounds")?;
+    let mut stream = Vec::new();
+    brotli_decompressor::Decompressor::new(compressed, 4096)
+        .read_to_end(&mut stream)
+        .context("corrupt Brotli stream")?;
+    let mut stream = Reader::new(&stream);
+    let mut tables = Vec::new();
+    for &(tag, transformed, len) in &entries {
+        tables.push((tag, transformed, stream.bytes(len)?.to_vec()));
+    }
+
+    let find = |tables: &[([u8; 4], bool, Vec<u8>)], tag: &[u8; 4]| {
+        tables.iter().position(|(t, _, _)| t == tag)
+    };
+    let mut x_mins = None;
+    if let Some(glyf) = find(&tables, b"glyf").filter(|&i| tables[i].1) {
+        let loca = find(&tables, b"loca").context("transformed glyf without loca")?;
+        let (glyf_data, loca_data, mins) = reconstruct_glyf(&tables[glyf].2)?;
+        tables[glyf].2 = glyf_data;
+        tables[loca].2 = loca_data;
+        x_mins = Some(mins);
+    }
+    if let Some(hmtx) = find(&tables, b"hmtx").filter(|&i| tables[i].1) {
+        let x_mins = x_mins.context("transformed hmtx without a transformed glyf")?;
+        let hhea = find(&tables, b"hhea").context("hmtx without hhea")?;
+        let mut hhea = Reader::new(&tables[hhea].2);
+        hhea.pos = 34;
+        let metrics = hhea.u16()? as usize;
+        tables[hmtx].2 = reconstruct_hmtx(&tables[hmtx].2, metrics, &x_mins)?;
+    }
+    let tables = tables
+        .into_iter()
+        .map(|(tag, _, data)| Table { tag, data })
+        .collect();
+    Ok(sfnt(flavor, tables))
+}
+
+fn with_sign(flag: u8, value: i32) -> i32 {
+    if flag & 1 != 0 {
+        value
+    } else {
+        -value
+    }
+}
+
+/// One point of a simple glyph from its WOFF2 flag and coordinate bytes:
+/// (dx, dy, on curve)
+fn triplet(flag: u8, glyphs: &mut Reader<'_>) -> Result<(i32, i32, bool)> {
+    let on_curve = flag & 0x80 == 0;
+    let flag = flag & 0x7f;
+    let len = match flag {
+        0..=83 => 1,
+        84..=119 => 2,
+        120..=123 => 3,
+        _ => 4,
+    };
+    let b: Vec<i32> = glyphs.bytes(len)?.iter().map(|&b| i32::from(b)).collect();
+    let f = i32::from(flag);
+    let (dx, dy) = match flag {
+        0..=9 => (0, with_sign(flag, ((f & 14) << 7) + b[0])),
+        10..=19 => (with_sign(flag, (((f - 10) & 14) << 7) + b[0]), 0),
+        20..=83 => {
+            let b0 = f - 20;
+            (
+                with_sign(flag, 1 + (b0 & 0x30) + (b[0] >> 4)),
+                with_sign(flag >> 1, 1 + ((b0 & 0x0c) << 2) + (b[0] & 0x0f)),
+            )
+        }
+        84..=119 => {
+            let b0 = f - 84;
+            (
+                with_sign(flag, 1 + ((b0 / 12) << 8) + b[0]),
+                with_sign(flag >> 1, 1 + (((b0 % 12) >> 2) << 8) + b[1]),
+            )
+        }
+        120..=123 => (
+            with_sign(flag, (b[0] << 4) + (b[1] >> 4)),
+            with_sign(flag >> 1, ((b[1] & 0x0f) << 8) + b[2]),
+        ),
+        _ => (
+            with_sign(flag, (b[0] << 8) + b[1]),
+            with_sign(flag >> 1, (b[2] << 8) + b[3]),
+        ),
+    };
+    Ok((dx, dy, on_curve))
+}
+
+fn push_i16(out: &mut Vec<u8>, value: i16) {
+    out.extend(value.to_be_bytes());
+}
+
+/// `glyf` data of a simple glyph with absolute `points`
+fn simple_glyph(
+    out: &mut Vec<u8>,
+    bbox: [i16; 4],
+    ends: &[u16],
+    instructions: &[u8],
+    points: &[(i32, i32, bool)],
+    overlap: bool,
+) -> Result<()> {
+    push_i16(out, ends.len() as i16);
+    bbox.iter().for_each(|&v| push_i16(out, v));
+    ends.iter().for_each(|&end| out.extend(end.to_be_bytes()));
+    out.extend((instructions.len() as u16).to_be_bytes());
+    out.extend(instructions);
+    let (mut flags, mut xs, mut ys) = (Vec::new(), Vec::new(), Vec::new());
+    let (mut x, mut y) = (0, 0);
+    for (i, &(px, py, on_curve)) in points.iter().enumerate() {
+        let (dx, dy) = (px - x, py - y);
+        (x, y) = (px, py);
+        let mut flag = u8::from(on_curve);
+        if i == 0 && overlap {
+            flag |= 0x40;
+        }
+        match dx {
+            0 => flag |= 0x10,
+            -255..=255 => {
+                flag |= if dx > 0 { 0x12 } else { 0x02 };
+                xs.push(dx.unsigned_abs() as u8);
+            }
+            _ => push_i16(
+                &mut xs,
+                i16::try_from(dx).context("coordinate out of range")?,
+            ),
+        }
+        match dy {
+            0 => flag |= 0x20,
+            -255..=255 => {
+                flag |= if dy > 0 { 0x24 } else { 0x04 };
+                ys.push(dy.unsigned_abs() as u8);
+            }
+            _ => push_i16(
+                &mut ys,
+                i16::try_from(dy).context("coordinate out of range")?,
+            ),
+        }
+        flags.push(flag);
+    }
+    out.extend(flags);
+    out.extend(xs);
+    out.extend(ys);
+    Ok(())
+}
+
+/// Rebuild `glyf` and `loca` from a transformed `glyf` table; also returns
+/// each glyph's xMin for a transformed `hmtx`
+fn reconstruct_glyf(data: &[u8]) -> Result<(Vec<u8>, Vec<u8>, Vec<i16>)> {
+    let mut header = Reader::new(data);
+    header.u16()?;
+    let options = header.u16()?;
+    let glyph_count = header.u16()? as usize;
+    let index_format = header.u16()?;
+    let mut offset = header.pos + 7 * 4;
+    let mut streams = Vec::new();
+    for _ in 0..7 {
+        let len = header.u32()? as usize;
+        let stream = data
+            .get(offset..offset + len)
+            .context("glyf stream out of bounds")?;
+        streams.push(Reader::new(stream));
+        offset += len;
+    }
+    let overlaps = match options & 1 {
+        0 => None,
+        _ => Some(
+            data.get(offset..offset + glyph_count.div_ceil(8))
+                .context("overlap bitmap out of bounds")?,
+        ),
+    };
+    let [mut contours, mut point_counts, mut flags, mut glyphs, mut composites, mut bboxes, mut instructions]: [Reader<'_>; 7] =
+        streams.try_into().ok().context("missing glyf streams")?;
+    let bit = |bitmap: &[u8], i: usize| bitmap[i >> 3] & (0x80 >> (i & 7)) != 0;
+    let bbox_bitmap = bboxes.bytes(((glyph_count + 31) >> 5) << 2)?;
+
+    let mut glyf = Vec::new();
+    let mut offsets = Vec::with_capacity(glyph_count + 1);
+    let mut x_mins = Vec::with_capacity(glyph_count);
+    for i in 0..glyph_count {
+        offsets.push(glyf.len());
+        let explicit_bbox = bit(bbox_bitmap, i);
+        let mut read_bbox = || -> Result<[i16; 4]> {
+            Ok([bboxes.i16()?, bboxes.i16()?, bboxes.i16()?, bboxes.i16()?])
+        };
+        match contours.i16()? {
+            0 => {
+                ensure!(!explicit_bbox, "empty glyph {} with a bounding box", i);
+                x_mins.push(0);
+            }
+            -1 => {
+                ensure!(
+                    explicit_bbox,
+                    "composite glyph {} without a bounding box",
+                    i
+                );
+                let bbox = read_bbox()?;
+                let start = composites.pos;
+                let mut has_instructions = false;
+                loop {
+                    let flag = composites.u16()?;
+                    let args = if flag & 0x0001 != 0 { 4 } else { 2 };
+                    let transform = match flag {
+                        f if f & 0x0008 != 0 => 2,
+                        f if f & 0x0040 != 0 => 4,
+                        f if f & 0x0080 != 0 => 8,
+                        _ => 0,
+                    };
+                    composites.bytes(2 + args + transform)?;
+                    has_instructions |= flag & 0x0100 != 0;
+                    if flag & 0x0020 == 0 {
+                        break;
+                    }
+                }
+                push_i16(&mut glyf, -1);
+                bbox.iter().for_each(|&v| push_i16(&mut glyf, v));
+                glyf.extend(&composites.data[start..composites.pos]);
+                if has_instructions {
+                    let len = glyphs.u255()?;
+                    glyf.extend(len.to_be_bytes());
+                    glyf.extend(instructions.bytes(len as usize)?);
+                }
+                x_mins.push(bbox[0]);
+            }
+            n if n > 0 => {
+                let mut ends = Vec::with_capacity(n as usize);
+                let mut total = 0usize;
+                for _ in 0..n {
+                    total += point_counts.u255()? as usize;
+                    let end = total
+                        .checked_sub(1)
+                        .context("the first contour has no points")?;
+                    ends.push(u16::try_from(end).context("too many points")?);
+                }
+                let mut points = Vec::with_capacity(total);
+                let (mut x, mut y) = (0, 0);
+                for _ in 0..total {
+                    let (dx, dy, on_curve) = triplet(flags.u8()?, &mut glyphs)?;
+                    (x, y) = (x + dx, y + dy);
+                    points.push((x, y, on_curve));
+                }
+                let instruction_len = glyphs.u255()? as usize;
+                let bbox = match explicit_bbox {
+                    true => read_bbox()?,
+                    false => {
+                        let range = |values: &mut dyn Iterator<Item = i32>| {
+                            values.fold((i32::MAX, i32::MIN), |(lo, hi), v| (lo.min(v), hi.max(v)))
+                        };
+                        let (x0, x1) = range(&mut points.iter().map(|p| p.0));
+                        let (y0, y1) = range(&mut points.iter().map(|p| p.1));
+                        [x0, y0, x1, y1].map(|v| v.clamp(i16::MIN.into(), i16::MAX.into()) as i16)
+                    }
+                };
+                let overlap = overlaps.is_some_and(|bitmap| bit(bitmap, i));
+                let code = instructions.bytes(instruction_len)?;
+                simple_glyph(&mut glyf, bbox, &ends, code, &points, overlap)?;
+                x_mins.push(bbox[0]);
+            }
+            n => bail!("glyph {} has {} contours", i, n),
+        }
+        glyf.resize(glyf.len().next_multiple_of(4), 0);
+    }
+    offsets.push(glyf.len());
+
+    let mut loca = Vec::new();
+    for offset in offsets {
+        match index_format {
+            0 => loca.extend(
+                u16::try_from(offset / 2)
+                    .context("glyf too large for short loca")?
+                    .to_be_bytes(),
+            ),
+            _ => loca.extend((offset as u32).to_be_bytes()),
+        }
+    }
+    Ok((glyf, loca, x_mins))
+}
+
+/// Rebuild `hmtx` from its transformed form, which may leave out left side
+/// bearings equal to the glyphs' xMin
+fn reconstruct_hmtx(data: &[u8], metrics: usize, x_mins: &[i16]) -> Result<Vec<u8>> {
+    let mut r = Reader::new(data);
+    let flags = r.u8()?;
+    ensure!(
+        metrics <= x_mins.len(),
+        "more horizontal metrics than glyphs"
+    );
+    let advances = (0..metrics).map(|_| r.u16()).collect::<Result<Vec<_>>>()?;
+    let mut bearings = Vec::with_capacity(x_mins.len());
+    for (i, &x_min) in x_mins.iter().enumerate() {
+        let stored = match i < metrics {
+            true => flags & 1 == 0,
+            false => flags & 2 == 0,
+        };
+        bearings.push(if stored { r.i16()? } else { x_min });
+    }
+    let mut out = Vec::new();
+    for (i, bearing) in bearings.into_iter().enumerate() {
+        if let Some(advance) = advances.get(i) {
+            out.extend(advance.to_be_bytes());
+        }
+        push_i16(&mut out, bearing);
+    }
+    Ok(out)
+}
+
+/// `font` with the `extra` tables added, for tests of optional tables
+#[cfg(test)]
+pub(crate) fn with_tables(font: &[u8], extra: Vec<Table>) -> Vec<u8> {
+    let mut all: Vec<Table> = tests::tables(font)
+        .into_iter()
+        .map(|(tag, data)| Table {
+            tag,
+            data: data.to_vec(),
+        })
+        .collect();
+    all.extend(extra);
+    sfnt(u32::from_be_bytes(font[..4].try_into().unwrap()), all)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font::{parse_face, EMBEDDED_FONT};
+    use std::io::Write;
+
+    /// Tables of an sfnt, in directory order
+    pub(super) fn tables(font: &[u8]) -> Vec<([u8; 4], &[u8])> {
+        let mut r = Reader::new(font);
+        r.pos = 4;
+        let count = r.u16().unwrap();
+        r.pos = 12;
+        (0..count)
+            .map(|_| {
+                let tag = r.bytes(4).unwrap().try_into().unwrap();
+                r.u32().unwrap();
+                let (offset, len) = (r.u32().unwrap() as usize, r.u32().unwrap() as usize);
+                (tag, &font[offset..offset + len])
+            })
+            .collect()
+    }
+
+    fn woff(font: &[u8]) -> Vec<u8> {
+        let tables = tables(font);
+        let mut directory = Vec::new();
+        let mut data = Vec::new();
+        let data_start = 44 + 20 * tables.len();
+        for (tag, table) in &tables {
+            let mut zlib = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::best());
+            zlib.write_all(table).unwrap();
+            let compressed = zlib.finish().unwrap();
+            let stored = if compressed.len() < table.len() {
+                &compressed[..]
+            } else {
+                table
+            };
+            directory.extend(tag);
+            directory.extend(((data_start + data.len()) as u32).to_be_bytes());
+            directory.extend((stored.len() as u32).to_be_bytes());
+            directory.extend((table.len() as u32).to_be_bytes());
+            directory.extend(checksum(table).to_be_bytes());
+            data.extend(stored);
+            data.resize(data.len().next_multiple_of(4), 0);
+        }
+        let mut out = b"wOFF".to_vec();
+        out.extend(&font[..4]);
+        out.extend(((data_start + data.len()) as u32).to_be_bytes());
+        out.extend((tables.len() as u16).to_be_bytes());
+        out.resize(44, 0);
+        out.extend(directory);
+        out.extend(data);
+        out
+    }
+
+    /// WOFF2 without transforms, as used for CFF fonts
+    fn woff2(font: &[u8]) -> Vec<u8> {
+        let tables = tables(font);
+        let mut directory = Vec::new();
+        let mut stream = Vec::new();
+        for (tag, table) in &tables {
+            match KNOWN_TAGS.iter().position(|known| *known == tag) {
+                Some(index) => directory.push(index as u8),
+                None => {
+                    directory.push(0x3f);
+                    directory.extend(tag);
+                }
+            }
+            let mut len = table.len() as u32;
+            let mut base128 = vec![(len & 0x7f) as u8];
+            while len > 0x7f {
+                len >>= 7;
+                base128.insert(0, (len & 0x7f) as u8 | 0x80);
+            }
+            directory.extend(base128);
+            stream.extend(*table);
+        }
+        let mut compressed = Vec::new();
+        brotli::BrotliCompress(
+            &mut &stream[..],
+            &mut compressed,
+            &brotli::enc::BrotliEncoderParams::default(),
+        )
+        .unwrap();
+        let mut out = b"wOF2".to_vec();
+        out.extend(&font[..4]);
+        out.extend([0; 4]);
+        out.extend((tables.len() as u16).to_be_bytes());
+        out.extend([0; 6]);
+        out.extend((compressed.len() as u32).to_be_bytes());
+        out.resize(48, 0);
+        out.extend(directory);
+        out.extend(compressed);
+        out
+    }
+
+    #[test]
+    fn web_fonts_unpack_to_the_same_tables() {
+        assert!(decode(EMBEDDED_FONT).unwrap().is_none());
+        for packed in [woff(EMBEDDED_FONT), woff2(EMBEDDED_FONT)] {
+            let font = decode(&packed).unwrap().unwrap();
+            let mut expected = tables(EMBEDDED_FONT);
+            expected.sort_by_key(|(tag, _)| *tag);
+            assert_eq!(tables(&font), expected);
+            let face = parse_face(&font, 0).unwrap();
+            assert!(face.glyph_index('あ').is_some());
+        }
+        assert!(decode(b"wOF2 truncated").is_err());
+    }
+
+    #[test]
+    fn transformed_glyphs_are_rebuilt() {
+        // Glyph 0: an on-curve triangle corner at the origin, one 100 units
+        // right and an off-curve point at (50, 300); glyph 1 is empty
+        let streams: [&[u8]; 7] = [
+            &[0, 1, 0, 0],
+            &[3],
+            &[1, 11, 0x80 | 90],
+            &[0, 100, 49, 43, 0],
+            &[],
+            &[0, 0, 0, 0],
+            &[],
+        ];
+        let mut transformed = vec![0, 0, 0, 0, 0, 2, 0, 0];
+        for stream in streams {
+            transformed.extend((stream.len() as u32).to_be_bytes());
+        }
+        streams
+            .iter()
+            .for_each(|stream| transformed.extend(*stream));
+
+        let (glyf, loca, x_mins) = reconstruct_glyf(&transformed).unwrap();
+        #[rustfmt::skip]
+        let expected: &[u8] = &[
+            0, 1, 0, 0, 0, 0, 0, 100, 1, 44, // contours, bbox
+            0, 2, 0, 0, // end point, no instructions
+            0x31, 0x33, 0x02, // flags
+            100, 50, // x deltas
+            1, 44, // y delta
+            0, 0, 0,
+        ];
+        assert_eq!(glyf, expected);
+        assert_eq!(loca, [0, 0, 0, 12, 0, 12]);
+        assert_eq!(x_mins, [0, 0]);
+
+        // Left side bearings left out for being the glyphs' xMin
+        let hmtx = reconstruct_hmtx(&[3, 0, 120], 1, &[-5, 7]).unwrap();
+        assert_eq!(hmtx, [0, 120, 0xff, 0xfb, 0, 7]);
+    }
+
+    #[test]
+    fn malformed_fonts_are_errors_not_panics() {
+        // One glyph whose only contour has no points
+        let streams: [&[u8]; 7] = [&[0, 1], &[0], &[], &[], &[], &[0, 0, 0, 0], &[]];
+        let mut transformed = vec![0, 0, 0, 0, 0, 1, 0, 0];
+        for stream in streams {
+            transformed.extend((stream.len() as u32).to_be_bytes());
+        }
+        streams
+            .iter()
+            .for_each(|stream| transformed.extend(*stream));
+        assert!(reconstruct_glyf(&transformed).is_err());
+
+        let tables = (0..5000u32)
+            .map(|i| Table {
+                tag: i.to_be_bytes(),
+                data: Vec::new(),
+            })
+            .collect();
+        let font = sfnt(0x0001_0000, tables);
+        assert_eq!(font[4..6], 5000u16.to_be_bytes());
+    }
+}
@@ -0,0 +1,198 @@
+//! Wrapping text at `max_width`.
+//!
+//! Break opportunities follow the main rules of UAX #14 without its full
+//! tables: after spaces and hyphens, and between Chinese and Japanese
+//! characters. Kinsoku shori applies to every break but those after
+//! spaces, even when any character may break: none before closing
+//! punctuation, small kana and the long vowel mark, none after opening
+//! brackets, and none inside a run of leaders (……). Lines are filled
+//! greedily using the real widths the caller measures; a comma or full
+//! stop that would not fit hangs past the end of its line (burasage)
+//! rather than pushing the character before it onto the next one.
+
+/// A line of text after wrapping
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextLine {
+    pub text: String,
+    /// Broken by wrapping rather than at a `\n` or the end of the text
+    pub wrapped: bool,
+}
+
+/// Whether `ch` is a CJK character lines may break around
+fn is_ideographic(ch: char) -> bool {
+    matches!(
+        ch as u32,
+        0x1100..=0x11FF // Hangul Jamo
+            | 0x2E80..=0x2FFF // CJK radicals, Kangxi
+            | 0x3000..=0x303F // CJK symbols and punctuation
+            | 0x3040..=0x30FF // Hiragana, Katakana
+            | 0x3100..=0x31FF // Bopomofo, Katakana extensions
+            | 0x3400..=0x4DBF // CJK Extension A
+            | 0x4E00..=0x9FFF // CJK Unified Ideographs
+            | 0xAC00..=0xD7AF // Hangul syllables
+            | 0xF900..=0xFAFF // CJK compatibility ideographs
+            | 0xFF00..=0xFFEF // Halfwidth and fullwidth forms
+            | 0x20000..=0x3FFFF // Supplementary ideographic planes
+    )
+}
+
+/// Characters a line must not start with
+fn no_break_before(ch: char) -> bool {
+    "、。，．,.:;!?)]}〕〉》」』】〙〗〟’”｠»）］｝！？：；・ー～〜゠‐‼⁇⁈⁉ぁぃぅぇぉっゃゅょゎゕゖァィゥェォッャュョヮヵヶ々〻ゝゞヽヾ゛゜｡｣､･ｧｨｩｪｫｬｭｮｯｰ"
+        .contains(ch)
+        || matches!(ch as u32, 0x31F0..=0x31FF) // Small katakana for Ainu
+        || is_combining(ch)
+}
+
+/// Characters a line must not end with
+fn no_break_after(ch: char) -> bool {
+    "([{〔〈《「『【〘〖〝‘“｟«（［｛｢".contains(ch)
+}
+
+/// Whether a line break between `before` and `after` would split a run of
+/// leaders or dashes that reads as one mark
+fn inseparable(before: char, after: char) -> bool {
+    before == after && matches!(before, '…' | '‥' | '—' | '―')
+}
+
+/// Punctuation that may hang past the end of a full line
+fn hangs(ch: char) -> bool {
+    "、。，．､｡".contains(ch)
+}
+
+/// Marks and joiners that belong to the character before them
+fn is_combining(ch: char) -> bool {
+    crate::layout::is_mark(ch)
+        || matches!(ch as u32, 0x200C..=0x200D | 0xFE00..=0xFE0F | 0xE0100..=0xE01EF)
+}
+
+/// Byte offsets in `line` where a new line may start; with `anywhere`,
+/// between any two characters
+pub fn break_opportunities(line: &str, anywhere: bool) -> Vec<usize> {
+    let chars: Vec<(usize, char)> = line.char_indices().collect();
+    let mut breaks = Vec::new();
+    for pair in chars.windows(2) {
+        let ((_, before), (at, after)) = (pair[0], pair[1]);
+        if after.is_whitespace() || is_combining(after) {
+            continue;
+        }
+        let kinsoku =
+            no_break_before(after) || no_break_after(before) || inseparable(before, after);
+        let allowed = before.is_whitespace()
+            || (!kinsoku
+                && (anywhere
+                    || (matches!(before, '-' | '‐' | '–' | '—') && after.is_alphanumeric())
+                    || is_ideographic(before)
+                    || is_ideographic(after)));
+        if allowed {
+            breaks.push(at);
+        }
+    }
+    breaks
+}
+
+/// Split `text` into lines no wider than `width` as `measure` reports it,
+/// breaking at the last opportunity that fits, a hanging comma or full
+/// stop at its end left out of the measure. Existing `\n` breaks are kept;
+/// a word wider than `width` stays whole on a line of its own unless
+/// `anywhere` lets it break between characters.
+pub fn wrap_lines(
+    text: &str,
+    width: f32,
+    anywhere: bool,
+    measure: impl Fn(&str) -> f32,
+) -> Vec<TextLine> {
+    let fits = |line: &str| {
+        let line = line.trim_end();
+        measure(line) <= width
+            || line.chars().next_back().is_some_and(|ch| {
+                hangs(ch) && measure(&line[..line.len() - ch.len_utf8()]) <= width
+            })
+    };
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut rest = paragraph;
+        loop {
+            let mut last = None;
+            let mut first = None;
+            if !fits(rest) {
+                for at in break_opportunities(rest, anywhere) {
+                    first.get_or_insert(at);
+                    if !fits(&rest[..at]) {
+                        break;
+                    }
+                    last = Some(at);
+                }
+            }
+            let Some(at) = last.or(first) else {
+                lines.push(TextLine {
+                    text: rest.to_string(),
+                    wrapped: false,
+                });
+                break;
+            };
+            lines.push(TextLine {
+                text: rest[..at].trim_end().to_string(),
+                wrapped: true,
+            });
+            rest = &rest[at..];
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(lines: &[TextLine]) -> Vec<&str> {
+        lines.iter().map(|line| line.text.as_str()).collect()
+    }
+
+    #[test]
+    fn breaks_follow_spaces_hyphens_and_kinsoku() {
+        assert_eq!(break_opportunities("ab  cd-ef", false), [4, 7]);
+        assert_eq!(break_opportunities("「東京」です。", false), [6, 12, 15]);
+        assert_eq!(break_opportunities("abc", true), [1, 2]);
+        // Kinsoku holds even where any character may break
+        assert_eq!(break_opportunities("あ。「い」", true), [6]);
+        assert_eq!(break_opportunities("ｱｯ……と", false), [6, 12]);
+    }
+
+    #[test]
+    fn commas_and_full_stops_hang_rather_than_push() {
+        let count = |s: &str| s.chars().count() as f32;
+        assert_eq!(
+            texts(&wrap_lines("あいう。えおか、きく", 3.0, false, count)),
+            ["あいう。", "えおか、", "きく"]
+        );
+        // Other closing punctuation pushes the character before it down
+        assert_eq!(
+            texts(&wrap_lines("あいう」えお", 3.0, true, count)),
+            ["あい", "う」え", "お"]
+        );
+    }
+
+    #[test]
+    fn lines_fill_up_to_the_width() {
+        let count = |s: &str| s.chars().count() as f32;
+        let lines = wrap_lines("one two three\nfour", 8.0, false, count);
+        assert_eq!(texts(&lines), ["one two", "three", "four"]);
+        assert_eq!(
+            lines.iter().map(|line| line.wrapped).collect::<Vec<_>>(),
+            [true, false, false]
+        );
+        assert_eq!(
+            texts(&wrap_lines("unbreakable word", 5.0, false, count)),
+            ["unbreakable", "word"]
+        );
+        assert_eq!(
+            texts(&wrap_lines("unbreakable", 5.0, true, count)),
+            ["unbre", "akabl", "e"]
+        );
+        assert_eq!(
+            texts(&wrap_lines("ありがとうございました。", 5.0, false, count)),
+            ["ありがとう", "ございまし", "た。"]
+        );
+    }
+}
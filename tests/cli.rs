@@ -1,3 +1,6 @@
+// The CLI tests draw with the builtin fonts
+#![cfg(feature = "embedded-font")]
+
 use std::error::Error;
 use std::str::FromStr;
 
@@ -77,3 +80,991 @@ fn cli_outputs_stl_with_depth_and_unit_normals() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+#[test]
+fn cli_streams_binary_stl_to_stdout() -> Result<(), Box<dyn Error>> {
+    let stl = |format: &str| -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+        let assert = cmd
+            .args(["--size", "8", "--format", format, "Hi"])
+            .assert()
+            .success();
+        Ok(assert.get_output().stdout.clone())
+    };
+    let ascii = stl("ascii")?;
+    let binary = stl("binary")?;
+    assert!(
+        binary.starts_with(b"wagyan "),
+        "header carries the metadata"
+    );
+
+    let facets = String::from_utf8(ascii.clone())?
+        .matches("facet normal")
+        .count();
+    let count = u32::from_le_bytes(binary[80..84].try_into()?) as usize;
+    assert_eq!(count, facets);
+    assert_eq!(binary.len(), 84 + 50 * count);
+    assert!(binary.len() * 3 < ascii.len());
+    Ok(())
+}
+
+#[test]
+fn cli_format_flag_overrides_the_extension() -> Result<(), Box<dyn Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .args(["--size", "8", "--format", "3mf", "Hi"])
+        .assert()
+        .success();
+    assert!(
+        assert.get_output().stdout.starts_with(b"PK"),
+        "3MF is a zip"
+    );
+
+    let dir = std::env::temp_dir().join(format!("wagyan-format-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("sign.model");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    cmd.args(["--size", "8", "--quiet", "--format", "obj", "--output"])
+        .arg(&path)
+        .arg("Hi")
+        .assert()
+        .success();
+    assert!(std::fs::read_to_string(&path)?.contains("\nf "));
+
+    let unknown = dir.join("sign.xyz");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .args(["--size", "8", "--output"])
+        .arg(&unknown)
+        .arg("Hi")
+        .assert()
+        .failure();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone())?;
+    assert!(stderr.contains("unknown output extension"), "{}", stderr);
+    assert!(!unknown.exists());
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn cli_fills_missing_glyphs_from_fallback_fonts() -> Result<(), Box<dyn Error>> {
+    let font = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/assets/fonts/NotoSansJP-Regular.otf"
+    );
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .args(["--fallback-font", font, "--fallback-font", font, "Aあ"])
+        .assert()
+        .success();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone())?;
+    assert!(!stderr.contains("Skip missing glyph"), "{}", stderr);
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .args(["--fallback-font", "no-such-font.ttf", "Hi"])
+        .assert()
+        .failure();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone())?;
+    assert!(
+        stderr.contains("failed to read font file: no-such-font.ttf"),
+        "{}",
+        stderr
+    );
+    Ok(())
+}
+
+/// DejaVu Sans Mono marked restricted-license (OS/2 fsType 2), written to
+/// a temporary file
+fn restricted_font(name: &str) -> Result<std::path::PathBuf, Box<dyn Error>> {
+    let mut bytes = std::fs::read(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/assets/fonts/DejaVuSansMono.ttf"
+    ))?;
+    let read_u16 = |b: &[u8], at: usize| u16::from_be_bytes([b[at], b[at + 1]]) as usize;
+    let read_u32 =
+        |b: &[u8], at: usize| u32::from_be_bytes([b[at], b[at + 1], b[at + 2], b[at + 3]]) as usize;
+    let os2 = (0..read_u16(&bytes, 4))
+        .map(|i| 12 + i * 16)
+        .find(|&record| &bytes[record..record + 4] == b"OS/2")
+        .map(|record| read_u32(&bytes, record + 8))
+        .ok_or("no OS/2 table")?;
+    bytes[os2 + 8..os2 + 10].copy_from_slice(&2u16.to_be_bytes());
+    let path = std::env::temp_dir().join(format!("wagyan-{}-{}.ttf", name, std::process::id()));
+    std::fs::write(&path, bytes)?;
+    Ok(path)
+}
+
+#[test]
+fn cli_refuses_restricted_fallback_fonts() -> Result<(), Box<dyn Error>> {
+    let font = restricted_font("restricted-fallback")?;
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .arg("--fallback-font")
+        .arg(&font)
+        .arg("Hi")
+        .assert()
+        .failure();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone())?;
+    assert!(stderr.contains("--ack-license"), "{}", stderr);
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    cmd.arg("--fallback-font")
+        .arg(&font)
+        .args(["--ack-license", "Hi"])
+        .assert()
+        .success();
+
+    // Fonts named by --markup tags and --runs runs are fallbacks as well
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .arg("--markup")
+        .arg(format!("A{{font={}}}B{{/font}}", font.display()))
+        .assert()
+        .failure();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone())?;
+    assert!(stderr.contains("--ack-license"), "{}", stderr);
+
+    let runs = font.with_extension("json");
+    std::fs::write(
+        &runs,
+        serde_json::json!([{"text": "A"}, {"text": "B", "font": font}]).to_string(),
+    )?;
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd.arg("--runs").arg(&runs).assert().failure();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone())?;
+    assert!(stderr.contains("--ack-license"), "{}", stderr);
+    std::fs::remove_file(&runs)?;
+    std::fs::remove_file(&font)?;
+    Ok(())
+}
+
+#[test]
+fn cli_reports_corrupt_web_fonts() -> Result<(), Box<dyn Error>> {
+    let dir = std::env::temp_dir().join(format!("wagyan-woff-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("broken.woff2");
+    std::fs::write(&path, b"wOF2\0\x01\0\0")?;
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd.arg("--font").arg(&path).arg("Hi").assert().failure();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone())?;
+    assert!(stderr.contains("invalid WOFF2 font"), "{}", stderr);
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn cli_rejects_variations_for_static_fonts() -> Result<(), Box<dyn Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .args(["--variation", "wght=700,wdth=85", "Hi"])
+        .assert()
+        .failure();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone())?;
+    assert!(stderr.contains("needs a variable font"), "{}", stderr);
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd.args(["--variation", "wght", "Hi"]).assert().failure();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone())?;
+    assert!(stderr.contains("expected AXIS=VALUE"), "{}", stderr);
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .args(["--instance", "SemiBold", "Hi"])
+        .assert()
+        .failure();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone())?;
+    assert!(stderr.contains("no named instances"), "{}", stderr);
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd.arg("--list-instances").assert().failure();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone())?;
+    assert!(stderr.contains("no named instances"), "{}", stderr);
+    Ok(())
+}
+
+#[test]
+fn cli_applies_opentype_features() -> Result<(), Box<dyn Error>> {
+    let glyphs = |features: &[&str]| -> Result<usize, Box<dyn Error>> {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+        let assert = cmd
+            .args(["measure", "--json", "ffi"])
+            .args(features)
+            .assert()
+            .success();
+        let report: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout)?;
+        Ok(report["lines"][0]["glyphs"].as_array().map_or(0, Vec::len))
+    };
+    assert_eq!(glyphs(&[])?, 3);
+    assert_eq!(glyphs(&["--features", "liga,ss01"])?, 1);
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .args(["--features", "ligatures", "ffi"])
+        .assert()
+        .failure();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone())?;
+    assert!(stderr.contains("feature tags are 1 to 4"), "{}", stderr);
+    Ok(())
+}
+
+#[test]
+fn cli_selects_the_shaper() -> Result<(), Box<dyn Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .args(["measure", "--shaper", "harfbuzz", "ffi"])
+        .assert();
+    if cfg!(feature = "shaping") {
+        let stdout = String::from_utf8(assert.success().get_output().stdout.clone())?;
+        assert_eq!(stdout.matches(" gid ").count(), 1, "{}", stdout);
+    } else {
+        let stderr = String::from_utf8(assert.failure().get_output().stderr.clone())?;
+        assert!(stderr.contains("`shaping` feature"), "{}", stderr);
+    }
+    Ok(())
+}
+
+#[test]
+fn cli_sets_vertical_columns() -> Result<(), Box<dyn Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .args([
+            "measure",
+            "--json",
+            "--writing-mode",
+            "vertical",
+            "あい\\nう",
+        ])
+        .assert()
+        .success();
+    let report: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout)?;
+    let glyphs = &report["lines"][0]["glyphs"];
+    assert_eq!(glyphs[0]["x"], glyphs[1]["x"]);
+    assert_eq!(
+        report["lines"][0]["width"].as_f64(),
+        report["em_size"].as_f64().map(|em| 2.0 * em)
+    );
+    let column = |i: usize| report["lines"][i]["baseline"].as_f64().unwrap_or(0.0);
+    assert!(column(1) < column(0));
+    Ok(())
+}
+
+#[test]
+fn cli_measure_honors_strict() -> Result<(), Box<dyn Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    cmd.args(["measure", "A\u{13000}"]).assert().success();
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .args(["measure", "--strict", "A\u{13000}"])
+        .assert()
+        .failure();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone())?;
+    assert!(stderr.contains("U+13000"), "{}", stderr);
+    Ok(())
+}
+
+#[test]
+fn cli_reports_missing_font_families() -> Result<(), Box<dyn Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .args(["--family", "No Such Family Wagyan", "--style", "bold", "Hi"])
+        .assert()
+        .failure();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone())?;
+    assert!(
+        stderr.contains(r#"no installed font family named "No Such Family Wagyan""#),
+        "{}",
+        stderr
+    );
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    cmd.args(["--style", "bold", "Hi"]).assert().failure();
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    cmd.args([
+        "nameplate",
+        "Ann",
+        "--style",
+        "pill",
+        "--font-style",
+        "bold",
+    ])
+    .assert()
+    .failure();
+    Ok(())
+}
+
+#[test]
+fn cli_writes_svg_with_glyph_curves() -> Result<(), Box<dyn Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .args(["--size", "20", "--plate", "2", "--format", "svg", "o"])
+        .assert()
+        .success();
+    let svg = String::from_utf8(assert.get_output().stdout.clone())?;
+    assert!(svg.contains(r#"<g id="CUT""#) && svg.contains(r#"<g id="ENGRAVE""#));
+    let engrave = svg.split(r#"<g id="ENGRAVE""#).nth(1).unwrap();
+    assert!(
+        engrave.contains('Q') || engrave.contains('C'),
+        "the letter keeps its curves"
+    );
+    Ok(())
+}
+
+#[test]
+fn cli_writes_step_solids_with_glyph_curves() -> Result<(), Box<dyn Error>> {
+    let dir = std::env::temp_dir().join(format!("wagyan-step-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let path = dir.join("sign.stp");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    cmd.args(["--size", "20", "--plate", "2", "--quiet", "--output"])
+        .arg(&path)
+        .arg("o")
+        .assert()
+        .success();
+    let step = std::fs::read_to_string(&path)?;
+    assert!(step.starts_with("ISO-10303-21;"));
+    assert!(step.contains("AUTOMOTIVE_DESIGN"));
+    assert_eq!(step.matches("MANIFOLD_SOLID_BREP('plate'").count(), 1);
+    assert_eq!(step.matches("MANIFOLD_SOLID_BREP('text'").count(), 1);
+    assert!(
+        step.contains("B_SPLINE_CURVE_WITH_KNOTS"),
+        "the letter keeps its curves"
+    );
+    assert_eq!(
+        step.matches("FACE_BOUND(").count(),
+        2,
+        "the counter of the o, in both caps"
+    );
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn cli_writes_every_output_from_one_model() -> Result<(), Box<dyn Error>> {
+    let dir = std::env::temp_dir().join(format!("wagyan-outputs-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    let names = ["sign.stl", "sign.3mf", "sign.svg"];
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    cmd.args(["--size", "8", "--quiet"]);
+    for name in names {
+        cmd.arg("-o").arg(dir.join(name));
+    }
+    let assert = cmd.arg("Hi").assert().success();
+    assert!(assert.get_output().stdout.is_empty());
+    assert!(std::fs::read_to_string(dir.join("sign.stl"))?.starts_with("solid sign"));
+    assert!(std::fs::read(dir.join("sign.3mf"))?.starts_with(b"PK"));
+    assert!(std::fs::read_to_string(dir.join("sign.svg"))?.contains("<svg"));
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn cli_colors_text_and_plate_separately() -> Result<(), Box<dyn Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .args(["--size", "8", "--plate", "2", "--format", "amf"])
+        .args(["--text-color", "#ff0000", "--plate-color", "#ffffff", "Hi"])
+        .assert()
+        .success();
+    let amf = String::from_utf8(assert.get_output().stdout.clone())?;
+    // Materials are numbered in part order: the plate comes first
+    assert!(amf.contains(r#"<metadata type="name">#ffffff</metadata>"#));
+    assert!(amf.contains(r#"<metadata type="name">#ff0000</metadata>"#));
+    let text = amf.split(r#"<volume materialid="2">"#).nth(1).unwrap();
+    assert!(text.contains(r#"<metadata type="name">text</metadata>"#));
+    Ok(())
+}
+
+#[test]
+fn cli_names_the_model() -> Result<(), Box<dyn Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .args(["--size", "8", "--name", "sign", "Hi"])
+        .assert()
+        .success();
+    let stl = String::from_utf8(assert.get_output().stdout.clone())?;
+    assert!(stl.starts_with("solid sign\n"));
+    assert!(stl.trim_end().ends_with("endsolid sign"));
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .args([
+            "--size", "8", "--plate", "1", "--name", "sign", "--format", "obj",
+        ])
+        .arg("Hi")
+        .assert()
+        .success();
+    let obj = String::from_utf8(assert.get_output().stdout.clone())?;
+    assert!(obj.contains("\no sign-plate\n") && obj.contains("\no sign-text\n"));
+    Ok(())
+}
+
+#[test]
+fn cli_exports_glyph_groups() -> Result<(), Box<dyn Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .args([
+            "--size",
+            "8",
+            "--plate",
+            "1",
+            "--glyph-groups",
+            "--format",
+            "obj",
+        ])
+        .arg("A B")
+        .assert()
+        .success();
+    let obj = String::from_utf8(assert.get_output().stdout.clone())?;
+    let objects: Vec<&str> = obj.lines().filter(|l| l.starts_with("o ")).collect();
+    assert_eq!(objects, ["o plate", "o A_0", "o B_1"]);
+    Ok(())
+}
+
+#[test]
+fn cli_crease_angle_controls_smoothing() -> Result<(), Box<dyn Error>> {
+    let normals = |angle: &str| -> Result<usize, Box<dyn Error>> {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+        let assert = cmd
+            .args([
+                "--size",
+                "20",
+                "--format",
+                "obj",
+                "--crease-angle",
+                angle,
+                "o",
+            ])
+            .assert()
+            .success();
+        let obj = String::from_utf8(assert.get_output().stdout.clone())?;
+        assert!(obj.contains("\ns 1\n"));
+        Ok(obj.lines().filter(|l| l.starts_with("vn ")).count())
+    };
+    // Every wall corner is blended at 180°, none at 0°
+    assert!(normals("180")? < normals("0")?);
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    cmd.args(["--crease-angle", "200", "o"]).assert().failure();
+    Ok(())
+}
+
+#[test]
+fn cli_writes_slicer_project_data() -> Result<(), Box<dyn Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .args(["--size", "8", "--plate", "2", "--orient", "flat"])
+        .args(["--slicer", "prusa", "--format", "3mf", "Hi"])
+        .assert()
+        .success();
+    let mut zip = zip::ZipArchive::new(std::io::Cursor::new(assert.get_output().stdout.clone()))?;
+    let mut read = |name: &str| -> Result<String, Box<dyn Error>> {
+        let mut text = String::new();
+        std::io::Read::read_to_string(&mut zip.by_name(name)?, &mut text)?;
+        Ok(text)
+    };
+    // Centered on the MK4 bed
+    assert!(read("3D/3dmodel.model")?.contains(r#"transform="1 0 0 0 1 0 0 0 1 125 105 "#));
+    let config = read("Metadata/Slic3r_PE_model.config")?;
+    assert!(config.contains(r#"key="name" value="text""#));
+    let gcodes = read("Metadata/Prusa_Slicer_custom_gcode_per_print_z.xml")?;
+    assert!(gcodes.contains(r#"gcode="M600""#));
+    Ok(())
+}
+
+#[test]
+fn cli_scales_to_the_requested_units() -> Result<(), Box<dyn Error>> {
+    let width = |units: &str| -> Result<f32, Box<dyn Error>> {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+        let assert = cmd
+            .args(["--size", "8", "--units", units, "Hi"])
+            .assert()
+            .success();
+        let stl = String::from_utf8(assert.get_output().stdout.clone())?;
+        let xs: Vec<f32> = stl
+            .lines()
+            .filter_map(|l| l.trim().strip_prefix("vertex "))
+            .map(|v| v.split_whitespace().next().unwrap().parse())
+            .collect::<Result<_, _>>()?;
+        let max = xs.iter().copied().fold(f32::MIN, f32::max);
+        let min = xs.iter().copied().fold(f32::MAX, f32::min);
+        Ok(max - min)
+    };
+    let mm = width("mm")?;
+    assert!((width("cm")? * 10.0 - mm).abs() < 1e-3);
+    assert!((width("inch")? * 25.4 - mm).abs() < 1e-3);
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .args(["--size", "8", "--units", "inch", "--format", "3mf", "Hi"])
+        .assert()
+        .success();
+    let mut zip = zip::ZipArchive::new(std::io::Cursor::new(assert.get_output().stdout.clone()))?;
+    let mut xml = String::new();
+    std::io::Read::read_to_string(&mut zip.by_name("3D/3dmodel.model")?, &mut xml)?;
+    assert!(xml.contains(r#"<model unit="inch" "#));
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .args(["--units", "cm", "--format", "amf", "Hi"])
+        .assert()
+        .failure();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone())?;
+    assert!(stderr.contains("AMF has no centimeter unit"), "{}", stderr);
+    Ok(())
+}
+
+#[test]
+fn cli_keeps_stdout_clean_when_writing_a_file() -> Result<(), Box<dyn Error>> {
+    let dir = std::env::temp_dir().join(format!("wagyan-stdout-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("status.stl");
+    let quiet_path = dir.join("quiet.stl");
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .args(["--size", "8", "--output"])
+        .arg(&path)
+        .arg("A")
+        .assert()
+        .success();
+    let output = assert.get_output();
+    assert!(output.stdout.is_empty(), "stdout must stay empty");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("wrote"));
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .args(["--size", "8", "--quiet", "--output"])
+        .arg(&quiet_path)
+        .arg("A")
+        .assert()
+        .success();
+    let output = assert.get_output();
+    assert!(output.stdout.is_empty() && output.stderr.is_empty());
+
+    assert!(std::fs::read_to_string(&path)?.starts_with("solid status"));
+    assert!(std::fs::read_to_string(&quiet_path)?.starts_with("solid quiet"));
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn cli_localizes_messages_but_not_the_model() -> Result<(), Box<dyn Error>> {
+    let stl = |lang: &str| -> Result<_, Box<dyn Error>> {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+        let assert = cmd
+            .env("WAGYAN_LANG", lang)
+            .args(["--size", "8", "A\u{13000}"])
+            .assert()
+            .success();
+        let output = assert.get_output();
+        // Facet order is not stable from run to run; compare the sorted lines
+        let stdout = String::from_utf8(output.stdout.clone())?;
+        let mut lines: Vec<String> = stdout.lines().map(str::to_string).collect();
+        lines.sort();
+        Ok((lines, String::from_utf8(output.stderr.clone())?))
+    };
+    let (en, en_log) = stl("en")?;
+    let (ja, ja_log) = stl("ja_JP.UTF-8")?;
+    assert_eq!(en, ja, "the STL must not depend on the language");
+    assert!(en_log.contains("Skip missing glyph"));
+    assert!(ja_log.contains("フォントにない文字"));
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd.args(["--lang", "ja", "--help"]).assert().success();
+    assert!(String::from_utf8_lossy(&assert.get_output().stdout).contains("押し出しの深さ"));
+
+    // Ahead of a subcommand too
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .args(["--lang", "ja", "nameplate", "--height-mm", "8", "田中"])
+        .assert()
+        .success();
+    assert!(String::from_utf8_lossy(&assert.get_output().stdout).starts_with("solid mesh"));
+
+    // Errors and their causes alike
+    let path = std::env::temp_dir().join(format!("wagyan-ja-error-{}.stl", std::process::id()));
+    std::fs::write(&path, "solid old\n")?;
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .args(["--lang", "ja", "A", "-o"])
+        .arg(&path)
+        .assert()
+        .failure();
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr).into_owned();
+    let _ = std::fs::remove_file(&path);
+    assert!(stderr.contains("変換に失敗しました"), "{}", stderr);
+    assert!(
+        stderr.contains("既存のファイルは上書きしません"),
+        "{}",
+        stderr
+    );
+    Ok(())
+}
+
+#[test]
+fn cli_creates_missing_output_directories() -> Result<(), Box<dyn Error>> {
+    let dir = std::env::temp_dir().join(format!("wagyan-dirs-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let path = dir.join("deep/dir/name.stl");
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    cmd.args(["--size", "8", "--quiet", "--output"])
+        .arg(&path)
+        .arg("Hi")
+        .assert()
+        .success();
+    assert!(std::fs::read_to_string(&path)?.starts_with("solid name"));
+    // Only the output is left, no temporary file
+    assert_eq!(std::fs::read_dir(dir.join("deep/dir"))?.count(), 1);
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn cli_refuses_to_overwrite_without_force() -> Result<(), Box<dyn Error>> {
+    let dir = std::env::temp_dir().join(format!("wagyan-overwrite-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("keep.stl");
+    std::fs::write(&path, "hand edited")?;
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    cmd.args(["--size", "8", "--output"])
+        .arg(&path)
+        .arg("A")
+        .assert()
+        .failure();
+    assert_eq!(std::fs::read_to_string(&path)?, "hand edited");
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    cmd.args(["--size", "8", "--backup", "--output"])
+        .arg(&path)
+        .arg("A")
+        .assert()
+        .success();
+    assert_eq!(
+        std::fs::read_to_string(dir.join("keep.stl.bak"))?,
+        "hand edited"
+    );
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    cmd.args(["--size", "8", "--force", "--output"])
+        .arg(&path)
+        .arg("B")
+        .assert()
+        .success();
+    assert!(std::fs::read_to_string(&path)?.starts_with("solid keep"));
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn cli_refuses_the_whole_batch_before_writing_any_output() -> Result<(), Box<dyn Error>> {
+    let dir = std::env::temp_dir().join(format!("wagyan-batch-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    let fresh = dir.join("new.stl");
+    let existing = dir.join("existing.3mf");
+    std::fs::write(&existing, "hand edited")?;
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    cmd.args(["--size", "8", "-o"])
+        .arg(&fresh)
+        .arg("-o")
+        .arg(&existing)
+        .arg("A")
+        .assert()
+        .failure();
+    assert!(!fresh.exists());
+    assert_eq!(std::fs::read_to_string(&existing)?, "hand edited");
+
+    // The same file twice, the second time through a template, is refused
+    // even when overwriting is allowed
+    for policy in ["--force", "--backup"] {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+        let assert = cmd
+            .args(["--size", "8", policy, "-o"])
+            .arg(&fresh)
+            .arg("-o")
+            .arg(dir.join("{text}.stl"))
+            .arg("new")
+            .assert()
+            .failure();
+        let stderr = String::from_utf8(assert.get_output().stderr.clone())?;
+        assert!(stderr.contains("output given more than once"), "{}", stderr);
+        assert!(!fresh.exists());
+    }
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn cli_sidecar_follows_the_overwrite_policy() -> Result<(), Box<dyn Error>> {
+    let dir = std::env::temp_dir().join(format!("wagyan-sidecar-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("sign.stl");
+    let sidecar = dir.join("sign.stl.json");
+    std::fs::write(&sidecar, "hand edited")?;
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    cmd.args(["--size", "8", "--sidecar", "--output"])
+        .arg(&path)
+        .arg("A")
+        .assert()
+        .failure();
+    assert_eq!(std::fs::read_to_string(&sidecar)?, "hand edited");
+    assert!(!path.exists());
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    cmd.args(["--size", "8", "--sidecar", "--backup", "--output"])
+        .arg(&path)
+        .arg("A")
+        .assert()
+        .success();
+    assert_eq!(
+        std::fs::read_to_string(dir.join("sign.stl.json.bak"))?,
+        "hand edited"
+    );
+    assert!(std::fs::read_to_string(&sidecar)?.contains("wagyan:BuildHash"));
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn cli_nameplate_subcommand_writes_a_plate() -> Result<(), Box<dyn Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .args(["nameplate", "--engrave", "--height-mm", "8", "Ab"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).into_owned();
+    assert!(stdout.starts_with("solid mesh"));
+
+    // Flat by default: the plate (3 mm, engraved text included) lies on the XY plane
+    let zs: Vec<f32> = stdout
+        .lines()
+        .filter_map(|line| line.trim_start().strip_prefix("vertex"))
+        .filter_map(|rest| rest.split_whitespace().nth(2)?.parse().ok())
+        .collect();
+    let (min_z, max_z) = zs
+        .iter()
+        .fold((f32::MAX, f32::MIN), |(lo, hi), z| (lo.min(*z), hi.max(*z)));
+    assert!(
+        (max_z - min_z - 3.0).abs() < 1e-3,
+        "thickness {}",
+        max_z - min_z
+    );
+    Ok(())
+}
+
+#[test]
+fn cli_cake_topper_accepts_one_or_two_sticks() -> Result<(), Box<dyn Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .args(["cake-topper", "--sticks", "1", "--height-mm", "10", "Hi"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).into_owned();
+    assert!(stdout.starts_with("solid mesh"));
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    cmd.args(["cake-topper", "--sticks", "3", "Hi"])
+        .assert()
+        .failure();
+
+    for flag in ["--stick-width=-5", "--outline=-2", "--thickness=0"] {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+        cmd.args(["cake-topper", flag, "Hi"]).assert().failure();
+    }
+    Ok(())
+}
+
+#[test]
+fn cli_cable_tag_batch_writes_one_file_per_label() -> Result<(), Box<dyn Error>> {
+    let dir = std::env::temp_dir().join(format!("wagyan-tags-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    let labels = dir.join("labels.csv");
+    std::fs::write(&labels, "srv-01,10.0.0.1\nsrv-02,10.0.0.2\n")?;
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    cmd.args(["cable-tag", "--batch"])
+        .arg(&labels)
+        .arg("--output")
+        .arg(dir.join("{n}-{text}.stl"))
+        .assert()
+        .success();
+    assert!(std::fs::read_to_string(dir.join("1-srv-01.stl"))?.starts_with("solid"));
+    assert!(std::fs::read_to_string(dir.join("2-srv-02.stl"))?.starts_with("solid"));
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn cli_stroke_font_engraves_centerlines_in_dxf() -> Result<(), Box<dyn Error>> {
+    let dir = std::env::temp_dir().join(format!("wagyan-stroke-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    let font = dir.join("simplex.jhf");
+    std::fs::write(&font, "12345  1JZ\n  714  9MWRFRT RRYQZR[SZRY\n")?;
+    let path = dir.join("bang.dxf");
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    cmd.arg("--stroke-font")
+        .arg(&font)
+        .args(["--plate", "1", "--output"])
+        .arg(&path)
+        .arg("!")
+        .assert()
+        .success();
+    let dxf = std::fs::read_to_string(&path)?;
+    // The plate is cut; the stem and dot are engraved as open polylines
+    assert_eq!(dxf.matches("\nPOLYLINE\n  8\nCUT\n").count(), 1);
+    assert_eq!(dxf.matches("\nPOLYLINE\n  8\nENGRAVE\n").count(), 2);
+    assert_eq!(dxf.matches(" 70\n0\n  0\nVERTEX\n").count(), 2);
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn cli_prints_font_info() -> Result<(), Box<dyn Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd.args(["font-info", "--json"]).assert().success();
+    let info: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout)?;
+    assert_eq!(info["family"], "Noto Sans Japanese");
+    assert_eq!(info["units_per_em"], 1000);
+    assert_eq!(info["gsub"], true);
+    assert!(info["glyph_count"].as_u64().unwrap_or(0) > 1000);
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd.arg("font-info").assert().success();
+    let stdout = String::from_utf8(assert.get_output().stdout.clone())?;
+    assert!(stdout.contains("kern: "), "{}", stdout);
+    Ok(())
+}
+
+#[test]
+fn cli_lists_faces_for_an_unknown_face_name() -> Result<(), Box<dyn Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .args(["measure", "--face-name", "No Such Face", "Hi"])
+        .assert()
+        .failure();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone())?;
+    assert!(
+        stderr.contains("no face named \"No Such Face\""),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("0: Noto Sans"), "{}", stderr);
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    cmd.args(["measure", "--face-name", "noto sans japanese regular", "Hi"])
+        .assert()
+        .success();
+    Ok(())
+}
+
+#[test]
+fn cli_strict_fails_on_missing_glyphs() -> Result<(), Box<dyn Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .args(["coverage", "--json", "Hi\u{13000}"])
+        .assert()
+        .success();
+    let report: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout)?;
+    assert_eq!(report["characters"], 3);
+    assert_eq!(report["missing"][0]["codepoint"], "U+13000");
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    cmd.args(["coverage", "--strict", "Hi\u{13000}"])
+        .assert()
+        .failure();
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd.args(["--strict", "Hi\u{13000}"]).assert().failure();
+    let output = assert.get_output();
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr.clone())?;
+    assert!(stderr.contains("U+13000"), "{}", stderr);
+    Ok(())
+}
+
+#[test]
+fn cli_draws_glyph_ids_and_codepoints() -> Result<(), Box<dyn Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .args(["--glyph-id", "34,35", "--dry-run"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(assert.get_output().stdout.clone())?;
+    assert!(stdout.contains("\"<34><35>\""), "{}", stdout);
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd.args(["--glyph-id", "60000"]).assert().failure();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone())?;
+    assert!(
+        stderr.contains("glyph id 60000 is out of range"),
+        "{}",
+        stderr
+    );
+
+    let by_codepoint = assert_cmd::cargo::cargo_bin_cmd!("wagyan")
+        .args(["--codepoint", "U+48,0x69"])
+        .output()?;
+    let by_text = assert_cmd::cargo::cargo_bin_cmd!("wagyan")
+        .arg("Hi")
+        .output()?;
+    assert!(by_codepoint.status.success());
+    assert_eq!(by_codepoint.stdout.len(), by_text.stdout.len());
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "builtin-mono")]
+fn cli_draws_with_a_builtin_font() -> Result<(), Box<dyn Error>> {
+    let sans = assert_cmd::cargo::cargo_bin_cmd!("wagyan")
+        .args(["Hi", "--dry-run"])
+        .output()?;
+    let mono = assert_cmd::cargo::cargo_bin_cmd!("wagyan")
+        .args(["Hi", "--builtin", "mono", "--dry-run"])
+        .output()?;
+    assert!(mono.status.success());
+    assert_ne!(sans.stdout, mono.stdout);
+
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    cmd.args(["Hi", "--builtin", "mono", "--font", "x.ttf"])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+#[test]
+fn cli_wraps_at_max_width() -> Result<(), Box<dyn Error>> {
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("wagyan");
+    let assert = cmd
+        .args([
+            "The quick brown fox",
+            "--size",
+            "20",
+            "--max-width",
+            "100",
+            "--dry-run",
+        ])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(assert.get_output().stdout.clone())?;
+    assert!(stdout.contains("lines: 2"), "{}", stdout);
+    assert!(stdout.contains("\"The quick\""), "{}", stdout);
+    Ok(())
+}